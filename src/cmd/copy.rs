@@ -0,0 +1,292 @@
+use crate::cmd::render::{template_mode, MODES};
+use crate::glob;
+use crate::logging::Logger;
+use crate::render as render_lib;
+use crate::render::TemplateRenderer;
+use crate::safety::{self, AtomicFile};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+pub struct Config {
+    pub src: String,
+    pub dest: String,
+    pub workdir: String,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub render: bool,
+    pub mode: String,
+}
+impl Config {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.src.is_empty() {
+            return Err("--src is required".into());
+        }
+        if self.dest.is_empty() {
+            return Err("--dest is required".into());
+        }
+        if !MODES.contains(&self.mode.as_str()) {
+            return Err(format!(
+                "--mode must be one of {}, got {:?}",
+                MODES.join(", "),
+                self.mode
+            ));
+        }
+        Ok(())
+    }
+}
+pub fn run(log: &Logger, cfg: &Config) -> Result<(), String> {
+    cfg.validate()?;
+    let dest = safety::validate_file_path(&cfg.workdir, &cfg.dest)?;
+    if !Path::new(&cfg.src).is_dir() {
+        return Err(format!("--src {:?} is not a directory", cfg.src));
+    }
+    let mut files = Vec::new();
+    collect_files(Path::new(&cfg.src), "", cfg, &mut Vec::new(), &mut files)?;
+    log.info(
+        "copying files",
+        &[
+            ("src", &cfg.src),
+            ("dest", &dest.display().to_string()),
+            ("files", &files.len().to_string()),
+        ],
+    );
+    let renderer = cfg.render.then(TemplateRenderer::new);
+    let dest = dest.to_string_lossy();
+    let mut rendered = 0;
+    for rel in &files {
+        let src = Path::new(&cfg.src).join(rel);
+        let out = safety::validate_file_path(&dest, rel)?;
+        if copy_file(log, cfg, renderer.as_ref(), &src, &out)? {
+            rendered += 1;
+        }
+        log.debug("copied", &[("file", rel)]);
+    }
+    log.info(
+        "copy completed",
+        &[
+            ("dest", &dest),
+            ("files", &files.len().to_string()),
+            ("rendered", &rendered.to_string()),
+        ],
+    );
+    Ok(())
+}
+/// Recursively collect files under `dir` as `/`-separated paths relative to
+/// `--src`, sorted for a deterministic copy order. `--exclude` prunes files
+/// and directories, `--include` selects files. A symlinked directory that
+/// leads back to one being walked (`ancestors`) is skipped.
+fn collect_files(
+    dir: &Path,
+    prefix: &str,
+    cfg: &Config,
+    ancestors: &mut Vec<PathBuf>,
+    files: &mut Vec<String>,
+) -> Result<(), String> {
+    let real = fs::canonicalize(dir).map_err(|e| format!("reading directory {:?}: {}", dir, e))?;
+    if ancestors.contains(&real) {
+        return Ok(());
+    }
+    ancestors.push(real);
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("reading directory {:?}: {}", dir, e))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("reading directory {:?}: {}", dir, e))?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Skip the `..data` internals of ConfigMap/Secret volumes; the
+        // visible files are symlinks into them.
+        if name.starts_with("..") {
+            continue;
+        }
+        let rel = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if glob::matches_any(&cfg.exclude, &rel) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, &rel, cfg, ancestors, files)?;
+        } else if cfg.include.is_empty() || glob::matches_any(&cfg.include, &rel) {
+            files.push(rel);
+        }
+    }
+    ancestors.pop();
+    Ok(())
+}
+/// Copy `src` to `out` atomically with the permissions of `src`, rendering
+/// text files first when a renderer is given. Returns whether it rendered.
+fn copy_file(
+    log: &Logger,
+    cfg: &Config,
+    renderer: Option<&TemplateRenderer>,
+    src: &Path,
+    out: &Path,
+) -> Result<bool, String> {
+    let src_label = src.to_string_lossy();
+    let meta = fs::metadata(src).map_err(|e| format!("reading {}: {}", src_label, e))?;
+    let mut file = AtomicFile::create(out)?;
+    let mut rendered = false;
+    let write = match renderer {
+        Some(renderer) => {
+            let data = fs::read(src).map_err(|e| format!("reading {}: {}", src_label, e))?;
+            let data = match String::from_utf8(data) {
+                Ok(text) => {
+                    rendered = true;
+                    let text = match template_mode(&cfg.mode, &src_label) {
+                        "envsubst" => render_lib::envsubst(&text)?,
+                        _ => renderer.render(&src_label, &text)?,
+                    };
+                    text.into_bytes()
+                }
+                Err(e) => {
+                    log.debug("not rendering binary file", &[("file", &src_label)]);
+                    e.into_bytes()
+                }
+            };
+            file.write_all(&data)
+        }
+        None => {
+            let mut input =
+                fs::File::open(src).map_err(|e| format!("reading {}: {}", src_label, e))?;
+            std::io::copy(&mut input, &mut file).map(|_| ())
+        }
+    };
+    write.map_err(|e| format!("writing output {:?}: {}", out, e))?;
+    fs::set_permissions(file.path(), meta.permissions())
+        .map_err(|e| format!("setting permissions on {:?}: {}", out, e))?;
+    file.commit()?;
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_logger() -> Logger {
+        Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        )
+    }
+
+    fn config(src: &TempDir, work: &TempDir) -> Config {
+        Config {
+            src: src.path().to_string_lossy().to_string(),
+            dest: "defaults".into(),
+            workdir: work.path().to_string_lossy().to_string(),
+            include: vec![],
+            exclude: vec![],
+            render: false,
+            mode: "envsubst".into(),
+        }
+    }
+
+    #[test]
+    fn test_copy_tree_with_globs() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        fs::create_dir_all(src.path().join("conf/tmp")).unwrap();
+        fs::create_dir_all(src.path().join("..data")).unwrap();
+        fs::write(src.path().join("conf/app.yaml"), "a: ${A}\n").unwrap();
+        fs::write(src.path().join("conf/tmp/scratch.yaml"), "x").unwrap();
+        fs::write(src.path().join("conf/notes.txt"), "n").unwrap();
+        fs::write(src.path().join("..data/app.yaml"), "hidden").unwrap();
+        let mut cfg = config(&src, &work);
+        cfg.include = vec!["**/*.yaml".into()];
+        cfg.exclude = vec!["conf/tmp".into()];
+        run(&test_logger(), &cfg).unwrap();
+        let dest = work.path().join("defaults");
+        // Copied verbatim without --render.
+        assert_eq!(
+            fs::read_to_string(dest.join("conf/app.yaml")).unwrap(),
+            "a: ${A}\n"
+        );
+        assert!(!dest.join("conf/tmp").exists());
+        assert!(!dest.join("conf/notes.txt").exists());
+        assert!(!dest.join("..data").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        let script = src.path().join("init.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+        run(&test_logger(), &config(&src, &work)).unwrap();
+        let meta = fs::metadata(work.path().join("defaults/init.sh")).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o750);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_symlink_loop() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        fs::create_dir(src.path().join("conf")).unwrap();
+        fs::write(src.path().join("conf/app.yaml"), "a").unwrap();
+        std::os::unix::fs::symlink("..", src.path().join("conf/up")).unwrap();
+        std::os::unix::fs::symlink(".", src.path().join("self")).unwrap();
+        run(&test_logger(), &config(&src, &work)).unwrap();
+        let dest = work.path().join("defaults");
+        assert_eq!(fs::read_to_string(dest.join("conf/app.yaml")).unwrap(), "a");
+        assert!(!dest.join("self").exists());
+        assert!(!dest.join("conf/up").exists());
+    }
+
+    #[test]
+    fn test_copy_render() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        fs::write(
+            src.path().join("app.conf"),
+            "name=${INITIUM_COPY_TEST_UNSET:-fallback}\n",
+        )
+        .unwrap();
+        fs::write(src.path().join("ports.tmpl"), "port={{ 8000 + 80 }}\n").unwrap();
+        fs::write(src.path().join("logo.bin"), [0xff, 0xfe, b'$', b'{']).unwrap();
+        let mut cfg = config(&src, &work);
+        cfg.render = true;
+        cfg.mode = "auto".into();
+        run(&test_logger(), &cfg).unwrap();
+        let dest = work.path().join("defaults");
+        assert_eq!(
+            fs::read_to_string(dest.join("app.conf")).unwrap(),
+            "name=fallback\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dest.join("ports.tmpl")).unwrap(),
+            "port=8080"
+        );
+        assert_eq!(
+            fs::read(dest.join("logo.bin")).unwrap(),
+            [0xff, 0xfe, b'$', b'{']
+        );
+    }
+
+    #[test]
+    fn test_copy_validation() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        let mut cfg = config(&src, &work);
+        cfg.mode = "mustache".into();
+        assert!(run(&test_logger(), &cfg).unwrap_err().contains("--mode"));
+        let mut cfg = config(&src, &work);
+        cfg.dest = "../outside".into();
+        let err = run(&test_logger(), &cfg).unwrap_err();
+        assert!(err.contains("path traversal detected"), "{}", err);
+        let mut cfg = config(&src, &work);
+        cfg.src = src.path().join("missing").to_string_lossy().to_string();
+        assert!(run(&test_logger(), &cfg)
+            .unwrap_err()
+            .contains("is not a directory"));
+    }
+}
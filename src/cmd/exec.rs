@@ -1,18 +1,1195 @@
+use crate::duration::format_duration;
+use crate::glob;
 use crate::logging::Logger;
-pub fn run(log: &Logger, args: &[String], workdir: &str) -> Result<(), String> {
+use crate::retry;
+use crate::safety;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+/// Options of `exec` besides the command.
+#[derive(Clone)]
+pub struct Config {
+    /// Working directory of the command; empty inherits it.
+    pub workdir: String,
+    /// Dotenv files whose variables are added to the command's environment.
+    pub env_files: Vec<String>,
+    /// Variables added after those of `env_files`.
+    pub env: Vec<(String, String)>,
+    /// Terminate the command when it runs longer.
+    pub timeout: Option<Duration>,
+    /// After a timeout, how long to wait between SIGTERM and SIGKILL.
+    pub kill_after: Duration,
+    /// Attempts and backoff for a command exiting non-zero.
+    pub retry: retry::Config,
+    /// Exit codes worth another attempt; empty retries any failing code.
+    pub retry_on_exit_codes: Vec<i32>,
+    /// Exit codes meaning success; empty means just 0.
+    pub ok_exit_codes: Vec<i32>,
+    /// User name whose uid and primary gid the command runs as; empty keeps
+    /// the current user.
+    pub user: String,
+    /// Run as this uid, overriding `user`.
+    pub uid: Option<u32>,
+    /// Run as this gid, overriding the primary group of `user`.
+    pub gid: Option<u32>,
+    /// File whose content is passed to the command on stdin.
+    pub stdin_file: String,
+    /// Environment variable whose value is passed to the command on stdin.
+    pub stdin_env: String,
+    /// Files receiving the raw stdout and stderr of the command as well,
+    /// relative to `workdir`; empty for none. They may be the same file.
+    pub stdout_file: String,
+    pub stderr_file: String,
+    /// Run the arguments, joined by spaces, as a `/bin/sh -c` script.
+    pub shell: bool,
+    /// Globs of inherited variable names passed to the command; empty
+    /// passes all.
+    pub env_allowlist: Vec<String>,
+    /// Globs of inherited variable names withheld from the command.
+    pub env_denylist: Vec<String>,
+    /// Added to the log lines of the command, telling concurrent commands
+    /// apart; empty for none.
+    pub label: String,
+    /// Create `workdir` when missing.
+    pub ensure_workdir: bool,
+    /// Permissions of a created `workdir`.
+    pub workdir_mode: Option<u32>,
+    /// Owner uid and gid of a created `workdir`.
+    pub workdir_owner: Option<(u32, Option<u32>)>,
+}
+/// The shell of `--shell`.
+const SHELL: &str = "/bin/sh";
+pub fn run(log: &Logger, args: &[String], cfg: &Config) -> Result<(), String> {
     if args.is_empty() {
         return Err("command is required after \"--\"".into());
     }
-    log.info("executing command", &[("command", &args[0])]);
-    let dir = if workdir.is_empty() {
+    let labeled = |kvs: &[(&'static str, String)]| {
+        let mut fields = Vec::new();
+        if !cfg.label.is_empty() {
+            fields.push(("label", cfg.label.clone()));
+        }
+        fields.extend_from_slice(kvs);
+        fields
+    };
+    let shell_args;
+    let args = if cfg.shell {
+        log.warn(
+            "shell interpretation enabled, running the command with /bin/sh -c",
+            &log_fields(&labeled(&[])),
+        );
+        shell_args = vec![SHELL.to_string(), "-c".into(), args.join(" ")];
+        &shell_args
+    } else {
+        args
+    };
+    let clear_env = !cfg.env_allowlist.is_empty() || !cfg.env_denylist.is_empty();
+    let mut env = if clear_env {
+        inherited_env(log, &cfg.env_allowlist, &cfg.env_denylist)
+    } else {
+        Vec::new()
+    };
+    for (key, value) in super::read_env_files(log, &cfg.env_files)?
+        .iter()
+        .chain(&cfg.env)
+    {
+        env.retain(|(k, _)| k != key);
+        env.push((key.clone(), value.clone()));
+    }
+    let (mut uid, mut gid) = (None, None);
+    if !cfg.user.is_empty() {
+        let (u, g) = lookup_user(&cfg.user)?;
+        (uid, gid) = (Some(u), Some(g));
+    }
+    uid = cfg.uid.or(uid);
+    gid = cfg.gid.or(gid);
+    let stdin = if !cfg.stdin_file.is_empty() {
+        Some(
+            std::fs::read(&cfg.stdin_file)
+                .map_err(|e| format!("reading --stdin-file '{}': {}", cfg.stdin_file, e))?,
+        )
+    } else if !cfg.stdin_env.is_empty() {
+        let value = std::env::var_os(&cfg.stdin_env)
+            .ok_or_else(|| format!("--stdin-env variable '{}' is not set", cfg.stdin_env))?;
+        Some(value.into_encoded_bytes())
+    } else {
+        None
+    };
+    let mut fields = labeled(&[("command", args[0].clone())]);
+    if !cfg.stdin_file.is_empty() {
+        fields.push(("stdin_file", cfg.stdin_file.clone()));
+    } else if !cfg.stdin_env.is_empty() {
+        fields.push(("stdin_env", cfg.stdin_env.clone()));
+    }
+    if let Some(u) = uid {
+        fields.push(("uid", u.to_string()));
+    }
+    if let Some(g) = gid {
+        fields.push(("gid", g.to_string()));
+    }
+    log.info("executing command", &log_fields(&fields));
+    let dir = if cfg.workdir.is_empty() {
         None
     } else {
-        Some(workdir)
+        check_workdir(log, cfg)?;
+        Some(cfg.workdir.as_str())
     };
-    let exit_code = super::run_command_in_dir(log, args, dir)?;
-    if exit_code != 0 {
-        return Err(format!("command exited with code {}", exit_code));
+    let stdout_file = create_output_file(&cfg.workdir, &cfg.stdout_file)?;
+    let stderr_file = if cfg.stderr_file == cfg.stdout_file {
+        // Shares the file offset, so the streams do not overwrite each other.
+        stdout_file
+            .as_ref()
+            .map(File::try_clone)
+            .transpose()
+            .map_err(|e| format!("opening output file {}: {}", cfg.stderr_file, e))?
+    } else {
+        create_output_file(&cfg.workdir, &cfg.stderr_file)?
+    };
+    let opts = super::CommandOptions {
+        dir,
+        env: &env,
+        timeout: cfg.timeout,
+        kill_after: cfg.kill_after,
+        clear_env,
+        uid,
+        gid,
+        stdin: stdin.as_deref(),
+        stdout_file: stdout_file.as_ref(),
+        stderr_file: stderr_file.as_ref(),
+        label: Some(cfg.label.as_str()).filter(|l| !l.is_empty()),
+    };
+    let mut attempt = 0;
+    loop {
+        let exit_code = super::run_command(log, args, &opts)?;
+        let ok = if cfg.ok_exit_codes.is_empty() {
+            exit_code == 0
+        } else {
+            cfg.ok_exit_codes.contains(&exit_code)
+        };
+        if ok {
+            if exit_code != 0 {
+                log.info(
+                    "command exited with an allowed code",
+                    &log_fields(&labeled(&[("exit_code", exit_code.to_string())])),
+                );
+            }
+            break;
+        }
+        if !cfg.retry_on_exit_codes.is_empty() && !cfg.retry_on_exit_codes.contains(&exit_code) {
+            return Err(format!(
+                "command exited with code {} (not in --retry-on-exit-codes)",
+                exit_code
+            ));
+        }
+        attempt += 1;
+        if attempt >= cfg.retry.max_attempts {
+            if attempt == 1 {
+                return Err(format!("command exited with code {}", exit_code));
+            }
+            return Err(format!(
+                "all {} attempts failed, last error: command exited with code {}",
+                attempt, exit_code
+            ));
+        }
+        let delay = retry::delay(&cfg.retry, attempt - 1);
+        log.warn(
+            "command failed, retrying",
+            &log_fields(&labeled(&[
+                ("exit_code", exit_code.to_string()),
+                ("attempt", attempt.to_string()),
+                ("delay", format_duration(delay)),
+            ])),
+        );
+        std::thread::sleep(delay);
+    }
+    log.info("command completed successfully", &log_fields(&labeled(&[])));
+    Ok(())
+}
+
+/// Fail clearly when `workdir` is not a directory, creating it first with
+/// `--ensure-workdir`.
+fn check_workdir(log: &Logger, cfg: &Config) -> Result<(), String> {
+    let path = Path::new(&cfg.workdir);
+    match std::fs::metadata(path) {
+        Ok(meta) if meta.is_dir() => return Ok(()),
+        Ok(_) => return Err(format!("workdir '{}' is not a directory", cfg.workdir)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && cfg.ensure_workdir => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(format!(
+                "workdir '{}' does not exist (use --ensure-workdir to create it)",
+                cfg.workdir
+            ))
+        }
+        Err(e) => return Err(format!("checking workdir '{}': {}", cfg.workdir, e)),
+    }
+    std::fs::create_dir_all(path)
+        .map_err(|e| format!("creating workdir '{}': {}", cfg.workdir, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Some(mode) = cfg.workdir_mode {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+                .map_err(|e| format!("setting mode of workdir '{}': {}", cfg.workdir, e))?;
+        }
+        if let Some((uid, gid)) = cfg.workdir_owner {
+            std::os::unix::fs::chown(path, Some(uid), gid)
+                .map_err(|e| format!("changing owner of workdir '{}': {}", cfg.workdir, e))?;
+        }
+    }
+    let mut fields = vec![("workdir", cfg.workdir.clone())];
+    if let Some(mode) = cfg.workdir_mode {
+        fields.push(("mode", format!("{:04o}", mode)));
+    }
+    if let Some((uid, gid)) = cfg.workdir_owner {
+        let owner = match gid {
+            Some(gid) => format!("{}:{}", uid, gid),
+            None => uid.to_string(),
+        };
+        fields.push(("owner", owner));
+    }
+    log.info("created workdir", &log_fields(&fields));
+    Ok(())
+}
+
+/// Parse an octal file mode such as `0750`.
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    let digits = mode.strip_prefix("0o").unwrap_or(mode);
+    match u32::from_str_radix(digits, 8) {
+        Ok(m) if m <= 0o7777 => Ok(m),
+        _ => Err(format!("'{}' is not an octal mode such as 0750", mode)),
+    }
+}
+
+/// Parse an owner `user[:group]`, where the user is a uid or a user name
+/// and the group a gid. A bare user name also sets its primary group.
+pub fn parse_owner(owner: &str) -> Result<(u32, Option<u32>), String> {
+    let (user, group) = match owner.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (owner, None),
+    };
+    let gid = group
+        .map(|g| {
+            g.parse::<u32>()
+                .map_err(|_| format!("group of '{}' must be a gid", owner))
+        })
+        .transpose()?;
+    if let Ok(uid) = user.parse::<u32>() {
+        return Ok((uid, gid));
+    }
+    if user.is_empty() {
+        return Err(format!("user of '{}' is empty", owner));
+    }
+    let (uid, primary_gid) = lookup_user(user)?;
+    Ok((uid, gid.or(Some(primary_gid))))
+}
+
+pub(crate) fn log_fields<'a>(fields: &'a [(&'static str, String)]) -> Vec<(&'static str, &'a str)> {
+    fields.iter().map(|(k, v)| (*k, v.as_str())).collect()
+}
+
+/// Run the `--cmd` command lines in order or, when `parallel`, all at once,
+/// each labelled with its position and program.
+pub fn run_commands(
+    log: &Logger,
+    cmds: &[String],
+    parallel: bool,
+    base: &Config,
+) -> Result<(), String> {
+    let mut jobs = Vec::new();
+    for (i, line) in cmds.iter().enumerate() {
+        // A --shell script is handed to the shell as it is.
+        let args = if base.shell {
+            vec![line.clone()]
+        } else {
+            shlex::split(line)
+                .ok_or_else(|| format!("invalid --cmd '{}': unbalanced quotes", line))?
+        };
+        let Some(program) = args.first() else {
+            return Err(format!("--cmd {} is empty", i + 1));
+        };
+        let mut cfg = base.clone();
+        cfg.label = format!(
+            "{}:{}",
+            i + 1,
+            program.rsplit('/').next().unwrap_or(program)
+        );
+        jobs.push((args, cfg));
+    }
+    if !parallel {
+        for (args, cfg) in &jobs {
+            run(log, args, cfg).map_err(|e| format!("command {} failed: {}", cfg.label, e))?;
+        }
+        return Ok(());
+    }
+    log.info(
+        "running commands concurrently",
+        &[("commands", &jobs.len().to_string())],
+    );
+    let results = run_concurrently(log, &jobs);
+    let failures: Vec<String> = jobs
+        .iter()
+        .zip(results)
+        .filter_map(|((_, cfg), result)| result.err().map(|e| format!("{}: {}", cfg.label, e)))
+        .collect();
+    if !failures.is_empty() {
+        return Err(format!(
+            "{} of {} commands failed: {}",
+            failures.len(),
+            jobs.len(),
+            failures.join("; ")
+        ));
+    }
+    log.info(
+        "all commands completed",
+        &[("commands", &jobs.len().to_string())],
+    );
+    Ok(())
+}
+
+/// Run each command on its own thread, returning the results in order.
+fn run_concurrently(log: &Logger, jobs: &[(Vec<String>, Config)]) -> Vec<Result<(), String>> {
+    std::thread::scope(|s| {
+        let handles: Vec<_> = jobs
+            .iter()
+            .map(|(args, cfg)| s.spawn(move || run(log, args, cfg)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join()
+                    .unwrap_or_else(|_| Err("command thread panicked".into()))
+            })
+            .collect()
+    })
+}
+
+/// The variables of initium's environment passed on by the allowlist and
+/// denylist globs.
+fn inherited_env(log: &Logger, allow: &[String], deny: &[String]) -> Vec<(String, String)> {
+    let mut kept = Vec::new();
+    let mut removed = 0;
+    for (key, value) in std::env::vars_os() {
+        let (Ok(key), Ok(value)) = (key.into_string(), value.into_string()) else {
+            removed += 1;
+            continue;
+        };
+        let allowed = allow.is_empty() || glob::matches_any(allow, &key);
+        if allowed && !glob::matches_any(deny, &key) {
+            kept.push((key, value));
+        } else {
+            log.debug("withholding environment variable", &[("name", &key)]);
+            removed += 1;
+        }
+    }
+    log.info(
+        "filtered environment",
+        &[
+            ("kept", &kept.len().to_string()),
+            ("removed", &removed.to_string()),
+        ],
+    );
+    kept
+}
+
+/// Run the parallel steps of `group` concurrently; the group fails when a
+/// step without `continue_on_error` does.
+fn run_group(log: &Logger, group: &Step, configs: &[Config]) -> Result<(), String> {
+    let jobs: Vec<_> = group
+        .parallel
+        .iter()
+        .zip(configs)
+        .map(|(step, cfg)| (step.command.clone(), cfg.clone()))
+        .collect();
+    let mut failures = Vec::new();
+    for (step, result) in group.parallel.iter().zip(run_concurrently(log, &jobs)) {
+        match result {
+            Ok(()) => {}
+            Err(e) if step.continue_on_error => log.warn(
+                "step failed, continuing",
+                &[("step", step.name.as_str()), ("error", &e)],
+            ),
+            Err(e) => failures.push(format!("step '{}' failed: {}", step.name, e)),
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+/// Create (or truncate) the file at `path`, which must stay inside
+/// `workdir`, with its parent directories; `None` for an empty path. With
+/// retries, it holds the output of every attempt.
+fn create_output_file(workdir: &str, path: &str) -> Result<Option<File>, String> {
+    if path.is_empty() {
+        return Ok(None);
+    }
+    let path = safety::validate_file_path(workdir, path)
+        .map_err(|e| format!("output file '{}': {}", path, e))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("creating directory {}: {}", parent.display(), e))?;
+    }
+    File::create(&path)
+        .map(Some)
+        .map_err(|e| format!("creating output file {}: {}", path.display(), e))
+}
+
+/// A `--steps` file: commands run one after another.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepsFile {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    name: String,
+    /// Program and arguments, run without a shell.
+    #[serde(default)]
+    command: Vec<String>,
+    /// Steps run concurrently instead of `command`, with this step's
+    /// settings as their defaults.
+    #[serde(default)]
+    parallel: Vec<Step>,
+    #[serde(default)]
+    workdir: Option<String>,
+    #[serde(default)]
+    env: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    timeout: Option<String>,
+    #[serde(default)]
+    retry: Option<StepRetry>,
+    /// Run `command`, joined by spaces, with `/bin/sh -c`.
+    #[serde(default)]
+    shell: Option<bool>,
+    #[serde(default)]
+    ok_exit_codes: Option<Vec<i32>>,
+    #[serde(default)]
+    stdout_file: Option<String>,
+    #[serde(default)]
+    stderr_file: Option<String>,
+    /// Log a failure of the step and go on with the next one.
+    #[serde(default)]
+    continue_on_error: bool,
+}
+
+/// Retry policy of a step; unset fields come from the command line.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepRetry {
+    max_attempts: Option<u32>,
+    initial_delay: Option<String>,
+    max_delay: Option<String>,
+    backoff_factor: Option<f64>,
+    jitter: Option<f64>,
+    on_exit_codes: Option<Vec<i32>>,
+}
+
+impl Step {
+    /// `base` with the settings of this step applied.
+    fn config(&self, base: &Config) -> Result<Config, String> {
+        let duration = |field: &str, value: &str| {
+            crate::duration::parse_duration(value).map_err(|e| format!("{}: {}", field, e))
+        };
+        let mut cfg = base.clone();
+        if let Some(workdir) = &self.workdir {
+            cfg.workdir = workdir.clone();
+        }
+        for (key, value) in &self.env {
+            let value = match value {
+                serde_yaml::Value::String(s) => s.clone(),
+                serde_yaml::Value::Number(n) => n.to_string(),
+                serde_yaml::Value::Bool(b) => b.to_string(),
+                _ => return Err(format!("env {}: expected a string, number or bool", key)),
+            };
+            cfg.env.push((key.clone(), value));
+        }
+        if let Some(shell) = self.shell {
+            cfg.shell = shell;
+        }
+        if let Some(codes) = &self.ok_exit_codes {
+            cfg.ok_exit_codes = codes.clone();
+        }
+        if let Some(path) = &self.stdout_file {
+            cfg.stdout_file = path.clone();
+        }
+        if let Some(path) = &self.stderr_file {
+            cfg.stderr_file = path.clone();
+        }
+        if let Some(timeout) = &self.timeout {
+            cfg.timeout = Some(duration("timeout", timeout)?);
+        }
+        if let Some(retry) = &self.retry {
+            if let Some(n) = retry.max_attempts {
+                cfg.retry.max_attempts = n;
+            }
+            if let Some(d) = &retry.initial_delay {
+                cfg.retry.initial_delay = duration("retry.initial_delay", d)?;
+            }
+            if let Some(d) = &retry.max_delay {
+                cfg.retry.max_delay = duration("retry.max_delay", d)?;
+            }
+            if let Some(f) = retry.backoff_factor {
+                cfg.retry.backoff_factor = f;
+            }
+            if let Some(j) = retry.jitter {
+                cfg.retry.jitter_fraction = j;
+            }
+            if let Some(codes) = &retry.on_exit_codes {
+                cfg.retry_on_exit_codes = codes.clone();
+            }
+            cfg.retry
+                .validate()
+                .map_err(|e| format!("invalid retry: {}", e))?;
+        }
+        Ok(cfg)
+    }
+}
+
+impl Step {
+    /// The configs of the commands of this step: its own, or those of its
+    /// parallel steps.
+    fn prepare(&self, base: &Config) -> Result<Vec<Config>, String> {
+        let cfg = self.config(base)?;
+        if self.parallel.is_empty() {
+            if self.command.is_empty() {
+                return Err("command is required".into());
+            }
+            return Ok(vec![cfg]);
+        }
+        if !self.command.is_empty() {
+            return Err("a step has either command or parallel, not both".into());
+        }
+        self.parallel
+            .iter()
+            .map(|step| {
+                if !step.parallel.is_empty() {
+                    return Err(format!(
+                        "step '{}': parallel groups cannot be nested",
+                        step.name
+                    ));
+                }
+                if step.command.is_empty() {
+                    return Err(format!("step '{}': command is required", step.name));
+                }
+                let mut member = step
+                    .config(&cfg)
+                    .map_err(|e| format!("step '{}': {}", step.name, e))?;
+                member.label = step.name.clone();
+                Ok(member)
+            })
+            .collect()
+    }
+}
+
+/// Run the steps of the YAML file at `path` in order, each like a single
+/// `exec` with `base` as the defaults. All steps are checked before the
+/// first one runs.
+pub fn run_steps(log: &Logger, path: &str, base: &Config) -> Result<(), String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("reading steps file '{}': {}", path, e))?;
+    let file: StepsFile = serde_yaml::from_str(&content)
+        .map_err(|e| format!("parsing steps file '{}': {}", path, e))?;
+    if file.steps.is_empty() {
+        return Err(format!("steps file '{}' has no steps", path));
+    }
+    let configs = file
+        .steps
+        .iter()
+        .map(|step| {
+            step.prepare(base)
+                .map_err(|e| format!("step '{}': {}", step.name, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let total = file.steps.len().to_string();
+    let mut failed = 0;
+    for (i, (step, cfg)) in file.steps.iter().zip(&configs).enumerate() {
+        let index = (i + 1).to_string();
+        log.info(
+            "running step",
+            &[
+                ("step", step.name.as_str()),
+                ("index", &index),
+                ("total", &total),
+            ],
+        );
+        let started = std::time::Instant::now();
+        let result = if step.parallel.is_empty() {
+            run(log, &step.command, &cfg[0])
+        } else {
+            run_group(log, step, cfg)
+        };
+        match result {
+            Ok(()) => log.info(
+                "step completed",
+                &[
+                    ("step", step.name.as_str()),
+                    ("duration", &format_duration(started.elapsed())),
+                ],
+            ),
+            Err(e) if step.continue_on_error => {
+                failed += 1;
+                log.warn(
+                    "step failed, continuing",
+                    &[("step", step.name.as_str()), ("error", &e)],
+                );
+            }
+            Err(e) => return Err(format!("step '{}' failed: {}", step.name, e)),
+        }
     }
-    log.info("command completed successfully", &[]);
+    log.info(
+        "all steps completed",
+        &[("steps", &total), ("failed", &failed.to_string())],
+    );
     Ok(())
 }
+
+/// The uid and primary gid of the user `name` from the user database.
+#[cfg(unix)]
+fn lookup_user(name: &str) -> Result<(u32, u32), String> {
+    let c_name =
+        std::ffi::CString::new(name).map_err(|_| format!("invalid user name '{}'", name))?;
+    let mut buf = vec![0u8; 4096];
+    loop {
+        // SAFETY: passwd is plain old data; getpwnam_r fills it with
+        // pointers into `buf`, which outlives every use of them.
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        let rc = unsafe {
+            libc::getpwnam_r(
+                c_name.as_ptr(),
+                &mut pwd,
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        if rc == libc::ERANGE && buf.len() < 1 << 20 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if rc != 0 {
+            return Err(format!(
+                "looking up user '{}': {}",
+                name,
+                std::io::Error::from_raw_os_error(rc)
+            ));
+        }
+        if result.is_null() {
+            return Err(format!("user '{}' not found", name));
+        }
+        return Ok((pwd.pw_uid, pwd.pw_gid));
+    }
+}
+#[cfg(not(unix))]
+fn lookup_user(_name: &str) -> Result<(u32, u32), String> {
+    Err("--user is only supported on unix".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_logger() -> Logger {
+        Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        )
+    }
+
+    fn test_config(max_attempts: u32, retry_on_exit_codes: Vec<i32>) -> Config {
+        Config {
+            workdir: String::new(),
+            env_files: vec![],
+            env: vec![],
+            timeout: None,
+            kill_after: Duration::from_secs(1),
+            retry: retry::Config {
+                max_attempts,
+                initial_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(10),
+                backoff_factor: 1.0,
+                jitter_fraction: 0.0,
+            },
+            retry_on_exit_codes,
+            ok_exit_codes: vec![0],
+            user: String::new(),
+            uid: None,
+            gid: None,
+            stdin_file: String::new(),
+            stdin_env: String::new(),
+            stdout_file: String::new(),
+            stderr_file: String::new(),
+            shell: false,
+            env_allowlist: vec![],
+            env_denylist: vec![],
+            label: String::new(),
+            ensure_workdir: false,
+            workdir_mode: None,
+            workdir_owner: None,
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_retries_exit_codes() {
+        let log = test_logger();
+        let dir = tempfile::TempDir::new().unwrap();
+        let counter = dir.path().join("attempts");
+        // Appends a line per attempt, exiting `code` until the third one.
+        let flaky = |code: i32| {
+            let script = format!(
+                "echo x >> {0}; [ $(wc -l < {0}) -ge 3 ] || exit {1}",
+                counter.display(),
+                code
+            );
+            vec!["sh".to_string(), "-c".into(), script]
+        };
+        let attempts = || {
+            let n = std::fs::read_to_string(&counter).unwrap().lines().count();
+            std::fs::remove_file(&counter).unwrap();
+            n
+        };
+
+        run(&log, &flaky(75), &test_config(5, vec![])).unwrap();
+        assert_eq!(attempts(), 3);
+
+        run(&log, &flaky(75), &test_config(5, vec![75, 69])).unwrap();
+        assert_eq!(attempts(), 3);
+
+        let err = run(&log, &flaky(75), &test_config(2, vec![])).unwrap_err();
+        assert_eq!(
+            err,
+            "all 2 attempts failed, last error: command exited with code 75"
+        );
+        assert_eq!(attempts(), 2);
+
+        let err = run(&log, &flaky(1), &test_config(5, vec![75])).unwrap_err();
+        assert!(err.contains("not in --retry-on-exit-codes"), "{}", err);
+        assert_eq!(attempts(), 1);
+
+        let err = run(&log, &flaky(1), &test_config(1, vec![])).unwrap_err();
+        assert_eq!(err, "command exited with code 1");
+        assert_eq!(attempts(), 1);
+
+        // An allowed non-zero code is a success and not retried.
+        let mut cfg = test_config(5, vec![]);
+        cfg.ok_exit_codes = vec![0, 1];
+        run(&log, &flaky(1), &cfg).unwrap();
+        assert_eq!(attempts(), 1);
+        cfg.ok_exit_codes = vec![2];
+        cfg.retry.max_attempts = 1;
+        let err = run(&log, &flaky(0), &cfg).unwrap_err();
+        assert_eq!(err, "command exited with code 0");
+        assert_eq!(attempts(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_lookup_user() {
+        assert_eq!(lookup_user("root").unwrap(), (0, 0));
+        assert_eq!(
+            lookup_user("no-such-user-initium").unwrap_err(),
+            "user 'no-such-user-initium' not found"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_as_user() {
+        // Switching users needs root.
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        let log = test_logger();
+        let check = |expected: &str| {
+            let script = format!("[ \"$(id -u):$(id -g)\" = {} ]", expected);
+            vec!["sh".to_string(), "-c".into(), script]
+        };
+        let mut cfg = test_config(1, vec![]);
+        cfg.uid = Some(65534);
+        cfg.gid = Some(65533);
+        run(&log, &check("65534:65533"), &cfg).unwrap();
+        cfg.uid = None;
+        cfg.gid = None;
+        cfg.user = "root".into();
+        run(&log, &check("0:0"), &cfg).unwrap();
+        cfg.gid = Some(65534);
+        run(&log, &check("0:65534"), &cfg).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_steps() {
+        let log = test_logger();
+        let dir = tempfile::TempDir::new().unwrap();
+        let out = dir.path().join("out");
+        let steps = dir.path().join("steps.yaml");
+        let write_steps = |yaml: &str| {
+            std::fs::write(&steps, yaml.replace("OUT", out.to_str().unwrap())).unwrap()
+        };
+        let run_steps = || super::run_steps(&log, steps.to_str().unwrap(), &test_config(1, vec![]));
+
+        write_steps(
+            r#"
+steps:
+  - name: first
+    command: ["sh", "-c", "echo \"first $GREETING $PORT\" >> OUT"]
+    env:
+      GREETING: hello
+      PORT: 5432
+  - name: flaky
+    command: ["sh", "-c", "exit 3"]
+    continue_on_error: true
+  - name: last
+    command: ["sh", "-c", "pwd >> OUT"]
+    workdir: /
+"#,
+        );
+        run_steps().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "first hello 5432\n/\n"
+        );
+        std::fs::remove_file(&out).unwrap();
+
+        write_steps(
+            r#"
+steps:
+  - name: fails
+    command: ["sh", "-c", "exit 3"]
+  - name: never
+    command: ["sh", "-c", "echo never >> OUT"]
+"#,
+        );
+        let err = run_steps().unwrap_err();
+        assert_eq!(err, "step 'fails' failed: command exited with code 3");
+        assert!(!out.exists());
+
+        // Invalid steps are reported before any step runs.
+        write_steps(
+            r#"
+steps:
+  - name: first
+    command: ["sh", "-c", "echo first >> OUT"]
+  - name: broken
+    command: ["true"]
+    timeout: soon
+"#,
+        );
+        let err = run_steps().unwrap_err();
+        assert!(err.starts_with("step 'broken': timeout:"), "{}", err);
+        assert!(!out.exists());
+
+        write_steps("steps:\n  - name: x\n    command: [\"true\"]\n    retries: 3\n");
+        let err = run_steps().unwrap_err();
+        assert!(err.contains("unknown field `retries`"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_stdin() {
+        let log = test_logger();
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("input");
+        std::fs::write(&input, "from file\n").unwrap();
+        let expect = |expected: &str| {
+            let script = format!("[ \"$(cat)\" = '{}' ]", expected);
+            vec!["sh".to_string(), "-c".into(), script]
+        };
+
+        let mut cfg = test_config(1, vec![]);
+        run(&log, &expect(""), &cfg).unwrap();
+
+        cfg.stdin_file = input.to_str().unwrap().into();
+        run(&log, &expect("from file"), &cfg).unwrap();
+
+        cfg.stdin_file = String::new();
+        cfg.stdin_env = "INITIUM_TEST_STDIN_PASSPHRASE".into();
+        let err = run(&log, &expect(""), &cfg).unwrap_err();
+        assert_eq!(
+            err,
+            "--stdin-env variable 'INITIUM_TEST_STDIN_PASSPHRASE' is not set"
+        );
+        std::env::set_var("INITIUM_TEST_STDIN_PASSPHRASE", "s3cret");
+        run(&log, &expect("s3cret"), &cfg).unwrap();
+
+        // A command not reading its input still succeeds.
+        std::fs::write(&input, vec![b'x'; 1 << 20]).unwrap();
+        cfg.stdin_file = input.to_str().unwrap().into();
+        cfg.stdin_env = String::new();
+        run(&log, &["true".to_string()], &cfg).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_output_files() {
+        let log = test_logger();
+        let dir = tempfile::TempDir::new().unwrap();
+        let script = vec![
+            "sh".to_string(),
+            "-c".into(),
+            "printf 'out 1\\nout 2'; echo err >&2".into(),
+        ];
+        let mut cfg = test_config(1, vec![]);
+        cfg.workdir = dir.path().to_str().unwrap().into();
+        cfg.stdout_file = "logs/stdout.txt".into();
+        cfg.stderr_file = "logs/stderr.txt".into();
+        run(&log, &script, &cfg).unwrap();
+        let read =
+            |name: &str| std::fs::read_to_string(dir.path().join("logs").join(name)).unwrap();
+        assert_eq!(read("stdout.txt"), "out 1\nout 2");
+        assert_eq!(read("stderr.txt"), "err\n");
+
+        // Both streams into one file, truncated by the next run.
+        cfg.stderr_file = "logs/stdout.txt".into();
+        let script = vec![
+            "sh".to_string(),
+            "-c".into(),
+            "echo out 1; echo err >&2; echo out 2".into(),
+        ];
+        run(&log, &script, &cfg).unwrap();
+        // Lines of the two streams may interleave.
+        let both = read("stdout.txt");
+        let mut lines: Vec<&str> = both.lines().collect();
+        lines.sort();
+        assert_eq!(lines, ["err", "out 1", "out 2"], "{}", both);
+
+        // Output files must stay inside the workdir.
+        let outside = dir.path().join("outside.txt");
+        for path in [outside.to_str().unwrap(), "../outside.txt"] {
+            cfg.stdout_file = path.into();
+            let err = run(&log, &script, &cfg).unwrap_err();
+            assert!(err.contains("output file"), "{}", err);
+        }
+        assert!(!outside.exists());
+        cfg.workdir = String::new();
+        cfg.stdout_file = "stdout.txt".into();
+        let err = run(&log, &script, &cfg).unwrap_err();
+        assert!(err.contains("workdir must not be empty"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_shell() {
+        let log = test_logger();
+        let dir = tempfile::TempDir::new().unwrap();
+        let out = dir.path().join("out");
+        let args: Vec<String> = ["echo", "a", "&&", "echo", "b", ">", out.to_str().unwrap()]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut cfg = test_config(1, vec![]);
+        cfg.shell = true;
+        run(&log, &args, &cfg).unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "b\n");
+
+        // Without --shell, the operators are plain arguments of echo.
+        std::fs::remove_file(&out).unwrap();
+        cfg.shell = false;
+        run(&log, &args, &cfg).unwrap();
+        assert!(!out.exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_env_filters() {
+        let log = test_logger();
+        std::env::set_var("INITIUM_TEST_FILTER_TOKEN", "t");
+        std::env::set_var("APP_TEST_FILTER_NAME", "app");
+        let dir = tempfile::TempDir::new().unwrap();
+        let env_file = dir.path().join("extra.env");
+        std::fs::write(&env_file, "INITIUM_TEST_FILTER_EXTRA=e\n").unwrap();
+        let expect = |token: &str, name: &str| {
+            let script = format!(
+                "[ \"${{INITIUM_TEST_FILTER_TOKEN-unset}}:${{APP_TEST_FILTER_NAME-unset}}:$INITIUM_TEST_FILTER_EXTRA\" = '{}:{}:e' ]",
+                token, name
+            );
+            vec!["/bin/sh".to_string(), "-c".into(), script]
+        };
+        let mut cfg = test_config(1, vec![]);
+        cfg.env_files = vec![env_file.to_str().unwrap().into()];
+        run(&log, &expect("t", "app"), &cfg).unwrap();
+
+        // Variables of --env-file are passed whatever the filters.
+        cfg.env_denylist = vec!["INITIUM_*".into()];
+        run(&log, &expect("unset", "app"), &cfg).unwrap();
+
+        cfg.env_denylist = vec![];
+        cfg.env_allowlist = vec!["INITIUM_TEST_*".into()];
+        run(&log, &expect("t", "unset"), &cfg).unwrap();
+
+        cfg.env_denylist = vec!["*_TOKEN".into()];
+        run(&log, &expect("unset", "unset"), &cfg).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_commands_parallel() {
+        use std::io::Write;
+        #[derive(Clone)]
+        struct SharedBuf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl Write for SharedBuf {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let buf = SharedBuf(Default::default());
+        let log = Logger::new(Box::new(buf.clone()), true, crate::logging::Level::Info);
+        let dir = tempfile::TempDir::new().unwrap();
+        let flag = dir.path().join("flag");
+        // The first command only finishes once the second one has run.
+        let cmds = vec![
+            format!(
+                "sh -c 'while [ ! -f {} ]; do sleep 0.05; done; echo waited'",
+                flag.display()
+            ),
+            format!("touch {}", flag.display()),
+        ];
+        let mut cfg = test_config(1, vec![]);
+        cfg.timeout = Some(Duration::from_secs(10));
+        run_commands(&log, &cmds, true, &cfg).unwrap();
+        let output = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = output
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .find(|v: &serde_json::Value| v["msg"] == "waited")
+            .unwrap();
+        assert_eq!(line["label"], "1:sh");
+
+        let cmds = vec![
+            "sh -c 'exit 3'".to_string(),
+            "true".into(),
+            "/bin/false".into(),
+        ];
+        let err = run_commands(&log, &cmds, true, &cfg).unwrap_err();
+        assert_eq!(
+            err,
+            "2 of 3 commands failed: 1:sh: command exited with code 3; 3:false: command exited with code 1"
+        );
+        let err = run_commands(&log, &cmds, false, &cfg).unwrap_err();
+        assert_eq!(err, "command 1:sh failed: command exited with code 3");
+        let err = run_commands(&log, &["'unbalanced".into()], false, &cfg).unwrap_err();
+        assert!(err.contains("unbalanced quotes"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_steps_parallel_group() {
+        let log = test_logger();
+        let dir = tempfile::TempDir::new().unwrap();
+        let steps = dir.path().join("steps.yaml");
+        let path = steps.to_str().unwrap().to_string();
+        let mut cfg = test_config(1, vec![]);
+        cfg.timeout = Some(Duration::from_secs(10));
+        std::fs::write(
+            &steps,
+            format!(
+                r#"
+steps:
+  - name: warm up
+    workdir: {0}
+    parallel:
+      - name: waiter
+        command: ["sh", "-c", "while [ ! -f flag ]; do sleep 0.05; done"]
+      - name: flagger
+        command: ["touch", "flag"]
+      - name: optional
+        command: ["false"]
+        continue_on_error: true
+  - name: after
+    command: ["test", "-f", "{0}/flag"]
+"#,
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+        run_steps(&log, &path, &cfg).unwrap();
+
+        std::fs::write(
+            &steps,
+            r#"
+steps:
+  - name: group
+    parallel:
+      - name: ok
+        command: ["true"]
+      - name: bad
+        command: ["false"]
+"#,
+        )
+        .unwrap();
+        let err = run_steps(&log, &path, &cfg).unwrap_err();
+        assert_eq!(
+            err,
+            "step 'group' failed: step 'bad' failed: command exited with code 1"
+        );
+
+        std::fs::write(
+            &steps,
+            "steps:\n  - name: group\n    command: [\"true\"]\n    parallel:\n      - name: x\n        command: [\"true\"]\n",
+        )
+        .unwrap();
+        let err = run_steps(&log, &path, &cfg).unwrap_err();
+        assert_eq!(
+            err,
+            "step 'group': a step has either command or parallel, not both"
+        );
+    }
+
+    #[test]
+    fn test_parse_mode_and_owner() {
+        assert_eq!(parse_mode("0750").unwrap(), 0o750);
+        assert_eq!(parse_mode("755").unwrap(), 0o755);
+        assert_eq!(parse_mode("0o2775").unwrap(), 0o2775);
+        assert!(parse_mode("0799").is_err());
+        assert!(parse_mode("77777").is_err());
+        assert_eq!(parse_owner("1000:2000").unwrap(), (1000, Some(2000)));
+        assert_eq!(parse_owner("1000").unwrap(), (1000, None));
+        assert!(parse_owner("1000:staff").is_err());
+        assert!(parse_owner(":1000").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_ensure_workdir() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        let log = test_logger();
+        let dir = tempfile::TempDir::new().unwrap();
+        let workdir = dir.path().join("a/b");
+        let mut cfg = test_config(1, vec![]);
+        cfg.workdir = workdir.to_str().unwrap().into();
+        let err = run(&log, &["true".to_string()], &cfg).unwrap_err();
+        assert!(
+            err.ends_with("does not exist (use --ensure-workdir to create it)"),
+            "{}",
+            err
+        );
+
+        cfg.ensure_workdir = true;
+        cfg.workdir_mode = Some(0o750);
+        if unsafe { libc::geteuid() } == 0 {
+            cfg.workdir_owner = Some((65534, Some(65534)));
+        }
+        run(&log, &["true".to_string()], &cfg).unwrap();
+        let meta = std::fs::metadata(&workdir).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o750);
+        if cfg.workdir_owner.is_some() {
+            assert_eq!((meta.uid(), meta.gid()), (65534, 65534));
+        }
+
+        std::fs::write(dir.path().join("file"), "").unwrap();
+        cfg.workdir = dir.path().join("file").to_str().unwrap().into();
+        let err = run(&log, &["true".to_string()], &cfg).unwrap_err();
+        assert!(err.ends_with("is not a directory"), "{}", err);
+    }
+}
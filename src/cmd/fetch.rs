@@ -1,8 +1,7 @@
 use crate::logging::Logger;
 use crate::retry;
 use crate::safety;
-use std::fs;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::time::{Duration, Instant};
 pub struct Config {
     pub url: String,
@@ -13,6 +12,8 @@ pub struct Config {
     pub follow_redirects: bool,
     pub allow_cross_site_redirects: bool,
     pub timeout: Duration,
+    pub rate_limit: Option<u64>,
+    pub decode_base64: bool,
 }
 impl Config {
     pub fn validate(&self) -> Result<(), String> {
@@ -25,6 +26,9 @@ impl Config {
         if self.allow_cross_site_redirects && !self.follow_redirects {
             return Err("--allow-cross-site-redirects requires --follow-redirects".into());
         }
+        if self.rate_limit == Some(0) {
+            return Err("--rate-limit must be > 0".into());
+        }
         Ok(())
     }
 }
@@ -52,6 +56,16 @@ pub fn run(log: &Logger, cfg: &Config, retry_cfg: &retry::Config) -> Result<(),
 }
 fn do_fetch(cfg: &Config) -> Result<(), String> {
     let out_path = safety::validate_file_path(&cfg.workdir, &cfg.output)?;
+    let builder = ureq::AgentBuilder::new().redirects(if cfg.follow_redirects { 10 } else { 0 });
+    // A throttled body takes as long as it takes, so the timeout then bounds
+    // connecting and each read rather than the whole request.
+    let builder = if cfg.rate_limit.is_some() {
+        builder
+            .timeout_connect(cfg.timeout)
+            .timeout_read(cfg.timeout)
+    } else {
+        builder.timeout(cfg.timeout)
+    };
     let agent = if cfg.insecure_tls {
         use std::sync::Arc;
         let crypto_provider = rustls::crypto::ring::default_provider();
@@ -61,16 +75,9 @@ fn do_fetch(cfg: &Config) -> Result<(), String> {
             .dangerous()
             .with_custom_certificate_verifier(Arc::new(super::wait_for::NoVerifier))
             .with_no_client_auth();
-        ureq::AgentBuilder::new()
-            .timeout(cfg.timeout)
-            .tls_config(Arc::new(tls_config))
-            .redirects(if cfg.follow_redirects { 10 } else { 0 })
-            .build()
+        builder.tls_config(Arc::new(tls_config)).build()
     } else {
-        ureq::AgentBuilder::new()
-            .timeout(cfg.timeout)
-            .redirects(if cfg.follow_redirects { 10 } else { 0 })
-            .build()
+        builder.build()
     };
     let mut req = agent.get(&cfg.url);
     if !cfg.auth_env.is_empty() {
@@ -91,13 +98,251 @@ fn do_fetch(cfg: &Config) -> Result<(), String> {
     if !(200..300).contains(&status) {
         return Err(format!("HTTP {} returned status {}", cfg.url, status));
     }
-    let mut body = Vec::new();
-    resp.into_reader()
-        .read_to_end(&mut body)
-        .map_err(|e| format!("reading response body: {}", e))?;
-    if let Some(parent) = out_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("creating output directory: {}", e))?;
+    let mut reader = resp.into_reader();
+    if let Some(bytes_per_sec) = cfg.rate_limit {
+        reader = Box::new(RateLimitedReader::new(reader, bytes_per_sec));
+    }
+    // Stream into a temp file that is only renamed over the target once the
+    // whole body has arrived; a failed attempt leaves the target untouched.
+    let mut file = safety::AtomicFile::create(&out_path)?;
+    if cfg.decode_base64 {
+        let mut body = String::new();
+        reader
+            .read_to_string(&mut body)
+            .map_err(|e| format!("reading response body: {}", e))?;
+        let decoded = crate::render::decode_base64(&body)
+            .map_err(|e| format!("decoding response body: {}", e))?;
+        file.write_all(&decoded)
+            .map_err(|e| format!("writing output {:?}: {}", out_path, e))?;
+    } else {
+        std::io::copy(&mut reader, &mut file)
+            .map_err(|e| format!("reading response body: {}", e))?;
+    }
+    file.commit()
+}
+
+/// Parse a bandwidth limit such as `10MB/s`, `512KiB`, or `1000000` into bytes
+/// per second. Decimal (`KB`, `MB`, `GB`) and binary (`KiB`, `MiB`, `GiB`)
+/// suffixes are accepted; the `/s` suffix is optional.
+pub fn parse_rate_limit(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty rate limit string".into());
+    }
+    let s = s.strip_suffix("/s").unwrap_or(s);
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (num_str, unit) = s.split_at(split);
+    let num: f64 = num_str.parse().map_err(|_| {
+        format!(
+            "invalid rate limit {:?}: expected a number with optional unit (e.g. 10MB/s)",
+            s
+        )
+    })?;
+    let multiplier: f64 = match unit.trim() {
+        "" | "B" => 1.0,
+        "K" | "KB" => 1e3,
+        "M" | "MB" => 1e6,
+        "G" | "GB" => 1e9,
+        "Ki" | "KiB" => 1024.0,
+        "Mi" | "MiB" => 1024.0 * 1024.0,
+        "Gi" | "GiB" => 1024.0 * 1024.0 * 1024.0,
+        other => {
+            return Err(format!(
+                "invalid rate limit unit {:?} (supported: B, KB, MB, GB, KiB, MiB, GiB)",
+                other
+            ))
+        }
+    };
+    let bytes = (num * multiplier).round();
+    if bytes < 1.0 {
+        return Err(format!(
+            "rate limit {:?} must be at least 1 byte per second",
+            s
+        ));
+    }
+    Ok(bytes as u64)
+}
+
+/// Reader that paces reads so the average throughput since the first read
+/// does not exceed `bytes_per_sec`.
+struct RateLimitedReader<R> {
+    inner: R,
+    bytes_per_sec: u64,
+    started: Option<Instant>,
+    transferred: u64,
+}
+
+impl<R: Read> RateLimitedReader<R> {
+    fn new(inner: R, bytes_per_sec: u64) -> Self {
+        Self {
+            inner,
+            bytes_per_sec,
+            started: None,
+            transferred: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for RateLimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        // Cap each read at roughly a tenth of a second's worth of data so the
+        // pacing stays smooth instead of bursting a full buffer at once.
+        let chunk = ((self.bytes_per_sec / 10).max(1) as usize).min(buf.len());
+        let n = self.inner.read(&mut buf[..chunk])?;
+        self.transferred += n as u64;
+        let expected = Duration::from_secs_f64(self.transferred as f64 / self.bytes_per_sec as f64);
+        let elapsed = started.elapsed();
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limit_units() {
+        assert_eq!(parse_rate_limit("100").unwrap(), 100);
+        assert_eq!(parse_rate_limit("100B/s").unwrap(), 100);
+        assert_eq!(parse_rate_limit("10MB/s").unwrap(), 10_000_000);
+        assert_eq!(parse_rate_limit("10MB").unwrap(), 10_000_000);
+        assert_eq!(parse_rate_limit("512KiB/s").unwrap(), 512 * 1024);
+        assert_eq!(parse_rate_limit("1.5GB/s").unwrap(), 1_500_000_000);
+        assert_eq!(parse_rate_limit("2MiB").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_rate_limit(" 1K ").unwrap(), 1000);
+    }
+
+    #[test]
+    fn test_parse_rate_limit_invalid() {
+        assert!(parse_rate_limit("").is_err());
+        assert!(parse_rate_limit("fast").is_err());
+        assert!(parse_rate_limit("10XB/s").is_err());
+        assert!(parse_rate_limit("-5MB").is_err());
+        assert!(parse_rate_limit("0").is_err());
+        assert!(parse_rate_limit("0.1").is_err());
+    }
+
+    #[test]
+    fn test_rate_limited_reader_paces_reads() {
+        let data = vec![7u8; 300];
+        let start = Instant::now();
+        let mut out = Vec::new();
+        RateLimitedReader::new(data.as_slice(), 1000)
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, data);
+        // 300 bytes at 1000 B/s must take at least ~300ms.
+        assert!(start.elapsed() >= Duration::from_millis(290));
+    }
+
+    #[test]
+    fn test_rate_limited_reader_empty_input() {
+        let mut out = Vec::new();
+        RateLimitedReader::new(std::io::empty(), 10)
+            .read_to_end(&mut out)
+            .unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_rate_limit() {
+        let cfg = Config {
+            url: "http://example.com".into(),
+            output: "out".into(),
+            workdir: "/work".into(),
+            auth_env: String::new(),
+            insecure_tls: false,
+            follow_redirects: false,
+            allow_cross_site_redirects: false,
+            timeout: Duration::from_secs(1),
+            rate_limit: Some(0),
+            decode_base64: false,
+        };
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn test_fetch_decode_base64_writes_binary() {
+        use std::io::Write;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            let body = "AAEC/w==\n";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        let work = tempfile::TempDir::new().unwrap();
+        let cfg = Config {
+            url: format!("http://{}/keystore", addr),
+            output: "keystore.p12".into(),
+            workdir: work.path().to_str().unwrap().into(),
+            auth_env: String::new(),
+            insecure_tls: false,
+            follow_redirects: false,
+            allow_cross_site_redirects: false,
+            timeout: Duration::from_secs(5),
+            rate_limit: None,
+            decode_base64: true,
+        };
+        do_fetch(&cfg).unwrap();
+        server.join().unwrap();
+        assert_eq!(
+            std::fs::read(work.path().join("keystore.p12")).unwrap(),
+            vec![0u8, 1, 2, 255]
+        );
+    }
+
+    #[test]
+    fn test_fetch_rate_limit_outlasts_timeout() {
+        use std::io::Write;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let body = vec![b'x'; 60_000];
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        let work = tempfile::TempDir::new().unwrap();
+        // 60 kB at 40 kB/s take 1.5s, past the 1s timeout.
+        let cfg = Config {
+            url: format!("http://{}/model.bin", addr),
+            output: "model.bin".into(),
+            workdir: work.path().to_str().unwrap().into(),
+            auth_env: String::new(),
+            insecure_tls: false,
+            follow_redirects: false,
+            allow_cross_site_redirects: false,
+            timeout: Duration::from_secs(1),
+            rate_limit: Some(40_000),
+            decode_base64: false,
+        };
+        do_fetch(&cfg).unwrap();
+        server.join().unwrap();
+        assert_eq!(
+            std::fs::read(work.path().join("model.bin")).unwrap().len(),
+            60_000
+        );
     }
-    fs::write(&out_path, &body).map_err(|e| format!("writing output {:?}: {}", out_path, e))?;
-    Ok(())
 }
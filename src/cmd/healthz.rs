@@ -0,0 +1,222 @@
+use crate::duration::format_duration;
+use crate::logging::Logger;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+/// What the endpoint reports about the wrapped subcommand.
+struct Status {
+    command: String,
+    /// `running`, `succeeded` or `failed`.
+    state: &'static str,
+    /// The last line it logged.
+    last_log: String,
+    started: Instant,
+}
+#[derive(Clone)]
+struct Progress(Arc<Mutex<Status>>);
+impl Progress {
+    fn new(command: &str) -> Self {
+        Progress(Arc::new(Mutex::new(Status {
+            command: command.into(),
+            state: "running",
+            last_log: String::new(),
+            started: Instant::now(),
+        })))
+    }
+    /// The HTTP status and JSON body of the endpoint: 200 once the
+    /// subcommand succeeded, 503 before and after a failure.
+    fn report(&self) -> (u16, serde_json::Value) {
+        let status = self.0.lock().unwrap();
+        // JSON log lines are embedded as objects.
+        let last_log = serde_json::from_str::<serde_json::Value>(&status.last_log)
+            .ok()
+            .filter(|v| v.is_object())
+            .unwrap_or_else(|| status.last_log.clone().into());
+        let body = serde_json::json!({
+            "command": status.command,
+            "status": status.state,
+            "elapsed": format_duration(Duration::from_secs(status.started.elapsed().as_secs())),
+            "last_log": last_log,
+        });
+        let code = if status.state == "succeeded" {
+            200
+        } else {
+            503
+        };
+        (code, body)
+    }
+}
+/// Passes log output on and keeps its last line as the progress.
+struct ProgressWriter {
+    out: Box<dyn Write + Send>,
+    progress: Progress,
+    line: Vec<u8>,
+}
+impl Write for ProgressWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.out.write_all(buf)?;
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+            self.progress.0.lock().unwrap().last_log = line;
+        }
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+/// Serve the progress of `command` on `listen` (`[host]:port`) while `f`
+/// runs it, logging through the `Logger` it is given. The server keeps
+/// running until initium exits, e.g. after `--sidecar`.
+pub fn run<T, F>(log: &Logger, listen: &str, command: &str, f: F) -> Result<T, String>
+where
+    F: FnOnce(&Logger) -> Result<T, String>,
+{
+    let addr = match listen.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => listen.to_string(),
+    };
+    let listener = TcpListener::bind(&addr).map_err(|e| format!("listening on {}: {}", addr, e))?;
+    let progress = Progress::new(command);
+    let inner_log = Logger::new(
+        Box::new(ProgressWriter {
+            out: Box::new(std::io::stderr()),
+            progress: progress.clone(),
+            line: Vec::new(),
+        }),
+        log.json(),
+        log.level(),
+    );
+    log.info(
+        "serving health endpoint",
+        &[("listen", addr.as_str()), ("command", command)],
+    );
+    let server_progress = progress.clone();
+    std::thread::spawn(move || serve(listener, &server_progress));
+    let result = f(&inner_log);
+    progress.0.lock().unwrap().state = if result.is_ok() {
+        "succeeded"
+    } else {
+        "failed"
+    };
+    result
+}
+fn serve(listener: TcpListener, progress: &Progress) {
+    for stream in listener.incoming().flatten() {
+        let _ = respond(stream, progress);
+    }
+}
+fn respond(mut stream: TcpStream, progress: &Progress) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let (code, body) = match path.split('?').next() {
+        Some("/" | "/healthz") => progress.report(),
+        _ => (404, serde_json::json!({ "error": "not found" })),
+    };
+    let reason = match code {
+        200 => "OK",
+        404 => "Not Found",
+        _ => "Service Unavailable",
+    };
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::Level;
+    use std::io::Read;
+
+    fn get(addr: &str, path: &str) -> (u16, serde_json::Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let code = response[9..12].parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        (code, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn test_run_reports_progress() {
+        let log = Logger::new(Box::new(std::io::sink()), false, Level::Info);
+        // Find a free port.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let server_addr = addr.clone();
+        let handle = std::thread::spawn(move || {
+            run(&log, &server_addr, "wait-for", |log| {
+                log.info("waiting for target", &[("target", "tcp://db:5432")]);
+                tx.send(()).unwrap();
+                done_rx.recv().unwrap();
+                Ok(())
+            })
+        });
+        rx.recv().unwrap();
+        let (code, body) = get(&addr, "/healthz");
+        assert_eq!(code, 503);
+        assert_eq!(body["command"], "wait-for");
+        assert_eq!(body["status"], "running");
+        let last_log = body["last_log"].as_str().unwrap();
+        assert!(
+            last_log.ends_with("waiting for target target=tcp://db:5432"),
+            "{}",
+            last_log
+        );
+        assert_eq!(get(&addr, "/other").0, 404);
+
+        done_tx.send(()).unwrap();
+        handle.join().unwrap().unwrap();
+        let (code, body) = get(&addr, "/");
+        assert_eq!(code, 200);
+        assert_eq!(body["status"], "succeeded");
+    }
+
+    #[test]
+    fn test_run_keeps_log_level() {
+        let log = Logger::new(Box::new(std::io::sink()), false, Level::Warn);
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        run(&log, &addr, "exec", |log| {
+            log.warn("command failed, retrying", &[]);
+            log.info("executing command", &[]);
+            Ok(())
+        })
+        .unwrap();
+        let (_, body) = get(&addr, "/");
+        let last_log = body["last_log"].as_str().unwrap();
+        assert!(
+            last_log.ends_with("command failed, retrying"),
+            "{}",
+            last_log
+        );
+    }
+}
@@ -0,0 +1,1086 @@
+use crate::duration::format_duration;
+use crate::logging::Logger;
+use crate::retry;
+use crate::seed::db::{Database, SeedMetadata};
+use crate::seed::hash::compute_content_hash;
+use crate::seed::schema::DatabaseConfig;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+pub struct Config {
+    pub driver: String,
+    /// Database URL; empty falls back to `DATABASE_URL`.
+    pub url: String,
+    /// Directory holding the `V<version>__<description>.sql` files.
+    pub dir: String,
+    /// Table recording the applied migrations.
+    pub table: String,
+    /// `db` serializes concurrent runs with a database lock, `none` does not.
+    pub lock: String,
+    /// How long to wait for another migrator holding the lock.
+    pub lock_timeout: Duration,
+    /// File recording the checksum of the migrations last applied; runs with
+    /// an unchanged directory skip the database. Empty disables it.
+    pub lock_file: String,
+    /// Exit with an error when the run takes longer; `None` waits forever.
+    pub timeout: Option<Duration>,
+    /// Backoff for connecting to a database that is still starting.
+    pub retry: retry::Config,
+    /// Dotenv files read for `DATABASE_URL` when `url` is empty.
+    pub env_files: Vec<String>,
+    /// `wait-for` targets that must be reachable before connecting.
+    pub wait_for: Vec<String>,
+    /// How long to wait for `wait_for`, bounded by `timeout`.
+    pub wait_timeout: Duration,
+    /// Revert the applied migrations newer than `to` instead of applying.
+    pub down: bool,
+    /// Highest version to apply, or to keep when reverting.
+    pub to: Option<u64>,
+    /// Record the migrations up to this version as applied without running
+    /// them, for databases whose schema predates the migrations table.
+    pub baseline: Option<u64>,
+    /// List the pending migrations instead of applying them.
+    pub plan: bool,
+    /// Where `plan` writes its JSON report.
+    pub report_file: Option<String>,
+    /// Run on the pod holding this Kubernetes Lease only.
+    pub leader_elect: Option<crate::lease::Config>,
+}
+
+const LOCK_MODES: [&str; 2] = ["db", "none"];
+
+/// A versioned SQL file.
+#[derive(Debug, PartialEq)]
+struct Migration {
+    version: u64,
+    /// File name without `.sql`, the key in the migrations table.
+    name: String,
+    path: PathBuf,
+    /// The paired `<name>__down.sql` reverting it.
+    down: Option<PathBuf>,
+}
+
+/// Apply the pending migrations of `cfg.dir` in version order, each in its
+/// own transaction.
+pub fn run(log: &Logger, cfg: &Config) -> Result<(), String> {
+    match cfg.timeout {
+        Some(timeout) => with_deadline(log, timeout, || migrate(log, cfg)),
+        None => migrate(log, cfg),
+    }
+}
+
+/// Run `f`, exiting the process when it is still running after `timeout`: a
+/// statement blocked on the database cannot be interrupted from here, and
+/// exiting closes the connection so the database rolls back the open
+/// migration.
+fn with_deadline<T>(log: &Logger, timeout: Duration, f: impl FnOnce() -> T) -> T {
+    let (done, finished) = std::sync::mpsc::channel::<()>();
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+                log.error(
+                    "migrate timed out",
+                    &[("timeout", &format_duration(timeout))],
+                );
+                std::process::exit(1);
+            }
+        });
+        let result = f();
+        drop(done);
+        result
+    })
+}
+
+fn migrate(log: &Logger, cfg: &Config) -> Result<(), String> {
+    let started = Instant::now();
+    if !LOCK_MODES.contains(&cfg.lock.as_str()) {
+        return Err(format!(
+            "invalid --lock '{}' (supported: {})",
+            cfg.lock,
+            LOCK_MODES.join(", ")
+        ));
+    }
+    let mut migrations = list_migrations(Path::new(&cfg.dir))?;
+    let to = match (cfg.down, cfg.to) {
+        (true, None) => return Err("--down requires --to <version>".into()),
+        (true, Some(to)) => to,
+        (false, Some(to)) => {
+            migrations.retain(|m| m.version <= to);
+            to
+        }
+        (false, None) => u64::MAX,
+    };
+    let checksum = if cfg.lock_file.is_empty() || cfg.down || cfg.plan {
+        None
+    } else {
+        let checksum = dir_checksum(&migrations)?;
+        let recorded = std::fs::read_to_string(&cfg.lock_file).unwrap_or_default();
+        if recorded.trim() == checksum {
+            log.info(
+                "migrations unchanged since the last run, skipping",
+                &[("lock_file", cfg.lock_file.as_str())],
+            );
+            return Ok(());
+        }
+        Some(checksum)
+    };
+    let run = || migrate_database(log, cfg, &migrations, to, started);
+    match &cfg.leader_elect {
+        Some(lease) if !cfg.plan => crate::lease::run_as_leader(log, lease, run)?,
+        _ => run()?,
+    }
+    if let Some(checksum) = checksum {
+        let data = format!("{}\n", checksum);
+        crate::safety::write_file_atomic(Path::new(&cfg.lock_file), data.as_bytes())
+            .map_err(|e| format!("writing lock file '{}': {}", cfg.lock_file, e))?;
+    } else if cfg.down && !cfg.lock_file.is_empty() {
+        // The directory is unchanged, but the next run must apply again.
+        match std::fs::remove_file(&cfg.lock_file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("removing lock file '{}': {}", cfg.lock_file, e));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Wait for the database, connect and apply or revert `migrations` under the
+/// migration lock.
+fn migrate_database(
+    log: &Logger,
+    cfg: &Config,
+    migrations: &[Migration],
+    to: u64,
+    started: Instant,
+) -> Result<(), String> {
+    if !cfg.wait_for.is_empty() {
+        let wait_timeout = match cfg.timeout {
+            Some(timeout) => cfg
+                .wait_timeout
+                .min(timeout.saturating_sub(started.elapsed())),
+            None => cfg.wait_timeout,
+        };
+        super::wait_for::run(log, &cfg.wait_for, &cfg.retry, wait_timeout, 200, false)?;
+    }
+    let mut url = cfg.url.clone();
+    if url.is_empty() {
+        let env = super::read_env_files(log, &cfg.env_files)?;
+        if let Some((_, value)) = env.into_iter().find(|(k, _)| k == "DATABASE_URL") {
+            url = value;
+        }
+    }
+    let config = DatabaseConfig {
+        driver: cfg.driver.clone(),
+        url,
+        ..Default::default()
+    };
+    log.info("connecting to database", &[("driver", cfg.driver.as_str())]);
+    let mut db = crate::seed::connect_retrying(log, &config, &cfg.retry)?;
+
+    // Serialize replicas starting at the same time: a PostgreSQL advisory
+    // lock or MySQL GET_LOCK held for the whole run.
+    let lock = format!("initium_migrate:{}", cfg.table);
+    let locked = cfg.lock == "db" && acquire_lock(log, db.as_mut(), &lock, cfg.lock_timeout)?;
+    let result = if cfg.plan {
+        let report_file = cfg.report_file.as_deref();
+        plan(
+            log,
+            db.as_mut(),
+            &cfg.table,
+            migrations,
+            cfg.baseline,
+            report_file,
+        )
+        .map(|_| ())
+    } else if cfg.down {
+        revert(log, db.as_mut(), &cfg.table, migrations, to)
+    } else {
+        apply(log, db.as_mut(), &cfg.table, migrations, cfg.baseline)
+    };
+    if locked {
+        if let Err(e) = db.release_lock(&lock) {
+            log.warn(
+                "failed to release migration lock",
+                &[("lock", lock.as_str()), ("error", e.as_str())],
+            );
+        }
+    }
+    result
+}
+
+/// SHA-256 over the names and contents of `migrations`, so renaming, editing,
+/// adding or removing a file changes it.
+fn dir_checksum(migrations: &[Migration]) -> Result<String, String> {
+    let mut content = Vec::new();
+    for m in migrations {
+        let sql = std::fs::read(&m.path)
+            .map_err(|e| format!("reading migration '{}': {}", m.path.display(), e))?;
+        content.extend_from_slice(m.name.as_bytes());
+        content.push(0);
+        content.extend_from_slice(&sql);
+        content.push(0);
+    }
+    Ok(compute_content_hash(&content))
+}
+
+fn acquire_lock(
+    log: &Logger,
+    db: &mut dyn Database,
+    lock: &str,
+    timeout: Duration,
+) -> Result<bool, String> {
+    log.debug(
+        "acquiring migration lock",
+        &[("lock", lock), ("timeout", &format_duration(timeout))],
+    );
+    let started = Instant::now();
+    let locked = db.acquire_lock(lock, timeout)?;
+    if locked {
+        log.info(
+            "acquired migration lock",
+            &[
+                ("lock", lock),
+                ("waited", &format_duration(started.elapsed())),
+            ],
+        );
+    } else {
+        log.debug(
+            "driver has no migration lock, concurrent runs are not serialized",
+            &[("driver", db.driver_name())],
+        );
+    }
+    Ok(locked)
+}
+
+/// Migrations not in the migrations table yet, with their SQL and checksum.
+struct Pending<'m> {
+    /// Up to the `--baseline` version: recorded without running.
+    baselined: Vec<(&'m Migration, String)>,
+    pending: Vec<(&'m Migration, String, String)>,
+}
+
+/// Compare `migrations` with the migrations table, failing on edited or
+/// out-of-order migrations.
+fn pending<'m>(
+    db: &mut dyn Database,
+    table: &str,
+    migrations: &'m [Migration],
+    baseline: Option<u64>,
+) -> Result<Pending<'m>, String> {
+    db.ensure_tracking_table(table)?;
+    db.migrate_tracking_table(table)?;
+
+    let mut last_applied = None;
+    let mut baselined = Vec::new();
+    let mut pending = Vec::new();
+    for m in migrations {
+        let sql = std::fs::read_to_string(&m.path)
+            .map_err(|e| format!("reading migration '{}': {}", m.path.display(), e))?;
+        let checksum = compute_content_hash(sql.as_bytes());
+        if !db.is_seed_applied(table, &m.name)? {
+            if baseline.is_some_and(|b| m.version <= b) {
+                baselined.push((m, checksum));
+                last_applied = Some(m);
+            } else {
+                pending.push((m, sql, checksum));
+            }
+            continue;
+        }
+        if db
+            .get_seed_hash(table, &m.name)?
+            .is_some_and(|stored| stored != checksum)
+        {
+            return Err(format!(
+                "migration '{}' changed after it was applied; add a new migration instead",
+                m.name
+            ));
+        }
+        last_applied = Some(m);
+    }
+    if let (Some(last), Some((first, _, _))) = (last_applied, pending.first()) {
+        if first.version < last.version {
+            return Err(format!(
+                "migration '{}' is older than the applied migration '{}'",
+                first.name, last.name
+            ));
+        }
+    }
+    Ok(Pending { baselined, pending })
+}
+
+/// Apply the pending `migrations`. Those up to version `baseline` are
+/// recorded as applied without running them.
+fn apply(
+    log: &Logger,
+    db: &mut dyn Database,
+    table: &str,
+    migrations: &[Migration],
+    baseline: Option<u64>,
+) -> Result<(), String> {
+    let Pending { baselined, pending } = pending(db, table, migrations, baseline)?;
+    if !baselined.is_empty() {
+        db.begin_transaction()?;
+        for (m, checksum) in &baselined {
+            let recorded = db
+                .mark_seed_applied(table, &m.name)
+                .and_then(|_| db.update_seed_entry(table, &m.name, checksum))
+                .and_then(|_| {
+                    db.record_seed_metadata(
+                        table,
+                        &m.name,
+                        &SeedMetadata::new(None, Duration::ZERO),
+                    )
+                });
+            if let Err(e) = recorded {
+                if let Err(rollback) = db.rollback_transaction() {
+                    log.warn("rollback failed", &[("error", rollback.as_str())]);
+                }
+                return Err(format!("baselining migration '{}' failed: {}", m.name, e));
+            }
+            log.info(
+                "baselined migration without running it",
+                &[("migration", m.name.as_str())],
+            );
+        }
+        db.commit_transaction()?;
+    }
+
+    for (m, sql, checksum) in &pending {
+        log.info("applying migration", &[("migration", m.name.as_str())]);
+        let started = Instant::now();
+        db.begin_transaction()?;
+        let applied = db
+            .execute_sql(sql)
+            .and_then(|_| db.mark_seed_applied(table, &m.name))
+            .and_then(|_| db.update_seed_entry(table, &m.name, checksum))
+            .and_then(|_| {
+                db.record_seed_metadata(table, &m.name, &SeedMetadata::new(None, started.elapsed()))
+            });
+        if let Err(e) = applied {
+            if let Err(rollback) = db.rollback_transaction() {
+                log.warn(
+                    "rollback failed",
+                    &[("migration", m.name.as_str()), ("error", rollback.as_str())],
+                );
+            }
+            return Err(format!("migration '{}' failed: {}", m.name, e));
+        }
+        db.commit_transaction()?;
+        log.info(
+            "applied migration",
+            &[
+                ("migration", m.name.as_str()),
+                ("duration", &format_duration(started.elapsed())),
+            ],
+        );
+    }
+    log.info(
+        "migrations up to date",
+        &[
+            ("applied", &pending.len().to_string()),
+            ("baselined", &baselined.len().to_string()),
+            ("total", &migrations.len().to_string()),
+        ],
+    );
+    Ok(())
+}
+
+/// The `--plan` report: what a run would apply.
+#[derive(Debug, PartialEq, Serialize)]
+struct PlanReport {
+    total: usize,
+    applied: usize,
+    baselined: Vec<PlannedMigration>,
+    pending: Vec<PlannedMigration>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct PlannedMigration {
+    version: u64,
+    name: String,
+    checksum: String,
+}
+
+/// List the pending migrations without applying them, and write them to
+/// `report_file` when set.
+fn plan(
+    log: &Logger,
+    db: &mut dyn Database,
+    table: &str,
+    migrations: &[Migration],
+    baseline: Option<u64>,
+    report_file: Option<&str>,
+) -> Result<PlanReport, String> {
+    let Pending { baselined, pending } = pending(db, table, migrations, baseline)?;
+    let planned = |m: &Migration, checksum: &str| PlannedMigration {
+        version: m.version,
+        name: m.name.clone(),
+        checksum: checksum.to_string(),
+    };
+    for (m, checksum) in &baselined {
+        log.info(
+            "would baseline migration",
+            &[
+                ("migration", m.name.as_str()),
+                ("checksum", checksum.as_str()),
+            ],
+        );
+    }
+    for (m, _, checksum) in &pending {
+        log.info(
+            "pending migration",
+            &[
+                ("migration", m.name.as_str()),
+                ("checksum", checksum.as_str()),
+            ],
+        );
+    }
+    let report = PlanReport {
+        total: migrations.len(),
+        applied: migrations.len() - baselined.len() - pending.len(),
+        baselined: baselined.iter().map(|(m, c)| planned(m, c)).collect(),
+        pending: pending.iter().map(|(m, _, c)| planned(m, c)).collect(),
+    };
+    log.info(
+        "migration plan",
+        &[
+            ("pending", &report.pending.len().to_string()),
+            ("baselined", &report.baselined.len().to_string()),
+            ("applied", &report.applied.to_string()),
+        ],
+    );
+    if let Some(path) = report_file {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| format!("encoding migration plan: {}", e))?;
+        crate::safety::write_file_atomic(Path::new(path), (json + "\n").as_bytes())
+            .map_err(|e| format!("writing migration plan '{}': {}", path, e))?;
+        log.info("wrote migration plan", &[("path", path)]);
+    }
+    Ok(report)
+}
+
+/// Revert the applied migrations newer than version `to`, newest first, each
+/// with its down file in its own transaction.
+fn revert(
+    log: &Logger,
+    db: &mut dyn Database,
+    table: &str,
+    migrations: &[Migration],
+    to: u64,
+) -> Result<(), String> {
+    db.ensure_tracking_table(table)?;
+    db.migrate_tracking_table(table)?;
+
+    let mut applied = Vec::new();
+    for m in migrations.iter().rev().filter(|m| m.version > to) {
+        if !db.is_seed_applied(table, &m.name)? {
+            continue;
+        }
+        // Check every down file before reverting anything.
+        let down = m.down.as_ref().ok_or_else(|| {
+            format!(
+                "migration '{}' has no down file {}__down.sql",
+                m.name, m.name
+            )
+        })?;
+        let sql = std::fs::read_to_string(down)
+            .map_err(|e| format!("reading migration '{}': {}", down.display(), e))?;
+        applied.push((m, sql));
+    }
+
+    for (m, sql) in &applied {
+        log.info("reverting migration", &[("migration", m.name.as_str())]);
+        let started = Instant::now();
+        db.begin_transaction()?;
+        let reverted = db
+            .execute_sql(sql)
+            .and_then(|_| db.remove_seed_mark(table, &m.name));
+        if let Err(e) = reverted {
+            if let Err(rollback) = db.rollback_transaction() {
+                log.warn(
+                    "rollback failed",
+                    &[("migration", m.name.as_str()), ("error", rollback.as_str())],
+                );
+            }
+            return Err(format!("reverting migration '{}' failed: {}", m.name, e));
+        }
+        db.commit_transaction()?;
+        log.info(
+            "reverted migration",
+            &[
+                ("migration", m.name.as_str()),
+                ("duration", &format_duration(started.elapsed())),
+            ],
+        );
+    }
+    log.info(
+        "migrations reverted",
+        &[
+            ("reverted", &applied.len().to_string()),
+            ("to", &to.to_string()),
+        ],
+    );
+    Ok(())
+}
+
+/// The `.sql` files of `dir` in version order, each with its paired
+/// `__down.sql` file. Other files are ignored.
+fn list_migrations(dir: &Path) -> Result<Vec<Migration>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("reading migrations dir '{}': {}", dir.display(), e))?;
+    let mut migrations: Vec<Migration> = Vec::new();
+    let mut downs = std::collections::BTreeMap::new();
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| format!("reading migrations dir '{}': {}", dir.display(), e))?;
+        let path = entry.path();
+        let file = entry.file_name().to_string_lossy().into_owned();
+        let Some(name) = file.strip_suffix(".sql") else {
+            continue;
+        };
+        if !path.is_file() {
+            continue;
+        }
+        let version = parse_version(name).ok_or_else(|| {
+            format!(
+                "migration file '{}' does not match V<version>__<description>.sql",
+                file
+            )
+        })?;
+        if let Some(up) = name.strip_suffix("__down") {
+            downs.insert(up.to_string(), path);
+            continue;
+        }
+        migrations.push(Migration {
+            version,
+            name: name.to_string(),
+            path,
+            down: None,
+        });
+    }
+    for m in &mut migrations {
+        m.down = downs.remove(&m.name);
+    }
+    if let Some(up) = downs.keys().next() {
+        return Err(format!(
+            "down file '{}__down.sql' has no matching migration '{}.sql'",
+            up, up
+        ));
+    }
+    migrations.sort_by(|a, b| (a.version, &a.name).cmp(&(b.version, &b.name)));
+    for pair in migrations.windows(2) {
+        if pair[0].version == pair[1].version {
+            return Err(format!(
+                "migrations '{}' and '{}' have the same version {}",
+                pair[0].name, pair[1].name, pair[0].version
+            ));
+        }
+    }
+    Ok(migrations)
+}
+
+/// The version of a `V<version>__<description>` file name.
+fn parse_version(name: &str) -> Option<u64> {
+    let (version, description) = name.strip_prefix('V')?.split_once("__")?;
+    if description.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    version.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::db::SqliteDb;
+
+    fn write(dir: &Path, file: &str, sql: &str) {
+        std::fs::write(dir.join(file), sql).unwrap();
+    }
+
+    fn test_logger() -> Logger {
+        Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        )
+    }
+
+    /// Migrate the SQLite database `app.db` in `dir` with the files in `dir`.
+    fn config(dir: &Path) -> Config {
+        Config {
+            driver: "sqlite".into(),
+            url: dir.join("app.db").to_str().unwrap().into(),
+            dir: dir.to_str().unwrap().into(),
+            table: "initium_migrations".into(),
+            lock: "db".into(),
+            lock_timeout: Duration::from_secs(1),
+            lock_file: String::new(),
+            timeout: None,
+            retry: retry::Config {
+                max_attempts: 3,
+                initial_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(20),
+                backoff_factor: 2.0,
+                jitter_fraction: 0.0,
+            },
+            env_files: Vec::new(),
+            wait_for: Vec::new(),
+            wait_timeout: Duration::from_secs(1),
+            down: false,
+            to: None,
+            baseline: None,
+            plan: false,
+            report_file: None,
+            leader_elect: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("V001__create_users"), Some(1));
+        assert_eq!(parse_version("V20__add_index"), Some(20));
+        assert_eq!(parse_version("V1_create"), None);
+        assert_eq!(parse_version("V1__"), None);
+        assert_eq!(parse_version("Vx__create"), None);
+        assert_eq!(parse_version("001__create"), None);
+    }
+
+    #[test]
+    fn test_list_migrations() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write(dir.path(), "V10__later.sql", "");
+        write(dir.path(), "V2__first.sql", "");
+        write(dir.path(), "README.md", "");
+        let names: Vec<String> = list_migrations(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        assert_eq!(names, vec!["V2__first", "V10__later"]);
+
+        write(dir.path(), "V02__again.sql", "");
+        let err = list_migrations(dir.path()).unwrap_err();
+        assert_eq!(
+            err,
+            "migrations 'V02__again' and 'V2__first' have the same version 2"
+        );
+
+        std::fs::remove_file(dir.path().join("V02__again.sql")).unwrap();
+        write(dir.path(), "V2__first__down.sql", "");
+        let migrations = list_migrations(dir.path()).unwrap();
+        assert_eq!(
+            migrations[0].down,
+            Some(dir.path().join("V2__first__down.sql"))
+        );
+        assert_eq!(migrations[1].down, None);
+
+        write(dir.path(), "V3__gone__down.sql", "");
+        let err = list_migrations(dir.path()).unwrap_err();
+        assert_eq!(
+            err,
+            "down file 'V3__gone__down.sql' has no matching migration 'V3__gone.sql'"
+        );
+        std::fs::remove_file(dir.path().join("V3__gone__down.sql")).unwrap();
+
+        write(dir.path(), "setup.sql", "");
+        let err = list_migrations(dir.path()).unwrap_err();
+        assert_eq!(
+            err,
+            "migration file 'setup.sql' does not match V<version>__<description>.sql"
+        );
+    }
+
+    #[test]
+    fn test_run_rejects_unknown_lock() {
+        let log = test_logger();
+        let cfg = Config {
+            url: ":memory:".into(),
+            lock: "file".into(),
+            ..config(Path::new("."))
+        };
+        assert_eq!(
+            run(&log, &cfg).unwrap_err(),
+            "invalid --lock 'file' (supported: db, none)"
+        );
+    }
+
+    #[test]
+    fn test_run_retries_connect() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = test_logger();
+        let cfg = Config {
+            url: format!("{}/missing/app.db", dir.path().display()),
+            timeout: Some(Duration::from_secs(60)),
+            ..config(dir.path())
+        };
+        let started = Instant::now();
+        let err = run(&log, &cfg).unwrap_err();
+        assert!(err.contains("gave up after 3 attempts"), "{}", err);
+        assert!(started.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_run_env_file_url() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("app.db");
+        let env_file = dir.path().join("db.env");
+        std::fs::write(
+            &env_file,
+            format!("DATABASE_URL={}\n", db_path.to_str().unwrap()),
+        )
+        .unwrap();
+        write(dir.path(), "V1__a.sql", "CREATE TABLE a (id INTEGER);");
+        let log = test_logger();
+        let cfg = Config {
+            url: String::new(),
+            env_files: vec![env_file.to_str().unwrap().into()],
+            ..config(dir.path())
+        };
+        run(&log, &cfg).unwrap();
+        let db = SqliteDb::connect(db_path.to_str().unwrap()).unwrap();
+        db.conn.execute("INSERT INTO a VALUES (1)", []).unwrap();
+    }
+
+    #[test]
+    fn test_run_waits_for_targets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("app.db");
+        write(dir.path(), "V1__a.sql", "CREATE TABLE a (id INTEGER);");
+        let log = test_logger();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+        let mut cfg = Config {
+            wait_for: vec![format!("tcp://{}", closed_addr)],
+            wait_timeout: Duration::from_millis(100),
+            ..config(dir.path())
+        };
+
+        let err = run(&log, &cfg).unwrap_err();
+        assert!(err.contains("not reachable"), "{}", err);
+        assert!(!db_path.exists(), "must not connect before the wait");
+
+        cfg.wait_for = vec![format!("tcp://{}", listener.local_addr().unwrap())];
+        run(&log, &cfg).unwrap();
+        assert!(db_path.exists());
+    }
+
+    #[test]
+    fn test_run_lock_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("app.db");
+        let lock_file = dir.path().join("migrate.lock");
+        let migrations_dir = dir.path().join("migrations");
+        std::fs::create_dir(&migrations_dir).unwrap();
+        write(&migrations_dir, "V1__a.sql", "CREATE TABLE a (id INTEGER);");
+        let log = test_logger();
+        let cfg = Config {
+            dir: migrations_dir.to_str().unwrap().into(),
+            lock: "none".into(),
+            lock_file: lock_file.to_str().unwrap().into(),
+            ..config(dir.path())
+        };
+        let tables = || -> i64 {
+            let db = SqliteDb::connect(db_path.to_str().unwrap()).unwrap();
+            db.conn
+                .query_row(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('a', 'b')",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap()
+        };
+
+        run(&log, &cfg).unwrap();
+        let recorded = std::fs::read_to_string(&lock_file).unwrap();
+        let expected = dir_checksum(&list_migrations(&migrations_dir).unwrap()).unwrap();
+        assert_eq!(recorded.trim(), expected);
+
+        // An unchanged directory skips the database entirely.
+        std::fs::remove_file(&db_path).unwrap();
+        run(&log, &cfg).unwrap();
+        assert_eq!(tables(), 0);
+
+        // A new migration changes the checksum, so the run goes ahead.
+        write(&migrations_dir, "V2__b.sql", "CREATE TABLE b (id INTEGER);");
+        run(&log, &cfg).unwrap();
+        assert_eq!(tables(), 2);
+        assert_ne!(std::fs::read_to_string(&lock_file).unwrap(), recorded);
+
+        // A lock file without a checksum does not skip.
+        std::fs::write(&lock_file, "").unwrap();
+        std::fs::remove_file(&db_path).unwrap();
+        run(&log, &cfg).unwrap();
+        assert_eq!(tables(), 2);
+    }
+
+    #[test]
+    fn test_apply_migrations() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("app.db");
+        let migrations_dir = dir.path().join("migrations");
+        std::fs::create_dir(&migrations_dir).unwrap();
+        write(
+            &migrations_dir,
+            "V1__create_users.sql",
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT);",
+        );
+        write(
+            &migrations_dir,
+            "V2__add_name.sql",
+            "ALTER TABLE users ADD COLUMN name TEXT;\nCREATE INDEX users_email ON users (email);",
+        );
+        let log = test_logger();
+        let migrate = || {
+            let mut db = SqliteDb::connect(db_path.to_str().unwrap()).unwrap();
+            let migrations = list_migrations(&migrations_dir).unwrap();
+            apply(&log, &mut db, "initium_migrations", &migrations, None)
+        };
+
+        migrate().unwrap();
+        // Applied migrations are skipped.
+        migrate().unwrap();
+        let db = SqliteDb::connect(db_path.to_str().unwrap()).unwrap();
+        db.conn
+            .execute(
+                "INSERT INTO users (email, name) VALUES ('a@example.com', 'A')",
+                [],
+            )
+            .unwrap();
+        let applied: Vec<String> = db
+            .conn
+            .prepare("SELECT seed_set FROM initium_migrations ORDER BY seed_set")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(applied, vec!["V1__create_users", "V2__add_name"]);
+
+        // A failing migration is rolled back and not recorded.
+        write(
+            &migrations_dir,
+            "V3__broken.sql",
+            "CREATE TABLE teams (id INTEGER);\nINSERT INTO missing VALUES (1);",
+        );
+        let err = migrate().unwrap_err();
+        assert!(err.starts_with("migration 'V3__broken' failed:"), "{}", err);
+        let teams: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'teams'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(teams, 0);
+        std::fs::remove_file(migrations_dir.join("V3__broken.sql")).unwrap();
+
+        write(&migrations_dir, "V1__create_users.sql", "SELECT 1;");
+        let err = migrate().unwrap_err();
+        assert_eq!(
+            err,
+            "migration 'V1__create_users' changed after it was applied; add a new migration instead"
+        );
+    }
+
+    #[test]
+    fn test_revert_migrations() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("app.db");
+        for (version, table) in [(1, "a"), (2, "b"), (3, "c")] {
+            let name = format!("V{}__create_{}", version, table);
+            write(
+                dir.path(),
+                &format!("{}.sql", name),
+                &format!("CREATE TABLE {} (id INTEGER);", table),
+            );
+            write(
+                dir.path(),
+                &format!("{}__down.sql", name),
+                &format!("DROP TABLE {};", table),
+            );
+        }
+        let log = test_logger();
+        let mut db = SqliteDb::connect(db_path.to_str().unwrap()).unwrap();
+        let migrations = list_migrations(dir.path()).unwrap();
+        apply(&log, &mut db, "m", &migrations, None).unwrap();
+
+        revert(&log, &mut db, "m", &migrations, 1).unwrap();
+        let tables = |db: &SqliteDb| -> Vec<String> {
+            db.conn
+                .prepare(
+                    "SELECT name FROM sqlite_master WHERE name IN ('a', 'b', 'c') ORDER BY name",
+                )
+                .unwrap()
+                .query_map([], |r| r.get(0))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        assert_eq!(tables(&db), vec!["a"]);
+        // Reverting again finds nothing newer to revert.
+        revert(&log, &mut db, "m", &migrations, 1).unwrap();
+
+        // The reverted migrations apply again.
+        apply(&log, &mut db, "m", &migrations, None).unwrap();
+        assert_eq!(tables(&db), vec!["a", "b", "c"]);
+
+        // Nothing is reverted when a down file is missing.
+        std::fs::remove_file(dir.path().join("V2__create_b__down.sql")).unwrap();
+        let migrations = list_migrations(dir.path()).unwrap();
+        let err = revert(&log, &mut db, "m", &migrations, 0).unwrap_err();
+        assert_eq!(
+            err,
+            "migration 'V2__create_b' has no down file V2__create_b__down.sql"
+        );
+        assert_eq!(tables(&db), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_apply_baseline() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("app.db");
+        write(
+            dir.path(),
+            "V1__create_a.sql",
+            "CREATE TABLE a (id INTEGER);",
+        );
+        write(
+            dir.path(),
+            "V2__create_b.sql",
+            "CREATE TABLE b (id INTEGER);",
+        );
+        write(
+            dir.path(),
+            "V3__create_c.sql",
+            "CREATE TABLE c (id INTEGER);",
+        );
+        let log = test_logger();
+        let mut db = SqliteDb::connect(db_path.to_str().unwrap()).unwrap();
+        // The existing schema already has a and b.
+        db.conn
+            .execute_batch("CREATE TABLE a (id INTEGER); CREATE TABLE b (id INTEGER);")
+            .unwrap();
+
+        let migrations = list_migrations(dir.path()).unwrap();
+        apply(&log, &mut db, "m", &migrations, Some(2)).unwrap();
+        let applied: Vec<String> = db
+            .conn
+            .prepare("SELECT seed_set FROM m ORDER BY seed_set")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            applied,
+            vec!["V1__create_a", "V2__create_b", "V3__create_c"]
+        );
+
+        // Baselined migrations are checked like applied ones.
+        write(dir.path(), "V1__create_a.sql", "SELECT 1;");
+        let err = apply(
+            &log,
+            &mut db,
+            "m",
+            &list_migrations(dir.path()).unwrap(),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.contains("changed after it was applied"), "{}", err);
+    }
+
+    #[test]
+    fn test_plan_lists_pending() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("app.db");
+        let report_file = dir.path().join("plan.json");
+        let migrations_dir = dir.path().join("migrations");
+        std::fs::create_dir(&migrations_dir).unwrap();
+        write(
+            &migrations_dir,
+            "V1__create_a.sql",
+            "CREATE TABLE a (id INTEGER);",
+        );
+        write(
+            &migrations_dir,
+            "V2__create_b.sql",
+            "CREATE TABLE b (id INTEGER);",
+        );
+        let log = test_logger();
+        let mut db = SqliteDb::connect(db_path.to_str().unwrap()).unwrap();
+        let migrations = list_migrations(&migrations_dir).unwrap();
+        apply(&log, &mut db, "m", &migrations[..1], None).unwrap();
+
+        let report = plan(
+            &log,
+            &mut db,
+            "m",
+            &migrations,
+            None,
+            Some(report_file.to_str().unwrap()),
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            PlanReport {
+                total: 2,
+                applied: 1,
+                baselined: Vec::new(),
+                pending: vec![PlannedMigration {
+                    version: 2,
+                    name: "V2__create_b".into(),
+                    checksum: compute_content_hash(b"CREATE TABLE b (id INTEGER);"),
+                }],
+            }
+        );
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report_file).unwrap()).unwrap();
+        assert_eq!(written["pending"][0]["name"], "V2__create_b");
+        let b: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'b'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(b, 0, "plan must not apply migrations");
+    }
+
+    #[test]
+    fn test_apply_rejects_out_of_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("app.db");
+        write(dir.path(), "V2__second.sql", "CREATE TABLE b (id INTEGER);");
+        let log = test_logger();
+        let mut db = SqliteDb::connect(db_path.to_str().unwrap()).unwrap();
+        apply(
+            &log,
+            &mut db,
+            "m",
+            &list_migrations(dir.path()).unwrap(),
+            None,
+        )
+        .unwrap();
+
+        write(dir.path(), "V1__first.sql", "CREATE TABLE a (id INTEGER);");
+        let err = apply(
+            &log,
+            &mut db,
+            "m",
+            &list_migrations(dir.path()).unwrap(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "migration 'V1__first' is older than the applied migration 'V2__second'"
+        );
+    }
+}
@@ -1,46 +1,620 @@
+pub mod copy;
 pub mod exec;
 pub mod fetch;
+pub mod healthz;
+pub mod migrate;
+pub mod perms;
 pub mod render;
+pub mod sleep;
+pub mod unarchive;
 pub mod wait_for;
+use crate::duration::format_duration;
 use crate::logging::Logger;
-use std::io::{BufRead, BufReader, Read};
-use std::process::Command;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 pub fn run_command_in_dir(log: &Logger, args: &[String], dir: Option<&str>) -> Result<i32, String> {
+    run_command(
+        log,
+        args,
+        &CommandOptions {
+            dir,
+            ..Default::default()
+        },
+    )
+}
+/// How `run_command` starts and bounds the child process.
+#[derive(Default)]
+pub struct CommandOptions<'a> {
+    pub dir: Option<&'a str>,
+    /// Added to the inherited environment.
+    pub env: &'a [(String, String)],
+    /// Start from an empty environment instead of the inherited one.
+    pub clear_env: bool,
+    /// Terminate the child's process group when it runs longer.
+    pub timeout: Option<Duration>,
+    /// After a timeout, how long to wait between SIGTERM and SIGKILL.
+    pub kill_after: Duration,
+    /// User and group the child runs as (unix only).
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// Written to the child's stdin, which is empty otherwise.
+    pub stdin: Option<&'a [u8]>,
+    /// Also receive the child's raw stdout and stderr.
+    pub stdout_file: Option<&'a File>,
+    pub stderr_file: Option<&'a File>,
+    /// Added to each logged output line, telling concurrent commands apart.
+    pub label: Option<&'a str>,
+}
+/// Run `args` with its output logged line by line, returning its exit code.
+pub fn run_command(log: &Logger, args: &[String], opts: &CommandOptions) -> Result<i32, String> {
+    run_command_with_signals(log, args, opts, &SIGNAL_RECEIVED)
+}
+/// `run_command`, with the signals initium received showing up in
+/// `received`. Once one has, no further command is started: initium is
+/// shutting down, and the signal would be forwarded to it as well.
+fn run_command_with_signals(
+    log: &Logger,
+    args: &[String],
+    opts: &CommandOptions,
+    received: &AtomicI32,
+) -> Result<i32, String> {
     let mut cmd = Command::new(&args[0]);
     cmd.args(&args[1..]);
-    if let Some(d) = dir {
+    if opts.clear_env {
+        cmd.env_clear();
+    }
+    cmd.envs(opts.env.iter().map(|(k, v)| (k, v)));
+    if let Some(d) = opts.dir {
         cmd.current_dir(d);
     }
-    cmd.stdin(std::process::Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Its own process group, so signals forwarded to it and a timeout
+        // also reach the processes it started.
+        cmd.process_group(0);
+        if let Some(gid) = opts.gid {
+            cmd.gid(gid);
+        }
+        if let Some(uid) = opts.uid {
+            cmd.uid(uid);
+        }
+    }
+    #[cfg(not(unix))]
+    if opts.uid.is_some() || opts.gid.is_some() {
+        return Err("running a command as another user is only supported on unix".into());
+    }
+    cmd.stdin(if opts.stdin.is_some() {
+        std::process::Stdio::piped()
+    } else {
+        std::process::Stdio::null()
+    });
     cmd.stdout(std::process::Stdio::piped());
     cmd.stderr(std::process::Stdio::piped());
+    // From before the child starts until it is reaped.
+    let _signals = SignalGuard::new();
+    let signal = received.load(Ordering::SeqCst);
+    if signal != 0 {
+        return Err(format!(
+            "command {:?} not started: initium received {}",
+            args[0],
+            signal_name(signal)
+        ));
+    }
     let mut child = cmd
         .spawn()
         .map_err(|e| format!("starting command {:?}: {}", args[0], e))?;
+    let stdin = child.stdin.take();
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
-    std::thread::scope(|s| {
+    let outcome = std::thread::scope(|s| {
+        if let (Some(mut w), Some(data)) = (stdin, opts.stdin) {
+            // A command that exits without reading all of it is not an error.
+            s.spawn(move || {
+                let _ = std::io::Write::write_all(&mut w, data);
+            });
+        }
         let h1 = s.spawn(|| {
             if let Some(r) = stdout {
-                stream_lines(log, r, "stdout");
+                stream_lines(log, r, "stdout", opts.label, opts.stdout_file);
             }
         });
         let h2 = s.spawn(|| {
             if let Some(r) = stderr {
-                stream_lines(log, r, "stderr");
+                stream_lines(log, r, "stderr", opts.label, opts.stderr_file);
             }
         });
+        let outcome = wait_child(log, &mut child, args, opts, received);
         h1.join().ok();
         h2.join().ok();
-    });
-    let status = child
+        outcome
+    })?;
+    match outcome {
+        Outcome::Exited(status) => Ok(status.code().unwrap_or(-1)),
+        Outcome::TimedOut => Err(format!(
+            "command {:?} timed out after {}",
+            args[0],
+            format_duration(opts.timeout.unwrap_or_default())
+        )),
+        Outcome::Interrupted(signal) => Err(format!(
+            "command {:?} interrupted by {}",
+            args[0],
+            signal_name(signal)
+        )),
+    }
+}
+/// How a command run by `run_command` ended.
+#[derive(Debug)]
+enum Outcome {
+    Exited(ExitStatus),
+    TimedOut,
+    /// initium received this signal and forwarded it to the command.
+    Interrupted(i32),
+}
+/// Set by the SIGTERM/SIGINT handler while something waits for signals.
+static SIGNAL_RECEIVED: AtomicI32 = AtomicI32::new(0);
+/// Live `SignalGuard`s; without any, a signal is not handled.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+/// While alive, SIGTERM and SIGINT are recorded for the waiting code
+/// instead of ending initium.
+pub struct SignalGuard(());
+impl SignalGuard {
+    pub fn new() -> Self {
+        #[cfg(unix)]
+        install_signal_handlers();
+        RUNNING.fetch_add(1, Ordering::SeqCst);
+        SignalGuard(())
+    }
+    /// Block until `duration` has elapsed, forever with `None`, or until
+    /// SIGTERM or SIGINT arrives, returning the name of the signal.
+    pub fn sleep(&self, duration: Option<Duration>) -> Option<&'static str> {
+        let deadline = duration.map(|d| Instant::now() + d);
+        loop {
+            let signal = SIGNAL_RECEIVED.load(Ordering::SeqCst);
+            if signal != 0 {
+                return Some(signal_name(signal));
+            }
+            let left = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => Duration::MAX,
+            };
+            if left.is_zero() {
+                return None;
+            }
+            std::thread::sleep(left.min(Duration::from_millis(100)));
+        }
+    }
+}
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+/// Wait for `child` and reap it. A signal showing up in `received` is
+/// forwarded to its process group; past the timeout, the group gets SIGTERM
+/// and, `kill_after` later, SIGKILL.
+fn wait_child(
+    log: &Logger,
+    child: &mut Child,
+    args: &[String],
+    opts: &CommandOptions,
+    received: &AtomicI32,
+) -> Result<Outcome, String> {
+    let deadline = opts.timeout.map(|t| Instant::now() + t);
+    let mut forwarded = None;
+    loop {
+        let status = child
+            .try_wait()
+            .map_err(|e| format!("waiting for command: {}", e))?;
+        if let Some(status) = status {
+            return Ok(match forwarded {
+                Some(signal) => {
+                    // Also ends processes of the group that outlived the
+                    // command, so none is orphaned.
+                    terminate(child, true);
+                    Outcome::Interrupted(signal)
+                }
+                None => Outcome::Exited(status),
+            });
+        }
+        let signal = received.load(Ordering::SeqCst);
+        if signal != 0 && forwarded.is_none() {
+            log.warn(
+                "forwarding signal to command",
+                &[
+                    ("command", args[0].as_str()),
+                    ("signal", signal_name(signal)),
+                ],
+            );
+            signal_group(child, signal);
+            forwarded = Some(signal);
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            stop(log, child, args, opts)?;
+            return Ok(Outcome::TimedOut);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+/// End a command that ran past its timeout: SIGTERM to its process group,
+/// then SIGKILL `kill_after` later.
+fn stop(
+    log: &Logger,
+    child: &mut Child,
+    args: &[String],
+    opts: &CommandOptions,
+) -> Result<(), String> {
+    log.warn(
+        "command timed out, terminating",
+        &[
+            ("command", args[0].as_str()),
+            (
+                "timeout",
+                &format_duration(opts.timeout.unwrap_or_default()),
+            ),
+        ],
+    );
+    terminate(child, false);
+    let deadline = Instant::now() + opts.kill_after;
+    loop {
+        let status = child
+            .try_wait()
+            .map_err(|e| format!("waiting for command: {}", e))?;
+        if status.is_some() {
+            break;
+        }
+        if Instant::now() >= deadline {
+            log.warn(
+                "command did not exit, killing",
+                &[
+                    ("command", args[0].as_str()),
+                    ("kill_after", &format_duration(opts.kill_after)),
+                ],
+            );
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    // Also ends processes of the group that outlived the command and would
+    // keep its output open.
+    terminate(child, true);
+    child
         .wait()
         .map_err(|e| format!("waiting for command: {}", e))?;
-    Ok(status.code().unwrap_or(-1))
+    Ok(())
+}
+/// Signal the process group of `child`: SIGTERM, or SIGKILL when `kill`.
+#[cfg(unix)]
+fn terminate(child: &mut Child, kill: bool) {
+    signal_group(child, if kill { libc::SIGKILL } else { libc::SIGTERM });
+}
+#[cfg(not(unix))]
+fn terminate(child: &mut Child, _kill: bool) {
+    let _ = child.kill();
+}
+#[cfg(unix)]
+fn signal_group(child: &mut Child, signal: i32) {
+    // SAFETY: kill(2) has no memory-safety preconditions. The child leads
+    // its own process group, whose id is its pid.
+    unsafe {
+        libc::kill(-(child.id() as i32), signal);
+    }
+}
+#[cfg(not(unix))]
+fn signal_group(child: &mut Child, _signal: i32) {
+    let _ = child.kill();
 }
-fn stream_lines<R: Read>(log: &Logger, reader: R, stream: &str) {
-    let buf = BufReader::new(reader);
-    for l in buf.lines().map_while(Result::ok) {
-        log.info(&l, &[("stream", stream)]);
+#[cfg(unix)]
+fn signal_name(signal: i32) -> &'static str {
+    match signal {
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGINT => "SIGINT",
+        _ => "signal",
+    }
+}
+#[cfg(not(unix))]
+fn signal_name(_signal: i32) -> &'static str {
+    "signal"
+}
+/// Handle SIGTERM and SIGINT from now on, unless they are ignored: while a
+/// command runs they are forwarded to it and a sleep ends, otherwise they
+/// act as by default.
+#[cfg(unix)]
+fn install_signal_handlers() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        for signal in [libc::SIGTERM, libc::SIGINT] {
+            // SAFETY: sigaction(2) is given valid pointers to initialized
+            // structs, and the handler only calls async-signal-safe functions.
+            unsafe {
+                let mut old: libc::sigaction = std::mem::zeroed();
+                libc::sigaction(signal, std::ptr::null(), &mut old);
+                if old.sa_sigaction == libc::SIG_IGN {
+                    continue;
+                }
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as usize;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                libc::sigaction(signal, &action, std::ptr::null_mut());
+            }
+        }
+    });
+}
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int) {
+    if RUNNING.load(Ordering::SeqCst) == 0 {
+        // No command to forward to: terminate as if never handled.
+        // SAFETY: signal(2) and raise(3) are async-signal-safe.
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+        return;
+    }
+    SIGNAL_RECEIVED.store(signal, Ordering::SeqCst);
+}
+/// Log each line of `reader`, copying the raw output to `tee` if given.
+fn stream_lines<R: Read>(
+    log: &Logger,
+    reader: R,
+    stream: &str,
+    label: Option<&str>,
+    mut tee: Option<&File>,
+) {
+    let mut buf = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match buf.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if let Some(file) = tee.as_mut() {
+            if let Err(e) = file.write_all(&line) {
+                log.warn(
+                    "writing command output to file failed",
+                    &[("stream", stream), ("error", &e.to_string())],
+                );
+                tee = None;
+            }
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.strip_suffix('\n').unwrap_or(&text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        match label {
+            Some(label) => log.info(text, &[("label", label), ("stream", stream)]),
+            None => log.info(text, &[("stream", stream)]),
+        }
+    }
+}
+/// Read dotenv files in order; a later file overrides an earlier one. Lines
+/// are `KEY=VALUE`, optionally prefixed with `export`; `#` starts a comment.
+/// Single-quoted values are literal, double-quoted values understand `\n`,
+/// `\"` and `\\`.
+pub fn read_env_files(log: &Logger, paths: &[String]) -> Result<Vec<(String, String)>, String> {
+    let mut vars: Vec<(String, String)> = Vec::new();
+    for path in paths {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("reading env file '{}': {}", path, e))?;
+        let parsed = parse_env_file(&content).map_err(|e| format!("env file '{}': {}", path, e))?;
+        log.info(
+            "loaded env file",
+            &[("path", path.as_str()), ("vars", &parsed.len().to_string())],
+        );
+        for (key, value) in parsed {
+            vars.retain(|(k, _)| *k != key);
+            vars.push((key, value));
+        }
+    }
+    Ok(vars)
+}
+fn parse_env_file(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected KEY=VALUE", i + 1))?;
+        let key = key.trim();
+        let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("line {}: invalid variable name '{}'", i + 1, key));
+        }
+        let value = parse_env_value(value.trim())
+            .ok_or_else(|| format!("line {}: unterminated quote in {}", i + 1, key))?;
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+fn parse_env_value(value: &str) -> Option<String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        return rest.find('\'').map(|end| rest[..end].to_string());
+    }
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(out),
+                '\\' => match chars.next()? {
+                    'n' => out.push('\n'),
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+        return None;
+    }
+    // Unquoted: a `#` after whitespace starts a comment.
+    let end = value.find(" #").unwrap_or(value.len());
+    Some(value[..end].trim_end().to_string())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_logger() -> Logger {
+        Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        )
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let vars = parse_env_file(
+            "# credentials\n\
+             DB_USER=app\n\
+             export DB_PASSWORD='p#ss word'\n\
+             \n\
+             GREETING=\"hello\\n\\\"world\\\"\"\n\
+             EMPTY=\n\
+             PORT=5432 # default\n",
+        )
+        .unwrap();
+        let expected = [
+            ("DB_USER", "app"),
+            ("DB_PASSWORD", "p#ss word"),
+            ("GREETING", "hello\n\"world\""),
+            ("EMPTY", ""),
+            ("PORT", "5432"),
+        ];
+        assert_eq!(
+            vars,
+            expected
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            parse_env_file("A=1\nnot a pair\n").unwrap_err(),
+            "line 2: expected KEY=VALUE"
+        );
+        assert_eq!(
+            parse_env_file("1A=x").unwrap_err(),
+            "line 1: invalid variable name '1A'"
+        );
+        assert_eq!(
+            parse_env_file("A=\"open").unwrap_err(),
+            "line 1: unterminated quote in A"
+        );
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_timeout() {
+        let log = test_logger();
+        let sh = |script: &str| vec!["sh".to_string(), "-c".into(), script.into()];
+        let opts = CommandOptions {
+            timeout: Some(Duration::from_millis(300)),
+            kill_after: Duration::from_millis(300),
+            ..Default::default()
+        };
+        assert_eq!(run_command(&log, &sh("exit 3"), &opts).unwrap(), 3);
+
+        // SIGTERM ends the command and the processes it started.
+        let started = Instant::now();
+        let err = run_command(&log, &sh("sleep 30 & wait"), &opts).unwrap_err();
+        assert_eq!(err, "command \"sh\" timed out after 300ms");
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // A command ignoring SIGTERM is killed after --kill-after.
+        let started = Instant::now();
+        let err = run_command(&log, &sh("trap '' TERM; sleep 30 & wait"), &opts).unwrap_err();
+        assert!(err.contains("timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_wait_child_forwards_signal() {
+        use std::os::unix::process::CommandExt;
+        let log = test_logger();
+        let args = vec![
+            "sh".to_string(),
+            "-c".into(),
+            "trap 'exit 0' TERM; sleep 30 & wait".into(),
+        ];
+        let mut child = Command::new(&args[0])
+            .args(&args[1..])
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let received = AtomicI32::new(libc::SIGTERM);
+        let started = Instant::now();
+        let outcome = wait_child(
+            &log,
+            &mut child,
+            &args,
+            &CommandOptions::default(),
+            &received,
+        )
+        .unwrap();
+        assert!(
+            matches!(outcome, Outcome::Interrupted(libc::SIGTERM)),
+            "{:?}",
+            outcome
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        // The whole group is gone, including the sleep the shell started,
+        // once its new parent has reaped it.
+        let gone = (0..100).any(|_| {
+            std::thread::sleep(Duration::from_millis(50));
+            (unsafe { libc::kill(-(child.id() as i32), 0) }) != 0
+        });
+        assert!(gone);
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_no_command_started_after_signal() {
+        let log = test_logger();
+        let dir = tempfile::TempDir::new().unwrap();
+        let marker = dir.path().join("started");
+        let received = AtomicI32::new(0);
+        let sh = |script: &str| vec!["sh".to_string(), "-c".into(), script.into()];
+        let opts = CommandOptions::default();
+        let err = std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(300));
+                received.store(libc::SIGTERM, Ordering::SeqCst);
+            });
+            run_command_with_signals(
+                &log,
+                &sh("trap 'exit 0' TERM; sleep 30 & wait"),
+                &opts,
+                &received,
+            )
+            .unwrap_err()
+        });
+        assert!(err.contains("interrupted by SIGTERM"), "{}", err);
+        // The next command, e.g. a later step, is not started.
+        let script = format!("touch {}", marker.display());
+        let err = run_command_with_signals(&log, &sh(&script), &opts, &received).unwrap_err();
+        assert!(err.contains("not started"), "{}", err);
+        assert!(!marker.exists());
+    }
+    #[test]
+    fn test_read_env_files_later_file_wins() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = dir.path().join("first.env");
+        let second = dir.path().join("second.env");
+        std::fs::write(&first, "A=1\nB=2\n").unwrap();
+        std::fs::write(&second, "B=3\n").unwrap();
+        let log = test_logger();
+        let paths = [first, second].map(|p| p.to_str().unwrap().to_string());
+        let vars = read_env_files(&log, &paths).unwrap();
+        assert_eq!(
+            vars,
+            vec![("A".into(), "1".into()), ("B".into(), "3".into())]
+        );
     }
 }
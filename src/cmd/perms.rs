@@ -0,0 +1,216 @@
+use crate::cmd::exec::log_fields;
+use crate::logging::Logger;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+pub struct Config {
+    pub paths: Vec<String>,
+    pub owner: Option<(u32, Option<u32>)>,
+    pub mode: Option<u32>,
+    pub recursive: bool,
+}
+/// System directories whose ownership or mode is never changed.
+const PROTECTED: &[&str] = &[
+    "/", "/bin", "/boot", "/etc", "/home", "/lib", "/lib32", "/lib64", "/opt", "/root", "/run",
+    "/sbin", "/tmp", "/usr", "/var",
+];
+/// Pseudo filesystems nothing beneath which is changed.
+const PROTECTED_TREES: &[&str] = &["/dev", "/proc", "/sys"];
+#[derive(Debug, Default, PartialEq)]
+struct Stats {
+    changed: usize,
+    unchanged: usize,
+}
+impl Config {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.paths.is_empty() {
+            return Err("--path is required".into());
+        }
+        if self.owner.is_none() && self.mode.is_none() {
+            return Err("--owner or --mode is required".into());
+        }
+        for path in &self.paths {
+            validate_path(path)?;
+        }
+        Ok(())
+    }
+}
+/// Accept absolute paths without `..` that are not system directories.
+fn validate_path(path: &str) -> Result<PathBuf, String> {
+    let p = Path::new(path);
+    if !p.is_absolute() {
+        return Err(format!("--path '{}' must be absolute", path));
+    }
+    if p.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("--path '{}' must not contain '..'", path));
+    }
+    let clean: PathBuf = p.components().collect();
+    if PROTECTED.iter().any(|s| clean == Path::new(s))
+        || PROTECTED_TREES.iter().any(|s| clean.starts_with(s))
+    {
+        return Err(format!(
+            "refusing to change permissions of system path '{}'",
+            path
+        ));
+    }
+    Ok(clean)
+}
+pub fn run(log: &Logger, cfg: &Config) -> Result<(), String> {
+    cfg.validate()?;
+    for path in &cfg.paths {
+        let clean = validate_path(path)?;
+        let mut fields = vec![("path", path.clone())];
+        if let Some((uid, gid)) = cfg.owner {
+            let owner = match gid {
+                Some(gid) => format!("{}:{}", uid, gid),
+                None => uid.to_string(),
+            };
+            fields.push(("owner", owner));
+        }
+        if let Some(mode) = cfg.mode {
+            fields.push(("mode", format!("{:04o}", mode)));
+        }
+        fields.push(("recursive", cfg.recursive.to_string()));
+        log.info("applying permissions", &log_fields(&fields));
+        let meta =
+            fs::symlink_metadata(&clean).map_err(|e| format!("reading '{}': {}", path, e))?;
+        if meta.file_type().is_symlink() {
+            return Err(format!("--path '{}' is a symlink", path));
+        }
+        let mut stats = Stats::default();
+        apply(&clean, &meta, cfg, &mut stats)?;
+        log.info(
+            "permissions applied",
+            &[
+                ("path", path.as_str()),
+                ("changed", &stats.changed.to_string()),
+                ("unchanged", &stats.unchanged.to_string()),
+            ],
+        );
+    }
+    Ok(())
+}
+/// Apply the owner and mode to `path` and, with `--recursive`, everything
+/// beneath it. Symlinks are never followed: their own owner is changed and
+/// their mode, which is meaningless, left alone.
+fn apply(path: &Path, meta: &fs::Metadata, cfg: &Config, stats: &mut Stats) -> Result<(), String> {
+    if set_perms(path, meta, cfg)? {
+        stats.changed += 1;
+    } else {
+        stats.unchanged += 1;
+    }
+    if !cfg.recursive || !meta.is_dir() {
+        return Ok(());
+    }
+    let entries = fs::read_dir(path).map_err(|e| format!("reading directory {:?}: {}", path, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("reading directory {:?}: {}", path, e))?;
+        let child = entry.path();
+        let meta =
+            fs::symlink_metadata(&child).map_err(|e| format!("reading {:?}: {}", child, e))?;
+        apply(&child, &meta, cfg, stats)?;
+    }
+    Ok(())
+}
+/// Change what differs from the wanted owner and mode; returns whether
+/// anything changed. The owner goes first since chown clears setuid bits.
+#[cfg(unix)]
+fn set_perms(path: &Path, meta: &fs::Metadata, cfg: &Config) -> Result<bool, String> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    let mut changed = false;
+    if let Some((uid, gid)) = cfg.owner {
+        if meta.uid() != uid || gid.is_some_and(|g| meta.gid() != g) {
+            std::os::unix::fs::lchown(path, Some(uid), gid)
+                .map_err(|e| format!("changing owner of {:?}: {}", path, e))?;
+            changed = true;
+        }
+    }
+    if let Some(mode) = cfg.mode {
+        if !meta.file_type().is_symlink() && (changed || meta.mode() & 0o7777 != mode) {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))
+                .map_err(|e| format!("changing mode of {:?}: {}", path, e))?;
+            changed |= meta.mode() & 0o7777 != mode;
+        }
+    }
+    Ok(changed)
+}
+#[cfg(not(unix))]
+fn set_perms(_path: &Path, _meta: &fs::Metadata, _cfg: &Config) -> Result<bool, String> {
+    Err("perms is only supported on unix".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_path() {
+        assert!(validate_path("/data").is_ok());
+        assert!(validate_path("/var/lib/app/").is_ok());
+        assert!(validate_path("/tmp/cache").is_ok());
+        assert!(validate_path("data").unwrap_err().contains("absolute"));
+        assert!(validate_path("/data/../etc").unwrap_err().contains(".."));
+        for path in [
+            "/", "//", "/etc", "/usr/", "/var", "/proc/1", "/sys/fs", "/dev/sda",
+        ] {
+            let err = validate_path(path).unwrap_err();
+            assert!(err.contains("system path"), "{}: {}", path, err);
+        }
+    }
+
+    #[test]
+    fn test_validate_requires_change() {
+        let cfg = Config {
+            paths: vec!["/data".into()],
+            owner: None,
+            mode: None,
+            recursive: false,
+        };
+        assert!(cfg.validate().unwrap_err().contains("--owner or --mode"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_recursive_mode_and_owner() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        let dir = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        fs::set_permissions(outside.path(), fs::Permissions::from_mode(0o600)).unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/file"), "x").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        let meta = fs::metadata(dir.path()).unwrap();
+        // Chowning to the current owner works without privileges.
+        let cfg = Config {
+            paths: vec![],
+            owner: Some((meta.uid(), Some(meta.gid()))),
+            mode: Some(0o750),
+            recursive: true,
+        };
+        let mut stats = Stats::default();
+        apply(dir.path(), &meta, &cfg, &mut stats).unwrap();
+        for path in [
+            dir.path(),
+            &dir.path().join("sub"),
+            &dir.path().join("sub/file"),
+        ] {
+            let mode = fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o7777, 0o750, "{:?}", path);
+        }
+        // The symlink target outside the tree is left alone.
+        let mode = fs::metadata(outside.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o600);
+        assert_eq!(stats.changed + stats.unchanged, 4);
+
+        // A second pass finds nothing to change.
+        let meta = fs::metadata(dir.path()).unwrap();
+        let mut stats = Stats::default();
+        apply(dir.path(), &meta, &cfg, &mut stats).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                changed: 0,
+                unchanged: 4
+            }
+        );
+    }
+}
@@ -1,54 +1,1510 @@
+use crate::diff;
+use crate::duration;
+use crate::glob;
 use crate::logging::Logger;
 use crate::render as render_lib;
+use crate::render::TemplateRenderer;
 use crate::safety;
+use crate::validate;
+use serde::Deserialize;
 use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-pub fn run(
-    log: &Logger,
-    template: &str,
-    output: &str,
-    workdir: &str,
-    mode: &str,
-) -> Result<(), String> {
-    if template.is_empty() {
-        return Err("--template is required".into());
+pub struct Config {
+    pub templates: Vec<String>,
+    pub outputs: Vec<String>,
+    pub pairs_file: String,
+    pub workdir: String,
+    pub mode: String,
+    pub exclude: Vec<String>,
+    pub values: Vec<String>,
+    pub template_dir: String,
+    pub strict: bool,
+    pub dry_run: bool,
+    pub diff: bool,
+    pub file_root: String,
+    pub fail_on_unset: bool,
+    pub validate_format: String,
+    pub validate_cmd: String,
+    pub schema: String,
+    pub merge: Vec<String>,
+    pub merge_lists: String,
+    pub secrets_dir: String,
+    pub watch: bool,
+    pub watch_interval: Duration,
+    pub watch_files: Vec<String>,
+    pub reload_pid: Option<i32>,
+    pub reload_pid_file: String,
+    pub decode_base64: bool,
+}
+
+/// Accepted `--mode` values.
+pub(crate) const MODES: &[&str] = &["envsubst", "gotemplate", "jinja", "auto"];
+
+/// Extensions that `--mode auto` renders with the template engine.
+const TEMPLATE_EXTENSIONS: &[&str] = &["j2", "jinja", "jinja2", "tmpl"];
+
+/// `--output` value that writes the rendered result to stdout.
+const STDOUT: &str = "-";
+
+#[derive(Debug, Deserialize)]
+struct RenderPair {
+    template: String,
+    output: String,
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.merge.is_empty() {
+            self.validate_pairs()?;
+        } else {
+            self.validate_merge()?;
+        }
+        if !MODES.contains(&self.mode.as_str()) {
+            return Err(format!(
+                "--mode must be one of {}, got {:?}",
+                MODES.join(", "),
+                self.mode
+            ));
+        }
+        if !self.values.is_empty() && !self.allows_template() {
+            return Err("--values requires --mode gotemplate".into());
+        }
+        if self.dry_run && self.diff {
+            return Err("--dry-run and --diff are mutually exclusive".into());
+        }
+        if self.fail_on_unset && !self.allows_envsubst() {
+            return Err(
+                "--fail-on-unset requires --mode envsubst (use --strict for gotemplate)".into(),
+            );
+        }
+        if self.strict && !self.allows_template() {
+            return Err("--strict requires --mode gotemplate".into());
+        }
+        if !self.validate_format.is_empty()
+            && !validate::FORMATS.contains(&self.validate_format.as_str())
+        {
+            return Err(format!(
+                "--validate must be one of {}, got {:?}",
+                validate::FORMATS.join(", "),
+                self.validate_format
+            ));
+        }
+        if !self.validate_cmd.is_empty()
+            && shlex::split(&self.validate_cmd).is_none_or(|w| w.is_empty())
+        {
+            return Err(format!(
+                "--validate-cmd {:?} is not a valid command",
+                self.validate_cmd
+            ));
+        }
+        self.validate_watch()?;
+        if self.decode_base64
+            && (!self.merge.is_empty()
+                || !self.validate_format.is_empty()
+                || !self.schema.is_empty())
+        {
+            return Err(
+                "--decode-base64 cannot be combined with --merge, --validate or --schema".into(),
+            );
+        }
+        if !self.secrets_dir.is_empty() && !self.allows_template() {
+            return Err("--secrets-dir requires --mode gotemplate".into());
+        }
+        if !self.template_dir.is_empty() {
+            if !self.allows_template() {
+                return Err("--template-dir requires --mode gotemplate".into());
+            }
+            if !Path::new(&self.template_dir).is_dir() {
+                return Err(format!(
+                    "--template-dir {} is not a directory",
+                    self.template_dir
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_pairs(&self) -> Result<(), String> {
+        if self.templates.is_empty() && self.pairs_file.is_empty() {
+            return Err("--template, --pairs-file or --merge is required".into());
+        }
+        if self.templates.len() != self.outputs.len() {
+            return Err(format!(
+                "each --template needs a matching --output (got {} templates, {} outputs)",
+                self.templates.len(),
+                self.outputs.len()
+            ));
+        }
+        if self.templates.iter().any(|t| t.is_empty()) {
+            return Err("--template must not be empty".into());
+        }
+        if self.outputs.iter().any(|o| o.is_empty()) {
+            return Err("--output must not be empty".into());
+        }
+        if self.merge_lists != "replace" {
+            return Err("--merge-lists requires --merge".into());
+        }
+        Ok(())
+    }
+
+    fn validate_watch(&self) -> Result<(), String> {
+        let reload = self.reload_pid.is_some() || !self.reload_pid_file.is_empty();
+        if !self.watch {
+            if reload || !self.watch_files.is_empty() {
+                return Err(
+                    "--watch-file, --reload-pid and --reload-pid-file require --watch".into(),
+                );
+            }
+            return Ok(());
+        }
+        if self.dry_run || self.diff {
+            return Err("--watch cannot be combined with --dry-run or --diff".into());
+        }
+        if self.outputs.iter().any(|o| o == STDOUT) {
+            return Err("--watch cannot write to --output -".into());
+        }
+        if self.watch_interval.is_zero() {
+            return Err("--watch-interval must be greater than zero".into());
+        }
+        if self.reload_pid.is_some() && !self.reload_pid_file.is_empty() {
+            return Err("--reload-pid and --reload-pid-file are mutually exclusive".into());
+        }
+        if self.reload_pid.is_some_and(|pid| pid <= 0) {
+            return Err("--reload-pid must be a positive process id".into());
+        }
+        if reload && !cfg!(unix) {
+            return Err("--reload-pid is only supported on Unix".into());
+        }
+        Ok(())
+    }
+
+    fn validate_merge(&self) -> Result<(), String> {
+        if !self.templates.is_empty() || !self.pairs_file.is_empty() {
+            return Err("--merge cannot be combined with --template or --pairs-file".into());
+        }
+        if self.outputs.len() != 1 || self.outputs[0].is_empty() {
+            return Err("--merge requires exactly one --output".into());
+        }
+        if self.merge.iter().any(|m| m.is_empty()) {
+            return Err("--merge must not be empty".into());
+        }
+        render_lib::ListMerge::parse(&self.merge_lists).map(|_| ())
+    }
+
+    /// Whether some templates may be rendered in gotemplate mode.
+    fn allows_template(&self) -> bool {
+        self.mode != "envsubst"
+    }
+
+    /// Whether some templates may be rendered in envsubst mode.
+    fn allows_envsubst(&self) -> bool {
+        self.mode == "envsubst" || self.mode == "auto"
+    }
+
+    /// The engine used for `template`: `envsubst` or `gotemplate`. `jinja` is
+    /// an alias for `gotemplate`, and `auto` picks by file extension.
+    fn mode_for(&self, template: &str) -> &'static str {
+        template_mode(&self.mode, template)
+    }
+
+    fn pairs(&self) -> Result<Vec<RenderPair>, String> {
+        let mut pairs: Vec<RenderPair> = self
+            .templates
+            .iter()
+            .zip(&self.outputs)
+            .map(|(t, o)| RenderPair {
+                template: t.clone(),
+                output: o.clone(),
+            })
+            .collect();
+        if !self.pairs_file.is_empty() {
+            pairs.extend(load_pairs_file(&self.pairs_file)?);
+        }
+        Ok(pairs)
+    }
+}
+
+/// Read a YAML/JSON list of `{template, output}` entries.
+fn load_pairs_file(path: &str) -> Result<Vec<RenderPair>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("reading pairs file {}: {}", path, e))?;
+    let pairs: Vec<RenderPair> = serde_yaml::from_str(&content)
+        .map_err(|e| format!("parsing pairs file {}: {}", path, e))?;
+    for (i, p) in pairs.iter().enumerate() {
+        if p.template.is_empty() || p.output.is_empty() {
+            return Err(format!(
+                "pairs file {}: entry {} must set both template and output",
+                path,
+                i + 1
+            ));
+        }
     }
-    if output.is_empty() {
-        return Err("--output is required".into());
+    Ok(pairs)
+}
+
+/// Render every configured pair. Returns true when `--diff` found at least one
+/// output that differs from the file on disk.
+pub fn run(log: &Logger, cfg: &Config) -> Result<bool, String> {
+    cfg.validate()?;
+    let schema = if cfg.schema.is_empty() {
+        None
+    } else {
+        Some(validate::load_schema(&cfg.schema)?)
+    };
+    let schema = schema.as_ref();
+    if !cfg.merge.is_empty() {
+        return run_merge(log, cfg, schema);
+    }
+    let pairs = cfg.pairs()?;
+    let file_root = if cfg.file_root.is_empty() {
+        &cfg.workdir
+    } else {
+        &cfg.file_root
+    };
+    let mut renderer = TemplateRenderer::new()
+        .with_strict(cfg.strict)
+        .with_file_root(file_root);
+    if !cfg.values.is_empty() {
+        renderer = renderer.with_values(render_lib::load_values(&cfg.values)?);
+    }
+    if !cfg.template_dir.is_empty() {
+        renderer = renderer.with_template_dir(&cfg.template_dir);
+    }
+    if !cfg.secrets_dir.is_empty() {
+        renderer = renderer.with_secrets(render_lib::load_secrets_dir(&cfg.secrets_dir)?);
     }
-    if mode != "envsubst" && mode != "gotemplate" {
+    let mut changed = false;
+    for pair in &pairs {
+        if pair.output == STDOUT {
+            if Path::new(&pair.template).is_dir() {
+                return Err(format!(
+                    "--output - cannot be used with template directory {}",
+                    pair.template
+                ));
+            }
+            let result = render_template(log, &renderer, cfg, &pair.template, "<stdout>")?;
+            write_stdout(log, cfg, schema, &result, &pair.template)?;
+        } else if Path::new(&pair.template).is_dir() {
+            changed |= render_tree(log, &renderer, cfg, schema, &pair.template, &pair.output)?;
+        } else {
+            let out_path = safety::validate_file_path(&cfg.workdir, &pair.output)?;
+            changed |= render_file(log, &renderer, cfg, schema, &pair.template, &out_path)?;
+        }
+    }
+    if pairs.len() > 1 {
+        log.info(
+            "all templates rendered",
+            &[("count", &pairs.len().to_string())],
+        );
+    }
+    Ok(changed)
+}
+
+/// Render once, then poll the inputs every `--watch-interval` and re-render
+/// whenever their content changes, sending SIGHUP to the reload target after
+/// each successful re-render. A failed re-render is logged and the previous
+/// outputs are kept. Only returns if the initial render fails.
+pub fn watch(log: &Logger, cfg: &Config) -> Result<(), String> {
+    run(log, cfg)?;
+    let paths = cfg.watched_paths()?;
+    let mut last = fingerprint(&paths);
+    log.info(
+        "watching for changes",
+        &[
+            ("paths", &paths.len().to_string()),
+            ("interval", &duration::format_duration(cfg.watch_interval)),
+        ],
+    );
+    loop {
+        std::thread::sleep(cfg.watch_interval);
+        let current = fingerprint(&paths);
+        if current == last {
+            continue;
+        }
+        last = current;
+        log.info("change detected, re-rendering", &[]);
+        match run(log, cfg) {
+            Ok(_) => {
+                if let Err(e) = reload(log, cfg) {
+                    log.warn(&e, &[]);
+                }
+            }
+            Err(e) => log.error(
+                "re-render failed, keeping previous outputs",
+                &[("error", &e)],
+            ),
+        }
+    }
+}
+
+impl Config {
+    /// Every input whose change should trigger a re-render.
+    fn watched_paths(&self) -> Result<Vec<String>, String> {
+        let mut paths: Vec<String> = if self.merge.is_empty() {
+            self.pairs()?.into_iter().map(|p| p.template).collect()
+        } else {
+            self.merge.clone()
+        };
+        paths.extend(self.values.iter().cloned());
+        paths.extend(self.watch_files.iter().cloned());
+        for single in [
+            &self.pairs_file,
+            &self.template_dir,
+            &self.secrets_dir,
+            &self.schema,
+        ] {
+            if !single.is_empty() {
+                paths.push(single.clone());
+            }
+        }
+        Ok(paths)
+    }
+}
+
+/// Hash the content of every file under `paths`. Content rather than mtime is
+/// compared because Kubernetes updates ConfigMap and Secret volumes by
+/// swapping a symlink, which leaves the visible files' own metadata intact.
+fn fingerprint(paths: &[String]) -> u64 {
+    fn visit(path: &Path, ancestors: &mut Vec<PathBuf>, hasher: &mut DefaultHasher) {
+        path.hash(hasher);
+        if path.is_dir() {
+            // Skip symlinks leading back to a directory being walked.
+            let Ok(real) = fs::canonicalize(path) else {
+                return;
+            };
+            if ancestors.contains(&real) {
+                return;
+            }
+            ancestors.push(real);
+            let mut entries: Vec<_> = fs::read_dir(path)
+                .map(|rd| rd.filter_map(Result::ok).map(|e| e.path()).collect())
+                .unwrap_or_default();
+            entries.sort();
+            for entry in entries {
+                visit(&entry, ancestors, hasher);
+            }
+            ancestors.pop();
+        } else {
+            fs::read(path).ok().hash(hasher);
+        }
+    }
+    let mut hasher = DefaultHasher::new();
+    for path in paths {
+        visit(Path::new(path), &mut Vec::new(), &mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Send SIGHUP to `--reload-pid` or the process named in `--reload-pid-file`.
+fn reload(log: &Logger, cfg: &Config) -> Result<(), String> {
+    let pid = match cfg.reload_pid {
+        Some(pid) => pid,
+        None if cfg.reload_pid_file.is_empty() => return Ok(()),
+        None => fs::read_to_string(&cfg.reload_pid_file)
+            .map_err(|e| format!("reading pid file {}: {}", cfg.reload_pid_file, e))?
+            .trim()
+            .parse()
+            .map_err(|e| format!("parsing pid file {}: {}", cfg.reload_pid_file, e))?,
+    };
+    log.info("sending SIGHUP", &[("pid", &pid.to_string())]);
+    send_sighup(pid)
+}
+
+#[cfg(unix)]
+fn send_sighup(pid: i32) -> Result<(), String> {
+    // SAFETY: kill(2) has no memory-safety preconditions.
+    if unsafe { libc::kill(pid, libc::SIGHUP) } != 0 {
         return Err(format!(
-            "--mode must be envsubst or gotemplate, got {:?}",
-            mode
+            "sending SIGHUP to pid {}: {}",
+            pid,
+            std::io::Error::last_os_error()
         ));
     }
+    Ok(())
+}
 
-    let out_path = safety::validate_file_path(workdir, output)?;
+#[cfg(not(unix))]
+fn send_sighup(_pid: i32) -> Result<(), String> {
+    Err("--reload-pid is only supported on Unix".into())
+}
+
+/// Deep-merge the `--merge` documents into the single output, as JSON when
+/// the output (or, for stdout, the first input) is a `.json` file and as
+/// YAML otherwise.
+fn run_merge(
+    log: &Logger,
+    cfg: &Config,
+    schema: Option<&jsonschema::Validator>,
+) -> Result<bool, String> {
+    let lists = render_lib::ListMerge::parse(&cfg.merge_lists)?;
+    let output = &cfg.outputs[0];
+    log.info(
+        "merging documents",
+        &[
+            ("files", &cfg.merge.join(",")),
+            ("output", output),
+            ("lists", &cfg.merge_lists),
+        ],
+    );
+    let merged = render_lib::load_merged(&cfg.merge, &lists, "merge file")?;
+    let format_source = if output == STDOUT {
+        &cfg.merge[0]
+    } else {
+        output
+    };
+    let content = if format_source.ends_with(".json") {
+        serde_json::to_string_pretty(&merged)
+            .map(|json| json + "\n")
+            .map_err(|e| format!("encoding merged output as JSON: {}", e))?
+    } else {
+        serde_yaml::to_string(&merged)
+            .map_err(|e| format!("encoding merged output as YAML: {}", e))?
+    };
+    if output == STDOUT {
+        write_stdout(log, cfg, schema, content.as_bytes(), &cfg.merge[0])?;
+        return Ok(false);
+    }
+    let out_path = safety::validate_file_path(&cfg.workdir, output)?;
+    write_output(log, cfg, schema, content.as_bytes(), &out_path)
+}
+
+/// The engine `mode` renders `template` with: `envsubst` or `gotemplate`.
+pub(crate) fn template_mode(mode: &str, template: &str) -> &'static str {
+    match mode {
+        "envsubst" => "envsubst",
+        "auto" => {
+            let ext = Path::new(template)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            if TEMPLATE_EXTENSIONS.contains(&ext) {
+                "gotemplate"
+            } else {
+                "envsubst"
+            }
+        }
+        _ => "gotemplate",
+    }
+}
+
+fn render_tree(
+    log: &Logger,
+    renderer: &TemplateRenderer,
+    cfg: &Config,
+    schema: Option<&jsonschema::Validator>,
+    template_dir: &str,
+    output_dir: &str,
+) -> Result<bool, String> {
+    let mut files = Vec::new();
+    collect_files(
+        Path::new(template_dir),
+        "",
+        &cfg.exclude,
+        &mut Vec::new(),
+        &mut files,
+    )?;
+    log.info(
+        "rendering template directory",
+        &[
+            ("template_dir", template_dir),
+            ("output_dir", output_dir),
+            ("files", &files.len().to_string()),
+        ],
+    );
+    let mut changed = false;
+    for rel in &files {
+        let src = Path::new(template_dir).join(rel);
+        let out_path =
+            safety::validate_file_path(&cfg.workdir, &format!("{}/{}", output_dir, rel))?;
+        changed |= render_file(
+            log,
+            renderer,
+            cfg,
+            schema,
+            src.to_str().unwrap_or(""),
+            &out_path,
+        )?;
+    }
+    Ok(changed)
+}
+
+/// Recursively collect files under `dir` as `/`-separated paths relative to
+/// the template root, sorted for deterministic rendering order. `ancestors`
+/// holds the resolved directories being walked, so a symlink looping back
+/// to one of them is skipped instead of followed forever.
+fn collect_files(
+    dir: &Path,
+    prefix: &str,
+    exclude: &[String],
+    ancestors: &mut Vec<PathBuf>,
+    files: &mut Vec<String>,
+) -> Result<(), String> {
+    let real = fs::canonicalize(dir)
+        .map_err(|e| format!("reading template directory {:?}: {}", dir, e))?;
+    if ancestors.contains(&real) {
+        return Ok(());
+    }
+    ancestors.push(real);
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("reading template directory {:?}: {}", dir, e))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("reading template directory {:?}: {}", dir, e))?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Kubernetes ConfigMap/Secret volumes contain `..data` and timestamped
+        // `..2024_...` directories that the visible files symlink into;
+        // rendering them would duplicate every file.
+        if name.starts_with("..") {
+            continue;
+        }
+        let rel = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if glob::matches_any(exclude, &rel) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, &rel, exclude, ancestors, files)?;
+        } else {
+            files.push(rel);
+        }
+    }
+    ancestors.pop();
+    Ok(())
+}
+
+fn render_template(
+    log: &Logger,
+    renderer: &TemplateRenderer,
+    cfg: &Config,
+    template: &str,
+    out_label: &str,
+) -> Result<Vec<u8>, String> {
     let data = fs::read_to_string(template)
         .map_err(|e| format!("reading template {}: {}", template, e))?;
+    let mode = cfg.mode_for(template);
 
     log.info(
         "rendering template",
         &[
             ("template", template),
-            ("output", out_path.to_str().unwrap_or("")),
+            ("output", out_label),
             ("mode", mode),
         ],
     );
 
-    let result = match mode {
-        "envsubst" => render_lib::envsubst(&data),
-        "gotemplate" => render_lib::template_render(&data)?,
+    let rendered = match mode {
+        "envsubst" if cfg.fail_on_unset => render_lib::envsubst_strict(&data)
+            .map_err(|e| format!("rendering {}: {}", template, e))?,
+        "envsubst" => render_lib::envsubst(&data)?,
+        "gotemplate" => renderer.render(template, &data)?,
         _ => unreachable!(),
     };
+    if cfg.decode_base64 {
+        return render_lib::decode_base64(&rendered)
+            .map_err(|e| format!("decoding rendered {}: {}", template, e));
+    }
+    Ok(rendered.into_bytes())
+}
+
+fn render_file(
+    log: &Logger,
+    renderer: &TemplateRenderer,
+    cfg: &Config,
+    schema: Option<&jsonschema::Validator>,
+    template: &str,
+    out_path: &Path,
+) -> Result<bool, String> {
+    let out_label = out_path.to_str().unwrap_or("");
+    let result = render_template(log, renderer, cfg, template, out_label)?;
+    write_output(log, cfg, schema, &result, out_path)
+}
+
+/// Check `result` and print it to stdout. `source` names the scratch file
+/// handed to `--validate-cmd`.
+fn write_stdout(
+    log: &Logger,
+    cfg: &Config,
+    schema: Option<&jsonschema::Validator>,
+    result: &[u8],
+    source: &str,
+) -> Result<(), String> {
+    let scratch = std::env::temp_dir().join(
+        Path::new(source)
+            .file_name()
+            .unwrap_or_else(|| "stdout".as_ref()),
+    );
+    check_output(log, cfg, schema, result, "<stdout>", &scratch, false)?;
+    std::io::stdout()
+        .lock()
+        .write_all(result)
+        .map_err(|e| format!("writing to stdout: {}", e))
+}
+
+/// Check `result` and write it to `out_path`, or print/diff it for
+/// `--dry-run`/`--diff`. Returns true when `--diff` found a difference.
+fn write_output(
+    log: &Logger,
+    cfg: &Config,
+    schema: Option<&jsonschema::Validator>,
+    result: &[u8],
+    out_path: &Path,
+) -> Result<bool, String> {
+    let out_label = out_path.to_str().unwrap_or("");
+    if cfg.dry_run || cfg.diff {
+        // Nothing is written, so validate against a scratch copy instead of
+        // creating directories next to the real output.
+        let scratch = std::env::temp_dir().join(out_path.file_name().unwrap_or_default());
+        check_output(log, cfg, schema, result, out_label, &scratch, false)?;
+    }
+
+    if cfg.dry_run {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "# {}", out_label)
+            .and_then(|_| stdout.write_all(result))
+            .map_err(|e| format!("writing to stdout: {}", e))?;
+        return Ok(false);
+    }
+
+    if cfg.diff {
+        let (existing, old_label) = match fs::read(out_path) {
+            Ok(existing) => (existing, out_label.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                (Vec::new(), "/dev/null".to_string())
+            }
+            Err(e) => return Err(format!("reading existing output {:?}: {}", out_path, e)),
+        };
+        let patch = match (std::str::from_utf8(&existing), std::str::from_utf8(result)) {
+            (Ok(old), Ok(new)) => diff::unified(old, new, &old_label, out_label),
+            _ if existing == result => String::new(),
+            _ => format!("Binary files {} and {} differ\n", old_label, out_label),
+        };
+        if patch.is_empty() {
+            log.info("output unchanged", &[("output", out_label)]);
+            return Ok(false);
+        }
+        std::io::stdout()
+            .lock()
+            .write_all(patch.as_bytes())
+            .map_err(|e| format!("writing to stdout: {}", e))?;
+        log.info("output differs", &[("output", out_label)]);
+        return Ok(true);
+    }
+
+    check_output(log, cfg, schema, result, out_label, out_path, true)?;
+    log.info("render completed", &[("output", out_label)]);
+    Ok(false)
+}
+
+/// Run the `--validate`, `--schema` and `--validate-cmd` checks on rendered output. The
+/// content is staged in a temporary file next to `dest` for the validator
+/// command and, when `commit` is set, atomically renamed over `dest` only
+/// once every check has passed.
+fn check_output(
+    log: &Logger,
+    cfg: &Config,
+    schema: Option<&jsonschema::Validator>,
+    content: &[u8],
+    out_label: &str,
+    dest: &Path,
+    commit: bool,
+) -> Result<(), String> {
+    let text = || {
+        std::str::from_utf8(content)
+            .map_err(|_| format!("validating {}: output is not valid UTF-8", out_label))
+    };
+    if !cfg.validate_format.is_empty() {
+        validate::check_format(&cfg.validate_format, text()?)
+            .map_err(|e| format!("validating {}: {}", out_label, e))?;
+    }
+    if let Some(schema) = schema {
+        validate::check_schema(schema, text()?)
+            .map_err(|e| format!("validating {} against {}: {}", out_label, cfg.schema, e))?;
+    }
+    if cfg.validate_cmd.is_empty() {
+        if commit {
+            safety::write_file_atomic(dest, content)?;
+        }
+        return Ok(());
+    }
+    let mut file = safety::AtomicFile::create(dest)?;
+    file.write_all(content)
+        .map_err(|e| format!("writing output {:?}: {}", dest, e))?;
+    run_validate_cmd(log, &cfg.validate_cmd, file.path(), out_label)?;
+    if commit {
+        file.commit()?;
+    }
+    Ok(())
+}
 
-    if let Some(parent) = out_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("creating output directory: {}", e))?;
+/// Run `--validate-cmd` with `{}` replaced by the staged file path, or with
+/// the path appended when the command has no `{}` placeholder.
+fn run_validate_cmd(log: &Logger, cmd: &str, path: &Path, out_label: &str) -> Result<(), String> {
+    let path = path.to_str().unwrap_or_default();
+    let words = shlex::split(cmd).unwrap_or_default();
+    let mut args: Vec<String> = words.iter().map(|w| w.replace("{}", path)).collect();
+    if !cmd.contains("{}") {
+        args.push(path.to_string());
     }
-    fs::write(&out_path, result).map_err(|e| format!("writing output {:?}: {}", out_path, e))?;
     log.info(
-        "render completed",
-        &[("output", out_path.to_str().unwrap_or(""))],
+        "running validator",
+        &[("output", out_label), ("command", &args[0])],
     );
+    let code = super::run_command_in_dir(log, &args, None)?;
+    if code != 0 {
+        return Err(format!(
+            "validating {}: {} exited with code {}",
+            out_label, args[0], code
+        ));
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::Level;
+    use tempfile::TempDir;
+
+    fn test_logger() -> Logger {
+        struct NullWriter;
+        impl Write for NullWriter {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        Logger::new(Box::new(NullWriter), false, Level::Info)
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn config(template: &Path, output: &str, workdir: &TempDir, mode: &str) -> Config {
+        Config {
+            templates: vec![template.to_str().unwrap().to_string()],
+            outputs: vec![output.to_string()],
+            pairs_file: String::new(),
+            workdir: workdir.path().to_str().unwrap().to_string(),
+            mode: mode.to_string(),
+            exclude: Vec::new(),
+            values: Vec::new(),
+            template_dir: String::new(),
+            strict: false,
+            dry_run: false,
+            diff: false,
+            file_root: String::new(),
+            fail_on_unset: false,
+            validate_format: String::new(),
+            validate_cmd: String::new(),
+            schema: String::new(),
+            merge: vec![],
+            merge_lists: "replace".into(),
+            secrets_dir: String::new(),
+            watch: false,
+            watch_interval: Duration::from_secs(2),
+            watch_files: vec![],
+            reload_pid: None,
+            reload_pid_file: String::new(),
+            decode_base64: false,
+        }
+    }
+
+    #[test]
+    fn test_render_directory_tree() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        std::env::set_var("TEST_RENDER_TREE_VAR", "tree");
+        write(&src.path().join("a.conf"), "a=${TEST_RENDER_TREE_VAR}");
+        write(
+            &src.path().join("nested/deep/b.conf"),
+            "b=$TEST_RENDER_TREE_VAR",
+        );
+        write(&src.path().join("skip.bak"), "ignored");
+        write(&src.path().join("..data/a.conf"), "configmap internals");
+
+        let mut cfg = config(src.path(), "out", &work, "envsubst");
+        cfg.exclude = vec!["*.bak".to_string()];
+        run(&test_logger(), &cfg).unwrap();
+
+        let out = work.path().join("out");
+        assert_eq!(fs::read_to_string(out.join("a.conf")).unwrap(), "a=tree");
+        assert_eq!(
+            fs::read_to_string(out.join("nested/deep/b.conf")).unwrap(),
+            "b=tree"
+        );
+        assert!(!out.join("skip.bak").exists());
+        assert!(!out.join("..data").exists());
+        std::env::remove_var("TEST_RENDER_TREE_VAR");
+    }
+
+    #[test]
+    fn test_render_directory_exclude_subtree() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("keep.txt"), "keep");
+        write(&src.path().join("drafts/x.txt"), "draft");
+
+        let mut cfg = config(src.path(), ".", &work, "gotemplate");
+        cfg.exclude = vec!["drafts".to_string()];
+        run(&test_logger(), &cfg).unwrap();
+
+        assert!(work.path().join("keep.txt").exists());
+        assert!(!work.path().join("drafts").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_render_directory_symlink_loop() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("conf/a.conf"), "a");
+        std::os::unix::fs::symlink("..", src.path().join("conf/up")).unwrap();
+        std::os::unix::fs::symlink(".", src.path().join("self")).unwrap();
+
+        run(
+            &test_logger(),
+            &config(src.path(), "out", &work, "envsubst"),
+        )
+        .unwrap();
+
+        let out = work.path().join("out");
+        assert_eq!(fs::read_to_string(out.join("conf/a.conf")).unwrap(), "a");
+        assert!(!out.join("self").exists());
+        assert!(!out.join("conf/up").exists());
+    }
+
+    #[test]
+    fn test_render_directory_empty() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        let cfg = config(src.path(), "out", &work, "envsubst");
+        assert!(run(&test_logger(), &cfg).is_ok());
+    }
+
+    #[test]
+    fn test_render_directory_traversal_rejected() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("a.conf"), "a");
+        let cfg = config(src.path(), "../escape", &work, "envsubst");
+        let err = run(&test_logger(), &cfg).unwrap_err();
+        assert!(err.contains("path traversal"));
+    }
+
+    #[test]
+    fn test_render_multiple_pairs() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("a.tmpl"), "a={{ 1 + 1 }}");
+        write(&src.path().join("b.tmpl"), "b={{ 'x' | upper }}");
+        let cfg = Config {
+            templates: vec![
+                src.path().join("a.tmpl").to_str().unwrap().to_string(),
+                src.path().join("b.tmpl").to_str().unwrap().to_string(),
+            ],
+            outputs: vec!["a.conf".into(), "sub/b.conf".into()],
+            pairs_file: String::new(),
+            workdir: work.path().to_str().unwrap().to_string(),
+            mode: "gotemplate".into(),
+            exclude: Vec::new(),
+            values: Vec::new(),
+            template_dir: String::new(),
+            strict: false,
+            dry_run: false,
+            diff: false,
+            file_root: String::new(),
+            fail_on_unset: false,
+            validate_format: String::new(),
+            validate_cmd: String::new(),
+            schema: String::new(),
+            merge: vec![],
+            merge_lists: "replace".into(),
+            secrets_dir: String::new(),
+            watch: false,
+            watch_interval: Duration::from_secs(2),
+            watch_files: vec![],
+            reload_pid: None,
+            reload_pid_file: String::new(),
+            decode_base64: false,
+        };
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(work.path().join("a.conf")).unwrap(),
+            "a=2"
+        );
+        assert_eq!(
+            fs::read_to_string(work.path().join("sub/b.conf")).unwrap(),
+            "b=X"
+        );
+    }
+
+    #[test]
+    fn test_render_pairs_file() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("a.tmpl"), "alpha");
+        write(&src.path().join("b.tmpl"), "beta");
+        let pairs = src.path().join("pairs.yaml");
+        write(
+            &pairs,
+            &format!(
+                "- template: {}\n  output: a.conf\n- template: {}\n  output: b.conf\n",
+                src.path().join("a.tmpl").display(),
+                src.path().join("b.tmpl").display()
+            ),
+        );
+        let cfg = Config {
+            templates: Vec::new(),
+            outputs: Vec::new(),
+            pairs_file: pairs.to_str().unwrap().to_string(),
+            workdir: work.path().to_str().unwrap().to_string(),
+            mode: "envsubst".into(),
+            exclude: Vec::new(),
+            values: Vec::new(),
+            template_dir: String::new(),
+            strict: false,
+            dry_run: false,
+            diff: false,
+            file_root: String::new(),
+            fail_on_unset: false,
+            validate_format: String::new(),
+            validate_cmd: String::new(),
+            schema: String::new(),
+            merge: vec![],
+            merge_lists: "replace".into(),
+            secrets_dir: String::new(),
+            watch: false,
+            watch_interval: Duration::from_secs(2),
+            watch_files: vec![],
+            reload_pid: None,
+            reload_pid_file: String::new(),
+            decode_base64: false,
+        };
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(work.path().join("a.conf")).unwrap(),
+            "alpha"
+        );
+        assert_eq!(
+            fs::read_to_string(work.path().join("b.conf")).unwrap(),
+            "beta"
+        );
+    }
+
+    #[test]
+    fn test_pairs_file_missing_output_rejected() {
+        let src = TempDir::new().unwrap();
+        let pairs = src.path().join("pairs.yaml");
+        write(&pairs, "- template: a.tmpl\n  output: \"\"\n");
+        let err = load_pairs_file(pairs.to_str().unwrap()).unwrap_err();
+        assert!(err.contains("must set both template and output"));
+    }
+
+    #[test]
+    fn test_validate_mismatched_pairs() {
+        let cfg = Config {
+            templates: vec!["a.tmpl".into(), "b.tmpl".into()],
+            outputs: vec!["a.conf".into()],
+            pairs_file: String::new(),
+            workdir: "/work".into(),
+            mode: "envsubst".into(),
+            exclude: Vec::new(),
+            values: Vec::new(),
+            template_dir: String::new(),
+            strict: false,
+            dry_run: false,
+            diff: false,
+            file_root: String::new(),
+            fail_on_unset: false,
+            validate_format: String::new(),
+            validate_cmd: String::new(),
+            schema: String::new(),
+            merge: vec![],
+            merge_lists: "replace".into(),
+            secrets_dir: String::new(),
+            watch: false,
+            watch_interval: Duration::from_secs(2),
+            watch_files: vec![],
+            reload_pid: None,
+            reload_pid_file: String::new(),
+            decode_base64: false,
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(err.contains("matching --output"));
+    }
+
+    #[test]
+    fn test_render_with_values_files() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(
+            &src.path().join("app.tmpl"),
+            "{{ values.app.name }}-{{ values.app.tier }}",
+        );
+        write(
+            &src.path().join("base.yaml"),
+            "app:\n  name: api\n  tier: dev\n",
+        );
+        write(&src.path().join("prod.yaml"), "app:\n  tier: prod\n");
+        let mut cfg = config(
+            &src.path().join("app.tmpl"),
+            "app.conf",
+            &work,
+            "gotemplate",
+        );
+        cfg.values = vec![
+            src.path().join("base.yaml").to_str().unwrap().to_string(),
+            src.path().join("prod.yaml").to_str().unwrap().to_string(),
+        ];
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(work.path().join("app.conf")).unwrap(),
+            "api-prod"
+        );
+    }
+
+    #[test]
+    fn test_render_with_template_dir_partials() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("partials/footer.j2"), "-- end --");
+        write(
+            &src.path().join("app.tmpl"),
+            "body\n{% include 'footer.j2' %}",
+        );
+        let mut cfg = config(
+            &src.path().join("app.tmpl"),
+            "app.conf",
+            &work,
+            "gotemplate",
+        );
+        cfg.template_dir = src.path().join("partials").to_str().unwrap().to_string();
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(work.path().join("app.conf")).unwrap(),
+            "body\n-- end --"
+        );
+    }
+
+    #[test]
+    fn test_render_strict_fails_on_typo() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(
+            &src.path().join("app.tmpl"),
+            "host={{ env.DB_HSOT_TYPO_XYZ }}",
+        );
+        let mut cfg = config(
+            &src.path().join("app.tmpl"),
+            "app.conf",
+            &work,
+            "gotemplate",
+        );
+        cfg.strict = true;
+        assert!(run(&test_logger(), &cfg).is_err());
+        assert!(!work.path().join("app.conf").exists());
+    }
+
+    #[test]
+    fn test_render_dry_run_writes_nothing() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("app.tmpl"), "preview");
+        let mut cfg = config(&src.path().join("app.tmpl"), "app.conf", &work, "envsubst");
+        cfg.dry_run = true;
+        assert!(!run(&test_logger(), &cfg).unwrap());
+        assert!(!work.path().join("app.conf").exists());
+    }
+
+    #[test]
+    fn test_render_diff_reports_changes() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("app.tmpl"), "port=8080\n");
+        let mut cfg = config(&src.path().join("app.tmpl"), "app.conf", &work, "envsubst");
+        cfg.diff = true;
+
+        // Missing output counts as a difference.
+        assert!(run(&test_logger(), &cfg).unwrap());
+        assert!(!work.path().join("app.conf").exists());
+
+        write(&work.path().join("app.conf"), "port=8080\n");
+        assert!(!run(&test_logger(), &cfg).unwrap());
+
+        write(&work.path().join("app.conf"), "port=9090\n");
+        assert!(run(&test_logger(), &cfg).unwrap());
+        assert_eq!(
+            fs::read_to_string(work.path().join("app.conf")).unwrap(),
+            "port=9090\n"
+        );
+    }
+
+    #[test]
+    fn test_render_file_function_uses_file_root() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        let secrets = TempDir::new().unwrap();
+        write(&secrets.path().join("ca.crt"), "CERT");
+        write(
+            &src.path().join("app.tmpl"),
+            "{% if fileExists('ca.crt') %}{{ file('ca.crt') }}{% endif %}",
+        );
+        let mut cfg = config(
+            &src.path().join("app.tmpl"),
+            "app.conf",
+            &work,
+            "gotemplate",
+        );
+        cfg.file_root = secrets.path().to_str().unwrap().to_string();
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(work.path().join("app.conf")).unwrap(),
+            "CERT"
+        );
+    }
+
+    #[test]
+    fn test_render_fail_on_unset() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(
+            &src.path().join("app.tmpl"),
+            "host=${TEST_FOU_HOST_XYZ}\nport=${TEST_FOU_PORT_XYZ}",
+        );
+        let mut cfg = config(&src.path().join("app.tmpl"), "app.conf", &work, "envsubst");
+        cfg.fail_on_unset = true;
+        let err = run(&test_logger(), &cfg).unwrap_err();
+        assert!(
+            err.contains("TEST_FOU_HOST_XYZ, TEST_FOU_PORT_XYZ"),
+            "{}",
+            err
+        );
+        assert!(!work.path().join("app.conf").exists());
+    }
+
+    #[test]
+    fn test_render_validate_format_blocks_write() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("app.json"), "{\"port\": 80,}");
+        let mut cfg = config(&src.path().join("app.json"), "app.json", &work, "envsubst");
+        cfg.validate_format = "json".into();
+        let err = run(&test_logger(), &cfg).unwrap_err();
+        assert!(err.contains("invalid JSON"), "{}", err);
+        assert_eq!(fs::read_dir(work.path()).unwrap().count(), 0);
+
+        write(&src.path().join("app.json"), "{\"port\": 80}");
+        run(&test_logger(), &cfg).unwrap();
+        assert!(work.path().join("app.json").exists());
+    }
+
+    #[test]
+    fn test_render_schema_blocks_write() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(
+            &src.path().join("schema.json"),
+            r#"{"type": "object", "properties": {"replicas": {"type": "integer", "minimum": 1}}}"#,
+        );
+        write(&src.path().join("app.yaml"), "replicas: 0\n");
+        let mut cfg = config(&src.path().join("app.yaml"), "app.yaml", &work, "envsubst");
+        cfg.schema = src.path().join("schema.json").to_str().unwrap().into();
+        let err = run(&test_logger(), &cfg).unwrap_err();
+        assert!(err.contains("/replicas"), "{}", err);
+        assert!(!work.path().join("app.yaml").exists());
+
+        write(&src.path().join("app.yaml"), "replicas: 3\n");
+        run(&test_logger(), &cfg).unwrap();
+        assert!(work.path().join("app.yaml").exists());
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_format() {
+        let work = TempDir::new().unwrap();
+        let mut cfg = config(Path::new("a.tmpl"), "a.conf", &work, "envsubst");
+        cfg.validate_format = "xml".into();
+        assert!(cfg.validate().unwrap_err().contains("--validate"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_render_validate_cmd() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("app.conf"), "listen 80;");
+        let mut cfg = config(&src.path().join("app.conf"), "app.conf", &work, "envsubst");
+        cfg.validate_cmd = "grep -q 'listen 80' {}".into();
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(work.path().join("app.conf")).unwrap(),
+            "listen 80;"
+        );
+
+        // A failing validator leaves the previous output in place and no
+        // temporary file behind.
+        write(&src.path().join("app.conf"), "listen 8080;");
+        cfg.validate_cmd = "grep -q 'listen 80;'".into();
+        let err = run(&test_logger(), &cfg).unwrap_err();
+        assert!(err.contains("grep exited with code 1"), "{}", err);
+        assert_eq!(
+            fs::read_to_string(work.path().join("app.conf")).unwrap(),
+            "listen 80;"
+        );
+        assert_eq!(fs::read_dir(work.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_render_decode_base64_binary_output() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        std::env::set_var("TEST_B64_KEYSTORE_XYZ", "AAEC/w==");
+        write(&src.path().join("ks.tmpl"), "${TEST_B64_KEYSTORE_XYZ}\n");
+        let mut cfg = config(&src.path().join("ks.tmpl"), "ks.p12", &work, "envsubst");
+        cfg.decode_base64 = true;
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read(work.path().join("ks.p12")).unwrap(),
+            vec![0u8, 1, 2, 255]
+        );
+
+        // Binary outputs are compared byte-wise in --diff mode.
+        cfg.diff = true;
+        assert!(!run(&test_logger(), &cfg).unwrap());
+        std::env::set_var("TEST_B64_KEYSTORE_XYZ", "AAED/w==");
+        assert!(run(&test_logger(), &cfg).unwrap());
+        std::env::remove_var("TEST_B64_KEYSTORE_XYZ");
+    }
+
+    #[test]
+    fn test_render_stdout_writes_no_file() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("app.tmpl"), "piped");
+        let cfg = config(&src.path().join("app.tmpl"), "-", &work, "envsubst");
+        run(&test_logger(), &cfg).unwrap();
+        assert!(!work.path().join("-").exists());
+    }
+
+    #[test]
+    fn test_render_stdout_rejects_directory() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        let cfg = config(src.path(), "-", &work, "envsubst");
+        let err = run(&test_logger(), &cfg).unwrap_err();
+        assert!(err.contains("--output -"));
+    }
+
+    #[test]
+    fn test_validate_dry_run_and_diff_exclusive() {
+        let work = TempDir::new().unwrap();
+        let mut cfg = config(Path::new("a.tmpl"), "a.conf", &work, "envsubst");
+        cfg.dry_run = true;
+        cfg.diff = true;
+        assert!(cfg.validate().unwrap_err().contains("mutually exclusive"));
+    }
+
+    #[test]
+    fn test_validate_template_dir_missing() {
+        let work = TempDir::new().unwrap();
+        let mut cfg = config(Path::new("a.tmpl"), "a.conf", &work, "gotemplate");
+        cfg.template_dir = "/nonexistent/partials".into();
+        assert!(cfg.validate().unwrap_err().contains("not a directory"));
+    }
+
+    #[test]
+    fn test_validate_values_requires_gotemplate() {
+        let work = TempDir::new().unwrap();
+        let mut cfg = config(Path::new("a.tmpl"), "a.conf", &work, "envsubst");
+        cfg.values = vec!["values.yaml".into()];
+        assert!(cfg.validate().unwrap_err().contains("--values"));
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_mode() {
+        let work = TempDir::new().unwrap();
+        let cfg = config(Path::new("a.tmpl"), "a.conf", &work, "handlebars");
+        assert!(cfg
+            .validate()
+            .unwrap_err()
+            .contains("--mode must be one of"));
+    }
+
+    #[test]
+    fn test_render_jinja_alias() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(&src.path().join("app.conf"), "{{ 1 + 1 }}");
+        let cfg = config(&src.path().join("app.conf"), "app.conf", &work, "jinja");
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(work.path().join("app.conf")).unwrap(),
+            "2"
+        );
+    }
+
+    #[test]
+    fn test_render_auto_mode_by_extension() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(
+            &src.path().join("a.conf.j2"),
+            "{{ 1 + 1 }} ${TEST_AUTO_UNSET_XYZ}",
+        );
+        write(
+            &src.path().join("b.conf"),
+            "{{ 1 + 1 }} ${TEST_AUTO_UNSET_XYZ}",
+        );
+        let cfg = config(src.path(), "out", &work, "auto");
+        run(&test_logger(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(work.path().join("out/a.conf.j2")).unwrap(),
+            "2 ${TEST_AUTO_UNSET_XYZ}"
+        );
+        assert_eq!(
+            fs::read_to_string(work.path().join("out/b.conf")).unwrap(),
+            "{{ 1 + 1 }} ${TEST_AUTO_UNSET_XYZ}"
+        );
+    }
+
+    #[test]
+    fn test_render_merge_documents() {
+        let src = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        write(
+            &src.path().join("base.yaml"),
+            "db:\n  host: localhost\n  port: 5432\n",
+        );
+        write(
+            &src.path().join("prod.json"),
+            r#"{"db": {"host": "db.prod"}}"#,
+        );
+        let mut cfg = config(Path::new(""), "final.json", &work, "envsubst");
+        cfg.templates.clear();
+        cfg.merge = vec![
+            src.path().join("base.yaml").to_str().unwrap().into(),
+            src.path().join("prod.json").to_str().unwrap().into(),
+        ];
+        run(&test_logger(), &cfg).unwrap();
+        let merged: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(work.path().join("final.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            merged,
+            serde_json::json!({"db": {"host": "db.prod", "port": 5432}})
+        );
+    }
+
+    #[test]
+    fn test_validate_merge_rejects_templates() {
+        let work = TempDir::new().unwrap();
+        let mut cfg = config(Path::new("a.tmpl"), "a.yaml", &work, "envsubst");
+        cfg.merge = vec!["base.yaml".into()];
+        assert!(cfg
+            .validate()
+            .unwrap_err()
+            .contains("--merge cannot be combined"));
+        cfg.templates.clear();
+        cfg.merge_lists = "zip".into();
+        assert!(cfg.validate().unwrap_err().contains("list merge strategy"));
+    }
+
+    #[test]
+    fn test_fingerprint_tracks_content() {
+        let src = TempDir::new().unwrap();
+        write(&src.path().join("tpl/a.conf"), "one");
+        let paths = vec![src.path().join("tpl").to_str().unwrap().to_string()];
+        let before = fingerprint(&paths);
+        assert_eq!(before, fingerprint(&paths));
+        write(&src.path().join("tpl/a.conf"), "two");
+        assert_ne!(before, fingerprint(&paths));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fingerprint_symlink_loop() {
+        let src = TempDir::new().unwrap();
+        write(&src.path().join("tpl/a.conf"), "one");
+        std::os::unix::fs::symlink(".", src.path().join("tpl/self")).unwrap();
+        let paths = vec![src.path().join("tpl").to_str().unwrap().to_string()];
+        let before = fingerprint(&paths);
+        write(&src.path().join("tpl/a.conf"), "two");
+        assert_ne!(before, fingerprint(&paths));
+    }
+
+    #[test]
+    fn test_validate_watch() {
+        let work = TempDir::new().unwrap();
+        let mut cfg = config(Path::new("a.tmpl"), "a.conf", &work, "envsubst");
+        cfg.reload_pid = Some(1);
+        assert!(cfg.validate().unwrap_err().contains("require --watch"));
+        cfg.watch = true;
+        assert!(cfg.validate().is_ok());
+        cfg.diff = true;
+        assert!(cfg.validate().unwrap_err().contains("--watch cannot"));
+        cfg.diff = false;
+        cfg.reload_pid_file = "/run/app.pid".into();
+        assert!(cfg.validate().unwrap_err().contains("mutually exclusive"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reload_sends_sighup() {
+        use std::os::unix::process::ExitStatusExt;
+        let work = TempDir::new().unwrap();
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid_file = work.path().join("app.pid");
+        fs::write(&pid_file, format!("{}\n", child.id())).unwrap();
+        let mut cfg = config(Path::new("a.tmpl"), "a.conf", &work, "envsubst");
+        cfg.reload_pid_file = pid_file.to_str().unwrap().into();
+        reload(&test_logger(), &cfg).unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGHUP));
+    }
+
+    #[test]
+    fn test_validate_requires_template_or_pairs_file() {
+        let cfg = Config {
+            templates: Vec::new(),
+            outputs: Vec::new(),
+            pairs_file: String::new(),
+            workdir: "/work".into(),
+            mode: "envsubst".into(),
+            exclude: Vec::new(),
+            values: Vec::new(),
+            template_dir: String::new(),
+            strict: false,
+            dry_run: false,
+            diff: false,
+            file_root: String::new(),
+            fail_on_unset: false,
+            validate_format: String::new(),
+            validate_cmd: String::new(),
+            schema: String::new(),
+            merge: vec![],
+            merge_lists: "replace".into(),
+            secrets_dir: String::new(),
+            watch: false,
+            watch_interval: Duration::from_secs(2),
+            watch_files: vec![],
+            reload_pid: None,
+            reload_pid_file: String::new(),
+            decode_base64: false,
+        };
+        assert!(cfg.validate().is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use crate::duration::{format_duration, parse_duration};
+use crate::logging::Logger;
+use std::time::Duration;
+/// Sleep for `duration`, or until SIGTERM or SIGINT with `infinity`; a
+/// signal ends the sleep successfully.
+pub fn run(log: &Logger, duration: &str) -> Result<(), String> {
+    let duration = parse(duration)?;
+    let signals = super::SignalGuard::new();
+    log.info(
+        "sleeping",
+        &[(
+            "duration",
+            &duration.map_or("infinity".into(), format_duration),
+        )],
+    );
+    match signals.sleep(duration) {
+        Some(signal) => log.info("received signal, exiting", &[("signal", signal)]),
+        None => log.info("sleep finished", &[]),
+    }
+    Ok(())
+}
+fn parse(duration: &str) -> Result<Option<Duration>, String> {
+    if duration == "infinity" {
+        return Ok(None);
+    }
+    parse_duration(duration)
+        .map(Some)
+        .map_err(|e| format!("invalid duration '{}': {}", duration, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("infinity").unwrap(), None);
+        assert_eq!(parse("1m30s").unwrap(), Some(Duration::from_secs(90)));
+        assert!(parse("forever").is_err());
+    }
+
+    #[test]
+    fn test_run_sleeps_for_duration() {
+        let log = Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        );
+        let started = std::time::Instant::now();
+        run(&log, "250ms").unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(250));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,469 @@
+use crate::glob::matches_any;
+use crate::logging::Logger;
+use crate::safety::{self, AtomicFile};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+pub struct Config {
+    pub file: String,
+    pub dest: String,
+    pub workdir: String,
+    pub strip_components: usize,
+    pub include: Vec<String>,
+}
+#[derive(Debug, PartialEq)]
+enum Format {
+    TarGz,
+    Tar,
+    Zip,
+}
+impl Format {
+    /// Detect the format from the magic bytes at the start of the file.
+    fn detect(path: &str) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        let mut f = fs::File::open(path).map_err(|e| format!("opening {}: {}", path, e))?;
+        let n = f
+            .read(&mut magic)
+            .map_err(|e| format!("reading {}: {}", path, e))?;
+        Ok(match &magic[..n] {
+            [0x1f, 0x8b, ..] => Format::TarGz,
+            [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => Format::Zip,
+            _ => Format::Tar,
+        })
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            Format::TarGz => "tar.gz",
+            Format::Tar => "tar",
+            Format::Zip => "zip",
+        }
+    }
+}
+enum Entry {
+    Dir,
+    File(Option<u32>),
+    Symlink(String),
+    Other,
+}
+struct Extractor<'a> {
+    log: &'a Logger,
+    cfg: &'a Config,
+    dest: PathBuf,
+    files: usize,
+    skipped: usize,
+}
+pub fn run(log: &Logger, cfg: &Config) -> Result<(), String> {
+    let dest = safety::validate_file_path(&cfg.workdir, &cfg.dest)?;
+    let format = Format::detect(&cfg.file)?;
+    log.info(
+        "extracting archive",
+        &[
+            ("file", &cfg.file),
+            ("dest", &dest.display().to_string()),
+            ("format", format.name()),
+        ],
+    );
+    fs::create_dir_all(&dest).map_err(|e| format!("creating {}: {}", dest.display(), e))?;
+    let mut ex = Extractor {
+        log,
+        cfg,
+        dest,
+        files: 0,
+        skipped: 0,
+    };
+    let file = fs::File::open(&cfg.file).map_err(|e| format!("opening {}: {}", cfg.file, e))?;
+    let result = match format {
+        Format::TarGz => ex.extract_tar(flate2::read::GzDecoder::new(file)),
+        Format::Tar => ex.extract_tar(file),
+        Format::Zip => ex.extract_zip(file),
+    };
+    if let Err(e) = result {
+        return Err(format!("extracting {}: {}", cfg.file, e));
+    }
+    log.info(
+        "archive extracted",
+        &[
+            ("file", &cfg.file),
+            ("files", &ex.files.to_string()),
+            ("skipped", &ex.skipped.to_string()),
+        ],
+    );
+    Ok(())
+}
+impl Extractor<'_> {
+    fn extract_tar<R: Read>(&mut self, reader: R) -> Result<(), String> {
+        let mut archive = tar::Archive::new(reader);
+        let entries = archive.entries().map_err(|e| e.to_string())?;
+        for entry in entries {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+            let header = entry.header();
+            let kind = match header.entry_type() {
+                tar::EntryType::Directory => Entry::Dir,
+                tar::EntryType::Regular | tar::EntryType::Continuous => {
+                    Entry::File(header.mode().ok())
+                }
+                tar::EntryType::Symlink => match entry.link_name_bytes() {
+                    Some(target) => Entry::Symlink(String::from_utf8_lossy(&target).to_string()),
+                    None => Entry::Other,
+                },
+                _ => Entry::Other,
+            };
+            self.extract(&name, kind, &mut entry)?;
+        }
+        Ok(())
+    }
+    fn extract_zip(&mut self, file: fs::File) -> Result<(), String> {
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+            let name = entry.name().to_string();
+            let kind = if entry.is_dir() {
+                Entry::Dir
+            } else if entry.is_symlink() {
+                let mut target = String::new();
+                entry
+                    .read_to_string(&mut target)
+                    .map_err(|e| format!("reading {}: {}", name, e))?;
+                Entry::Symlink(target)
+            } else {
+                Entry::File(entry.unix_mode())
+            };
+            self.extract(&name, kind, &mut entry)?;
+        }
+        Ok(())
+    }
+    /// Extract one entry, after stripping leading components and matching
+    /// the include globs. Entries resolving outside `dest`, or that would be
+    /// written through a symlink, fail the extraction.
+    fn extract(&mut self, name: &str, kind: Entry, reader: &mut dyn Read) -> Result<(), String> {
+        if Path::new(name).is_absolute() {
+            return Err(format!("absolute entry path not allowed: {:?}", name));
+        }
+        let parts: Vec<&str> = name
+            .split('/')
+            .filter(|p| !p.is_empty() && *p != ".")
+            .collect();
+        if parts.len() <= self.cfg.strip_components {
+            return Ok(());
+        }
+        let rel = parts[self.cfg.strip_components..].join("/");
+        let dest = self.dest.to_string_lossy();
+        let path = safety::validate_file_path(&dest, &rel)?;
+        check_no_symlinks(&self.dest, &rel)?;
+        let included = self.cfg.include.is_empty() || matches_any(&self.cfg.include, &rel);
+        match kind {
+            Entry::Dir => {
+                // Directories are created on demand for the files they hold.
+                if included {
+                    fs::create_dir_all(&path)
+                        .map_err(|e| format!("creating {}: {}", path.display(), e))?;
+                }
+                return Ok(());
+            }
+            _ if !included => return Ok(()),
+            Entry::Other => {
+                self.log
+                    .warn("skipping unsupported archive entry", &[("entry", name)]);
+                self.skipped += 1;
+                return Ok(());
+            }
+            _ => {}
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("creating {}: {}", parent.display(), e))?;
+        }
+        match kind {
+            // Written under a temporary name and renamed over whatever an
+            // earlier run left, so a failed entry leaves no partial file.
+            Entry::File(mode) => {
+                let mut out = AtomicFile::create(&path)?;
+                std::io::copy(reader, &mut out)
+                    .map_err(|e| format!("writing {}: {}", path.display(), e))?;
+                set_mode(out.path(), mode)?;
+                out.commit()?;
+            }
+            Entry::Symlink(target) => {
+                if Path::new(&target).is_absolute() {
+                    return Err(format!(
+                        "symlink {:?} has absolute target {:?}",
+                        name, target
+                    ));
+                }
+                // Only links pointing down stay inside dest: once `..` is
+                // allowed, it can climb out through another link, including
+                // one extracted later.
+                if Path::new(&target)
+                    .components()
+                    .any(|c| c == Component::ParentDir)
+                {
+                    return Err(format!(
+                        "symlink {:?} target {:?} must not contain '..'",
+                        name, target
+                    ));
+                }
+                // Replace what an earlier run left, without following it.
+                if fs::symlink_metadata(&path).is_ok_and(|m| !m.is_dir()) {
+                    fs::remove_file(&path)
+                        .map_err(|e| format!("removing {}: {}", path.display(), e))?;
+                }
+                symlink(&target, &path)?;
+            }
+            _ => unreachable!(),
+        }
+        self.log.debug("extracted", &[("entry", &rel)]);
+        self.files += 1;
+        Ok(())
+    }
+}
+/// Reject paths whose parent directories include a symlink, which may point
+/// anywhere once an archive has created it.
+fn check_no_symlinks(dest: &Path, rel: &str) -> Result<(), String> {
+    let mut path = dest.to_path_buf();
+    let parts: Vec<&str> = rel.split('/').collect();
+    for part in &parts[..parts.len() - 1] {
+        path.push(part);
+        match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(format!(
+                    "entry {:?} would be written through symlink {}",
+                    rel,
+                    path.display()
+                ));
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    Ok(())
+}
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: Option<u32>) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        // Never restore setuid, setgid or sticky bits from an archive.
+        Some(mode) => fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+            .map_err(|e| format!("setting mode on {}: {}", path.display(), e)),
+        None => Ok(()),
+    }
+}
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> Result<(), String> {
+    Ok(())
+}
+#[cfg(unix)]
+fn symlink(target: &str, path: &Path) -> Result<(), String> {
+    std::os::unix::fs::symlink(target, path)
+        .map_err(|e| format!("creating symlink {}: {}", path.display(), e))
+}
+#[cfg(not(unix))]
+fn symlink(_target: &str, path: &Path) -> Result<(), String> {
+    Err(format!(
+        "creating symlink {}: not supported on this platform",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn test_logger() -> Logger {
+        Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        )
+    }
+
+    /// Append an entry with a raw name, bypassing the path checks of
+    /// `tar::Header::set_path` so hostile archives can be built.
+    fn append(
+        builder: &mut tar::Builder<impl Write>,
+        name: &str,
+        kind: tar::EntryType,
+        link: &str,
+        data: &[u8],
+    ) {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        header.set_entry_type(kind);
+        header.set_mode(0o640);
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+        builder.append(&header, data).unwrap();
+    }
+
+    fn tar_gz(dir: &Path, entries: &[(&str, tar::EntryType, &str, &[u8])]) -> String {
+        let path = dir.join("bundle.tgz");
+        let gz = flate2::write::GzEncoder::new(
+            fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(gz);
+        for (name, kind, link, data) in entries {
+            append(&mut builder, name, *kind, link, data);
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn config(dir: &TempDir, file: String) -> Config {
+        Config {
+            file,
+            dest: "plugins".into(),
+            workdir: dir.path().to_string_lossy().to_string(),
+            strip_components: 0,
+            include: vec![],
+        }
+    }
+
+    #[test]
+    fn test_extract_tar_gz_strip_and_include() {
+        let dir = TempDir::new().unwrap();
+        let file = tar_gz(
+            dir.path(),
+            &[
+                ("bundle-1.0/", tar::EntryType::Directory, "", b""),
+                ("bundle-1.0/a.jar", tar::EntryType::Regular, "", b"a"),
+                ("bundle-1.0/lib/b.jar", tar::EntryType::Regular, "", b"b"),
+                ("bundle-1.0/README", tar::EntryType::Regular, "", b"r"),
+            ],
+        );
+        let mut cfg = config(&dir, file);
+        cfg.strip_components = 1;
+        cfg.include = vec!["**/*.jar".into()];
+        run(&test_logger(), &cfg).unwrap();
+        let dest = dir.path().join("plugins");
+        assert_eq!(fs::read(dest.join("a.jar")).unwrap(), b"a");
+        assert_eq!(fs::read(dest.join("lib/b.jar")).unwrap(), b"b");
+        assert!(!dest.join("README").exists());
+        assert!(!dest.join("bundle-1.0").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dest.join("a.jar"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("conf/", options).unwrap();
+        zip.start_file("conf/app.yaml", options).unwrap();
+        zip.write_all(b"port: 8080\n").unwrap();
+        zip.finish().unwrap();
+        let cfg = config(&dir, path.to_string_lossy().to_string());
+        run(&test_logger(), &cfg).unwrap();
+        let out = dir.path().join("plugins/conf/app.yaml");
+        assert_eq!(fs::read_to_string(out).unwrap(), "port: 8080\n");
+    }
+
+    #[test]
+    fn test_extract_truncated_entry_leaves_no_file() {
+        let dir = TempDir::new().unwrap();
+        // Incompressible data, so truncating the archive cuts into it.
+        let mut x = 0x2545_f491_4f6c_dd1du64;
+        let data: Vec<u8> = (0..256 * 1024)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x as u8
+            })
+            .collect();
+        let file = tar_gz(
+            dir.path(),
+            &[("big.bin", tar::EntryType::Regular, "", data.as_slice())],
+        );
+        let len = fs::metadata(&file).unwrap().len();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_len(len / 2)
+            .unwrap();
+        assert!(run(&test_logger(), &config(&dir, file)).is_err());
+        let dest = dir.path().join("plugins");
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_extract_rejects_traversal() {
+        let dir = TempDir::new().unwrap();
+        let file = tar_gz(
+            dir.path(),
+            &[("../evil", tar::EntryType::Regular, "", b"x")],
+        );
+        let err = run(&test_logger(), &config(&dir, file)).unwrap_err();
+        assert!(err.contains("path traversal detected"), "{}", err);
+        assert!(!dir.path().join("evil").exists());
+
+        let file = tar_gz(
+            dir.path(),
+            &[("/etc/evil", tar::EntryType::Regular, "", b"x")],
+        );
+        let err = run(&test_logger(), &config(&dir, file)).unwrap_err();
+        assert!(err.contains("absolute entry path"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let file = tar_gz(
+            dir.path(),
+            &[
+                ("lib/b.jar", tar::EntryType::Regular, "", b"b"),
+                ("current", tar::EntryType::Symlink, "lib", b""),
+            ],
+        );
+        let cfg = config(&dir, file);
+        run(&test_logger(), &cfg).unwrap();
+        // Extracting again replaces the link instead of following it.
+        run(&test_logger(), &cfg).unwrap();
+        let link = dir.path().join("plugins/current");
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("lib"));
+
+        let dir = TempDir::new().unwrap();
+        let file = tar_gz(dir.path(), &[("up", tar::EntryType::Symlink, "../..", b"")]);
+        let err = run(&test_logger(), &config(&dir, file)).unwrap_err();
+        assert!(err.contains("must not contain '..'"), "{}", err);
+
+        // Chained links escape even though each resolves inside dest.
+        let dir = TempDir::new().unwrap();
+        let file = tar_gz(
+            dir.path(),
+            &[
+                ("a/up", tar::EntryType::Symlink, "..", b""),
+                ("b", tar::EntryType::Symlink, "a/up/..", b""),
+            ],
+        );
+        let err = run(&test_logger(), &config(&dir, file)).unwrap_err();
+        assert!(err.contains("must not contain '..'"), "{}", err);
+        assert!(fs::symlink_metadata(dir.path().join("plugins/b")).is_err());
+
+        // A link staying inside dest must not be written through either.
+        let dir = TempDir::new().unwrap();
+        let file = tar_gz(
+            dir.path(),
+            &[
+                ("self", tar::EntryType::Symlink, ".", b""),
+                ("self/x", tar::EntryType::Regular, "", b"x"),
+            ],
+        );
+        let err = run(&test_logger(), &config(&dir, file)).unwrap_err();
+        assert!(err.contains("written through symlink"), "{}", err);
+    }
+}
@@ -0,0 +1,155 @@
+/// Produce a unified diff (3 lines of context) between `old` and `new`.
+/// Returns an empty string when the inputs are identical.
+pub fn unified(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&a, &b);
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (start, end) in hunk_ranges(&ops, CONTEXT) {
+        let hunk = &ops[start..end];
+        let (old_start, new_start) = match ops[start] {
+            Op::Equal(i, j) | Op::Delete(i, j) | Op::Insert(i, j) => (i, j),
+        };
+        let old_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Insert(..)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Delete(..)))
+            .count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range_label(old_start, old_len),
+            range_label(new_start, new_len)
+        ));
+        for op in hunk {
+            match *op {
+                Op::Equal(i, _) => out.push_str(&format!(" {}\n", a[i])),
+                Op::Delete(i, _) => out.push_str(&format!("-{}\n", a[i])),
+                Op::Insert(_, j) => out.push_str(&format!("+{}\n", b[j])),
+            }
+        }
+    }
+    if !old.is_empty() && !new.is_empty() && old.ends_with('\n') != new.ends_with('\n') {
+        out.push_str("\\ trailing newline differs\n");
+    }
+    out
+}
+
+const CONTEXT: usize = 3;
+
+/// Edit operations carrying the (old, new) line positions they apply at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize, usize),
+    Insert(usize, usize),
+}
+
+fn range_label(start: usize, len: usize) -> String {
+    // Unified diff ranges are 1-based; an empty range points at the line
+    // before the change.
+    let first = if len == 0 { start } else { start + 1 };
+    if len == 1 {
+        first.to_string()
+    } else {
+        format!("{},{}", first, len)
+    }
+}
+
+/// Longest-common-subsequence line diff. Config files are small, so the
+/// quadratic table is cheaper than pulling in a diff crate.
+fn diff_lines(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            ops.push(Op::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Delete(i, j));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(i, j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+/// Group changed operations into hunks padded with `context` equal lines,
+/// merging hunks whose context would overlap.
+fn hunk_ranges(ops: &[Op], context: usize) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (idx, op) in ops.iter().enumerate() {
+        if matches!(op, Op::Equal(..)) {
+            continue;
+        }
+        let start = idx.saturating_sub(context);
+        let end = (idx + 1 + context).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_is_empty() {
+        assert_eq!(unified("a\nb\n", "a\nb\n", "old", "new"), "");
+    }
+
+    #[test]
+    fn test_single_line_change() {
+        let d = unified("a\nb\nc\n", "a\nB\nc\n", "old", "new");
+        assert_eq!(d, "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+    }
+
+    #[test]
+    fn test_new_file() {
+        let d = unified("", "x\ny\n", "/dev/null", "new");
+        assert_eq!(d, "--- /dev/null\n+++ new\n@@ -0,0 +1,2 @@\n+x\n+y\n");
+    }
+
+    #[test]
+    fn test_separate_hunks() {
+        let lines: Vec<String> = (1..=20).map(|i| i.to_string()).collect();
+        let old = lines.join("\n") + "\n";
+        let mut changed = lines.clone();
+        changed[1] = "two".into();
+        changed[17] = "eighteen".into();
+        let new = changed.join("\n") + "\n";
+        let d = unified(&old, &new, "old", "new");
+        assert_eq!(d.matches("@@ ").count(), 2, "{}", d);
+        assert!(d.contains("-2\n+two\n"));
+        assert!(d.contains("-18\n+eighteen\n"));
+    }
+
+    #[test]
+    fn test_trailing_newline_difference() {
+        let d = unified("a\n", "a", "old", "new");
+        assert!(d.contains("trailing newline differs"));
+    }
+}
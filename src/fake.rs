@@ -0,0 +1,411 @@
+use chrono::NaiveDate;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::sync::{Arc, Mutex};
+
+const FIRST_NAMES: &[&str] = &[
+    "Ada", "Alan", "Alice", "Amara", "Ana", "Ben", "Carlos", "Chen", "Clara", "Daniel", "Elena",
+    "Emma", "Farah", "Grace", "Hana", "Ines", "Ivan", "James", "Jonas", "Kai", "Lena", "Leo",
+    "Lucia", "Maya", "Mei", "Mohammed", "Nina", "Noah", "Olga", "Omar", "Priya", "Rosa", "Sam",
+    "Sara", "Tariq", "Tom", "Vera", "Wei", "Yusuf", "Zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Andersen", "Bauer", "Brown", "Costa", "Dubois", "Evans", "Fischer", "Garcia", "Hansen", "Ito",
+    "Jensen", "Khan", "Kim", "Kowalski", "Lopez", "Martin", "Meyer", "Moreau", "Nakamura",
+    "Nguyen", "Novak", "Okafor", "Olsen", "Patel", "Petrov", "Rossi", "Santos", "Schmidt", "Silva",
+    "Smith", "Suzuki", "Tanaka", "Taylor", "Wagner", "Wang", "Weber", "Wilson", "Yilmaz", "Young",
+    "Zhang",
+];
+
+const COMPANY_SUFFIXES: &[&str] = &[
+    "Inc", "LLC", "Ltd", "Group", "Labs", "Systems", "Partners", "& Co",
+];
+
+const CITIES: &[&str] = &[
+    "Amsterdam",
+    "Austin",
+    "Barcelona",
+    "Berlin",
+    "Bogota",
+    "Cape Town",
+    "Chicago",
+    "Dublin",
+    "Helsinki",
+    "Istanbul",
+    "Lagos",
+    "Lisbon",
+    "London",
+    "Melbourne",
+    "Montreal",
+    "Mumbai",
+    "Nairobi",
+    "Osaka",
+    "Oslo",
+    "Paris",
+    "Prague",
+    "Santiago",
+    "Seoul",
+    "Singapore",
+    "Toronto",
+    "Vienna",
+    "Warsaw",
+    "Zurich",
+];
+
+/// Reserved for documentation (RFC 2606), so generated addresses never reach
+/// real mailboxes.
+const EMAIL_DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+const WORDS: &[&str] = &[
+    "lorem",
+    "ipsum",
+    "dolor",
+    "sit",
+    "amet",
+    "consectetur",
+    "adipiscing",
+    "elit",
+    "sed",
+    "do",
+    "eiusmod",
+    "tempor",
+    "incididunt",
+    "ut",
+    "labore",
+    "et",
+    "dolore",
+    "magna",
+    "aliqua",
+    "enim",
+    "ad",
+    "minim",
+    "veniam",
+    "quis",
+    "nostrud",
+    "exercitation",
+    "ullamco",
+    "laboris",
+    "nisi",
+    "aliquip",
+    "ex",
+    "ea",
+    "commodo",
+    "consequat",
+    "duis",
+    "aute",
+    "irure",
+    "in",
+    "reprehenderit",
+    "voluptate",
+    "velit",
+    "esse",
+    "cillum",
+    "fugiat",
+    "nulla",
+    "pariatur",
+    "excepteur",
+    "sint",
+    "occaecat",
+    "cupidatat",
+    "non",
+    "proident",
+    "sunt",
+    "culpa",
+    "qui",
+    "officia",
+    "deserunt",
+    "mollit",
+    "anim",
+    "id",
+    "est",
+    "laborum",
+];
+
+/// Most words `fake_sentence()` and sentences `fake_paragraph()` produce.
+const MAX_FAKE_LEN: usize = 1000;
+
+type SharedRng = Arc<Mutex<StdRng>>;
+
+fn invalid(msg: String) -> minijinja::Error {
+    minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, msg)
+}
+
+fn pick(rng: &mut StdRng, list: &[&'static str]) -> &'static str {
+    list.choose(rng).expect("word lists are not empty")
+}
+
+/// Register the `fake_*` functions for demo data, drawing from a single
+/// generator that is deterministic when `seed` is set.
+pub fn register(env: &mut minijinja::Environment<'_>, seed: Option<u64>) {
+    let rng: SharedRng = Arc::new(Mutex::new(match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }));
+    let r = rng.clone();
+    env.add_function("fake_first_name", move || {
+        pick(&mut r.lock().unwrap(), FIRST_NAMES).to_string()
+    });
+    let r = rng.clone();
+    env.add_function("fake_last_name", move || {
+        pick(&mut r.lock().unwrap(), LAST_NAMES).to_string()
+    });
+    let r = rng.clone();
+    env.add_function("fake_name", move || fake_name(&mut r.lock().unwrap()));
+    let r = rng.clone();
+    env.add_function("fake_email", move |name: Option<String>| {
+        fake_email(&mut r.lock().unwrap(), name.as_deref())
+    });
+    let r = rng.clone();
+    env.add_function("fake_company", move || {
+        let rng = &mut *r.lock().unwrap();
+        format!("{} {}", pick(rng, LAST_NAMES), pick(rng, COMPANY_SUFFIXES))
+    });
+    let r = rng.clone();
+    env.add_function("fake_city", move || {
+        pick(&mut r.lock().unwrap(), CITIES).to_string()
+    });
+    let r = rng.clone();
+    env.add_function("fake_phone", move || {
+        // 555-0100 to 555-0199 are reserved for fictional use.
+        format!("+1-555-01{:02}", r.lock().unwrap().gen_range(0..100))
+    });
+    let r = rng.clone();
+    env.add_function("fake_word", move || {
+        pick(&mut r.lock().unwrap(), WORDS).to_string()
+    });
+    let r = rng.clone();
+    env.add_function("fake_sentence", move |words: Option<usize>| {
+        fake_sentence(&mut r.lock().unwrap(), words)
+    });
+    let r = rng.clone();
+    env.add_function("fake_paragraph", move |sentences: Option<usize>| {
+        fake_paragraph(&mut r.lock().unwrap(), sentences)
+    });
+    let r = rng.clone();
+    env.add_function("fake_int", move |min: i64, max: i64| {
+        fake_int(&mut r.lock().unwrap(), min, max)
+    });
+    let r = rng.clone();
+    env.add_function(
+        "fake_float",
+        move |min: f64, max: f64, decimals: Option<u32>| {
+            fake_float(&mut r.lock().unwrap(), min, max, decimals)
+        },
+    );
+    let r = rng.clone();
+    env.add_function("fake_bool", move |probability: Option<f64>| {
+        fake_bool(&mut r.lock().unwrap(), probability)
+    });
+    env.add_function("fake_date", move |start: String, end: String| {
+        fake_date(&mut rng.lock().unwrap(), &start, &end)
+    });
+}
+
+fn fake_name(rng: &mut StdRng) -> String {
+    format!("{} {}", pick(rng, FIRST_NAMES), pick(rng, LAST_NAMES))
+}
+
+/// An address on a reserved domain, built from `name` when given. A numeric
+/// suffix keeps addresses of the same name apart.
+fn fake_email(rng: &mut StdRng, name: Option<&str>) -> String {
+    let name = match name {
+        Some(n) => n.to_string(),
+        None => fake_name(rng),
+    };
+    let local: Vec<String> = name
+        .split_whitespace()
+        .map(|part| {
+            part.chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase()
+        })
+        .filter(|part| !part.is_empty())
+        .collect();
+    let local = if local.is_empty() {
+        "user".to_string()
+    } else {
+        local.join(".")
+    };
+    format!(
+        "{}{}@{}",
+        local,
+        rng.gen_range(1..10000),
+        pick(rng, EMAIL_DOMAINS)
+    )
+}
+
+/// Lorem ipsum words, capitalized and ending in a period; 4 to 12 words
+/// unless `words` is given.
+fn fake_sentence(rng: &mut StdRng, words: Option<usize>) -> Result<String, minijinja::Error> {
+    let n = words.unwrap_or_else(|| rng.gen_range(4..=12));
+    if n == 0 || n > MAX_FAKE_LEN {
+        return Err(invalid(format!(
+            "fake_sentence: words must be between 1 and {}",
+            MAX_FAKE_LEN
+        )));
+    }
+    let mut sentence = (0..n)
+        .map(|_| pick(rng, WORDS))
+        .collect::<Vec<_>>()
+        .join(" ");
+    sentence[..1].make_ascii_uppercase();
+    sentence.push('.');
+    Ok(sentence)
+}
+
+/// 3 to 6 sentences unless `sentences` is given.
+fn fake_paragraph(rng: &mut StdRng, sentences: Option<usize>) -> Result<String, minijinja::Error> {
+    let n = sentences.unwrap_or_else(|| rng.gen_range(3..=6));
+    if n == 0 || n > MAX_FAKE_LEN {
+        return Err(invalid(format!(
+            "fake_paragraph: sentences must be between 1 and {}",
+            MAX_FAKE_LEN
+        )));
+    }
+    let sentences = (0..n)
+        .map(|_| fake_sentence(rng, None))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(sentences.join(" "))
+}
+
+/// Integer in `[min, max]`; unlike `random_int`, `max` is included.
+fn fake_int(rng: &mut StdRng, min: i64, max: i64) -> Result<i64, minijinja::Error> {
+    if min > max {
+        return Err(invalid(format!(
+            "fake_int: min ({}) must not be greater than max ({})",
+            min, max
+        )));
+    }
+    Ok(rng.gen_range(min..=max))
+}
+
+/// Number in `[min, max]`, rounded to `decimals` places (default 2).
+fn fake_float(
+    rng: &mut StdRng,
+    min: f64,
+    max: f64,
+    decimals: Option<u32>,
+) -> Result<f64, minijinja::Error> {
+    if !min.is_finite() || !max.is_finite() || min > max {
+        return Err(invalid(format!(
+            "fake_float: min ({}) must not be greater than max ({})",
+            min, max
+        )));
+    }
+    let scale = 10f64.powi(decimals.unwrap_or(2).min(15) as i32);
+    let value = (rng.gen_range(min..=max) * scale).round() / scale;
+    Ok(value.clamp(min, max))
+}
+
+/// `true` with the given probability (default 0.5).
+fn fake_bool(rng: &mut StdRng, probability: Option<f64>) -> Result<bool, minijinja::Error> {
+    let p = probability.unwrap_or(0.5);
+    if !(0.0..=1.0).contains(&p) {
+        return Err(invalid(format!(
+            "fake_bool: probability ({}) must be between 0 and 1",
+            p
+        )));
+    }
+    Ok(rng.gen_bool(p))
+}
+
+/// A `YYYY-MM-DD` date between `start` and `end`, both included.
+fn fake_date(rng: &mut StdRng, start: &str, end: &str) -> Result<String, minijinja::Error> {
+    let parse = |s: &str| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map_err(|e| invalid(format!("fake_date: invalid date '{}': {}", s, e)))
+    };
+    let (start_date, end_date) = (parse(start)?, parse(end)?);
+    if start_date > end_date {
+        return Err(invalid(format!(
+            "fake_date: start ({}) must not be after end ({})",
+            start, end
+        )));
+    }
+    let days = (end_date - start_date).num_days();
+    let date = start_date + chrono::Days::new(rng.gen_range(0..=days) as u64);
+    Ok(date.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded_env(seed: u64) -> minijinja::Environment<'static> {
+        let mut env = minijinja::Environment::new();
+        register(&mut env, Some(seed));
+        env
+    }
+
+    fn render(env: &minijinja::Environment, tmpl: &str) -> Result<String, minijinja::Error> {
+        env.render_str(tmpl, minijinja::context!())
+    }
+
+    #[test]
+    fn test_fake_functions_deterministic_with_seed() {
+        let tmpl = "{{ fake_name() }}|{{ fake_email() }}|{{ fake_company() }}|{{ fake_city() }}|\
+                    {{ fake_phone() }}|{{ fake_paragraph() }}|{{ fake_date('2020-01-01', '2024-12-31') }}";
+        let a = render(&seeded_env(42), tmpl).unwrap();
+        assert_eq!(a, render(&seeded_env(42), tmpl).unwrap());
+        assert_ne!(a, render(&seeded_env(43), tmpl).unwrap());
+    }
+
+    #[test]
+    fn test_fake_names_and_emails() {
+        let env = seeded_env(1);
+        let name = render(&env, "{{ fake_name() }}").unwrap();
+        let (first, last) = name.split_once(' ').unwrap();
+        assert!(FIRST_NAMES.contains(&first) && LAST_NAMES.contains(&last));
+
+        let email = render(&env, "{{ fake_email(\"Mary-Jane O'Neil\") }}").unwrap();
+        let (local, domain) = email.split_once('@').unwrap();
+        assert!(local.starts_with("maryjane.oneil"), "{}", email);
+        assert!(local["maryjane.oneil".len()..]
+            .chars()
+            .all(|c| c.is_ascii_digit()));
+        assert!(EMAIL_DOMAINS.contains(&domain));
+
+        let phone = render(&env, "{{ fake_phone() }}").unwrap();
+        assert!(
+            phone.starts_with("+1-555-01") && phone.len() == 11,
+            "{}",
+            phone
+        );
+    }
+
+    #[test]
+    fn test_fake_text() {
+        let env = seeded_env(2);
+        let sentence = render(&env, "{{ fake_sentence(5) }}").unwrap();
+        assert_eq!(sentence.split(' ').count(), 5);
+        assert!(sentence.starts_with(|c: char| c.is_ascii_uppercase()));
+        assert!(sentence.ends_with('.'));
+        let paragraph = render(&env, "{{ fake_paragraph(3) }}").unwrap();
+        assert_eq!(paragraph.matches('.').count(), 3);
+        assert!(render(&env, "{{ fake_sentence(0) }}").is_err());
+        assert!(render(&env, "{{ fake_paragraph(5000) }}").is_err());
+    }
+
+    #[test]
+    fn test_fake_numbers_in_range() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..200 {
+            assert!((-2..=2).contains(&fake_int(&mut rng, -2, 2).unwrap()));
+            let f = fake_float(&mut rng, 1.0, 2.0, Some(1)).unwrap();
+            assert!((1.0..=2.0).contains(&f));
+            assert_eq!((f * 10.0).round(), f * 10.0);
+            let d = fake_date(&mut rng, "2024-02-27", "2024-03-02").unwrap();
+            assert!(("2024-02-27"..="2024-03-02").contains(&d.as_str()), "{}", d);
+        }
+        assert_eq!(fake_int(&mut rng, 7, 7).unwrap(), 7);
+        assert!(fake_int(&mut rng, 3, 2).is_err());
+        assert!(fake_float(&mut rng, 2.0, 1.0, None).is_err());
+        assert!(fake_bool(&mut rng, Some(1.5)).is_err());
+        assert!(fake_bool(&mut rng, Some(1.0)).unwrap());
+        assert!(fake_date(&mut rng, "2024-03-02", "2024-02-27").is_err());
+        assert!(fake_date(&mut rng, "yesterday", "2024-02-27").is_err());
+    }
+}
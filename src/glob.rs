@@ -0,0 +1,116 @@
+/// Match a `/`-separated relative path against a shell-style glob pattern.
+///
+/// Supported syntax:
+/// - `*` matches any run of characters within a single path segment
+/// - `?` matches exactly one character other than `/`
+/// - `**` as a whole segment matches zero or more segments
+///
+/// Patterns without a `/` are matched against the final path segment only, so
+/// `*.bak` excludes backup files at any depth.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_start_matches("./");
+    let path = path.trim_start_matches("./");
+    if !pattern.contains('/') {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        return match_segment(pattern.as_bytes(), name.as_bytes());
+    }
+    let pat_segs: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path_segs: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match_segments(&pat_segs, &path_segs)
+}
+
+/// Returns true if any of the patterns matches the path.
+pub fn matches_any(patterns: &[String], path: &str) -> bool {
+    patterns.iter().any(|p| matches(p, path))
+}
+
+fn match_segments(pat: &[&str], path: &[&str]) -> bool {
+    match pat.first() {
+        None => path.is_empty(),
+        Some(&"**") => (0..=path.len()).any(|skip| match_segments(&pat[1..], &path[skip..])),
+        Some(p) => {
+            !path.is_empty()
+                && match_segment(p.as_bytes(), path[0].as_bytes())
+                && match_segments(&pat[1..], &path[1..])
+        }
+    }
+}
+
+fn match_segment(pat: &[u8], s: &[u8]) -> bool {
+    // Iterative wildcard matching with single-star backtracking.
+    let (mut p, mut i) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while i < s.len() {
+        if p < pat.len() && (pat[p] == b'?' || pat[p] == s[i]) {
+            p += 1;
+            i += 1;
+        } else if p < pat.len() && pat[p] == b'*' {
+            star = Some((p, i));
+            p += 1;
+        } else if let Some((sp, si)) = star {
+            p = sp + 1;
+            i = si + 1;
+            star = Some((sp, si + 1));
+        } else {
+            return false;
+        }
+    }
+    while p < pat.len() && pat[p] == b'*' {
+        p += 1;
+    }
+    p == pat.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basename_patterns() {
+        assert!(matches("*.bak", "app.conf.bak"));
+        assert!(matches("*.bak", "nested/dir/app.conf.bak"));
+        assert!(!matches("*.bak", "app.conf"));
+        assert!(matches("app.???", "app.yml"));
+        assert!(!matches("app.???", "app.yaml"));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        assert!(matches("secrets/*", "secrets/db.yaml"));
+        assert!(!matches("secrets/*", "secrets/nested/db.yaml"));
+        assert!(!matches("secrets/*", "other/db.yaml"));
+        assert!(matches("./secrets/*", "secrets/db.yaml"));
+    }
+
+    #[test]
+    fn test_double_star() {
+        assert!(matches("**/*.md", "README.md"));
+        assert!(matches("**/*.md", "docs/a/b/README.md"));
+        assert!(matches("docs/**", "docs/a/b/c.txt"));
+        assert!(matches("docs/**/c.txt", "docs/c.txt"));
+        assert!(!matches("docs/**/c.txt", "other/c.txt"));
+    }
+
+    #[test]
+    fn test_star_does_not_cross_segments() {
+        assert!(!matches("a/*.txt", "a/b/c.txt"));
+        assert!(matches("a/*/c.txt", "a/b/c.txt"));
+    }
+
+    #[test]
+    fn test_empty_inputs() {
+        assert!(matches("*", ""));
+        assert!(!matches("?", ""));
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+    }
+
+    #[test]
+    fn test_matches_any() {
+        let pats = vec!["*.bak".to_string(), "tmp/**".to_string()];
+        assert!(matches_any(&pats, "tmp/x/y"));
+        assert!(matches_any(&pats, "a.bak"));
+        assert!(!matches_any(&pats, "a.conf"));
+        assert!(!matches_any(&[], "a.conf"));
+    }
+}
@@ -0,0 +1,543 @@
+use crate::logging::Logger;
+use serde_json::{json, Value};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SA_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+/// Set on the Lease once the leader succeeded, so later pods skip.
+const COMPLETED_ANNOTATION: &str = "initium.kitstream.io/completed";
+
+/// `--leader-elect` options.
+pub struct Config {
+    /// Lease name; unique per release so that a new release runs again.
+    pub name: String,
+    /// Namespace of the Lease; empty uses the pod's namespace.
+    pub namespace: String,
+    /// How long the Lease stays held after its leader stopped renewing it.
+    pub duration: Duration,
+}
+
+/// Run `f` on a single pod: the pod holding the Lease `cfg.name` runs it
+/// while the others wait, and once it succeeded every pod skips it. A leader
+/// that fails releases the Lease so another pod tries; one that dies loses it
+/// after `cfg.duration`.
+pub fn run_as_leader(
+    log: &Logger,
+    cfg: &Config,
+    f: impl FnOnce() -> Result<(), String>,
+) -> Result<(), String> {
+    let pod = crate::pod::discover();
+    let identity = if pod.name.is_empty() {
+        std::env::var("HOSTNAME").unwrap_or_default()
+    } else {
+        pod.name
+    };
+    if identity.is_empty() {
+        return Err("leader election needs the pod name: set POD_NAME".into());
+    }
+    let namespace = if cfg.namespace.is_empty() {
+        pod.namespace
+    } else {
+        cfg.namespace.clone()
+    };
+    if namespace.is_empty() {
+        return Err("leader election needs the pod namespace: set --lease-namespace".into());
+    }
+    let client = Client::in_cluster()?;
+    lead(log, &client, &namespace, cfg, &identity, f)
+}
+
+enum Acquire {
+    Leader(Value),
+    Held(String),
+    Completed,
+}
+
+fn lead(
+    log: &Logger,
+    client: &Client,
+    namespace: &str,
+    cfg: &Config,
+    identity: &str,
+    f: impl FnOnce() -> Result<(), String>,
+) -> Result<(), String> {
+    let url = client.lease_url(namespace, &cfg.name);
+    let poll = (cfg.duration / 3).max(Duration::from_millis(100));
+    let mut waiting_for = String::new();
+    let lease = loop {
+        match try_acquire(client, &url, namespace, cfg, identity)? {
+            Acquire::Leader(lease) => break lease,
+            Acquire::Completed => {
+                log.info(
+                    "leader already finished, skipping",
+                    &[("lease", cfg.name.as_str())],
+                );
+                return Ok(());
+            }
+            Acquire::Held(holder) => {
+                if holder != waiting_for {
+                    log.info(
+                        "waiting for leader",
+                        &[("lease", cfg.name.as_str()), ("leader", holder.as_str())],
+                    );
+                    waiting_for = holder;
+                }
+                std::thread::sleep(poll);
+            }
+        }
+    };
+    log.info(
+        "acquired lease, running as leader",
+        &[("lease", cfg.name.as_str()), ("identity", identity)],
+    );
+
+    let (result, lease) = with_renewal(log, client, &url, identity, poll, lease, f);
+    if let Err(e) = release(client, &url, identity, lease, result.is_ok()) {
+        log.warn(
+            "failed to release lease",
+            &[("lease", cfg.name.as_str()), ("error", e.as_str())],
+        );
+    }
+    result
+}
+
+fn try_acquire(
+    client: &Client,
+    url: &str,
+    namespace: &str,
+    cfg: &Config,
+    identity: &str,
+) -> Result<Acquire, String> {
+    let now = micro_time();
+    let duration = cfg.duration.as_secs().max(1);
+    let Some(mut lease) = client.send("GET", url, None)? else {
+        let lease = json!({
+            "apiVersion": "coordination.k8s.io/v1",
+            "kind": "Lease",
+            "metadata": {"name": cfg.name, "namespace": namespace},
+            "spec": {
+                "holderIdentity": identity,
+                "leaseDurationSeconds": duration,
+                "acquireTime": now,
+                "renewTime": now,
+                "leaseTransitions": 0,
+            },
+        });
+        let collection = url.rsplit_once('/').map_or(url, |(c, _)| c);
+        return Ok(match client.send("POST", collection, Some(&lease))? {
+            Some(lease) => Acquire::Leader(lease),
+            None => Acquire::Held(String::new()),
+        });
+    };
+    if !lease["metadata"]["annotations"][COMPLETED_ANNOTATION].is_null() {
+        return Ok(Acquire::Completed);
+    }
+    let holder = lease["spec"]["holderIdentity"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    if !holder.is_empty() && holder != identity && !expired(&lease) {
+        return Ok(Acquire::Held(holder));
+    }
+    let spec = &mut lease["spec"];
+    if holder != identity {
+        let transitions = spec["leaseTransitions"].as_i64().unwrap_or(0);
+        spec["leaseTransitions"] = json!(transitions + 1);
+        spec["acquireTime"] = json!(now);
+    }
+    spec["holderIdentity"] = json!(identity);
+    spec["leaseDurationSeconds"] = json!(duration);
+    spec["renewTime"] = json!(now);
+    Ok(match client.send("PUT", url, Some(&lease))? {
+        Some(lease) => Acquire::Leader(lease),
+        None => Acquire::Held(holder),
+    })
+}
+
+/// Whether the holder of `lease` stopped renewing it.
+fn expired(lease: &Value) -> bool {
+    let renewed = lease["spec"]["renewTime"]
+        .as_str()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok());
+    let duration = lease["spec"]["leaseDurationSeconds"].as_i64().unwrap_or(0);
+    match renewed {
+        Some(renewed) => renewed + chrono::Duration::seconds(duration) < chrono::Utc::now(),
+        None => true,
+    }
+}
+
+/// Run `f` while renewing the Lease every `interval`; returns the result of
+/// `f` and the latest Lease.
+fn with_renewal(
+    log: &Logger,
+    client: &Client,
+    url: &str,
+    identity: &str,
+    interval: Duration,
+    lease: Value,
+    f: impl FnOnce() -> Result<(), String>,
+) -> (Result<(), String>, Value) {
+    let (done, finished) = std::sync::mpsc::channel::<()>();
+    std::thread::scope(|s| {
+        let renewer = s.spawn(move || {
+            let mut lease = lease;
+            while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(interval) {
+                lease["spec"]["renewTime"] = json!(micro_time());
+                let renewed = client.send("PUT", url, Some(&lease)).and_then(|renewed| {
+                    match renewed {
+                        Some(renewed) => Ok(renewed),
+                        // Changed by someone else: renew the current version.
+                        None => client
+                            .send("GET", url, None)?
+                            .ok_or_else(|| "lease was deleted".to_string()),
+                    }
+                });
+                match renewed {
+                    Ok(renewed) if renewed["spec"]["holderIdentity"] != identity => {
+                        log.warn(
+                            "lost the lease while running as leader",
+                            &[(
+                                "leader",
+                                renewed["spec"]["holderIdentity"]
+                                    .as_str()
+                                    .unwrap_or_default(),
+                            )],
+                        );
+                        lease = renewed;
+                    }
+                    Ok(renewed) => lease = renewed,
+                    Err(e) => log.warn("failed to renew lease", &[("error", e.as_str())]),
+                }
+            }
+            lease
+        });
+        let result = f();
+        drop(done);
+        let lease = renewer.join().expect("lease renewal panicked");
+        (result, lease)
+    })
+}
+
+/// Give up the Lease, marking it completed when the leader succeeded.
+fn release(
+    client: &Client,
+    url: &str,
+    identity: &str,
+    mut lease: Value,
+    completed: bool,
+) -> Result<(), String> {
+    for _ in 0..3 {
+        if lease["spec"]["holderIdentity"] != identity {
+            return Err("the lease is held by another pod".into());
+        }
+        lease["spec"]["holderIdentity"] = Value::Null;
+        lease["spec"]["renewTime"] = json!(micro_time());
+        if completed {
+            lease["metadata"]["annotations"][COMPLETED_ANNOTATION] = json!(micro_time());
+        }
+        if client.send("PUT", url, Some(&lease))?.is_some() {
+            return Ok(());
+        }
+        lease = client
+            .send("GET", url, None)?
+            .ok_or_else(|| "lease was deleted".to_string())?;
+    }
+    Err("the lease kept changing".into())
+}
+
+/// RFC 3339 with microseconds, the `MicroTime` format of the API.
+fn micro_time() -> String {
+    chrono::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%S%.6fZ")
+        .to_string()
+}
+
+/// Kubernetes API access with the pod's service account.
+struct Client {
+    base: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl Client {
+    fn in_cluster() -> Result<Self, String> {
+        let host = std::env::var("KUBERNETES_SERVICE_HOST").map_err(|_| {
+            "leader election needs the Kubernetes API: KUBERNETES_SERVICE_HOST is not set"
+                .to_string()
+        })?;
+        let port = std::env::var("KUBERNETES_SERVICE_PORT").unwrap_or_else(|_| "443".into());
+        let host = if host.contains(':') {
+            format!("[{}]", host)
+        } else {
+            host
+        };
+        let token_file = format!("{}/token", SA_DIR);
+        let token = std::fs::read_to_string(&token_file)
+            .map_err(|e| format!("reading service account token '{}': {}", token_file, e))?;
+
+        use rustls::pki_types::{pem::PemObject, CertificateDer};
+        let ca_file = format!("{}/ca.crt", SA_DIR);
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(&ca_file)
+            .map_err(|e| format!("reading cluster CA '{}': {}", ca_file, e))?
+        {
+            let cert = cert.map_err(|e| format!("reading cluster CA '{}': {}", ca_file, e))?;
+            roots
+                .add(cert)
+                .map_err(|e| format!("reading cluster CA '{}': {}", ca_file, e))?;
+        }
+        let provider = rustls::crypto::ring::default_provider();
+        let tls = rustls::ClientConfig::builder_with_provider(Arc::new(provider))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("configuring TLS: {}", e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Self {
+            base: format!("https://{}:{}", host, port),
+            token: token.trim().to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .tls_config(Arc::new(tls))
+                .build(),
+        })
+    }
+
+    fn lease_url(&self, namespace: &str, name: &str) -> String {
+        format!(
+            "{}/apis/coordination.k8s.io/v1/namespaces/{}/leases/{}",
+            self.base, namespace, name
+        )
+    }
+
+    /// Send a request; `None` when the Lease does not exist (404) or was
+    /// changed concurrently (409).
+    fn send(&self, method: &str, url: &str, body: Option<&Value>) -> Result<Option<Value>, String> {
+        let request = self
+            .agent
+            .request(method, url)
+            .set("Authorization", &format!("Bearer {}", self.token));
+        let response = match body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string()),
+            None => request.call(),
+        };
+        let decode = |response: ureq::Response| -> Result<Value, String> {
+            let text = response.into_string().map_err(|e| e.to_string())?;
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        };
+        match response {
+            Ok(response) => decode(response)
+                .map(Some)
+                .map_err(|e| format!("{} {}: decoding response: {}", method, url, e)),
+            Err(ureq::Error::Status(404 | 409, _)) => Ok(None),
+            Err(ureq::Error::Status(status, response)) => {
+                let message = decode(response)
+                    .ok()
+                    .and_then(|v| v["message"].as_str().map(String::from))
+                    .unwrap_or_default();
+                Err(format!("{} {}: HTTP {}: {}", method, url, status, message))
+            }
+            Err(e) => Err(format!("{} {}: {}", method, url, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::Level;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::sync::Mutex;
+
+    type Store = Arc<Mutex<Option<Value>>>;
+
+    /// A Kubernetes API serving a single Lease, with resourceVersion checks.
+    fn serve(store: Store) -> Client {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let method = request_line.split(' ').next().unwrap();
+                let (status, response) = handle(&store, method, &body);
+                let response = response.to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+        });
+        Client {
+            base,
+            token: "token".into(),
+            agent: ureq::AgentBuilder::new().build(),
+        }
+    }
+
+    fn handle(store: &Store, method: &str, body: &[u8]) -> (u16, Value) {
+        let mut stored = store.lock().unwrap();
+        let conflict = (409, json!({"message": "conflict"}));
+        match method {
+            "GET" => match &*stored {
+                Some(lease) => (200, lease.clone()),
+                None => (404, json!({"message": "not found"})),
+            },
+            "POST" if stored.is_some() => conflict,
+            "POST" | "PUT" => {
+                let mut lease: Value = serde_json::from_slice(body).unwrap();
+                let version = stored
+                    .as_ref()
+                    .map(|l| l["metadata"]["resourceVersion"].clone());
+                if method == "PUT" && version != Some(lease["metadata"]["resourceVersion"].clone())
+                {
+                    return conflict;
+                }
+                let next = version
+                    .and_then(|v| v.as_str()?.parse::<u64>().ok())
+                    .unwrap_or(0)
+                    + 1;
+                lease["metadata"]["resourceVersion"] = json!(next.to_string());
+                *stored = Some(lease.clone());
+                (200, lease)
+            }
+            _ => (405, json!({"message": "method not allowed"})),
+        }
+    }
+
+    fn lease_config() -> Config {
+        Config {
+            name: "app-v1-migrate".into(),
+            namespace: String::new(),
+            duration: Duration::from_secs(1),
+        }
+    }
+
+    fn held_by(holder: &str, renewed: chrono::DateTime<chrono::Utc>) -> Value {
+        json!({
+            "metadata": {"name": "app-v1-migrate", "resourceVersion": "7"},
+            "spec": {
+                "holderIdentity": holder,
+                "leaseDurationSeconds": 30,
+                "renewTime": renewed.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string(),
+                "leaseTransitions": 2,
+            },
+        })
+    }
+
+    fn log() -> Logger {
+        Logger::new(Box::new(std::io::sink()), false, Level::Info)
+    }
+
+    #[test]
+    fn test_leader_runs_once() {
+        let store = Store::default();
+        let client = serve(store.clone());
+        let cfg = lease_config();
+        let mut runs = 0;
+        lead(&log(), &client, "apps", &cfg, "pod-a", || {
+            runs += 1;
+            Ok(())
+        })
+        .unwrap();
+        let lease = store.lock().unwrap().clone().unwrap();
+        assert!(lease["spec"]["holderIdentity"].is_null());
+        assert!(lease["metadata"]["annotations"][COMPLETED_ANNOTATION].is_string());
+
+        lead(&log(), &client, "apps", &cfg, "pod-b", || {
+            runs += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(runs, 1, "pods after the leader must skip");
+    }
+
+    #[test]
+    fn test_failed_leader_releases_lease() {
+        let store = Store::default();
+        let client = serve(store.clone());
+        let cfg = lease_config();
+        let err = lead(&log(), &client, "apps", &cfg, "pod-a", || {
+            Err("migration failed".into())
+        })
+        .unwrap_err();
+        assert_eq!(err, "migration failed");
+        let lease = store.lock().unwrap().clone().unwrap();
+        assert!(lease["spec"]["holderIdentity"].is_null());
+        assert!(lease["metadata"]["annotations"].is_null());
+
+        let mut ran = false;
+        lead(&log(), &client, "apps", &cfg, "pod-b", || {
+            ran = true;
+            Ok(())
+        })
+        .unwrap();
+        assert!(ran, "another pod must take over after a failure");
+    }
+
+    #[test]
+    fn test_waits_for_leader_then_skips() {
+        let store: Store = Arc::new(Mutex::new(Some(held_by("pod-a", chrono::Utc::now()))));
+        let client = serve(store.clone());
+        let leader = {
+            let store = store.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(300));
+                let mut stored = store.lock().unwrap();
+                let lease = stored.as_mut().unwrap();
+                lease["spec"]["holderIdentity"] = Value::Null;
+                lease["metadata"]["annotations"][COMPLETED_ANNOTATION] = json!("done");
+            })
+        };
+        let started = std::time::Instant::now();
+        let mut ran = false;
+        lead(&log(), &client, "apps", &lease_config(), "pod-b", || {
+            ran = true;
+            Ok(())
+        })
+        .unwrap();
+        leader.join().unwrap();
+        assert!(!ran);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_takes_over_expired_lease() {
+        let stale = chrono::Utc::now() - chrono::Duration::hours(1);
+        let store: Store = Arc::new(Mutex::new(Some(held_by("pod-a", stale))));
+        let client = serve(store.clone());
+        let mut ran = false;
+        lead(&log(), &client, "apps", &lease_config(), "pod-b", || {
+            let lease = store.lock().unwrap().clone().unwrap();
+            assert_eq!(lease["spec"]["holderIdentity"], "pod-b");
+            assert_eq!(lease["spec"]["leaseTransitions"], 3);
+            ran = true;
+            Ok(())
+        })
+        .unwrap();
+        assert!(ran);
+    }
+}
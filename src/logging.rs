@@ -76,6 +76,14 @@ impl Logger {
         *self.json_mode.lock().unwrap() = enabled;
     }
 
+    pub fn json(&self) -> bool {
+        *self.json_mode.lock().unwrap()
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
     fn log(&self, level: Level, msg: &str, kvs: &[(&str, &str)]) {
         if level < self.level {
             return;
@@ -1,13 +1,19 @@
 #![doc = include_str!("../README.md")]
 
 mod cmd;
+mod diff;
 mod duration;
+mod fake;
+mod glob;
+mod lease;
 mod logging;
+mod pod;
 mod render;
 mod retry;
 mod safety;
 mod seed;
 mod template_funcs;
+mod validate;
 
 use clap::{Parser, Subcommand};
 
@@ -41,6 +47,8 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once at startup, so the size of the `render` variant doesn't matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Wait for TCP or HTTP(S) endpoints to become available
@@ -111,20 +119,30 @@ enum Commands {
     },
 
     /// Apply structured database seeds from a YAML/JSON spec file
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Seed {
+        #[command(subcommand)]
+        action: Option<SeedAction>,
         #[arg(
             long,
             required = true,
             env = "INITIUM_SPEC",
             help = "Path to seed spec file (YAML or JSON)"
         )]
-        spec: String,
+        spec: Option<String>,
         #[arg(
             long,
             env = "INITIUM_RESET",
             help = "Reset mode: delete existing data before re-seeding"
         )]
         reset: bool,
+        #[arg(
+            long,
+            env = "INITIUM_RESET_SET",
+            value_delimiter = ',',
+            help = "Reset only seed sets matching this glob before re-seeding (repeatable)"
+        )]
+        reset_set: Vec<String>,
         #[arg(
             long,
             env = "INITIUM_DRY_RUN",
@@ -137,24 +155,223 @@ enum Commands {
             help = "Override all seed sets to reconcile mode for this run"
         )]
         reconcile_all: bool,
+        #[arg(
+            long,
+            env = "INITIUM_AUTO_ORDER",
+            help = "Order the tables of every seed set by their foreign keys and @ref: values"
+        )]
+        auto_order: bool,
+        #[arg(
+            long,
+            env = "INITIUM_PHASE",
+            value_delimiter = ',',
+            help = "Only run phases matching this glob; prefix with ! to skip matches (repeatable)"
+        )]
+        phase: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_SEED_SET",
+            value_delimiter = ',',
+            help = "Only run seed sets matching this glob; prefix with ! to skip matches (repeatable)"
+        )]
+        seed_set: Vec<String>,
+        #[arg(
+            long,
+            default_value = "5m",
+            env = "INITIUM_LOCK_TIMEOUT",
+            help = "How long to wait for another seeder holding the seed lock (e.g. 30s, 5m)"
+        )]
+        lock_timeout: String,
+        #[arg(
+            long,
+            env = "INITIUM_REPORT_FILE",
+            help = "Write a JSON report of the run (per-table row counts, durations) to this path"
+        )]
+        report_file: Option<String>,
+        #[arg(
+            long,
+            env = "INITIUM_VALIDATE_ONLY",
+            conflicts_with_all = ["reset", "reset_set", "dry_run", "reconcile_all"],
+            help = "Check the spec against the seed spec schema without connecting to a database"
+        )]
+        validate_only: bool,
+        #[command(flatten)]
+        leader_elect: LeaderElectArgs,
+    },
+
+    /// Apply versioned SQL migrations from a directory
+    Migrate {
+        #[arg(
+            long,
+            required = true,
+            env = "INITIUM_DIR",
+            help = "Directory of V<version>__<description>.sql files"
+        )]
+        dir: String,
+        #[arg(
+            long,
+            default_value = "postgres",
+            env = "INITIUM_DRIVER",
+            help = "Database driver (sqlite, postgres, mysql)"
+        )]
+        driver: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "DATABASE_URL",
+            hide_env_values = true,
+            help = "Database URL"
+        )]
+        url: String,
+        #[arg(
+            long,
+            default_value = "initium_migrations",
+            env = "INITIUM_MIGRATIONS_TABLE",
+            help = "Table recording the applied migrations"
+        )]
+        table: String,
+        #[arg(
+            long,
+            default_value = "db",
+            env = "INITIUM_LOCK",
+            help = "How concurrent runs are serialized: db (PostgreSQL advisory lock, MySQL GET_LOCK) or none"
+        )]
+        lock: String,
+        #[arg(
+            long,
+            default_value = "5m",
+            env = "INITIUM_LOCK_TIMEOUT",
+            help = "How long to wait for another migrator holding the lock (e.g. 30s, 5m)"
+        )]
+        lock_timeout: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_LOCK_FILE",
+            help = "File recording the checksum of the applied migrations; skip the run when it matches"
+        )]
+        lock_file: String,
+        #[arg(
+            long,
+            env = "INITIUM_TIMEOUT",
+            help = "Fail the run when it takes longer (e.g. 30s, 5m); no limit by default"
+        )]
+        timeout: Option<String>,
+        #[arg(
+            long,
+            default_value = "10",
+            env = "INITIUM_MAX_ATTEMPTS",
+            help = "Maximum database connection attempts"
+        )]
+        max_attempts: u32,
+        #[arg(
+            long,
+            default_value = "1s",
+            env = "INITIUM_INITIAL_DELAY",
+            help = "Initial retry delay (e.g. 500ms, 1s, 5s)"
+        )]
+        initial_delay: String,
+        #[arg(
+            long,
+            default_value = "30s",
+            env = "INITIUM_MAX_DELAY",
+            help = "Maximum retry delay (e.g. 10s, 30s, 1m)"
+        )]
+        max_delay: String,
+        #[arg(
+            long,
+            default_value = "2.0",
+            env = "INITIUM_BACKOFF_FACTOR",
+            help = "Backoff multiplier"
+        )]
+        backoff_factor: f64,
+        #[arg(
+            long,
+            default_value = "0.1",
+            env = "INITIUM_JITTER",
+            help = "Jitter fraction (0.0-1.0)"
+        )]
+        jitter: f64,
+        #[arg(
+            long,
+            env = "INITIUM_ENV_FILE",
+            value_delimiter = ',',
+            help = "Dotenv file providing DATABASE_URL when --url is not set (repeatable)"
+        )]
+        env_file: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_WAIT_FOR",
+            value_delimiter = ',',
+            help = "Wait for this target (tcp://host:port or http(s)://...) before migrating (repeatable)"
+        )]
+        wait_for: Vec<String>,
+        #[arg(
+            long,
+            default_value = "5m",
+            env = "INITIUM_WAIT_TIMEOUT",
+            help = "How long to wait for the --wait-for targets, within --timeout (e.g. 30s, 2m)"
+        )]
+        wait_timeout: String,
+        #[arg(
+            long,
+            requires = "to",
+            env = "INITIUM_DOWN",
+            help = "Revert the applied migrations newer than --to with their __down.sql files"
+        )]
+        down: bool,
+        #[arg(
+            long,
+            env = "INITIUM_TO",
+            help = "Highest migration version to apply, or to keep with --down"
+        )]
+        to: Option<u64>,
+        #[arg(
+            long,
+            conflicts_with = "down",
+            env = "INITIUM_BASELINE",
+            help = "Record the migrations up to this version as applied without running them"
+        )]
+        baseline: Option<u64>,
+        #[arg(
+            long,
+            conflicts_with = "down",
+            env = "INITIUM_PLAN",
+            help = "List the pending migrations without applying them"
+        )]
+        plan: bool,
+        #[arg(
+            long,
+            requires = "plan",
+            env = "INITIUM_REPORT_FILE",
+            help = "Write the --plan report as JSON to this path"
+        )]
+        report_file: Option<String>,
+        #[command(flatten)]
+        leader_elect: LeaderElectArgs,
     },
 
     /// Render templates into config files
     Render {
         #[arg(
             long,
-            required = true,
             env = "INITIUM_TEMPLATE",
-            help = "Path to template file"
+            help = "Path to template file, or a directory to render recursively (repeatable)"
         )]
-        template: String,
+        template: Vec<String>,
         #[arg(
             long,
-            required = true,
             env = "INITIUM_OUTPUT",
-            help = "Output file path relative to workdir"
+            help = "Output file path relative to workdir, paired with --template by position (repeatable; - for stdout)"
         )]
-        output: String,
+        output: Vec<String>,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_PAIRS_FILE",
+            help = "YAML/JSON file listing template/output pairs to render"
+        )]
+        pairs_file: String,
         #[arg(
             long,
             default_value = "/work",
@@ -166,9 +383,143 @@ enum Commands {
             long,
             default_value = "envsubst",
             env = "INITIUM_MODE",
-            help = "Template mode: envsubst or gotemplate"
+            help = "Template mode: envsubst, gotemplate (alias jinja), or auto to pick by file extension"
         )]
         mode: String,
+        #[arg(
+            long,
+            env = "INITIUM_EXCLUDE",
+            value_delimiter = ',',
+            help = "Glob of files to skip when --template is a directory (repeatable)"
+        )]
+        exclude: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_VALUES",
+            value_delimiter = ',',
+            help = "YAML/JSON values file exposed to templates as `values` (repeatable, later files override earlier)"
+        )]
+        values: Vec<String>,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_TEMPLATE_DIR",
+            help = "Directory that {% include %}/{% import %} names are resolved against (gotemplate mode)"
+        )]
+        template_dir: String,
+        #[arg(
+            long,
+            env = "INITIUM_STRICT",
+            help = "Fail on undefined template variables instead of rendering empty strings (gotemplate mode)"
+        )]
+        strict: bool,
+        #[arg(
+            long,
+            env = "INITIUM_DRY_RUN",
+            help = "Print rendered output to stdout instead of writing files"
+        )]
+        dry_run: bool,
+        #[arg(
+            long,
+            env = "INITIUM_DIFF",
+            help = "Show a unified diff against existing output files without writing; exit 2 if any differ"
+        )]
+        diff: bool,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_FILE_ROOT",
+            help = "Directory that file()/fileExists() may read from (defaults to --workdir)"
+        )]
+        file_root: String,
+        #[arg(
+            long,
+            env = "INITIUM_FAIL_ON_UNSET",
+            help = "Fail listing every unset variable instead of leaving ${VAR} literals (envsubst mode)"
+        )]
+        fail_on_unset: bool,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_VALIDATE",
+            help = "Check rendered output parses as yaml, json, toml or ini before writing"
+        )]
+        validate: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_VALIDATE_CMD",
+            help = "Command run against the rendered output before writing; {} is replaced by the file path"
+        )]
+        validate_cmd: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_SCHEMA",
+            help = "JSON Schema (JSON or YAML) that rendered JSON/YAML output must satisfy before writing"
+        )]
+        schema: String,
+        #[arg(
+            long,
+            env = "INITIUM_MERGE",
+            value_delimiter = ',',
+            num_args = 1..,
+            help = "Deep-merge these YAML/JSON files in order into --output instead of rendering templates"
+        )]
+        merge: Vec<String>,
+        #[arg(
+            long,
+            default_value = "replace",
+            env = "INITIUM_MERGE_LISTS",
+            help = "How --merge combines lists: replace, append, or key:<field> to merge items by a key field"
+        )]
+        merge_lists: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_SECRETS_DIR",
+            help = "Directory whose files are exposed to templates as secrets.<filename> (gotemplate mode)"
+        )]
+        secrets_dir: String,
+        #[arg(
+            long,
+            env = "INITIUM_WATCH",
+            help = "Keep running and re-render when templates, values or watched files change"
+        )]
+        watch: bool,
+        #[arg(
+            long,
+            default_value = "2s",
+            env = "INITIUM_WATCH_INTERVAL",
+            help = "How often --watch checks inputs for changes (e.g. 2s, 1m)"
+        )]
+        watch_interval: String,
+        #[arg(
+            long,
+            env = "INITIUM_WATCH_FILE",
+            value_delimiter = ',',
+            help = "Extra file or directory whose changes trigger a re-render in --watch mode (repeatable)"
+        )]
+        watch_file: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_RELOAD_PID",
+            help = "Send SIGHUP to this process after each --watch re-render"
+        )]
+        reload_pid: Option<i32>,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_RELOAD_PID_FILE",
+            help = "Send SIGHUP to the process whose pid is in this file after each --watch re-render"
+        )]
+        reload_pid_file: String,
+        #[arg(
+            long,
+            env = "INITIUM_DECODE_BASE64",
+            help = "Base64-decode rendered output and write the raw bytes (keystores, DER certificates)"
+        )]
+        decode_base64: bool,
     },
 
     /// Fetch secrets or config from HTTP(S) endpoints
@@ -248,6 +599,19 @@ enum Commands {
             help = "Jitter fraction"
         )]
         jitter: f64,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_RATE_LIMIT",
+            help = "Maximum download rate (e.g. 512KiB/s, 10MB/s); unlimited when empty. --timeout then bounds connecting and each read, not the whole download"
+        )]
+        rate_limit: String,
+        #[arg(
+            long,
+            env = "INITIUM_DECODE_BASE64",
+            help = "Base64-decode the response body before writing (for binary artifacts)"
+        )]
+        decode_base64: bool,
     },
 
     /// Run arbitrary commands with structured logging
@@ -259,9 +623,402 @@ enum Commands {
             help = "Working directory"
         )]
         workdir: String,
+        #[arg(
+            long,
+            env = "INITIUM_ENV_FILE",
+            value_delimiter = ',',
+            help = "Dotenv file of variables for the command (repeatable)"
+        )]
+        env_file: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_TIMEOUT",
+            help = "Terminate the command's process group when it runs longer (e.g. 30s, 5m)"
+        )]
+        timeout: Option<String>,
+        #[arg(
+            long,
+            default_value = "10s",
+            env = "INITIUM_KILL_AFTER",
+            help = "After --timeout, how long to wait between SIGTERM and SIGKILL"
+        )]
+        kill_after: String,
+        #[arg(
+            long,
+            default_value = "1",
+            env = "INITIUM_MAX_ATTEMPTS",
+            help = "Max attempts of a command exiting non-zero"
+        )]
+        max_attempts: u32,
+        #[arg(
+            long,
+            default_value = "1s",
+            env = "INITIUM_INITIAL_DELAY",
+            help = "Initial retry delay (e.g. 500ms, 1s, 5s)"
+        )]
+        initial_delay: String,
+        #[arg(
+            long,
+            default_value = "30s",
+            env = "INITIUM_MAX_DELAY",
+            help = "Maximum retry delay (e.g. 10s, 30s, 1m)"
+        )]
+        max_delay: String,
+        #[arg(
+            long,
+            default_value = "2.0",
+            env = "INITIUM_BACKOFF_FACTOR",
+            help = "Backoff factor"
+        )]
+        backoff_factor: f64,
+        #[arg(
+            long,
+            default_value = "0.1",
+            env = "INITIUM_JITTER",
+            help = "Jitter fraction"
+        )]
+        jitter: f64,
+        #[arg(
+            long,
+            env = "INITIUM_RETRY_ON_EXIT_CODES",
+            value_delimiter = ',',
+            help = "Exit codes to retry (comma-separated); any non-zero code when unset"
+        )]
+        retry_on_exit_codes: Vec<i32>,
+        #[arg(
+            long,
+            default_value = "0",
+            env = "INITIUM_OK_EXIT_CODES",
+            value_delimiter = ',',
+            help = "Exit codes meaning success (comma-separated)"
+        )]
+        ok_exit_codes: Vec<i32>,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_USER",
+            help = "Run the command with this user's uid and primary gid"
+        )]
+        user: String,
+        #[arg(long, env = "INITIUM_UID", help = "Run the command as this uid")]
+        uid: Option<u32>,
+        #[arg(long, env = "INITIUM_GID", help = "Run the command as this gid")]
+        gid: Option<u32>,
+        #[arg(
+            long,
+            env = "INITIUM_STEPS",
+            conflicts_with = "args",
+            help = "YAML file of steps to run in order instead of a single command"
+        )]
+        steps: Option<String>,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_STDIN_FILE",
+            conflicts_with = "stdin_env",
+            help = "Pass this file's content to the command on stdin"
+        )]
+        stdin_file: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_STDIN_ENV",
+            help = "Pass this environment variable's value to the command on stdin"
+        )]
+        stdin_env: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_STDOUT_FILE",
+            help = "Also write the command's raw stdout to this file inside --workdir"
+        )]
+        stdout_file: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "INITIUM_STDERR_FILE",
+            help = "Also write the command's raw stderr to this file inside --workdir"
+        )]
+        stderr_file: String,
+        #[arg(
+            long,
+            env = "INITIUM_SHELL",
+            help = "Run the arguments as a /bin/sh -c script (enables shell interpretation)"
+        )]
+        shell: bool,
+        #[arg(
+            long,
+            env = "INITIUM_ENV_ALLOWLIST",
+            value_delimiter = ',',
+            help = "Globs of environment variables passed to the command (repeatable); all when unset"
+        )]
+        env_allowlist: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_ENV_DENYLIST",
+            value_delimiter = ',',
+            help = "Globs of environment variables withheld from the command (repeatable)"
+        )]
+        env_denylist: Vec<String>,
+        #[arg(
+            long = "cmd",
+            env = "INITIUM_CMD",
+            conflicts_with_all = ["args", "steps"],
+            help = "Command line to run instead of the trailing command (repeatable)"
+        )]
+        cmds: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_PARALLEL",
+            requires = "cmds",
+            help = "Run the --cmd commands concurrently"
+        )]
+        parallel: bool,
+        #[arg(
+            long,
+            env = "INITIUM_ENSURE_WORKDIR",
+            requires = "workdir",
+            help = "Create --workdir when it does not exist"
+        )]
+        ensure_workdir: bool,
+        #[arg(
+            long,
+            env = "INITIUM_WORKDIR_MODE",
+            requires = "ensure_workdir",
+            help = "Octal mode of a workdir created by --ensure-workdir (e.g. 0750)"
+        )]
+        workdir_mode: Option<String>,
+        #[arg(
+            long,
+            env = "INITIUM_WORKDIR_OWNER",
+            requires = "ensure_workdir",
+            help = "Owner user[:group] of a workdir created by --ensure-workdir (e.g. 1000:1000)"
+        )]
+        workdir_owner: Option<String>,
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         args: Vec<String>,
     },
+
+    /// Extract a tar, tar.gz or zip archive into a directory
+    Unarchive {
+        #[arg(
+            long,
+            required = true,
+            env = "INITIUM_FILE",
+            help = "Archive to extract"
+        )]
+        file: String,
+        #[arg(
+            long,
+            required = true,
+            env = "INITIUM_DEST",
+            help = "Destination directory relative to workdir"
+        )]
+        dest: String,
+        #[arg(
+            long,
+            default_value = "/work",
+            env = "INITIUM_WORKDIR",
+            help = "Working directory"
+        )]
+        workdir: String,
+        #[arg(
+            long,
+            default_value = "0",
+            env = "INITIUM_STRIP_COMPONENTS",
+            help = "Leading path components to remove from entry names"
+        )]
+        strip_components: usize,
+        #[arg(
+            long,
+            env = "INITIUM_INCLUDE",
+            value_delimiter = ',',
+            help = "Glob of entries to extract, matched after stripping (repeatable)"
+        )]
+        include: Vec<String>,
+    },
+
+    /// Copy a directory tree into the workdir, optionally rendering each file
+    Copy {
+        #[arg(long, required = true, env = "INITIUM_SRC", help = "Directory to copy")]
+        src: String,
+        #[arg(
+            long,
+            required = true,
+            env = "INITIUM_DEST",
+            help = "Destination directory relative to workdir"
+        )]
+        dest: String,
+        #[arg(
+            long,
+            default_value = "/work",
+            env = "INITIUM_WORKDIR",
+            help = "Working directory"
+        )]
+        workdir: String,
+        #[arg(
+            long,
+            env = "INITIUM_INCLUDE",
+            value_delimiter = ',',
+            help = "Glob of files to copy, relative to --src (repeatable)"
+        )]
+        include: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_EXCLUDE",
+            value_delimiter = ',',
+            help = "Glob of files or directories to skip, relative to --src (repeatable)"
+        )]
+        exclude: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_RENDER",
+            help = "Render each text file as a template while copying"
+        )]
+        render: bool,
+        #[arg(
+            long,
+            default_value = "envsubst",
+            env = "INITIUM_MODE",
+            help = "Template mode for --render: envsubst, gotemplate (alias jinja), or auto to pick by file extension"
+        )]
+        mode: String,
+    },
+
+    /// Change the owner and mode of files, e.g. on persistent volumes
+    Perms {
+        #[arg(
+            long,
+            required = true,
+            env = "INITIUM_PATH",
+            value_delimiter = ',',
+            help = "Absolute path to change (repeatable)"
+        )]
+        path: Vec<String>,
+        #[arg(
+            long,
+            env = "INITIUM_PERMS_OWNER",
+            required_unless_present = "mode",
+            help = "Owner user[:group] to set (e.g. 1000:1000)"
+        )]
+        owner: Option<String>,
+        #[arg(
+            long,
+            env = "INITIUM_PERMS_MODE",
+            help = "Octal mode to set (e.g. 0750)"
+        )]
+        mode: Option<String>,
+        #[arg(
+            long,
+            env = "INITIUM_RECURSIVE",
+            help = "Also change everything beneath each path, without following symlinks"
+        )]
+        recursive: bool,
+    },
+
+    /// Serve the progress of a subcommand over HTTP while it runs
+    Healthz {
+        #[arg(
+            long,
+            default_value = ":8080",
+            env = "INITIUM_HEALTHZ_LISTEN",
+            help = "Address to serve the endpoint on ([host]:port)"
+        )]
+        listen: String,
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            required = true,
+            help = "Subcommand to run, with its flags"
+        )]
+        args: Vec<String>,
+    },
+
+    /// Sleep for a duration, or until SIGTERM/SIGINT (for sidecar containers)
+    Sleep {
+        #[arg(
+            default_value = "infinity",
+            help = "How long to sleep (e.g. 30s, 5m), or infinity"
+        )]
+        duration: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SeedAction {
+    /// Write rows of existing tables as a seed spec
+    Export {
+        #[arg(
+            long,
+            default_value = "postgres",
+            env = "INITIUM_DRIVER",
+            help = "Database driver (sqlite, postgres)"
+        )]
+        driver: String,
+        #[arg(
+            long,
+            default_value = "",
+            env = "DATABASE_URL",
+            hide_env_values = true,
+            help = "Database URL"
+        )]
+        url: String,
+        #[arg(
+            long,
+            required = true,
+            env = "INITIUM_TABLES",
+            value_delimiter = ',',
+            help = "Tables to export, optionally schema-qualified (repeatable)"
+        )]
+        tables: Vec<String>,
+        #[arg(
+            long,
+            default_value = "-",
+            env = "INITIUM_OUTPUT",
+            help = "Seed spec file to write (- for stdout)"
+        )]
+        output: String,
+    },
+}
+
+#[derive(clap::Args)]
+struct LeaderElectArgs {
+    #[arg(
+        long,
+        env = "INITIUM_LEADER_ELECT",
+        value_name = "LEASE",
+        help = "Run on one pod only, holding this Kubernetes Lease; name it per release"
+    )]
+    leader_elect: Option<String>,
+    #[arg(
+        long,
+        default_value = "",
+        env = "INITIUM_LEASE_NAMESPACE",
+        help = "Namespace of the Lease (default: the pod's namespace)"
+    )]
+    lease_namespace: String,
+    #[arg(
+        long,
+        default_value = "15s",
+        env = "INITIUM_LEASE_DURATION",
+        help = "How long a Lease whose leader stopped renewing it stays held (e.g. 15s, 1m)"
+    )]
+    lease_duration: String,
+}
+
+impl LeaderElectArgs {
+    fn config(self) -> Result<Option<lease::Config>, String> {
+        let Some(name) = self.leader_elect else {
+            return Ok(None);
+        };
+        let duration = duration::parse_duration(&self.lease_duration)
+            .map_err(|e| format!("invalid --lease-duration: {}", e))?;
+        Ok(Some(lease::Config {
+            name,
+            namespace: self.lease_namespace,
+            duration,
+        }))
+    }
 }
 
 fn main() {
@@ -271,7 +1028,34 @@ fn main() {
         log.set_json(true);
     }
 
-    let result = match cli.command {
+    match run(&log, cli.command) {
+        Ok(0) => {}
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            log.error(&e, &[]);
+            std::process::exit(1);
+        }
+    }
+
+    if cli.sidecar {
+        let signals = cmd::SignalGuard::new();
+        log.info(
+            "tasks completed, entering sidecar mode (sleeping indefinitely)",
+            &[],
+        );
+        if let Some(signal) = signals.sleep(None) {
+            log.info("received signal, exiting", &[("signal", signal)]);
+        }
+    }
+}
+
+/// Exit code of `render --diff` when an output differs.
+const EXIT_DIFFERENCES: i32 = 2;
+
+/// Run a subcommand, returning the exit code it finished with.
+fn run(log: &logging::Logger, command: Commands) -> Result<i32, String> {
+    let mut code = 0;
+    let result = match command {
         Commands::WaitFor {
             target,
             timeout,
@@ -298,20 +1082,197 @@ fn main() {
             };
             cfg.validate()
                 .map_err(|e| format!("invalid retry config: {}", e))?;
-            cmd::wait_for::run(&log, &target, &cfg, timeout_dur, http_status, insecure_tls)
+            cmd::wait_for::run(log, &target, &cfg, timeout_dur, http_status, insecure_tls)
         })(),
         Commands::Seed {
+            action:
+                Some(SeedAction::Export {
+                    driver,
+                    url,
+                    tables,
+                    output,
+                }),
+            ..
+        } => seed::export::run(
+            log,
+            &seed::export::Config {
+                driver,
+                url,
+                tables,
+                output,
+            },
+        ),
+        Commands::Seed {
+            action: None,
             spec,
             reset,
+            reset_set,
             dry_run,
             reconcile_all,
-        } => seed::run(&log, &spec, reset, dry_run, reconcile_all),
+            auto_order,
+            phase,
+            seed_set,
+            lock_timeout,
+            report_file,
+            validate_only,
+            leader_elect,
+        } => (|| {
+            // Required by clap unless a subcommand is given.
+            let spec = spec.unwrap_or_default();
+            if validate_only {
+                seed::validate::run(log, &spec)
+            } else {
+                let lock_timeout = duration::parse_duration(&lock_timeout)
+                    .map_err(|e| format!("invalid --lock-timeout: {}", e))?;
+                let seed_cfg = seed::Config {
+                    reset,
+                    reset_sets: reset_set,
+                    dry_run,
+                    reconcile_all,
+                    auto_order,
+                    phases: phase,
+                    seed_sets: seed_set,
+                    lock_timeout: Some(lock_timeout),
+                    report_file,
+                    leader_elect: leader_elect.config()?,
+                };
+                seed::run(log, &spec, &seed_cfg)
+            }
+        })(),
+        Commands::Migrate {
+            dir,
+            driver,
+            url,
+            table,
+            lock,
+            lock_timeout,
+            lock_file,
+            timeout,
+            max_attempts,
+            initial_delay,
+            max_delay,
+            backoff_factor,
+            jitter,
+            env_file,
+            wait_for,
+            wait_timeout,
+            down,
+            to,
+            baseline,
+            plan,
+            report_file,
+            leader_elect,
+        } => (|| {
+            let lock_timeout = duration::parse_duration(&lock_timeout)
+                .map_err(|e| format!("invalid --lock-timeout: {}", e))?;
+            let timeout = timeout
+                .map(|t| duration::parse_duration(&t))
+                .transpose()
+                .map_err(|e| format!("invalid --timeout: {}", e))?;
+            let wait_timeout = duration::parse_duration(&wait_timeout)
+                .map_err(|e| format!("invalid --wait-timeout: {}", e))?;
+            let initial_delay = duration::parse_duration(&initial_delay)
+                .map_err(|e| format!("invalid --initial-delay: {}", e))?;
+            let max_delay = duration::parse_duration(&max_delay)
+                .map_err(|e| format!("invalid --max-delay: {}", e))?;
+            let retry = retry::Config {
+                max_attempts,
+                initial_delay,
+                max_delay,
+                backoff_factor,
+                jitter_fraction: jitter,
+            };
+            retry
+                .validate()
+                .map_err(|e| format!("invalid retry config: {}", e))?;
+            cmd::migrate::run(
+                log,
+                &cmd::migrate::Config {
+                    driver,
+                    url,
+                    dir,
+                    table,
+                    lock,
+                    lock_timeout,
+                    lock_file,
+                    timeout,
+                    retry,
+                    env_files: env_file,
+                    wait_for,
+                    wait_timeout,
+                    down,
+                    to,
+                    baseline,
+                    plan,
+                    report_file,
+                    leader_elect: leader_elect.config()?,
+                },
+            )
+        })(),
         Commands::Render {
             template,
             output,
+            pairs_file,
             workdir,
             mode,
-        } => cmd::render::run(&log, &template, &output, &workdir, &mode),
+            exclude,
+            values,
+            template_dir,
+            strict,
+            dry_run,
+            diff,
+            file_root,
+            fail_on_unset,
+            validate,
+            validate_cmd,
+            schema,
+            merge,
+            merge_lists,
+            secrets_dir,
+            watch,
+            watch_interval,
+            watch_file,
+            reload_pid,
+            reload_pid_file,
+            decode_base64,
+        } => (|| {
+            let watch_interval = duration::parse_duration(&watch_interval)
+                .map_err(|e| format!("invalid --watch-interval: {}", e))?;
+            let render_cfg = cmd::render::Config {
+                templates: template,
+                outputs: output,
+                pairs_file,
+                workdir,
+                mode,
+                exclude,
+                values,
+                template_dir,
+                strict,
+                dry_run,
+                diff,
+                file_root,
+                fail_on_unset,
+                validate_format: validate,
+                validate_cmd,
+                schema,
+                merge,
+                merge_lists,
+                secrets_dir,
+                watch,
+                watch_interval,
+                watch_files: watch_file,
+                reload_pid,
+                reload_pid_file,
+                decode_base64,
+            };
+            if render_cfg.watch {
+                return cmd::render::watch(log, &render_cfg);
+            }
+            if cmd::render::run(log, &render_cfg)? {
+                code = EXIT_DIFFERENCES;
+            }
+            Ok(())
+        })(),
         Commands::Fetch {
             url,
             output,
@@ -326,6 +1287,8 @@ fn main() {
             max_delay,
             backoff_factor,
             jitter,
+            rate_limit,
+            decode_base64,
         } => (|| {
             let timeout_dur = duration::parse_duration(&timeout)
                 .map_err(|e| format!("invalid --timeout: {}", e))?;
@@ -333,6 +1296,14 @@ fn main() {
                 .map_err(|e| format!("invalid --initial-delay: {}", e))?;
             let max_delay_dur = duration::parse_duration(&max_delay)
                 .map_err(|e| format!("invalid --max-delay: {}", e))?;
+            let rate_limit_bps = if rate_limit.is_empty() {
+                None
+            } else {
+                Some(
+                    cmd::fetch::parse_rate_limit(&rate_limit)
+                        .map_err(|e| format!("invalid --rate-limit: {}", e))?,
+                )
+            };
             let fetch_cfg = cmd::fetch::Config {
                 url,
                 output,
@@ -342,6 +1313,8 @@ fn main() {
                 follow_redirects,
                 allow_cross_site_redirects,
                 timeout: timeout_dur,
+                rate_limit: rate_limit_bps,
+                decode_base64,
             };
             let retry_cfg = retry::Config {
                 max_attempts,
@@ -353,23 +1326,168 @@ fn main() {
             retry_cfg
                 .validate()
                 .map_err(|e| format!("invalid retry config: {}", e))?;
-            cmd::fetch::run(&log, &fetch_cfg, &retry_cfg)
+            cmd::fetch::run(log, &fetch_cfg, &retry_cfg)
         })(),
-        Commands::Exec { workdir, args } => cmd::exec::run(&log, &args, &workdir),
-    };
-
-    if let Err(e) = result {
-        log.error(&e, &[]);
-        std::process::exit(1);
-    }
-
-    if cli.sidecar {
-        log.info(
-            "tasks completed, entering sidecar mode (sleeping indefinitely)",
-            &[],
-        );
-        loop {
-            std::thread::sleep(std::time::Duration::from_secs(3600));
+        Commands::Exec {
+            workdir,
+            env_file,
+            timeout,
+            kill_after,
+            max_attempts,
+            initial_delay,
+            max_delay,
+            backoff_factor,
+            jitter,
+            retry_on_exit_codes,
+            ok_exit_codes,
+            user,
+            uid,
+            gid,
+            steps,
+            stdin_file,
+            stdin_env,
+            stdout_file,
+            stderr_file,
+            shell,
+            env_allowlist,
+            env_denylist,
+            cmds,
+            parallel,
+            ensure_workdir,
+            workdir_mode,
+            workdir_owner,
+            args,
+        } => (|| {
+            let timeout = timeout
+                .map(|t| duration::parse_duration(&t))
+                .transpose()
+                .map_err(|e| format!("invalid --timeout: {}", e))?;
+            let kill_after = duration::parse_duration(&kill_after)
+                .map_err(|e| format!("invalid --kill-after: {}", e))?;
+            let retry_cfg = retry::Config {
+                max_attempts,
+                initial_delay: duration::parse_duration(&initial_delay)
+                    .map_err(|e| format!("invalid --initial-delay: {}", e))?,
+                max_delay: duration::parse_duration(&max_delay)
+                    .map_err(|e| format!("invalid --max-delay: {}", e))?,
+                backoff_factor,
+                jitter_fraction: jitter,
+            };
+            retry_cfg
+                .validate()
+                .map_err(|e| format!("invalid retry config: {}", e))?;
+            let exec_cfg = cmd::exec::Config {
+                workdir,
+                env_files: env_file,
+                env: vec![],
+                timeout,
+                kill_after,
+                retry: retry_cfg,
+                retry_on_exit_codes,
+                ok_exit_codes,
+                user,
+                uid,
+                gid,
+                stdin_file,
+                stdin_env,
+                stdout_file,
+                stderr_file,
+                shell,
+                env_allowlist,
+                env_denylist,
+                label: String::new(),
+                ensure_workdir,
+                workdir_mode: workdir_mode
+                    .map(|m| cmd::exec::parse_mode(&m))
+                    .transpose()
+                    .map_err(|e| format!("invalid --workdir-mode: {}", e))?,
+                workdir_owner: workdir_owner
+                    .map(|o| cmd::exec::parse_owner(&o))
+                    .transpose()
+                    .map_err(|e| format!("invalid --workdir-owner: {}", e))?,
+            };
+            match steps {
+                Some(path) => cmd::exec::run_steps(log, &path, &exec_cfg),
+                None if !cmds.is_empty() => {
+                    cmd::exec::run_commands(log, &cmds, parallel, &exec_cfg)
+                }
+                None => cmd::exec::run(log, &args, &exec_cfg),
+            }
+        })(),
+        Commands::Sleep { duration } => cmd::sleep::run(log, &duration),
+        Commands::Unarchive {
+            file,
+            dest,
+            workdir,
+            strip_components,
+            include,
+        } => cmd::unarchive::run(
+            log,
+            &cmd::unarchive::Config {
+                file,
+                dest,
+                workdir,
+                strip_components,
+                include,
+            },
+        ),
+        Commands::Copy {
+            src,
+            dest,
+            workdir,
+            include,
+            exclude,
+            render,
+            mode,
+        } => cmd::copy::run(
+            log,
+            &cmd::copy::Config {
+                src,
+                dest,
+                workdir,
+                include,
+                exclude,
+                render,
+                mode,
+            },
+        ),
+        Commands::Perms {
+            path,
+            owner,
+            mode,
+            recursive,
+        } => (|| {
+            let perms_cfg = cmd::perms::Config {
+                paths: path,
+                owner: owner
+                    .map(|o| cmd::exec::parse_owner(&o))
+                    .transpose()
+                    .map_err(|e| format!("invalid --owner: {}", e))?,
+                mode: mode
+                    .map(|m| cmd::exec::parse_mode(&m))
+                    .transpose()
+                    .map_err(|e| format!("invalid --mode: {}", e))?,
+                recursive,
+            };
+            cmd::perms::run(log, &perms_cfg)
+        })(),
+        Commands::Healthz { listen, args } => {
+            let name = args
+                .iter()
+                .find(|a| !a.starts_with('-'))
+                .cloned()
+                .unwrap_or_default();
+            let inner = Cli::try_parse_from(std::iter::once("initium".to_string()).chain(args))
+                .map_err(|e| format!("invalid subcommand for healthz: {}", e.render()))?;
+            if matches!(inner.command, Commands::Healthz { .. }) {
+                return Err("healthz cannot run healthz".into());
+            }
+            if inner.json {
+                log.set_json(true);
+            }
+            code = cmd::healthz::run(log, &listen, &name, |log| run(log, inner.command))?;
+            Ok(())
         }
-    }
+    };
+    result.map(|()| code)
 }
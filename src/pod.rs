@@ -0,0 +1,165 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Default mount path of a Downward API volume.
+const DEFAULT_PODINFO_DIR: &str = "/etc/podinfo";
+/// Namespace file present in every pod that mounts a service account token.
+const SA_NAMESPACE_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+/// Pod identity exposed to templates as `pod`, populated from the Kubernetes
+/// Downward API. Fields that are not provided are empty.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PodInfo {
+    pub name: String,
+    pub namespace: String,
+    pub uid: String,
+    pub ip: String,
+    pub node_name: String,
+    pub service_account: String,
+    pub labels: BTreeMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// Discover pod information from `POD_*` env vars and the Downward API volume
+/// at `INITIUM_PODINFO_DIR` (default `/etc/podinfo`). Env vars win over files.
+pub fn discover() -> PodInfo {
+    let dir = std::env::var("INITIUM_PODINFO_DIR").unwrap_or_else(|_| DEFAULT_PODINFO_DIR.into());
+    load(
+        |name| std::env::var(name).ok().filter(|v| !v.is_empty()),
+        Path::new(&dir),
+        Path::new(SA_NAMESPACE_FILE),
+    )
+}
+
+fn load(env: impl Fn(&str) -> Option<String>, dir: &Path, sa_namespace: &Path) -> PodInfo {
+    let read = |file: &str| {
+        fs::read_to_string(dir.join(file))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let field = |var: &str, file: &str| env(var).or_else(|| read(file)).unwrap_or_default();
+    let map = |var: &str, file: &str| {
+        env(var)
+            .or_else(|| read(file))
+            .map(|s| parse_kv_lines(&s))
+            .unwrap_or_default()
+    };
+    let mut info = PodInfo {
+        name: field("POD_NAME", "name"),
+        namespace: field("POD_NAMESPACE", "namespace"),
+        uid: field("POD_UID", "uid"),
+        ip: field("POD_IP", "ip"),
+        node_name: field("NODE_NAME", "node_name"),
+        service_account: field("POD_SERVICE_ACCOUNT", "service_account"),
+        labels: map("POD_LABELS", "labels"),
+        annotations: map("POD_ANNOTATIONS", "annotations"),
+    };
+    if info.namespace.is_empty() {
+        info.namespace = fs::read_to_string(sa_namespace)
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default();
+    }
+    info
+}
+
+/// Parse the Downward API `labels`/`annotations` format: one `key="value"`
+/// entry per line, with the value quoted and escaped like a Go string.
+fn parse_kv_lines(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            Some((key.to_string(), unquote(value)))
+        })
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return value.to_string();
+    };
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_load_from_downward_api_volume() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("name"), "web-0\n").unwrap();
+        fs::write(dir.path().join("namespace"), "prod").unwrap();
+        fs::write(
+            dir.path().join("labels"),
+            "app=\"web\"\ntier=\"frontend\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("annotations"),
+            "note=\"say \\\"hi\\\"\\nbye\"\n",
+        )
+        .unwrap();
+        let info = load(no_env, dir.path(), Path::new("/nonexistent"));
+        assert_eq!(info.name, "web-0");
+        assert_eq!(info.namespace, "prod");
+        assert_eq!(info.labels["app"], "web");
+        assert_eq!(info.labels["tier"], "frontend");
+        assert_eq!(info.annotations["note"], "say \"hi\"\nbye");
+        assert_eq!(info.ip, "");
+    }
+
+    #[test]
+    fn test_env_overrides_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("name"), "from-file").unwrap();
+        let env = |name: &str| match name {
+            "POD_NAME" => Some("from-env".to_string()),
+            "POD_IP" => Some("10.0.0.7".to_string()),
+            "POD_LABELS" => Some("app=\"api\"".to_string()),
+            _ => None,
+        };
+        let info = load(env, dir.path(), Path::new("/nonexistent"));
+        assert_eq!(info.name, "from-env");
+        assert_eq!(info.ip, "10.0.0.7");
+        assert_eq!(info.labels["app"], "api");
+    }
+
+    #[test]
+    fn test_namespace_falls_back_to_service_account() {
+        let dir = TempDir::new().unwrap();
+        let sa = dir.path().join("sa-namespace");
+        fs::write(&sa, "kube-system\n").unwrap();
+        let info = load(no_env, &dir.path().join("missing"), &sa);
+        assert_eq!(info.namespace, "kube-system");
+    }
+
+    #[test]
+    fn test_missing_everything_is_empty() {
+        let info = load(no_env, Path::new("/nonexistent"), Path::new("/nonexistent"));
+        assert_eq!(info, PodInfo::default());
+    }
+}
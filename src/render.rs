@@ -1,18 +1,49 @@
+use std::collections::BTreeMap;
 use std::env;
-pub fn envsubst(input: &str) -> String {
+/// Substitute `$VAR` and `${VAR}` references with environment values, leaving
+/// unknown variables untouched. Braced references also accept the shell forms
+/// `${VAR:-default}` / `${VAR-default}` (fallback when unset or empty / unset)
+/// and `${VAR:?message}` / `${VAR?message}` (fail when unset or empty / unset).
+pub fn envsubst(input: &str) -> Result<String, String> {
+    substitute(input, &mut Vec::new())
+}
+
+/// Like [`envsubst`], but fails listing every referenced variable that is
+/// unset and has no default, instead of leaving `${VAR}` literals behind.
+pub fn envsubst_strict(input: &str) -> Result<String, String> {
+    let mut missing = Vec::new();
+    let result = substitute(input, &mut missing)?;
+    if !missing.is_empty() {
+        return Err(format!(
+            "unset environment variables: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(result)
+}
+
+/// Expand references in `input`, recording the names of unset variables that
+/// were left untouched in `missing` (deduplicated, in order of appearance).
+fn substitute(input: &str, missing: &mut Vec<String>) -> Result<String, String> {
     let mut result = String::with_capacity(input.len());
     let bytes = input.as_bytes();
     let len = bytes.len();
     let mut i = 0;
+    let mut literal_start = 0;
     while i < len {
         if bytes[i] == b'$' && i + 1 < len {
             if bytes[i + 1] == b'{' {
-                if let Some((name, end)) = parse_braced_var(input, i + 2) {
-                    match env::var(name) {
-                        Ok(val) => result.push_str(&val),
-                        Err(_) => result.push_str(&input[i..end]),
+                if let Some((expr, end)) = parse_braced_var(input, i + 2) {
+                    result.push_str(&input[literal_start..i]);
+                    match expand(&expr, missing)? {
+                        Some(val) => result.push_str(&val),
+                        None => {
+                            record_missing(missing, expr.name);
+                            result.push_str(&input[i..end]);
+                        }
                     }
                     i = end;
+                    literal_start = i;
                     continue;
                 }
             } else if is_var_start(bytes[i + 1]) {
@@ -21,19 +52,64 @@ pub fn envsubst(input: &str) -> String {
                 while end < len && is_var_char(bytes[end]) {
                     end += 1;
                 }
+                result.push_str(&input[literal_start..i]);
                 let name = &input[start..end];
                 match env::var(name) {
                     Ok(val) => result.push_str(&val),
-                    Err(_) => result.push_str(&input[i..end]),
+                    Err(_) => {
+                        record_missing(missing, name);
+                        result.push_str(&input[i..end]);
+                    }
                 }
                 i = end;
+                literal_start = i;
                 continue;
             }
         }
-        result.push(bytes[i] as char);
         i += 1;
     }
-    result
+    result.push_str(&input[literal_start..]);
+    Ok(result)
+}
+
+fn record_missing(missing: &mut Vec<String>, name: &str) {
+    if !missing.iter().any(|m| m == name) {
+        missing.push(name.to_string());
+    }
+}
+
+/// A parsed `${...}` expression.
+struct BracedVar<'a> {
+    name: &'a str,
+    /// Operator (`:-`, `-`, `:?`, `?`) and its word, if any.
+    op: Option<(&'a str, &'a str)>,
+}
+
+/// Resolve a braced expression. `Ok(None)` means "unset with no fallback":
+/// the caller keeps the original text.
+fn expand(expr: &BracedVar, missing: &mut Vec<String>) -> Result<Option<String>, String> {
+    let value = env::var(expr.name).ok();
+    let Some((op, word)) = expr.op else {
+        return Ok(value);
+    };
+    let use_word = match op {
+        ":-" | ":?" => value.as_deref().is_none_or(str::is_empty),
+        _ => value.is_none(),
+    };
+    if !use_word {
+        return Ok(value);
+    }
+    match op {
+        ":-" | "-" => substitute(word, missing).map(Some),
+        _ => {
+            let message = substitute(word, missing)?;
+            Err(if message.is_empty() {
+                format!("{}: parameter null or not set", expr.name)
+            } else {
+                format!("{}: {}", expr.name, message)
+            })
+        }
+    }
 }
 
 fn is_var_start(b: u8) -> bool {
@@ -44,7 +120,7 @@ fn is_var_char(b: u8) -> bool {
     b.is_ascii_alphanumeric() || b == b'_'
 }
 
-fn parse_braced_var(input: &str, start: usize) -> Option<(&str, usize)> {
+fn parse_braced_var(input: &str, start: usize) -> Option<(BracedVar<'_>, usize)> {
     let bytes = input.as_bytes();
     if start >= bytes.len() || !is_var_start(bytes[start]) {
         return None;
@@ -53,30 +129,457 @@ fn parse_braced_var(input: &str, start: usize) -> Option<(&str, usize)> {
     while end < bytes.len() && is_var_char(bytes[end]) {
         end += 1;
     }
+    let name = &input[start..end];
     if end < bytes.len() && bytes[end] == b'}' {
-        Some((&input[start..end], end + 1))
-    } else {
-        None
-    }
-}
-pub fn template_render(input: &str) -> Result<String, String> {
-    let env_map: std::collections::HashMap<String, String> = env::vars().collect();
-    let mut jinja_env = minijinja::Environment::new();
-    jinja_env.set_undefined_behavior(minijinja::UndefinedBehavior::Lenient);
-    crate::template_funcs::register(&mut jinja_env);
-    jinja_env
-        .add_template("t", input)
-        .map_err(|e| format!("parsing template: {}", e))?;
-    let tmpl = jinja_env
-        .get_template("t")
-        .map_err(|e| format!("getting template: {}", e))?;
-    tmpl.render(minijinja::context!(env => env_map))
-        .map_err(|e| format!("executing template: {}", e))
+        return Some((BracedVar { name, op: None }, end + 1));
+    }
+    let rest = &input[end..];
+    let op = [":-", ":?", "-", "?"]
+        .into_iter()
+        .find(|op| rest.starts_with(op))?;
+    // The word runs to the matching `}`, so defaults may nest `${...}`.
+    let word_start = end + op.len();
+    let mut depth = 0;
+    let mut j = word_start;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'$' if j + 1 < bytes.len() && bytes[j + 1] == b'{' => {
+                depth += 1;
+                j += 1;
+            }
+            b'}' if depth == 0 => {
+                return Some((
+                    BracedVar {
+                        name,
+                        op: Some((op, &input[word_start..j])),
+                    },
+                    j + 1,
+                ));
+            }
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        j += 1;
+    }
+    None
+}
+
+/// A MiniJinja environment plus the render context, built once and shared by
+/// every template rendered in the same invocation.
+pub struct TemplateRenderer {
+    env: minijinja::Environment<'static>,
+    env_map: std::collections::HashMap<String, String>,
+    pod: crate::pod::PodInfo,
+    secrets: BTreeMap<String, String>,
+    values: serde_yaml::Value,
+    ctx: minijinja::Value,
+}
+
+impl TemplateRenderer {
+    pub fn new() -> Self {
+        let env_map: std::collections::HashMap<String, String> = env::vars().collect();
+        let mut jinja_env = minijinja::Environment::new();
+        jinja_env.set_undefined_behavior(minijinja::UndefinedBehavior::Lenient);
+        crate::template_funcs::register(&mut jinja_env);
+        let mut renderer = Self {
+            env: jinja_env,
+            env_map,
+            pod: crate::pod::discover(),
+            secrets: BTreeMap::new(),
+            values: serde_yaml::Value::Mapping(serde_yaml::Mapping::new()),
+            ctx: minijinja::Value::UNDEFINED,
+        };
+        renderer.set_context();
+        renderer
+    }
+
+    /// Expose `values` (typically merged from `--values` files) to templates.
+    pub fn with_values(mut self, values: serde_yaml::Value) -> Self {
+        self.values = values;
+        self.set_context();
+        self
+    }
+
+    /// Expose `secrets` (typically loaded by [`load_secrets_dir`]) to templates.
+    pub fn with_secrets(mut self, secrets: BTreeMap<String, String>) -> Self {
+        self.secrets = secrets;
+        self.set_context();
+        self
+    }
+
+    /// Fail on undefined variables instead of rendering them as empty strings.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        if strict {
+            self.env
+                .set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+        }
+        self
+    }
+
+    /// Enable `file()`/`fileExists()` for paths under `root`.
+    pub fn with_file_root(mut self, root: &str) -> Self {
+        crate::template_funcs::register_file_funcs(&mut self.env, root);
+        self
+    }
+
+    /// Resolve `{% include %}`, `{% import %}` and `{% extends %}` names
+    /// relative to `dir`. Names containing `..` segments are rejected by the
+    /// loader, so partials cannot escape the directory.
+    pub fn with_template_dir(mut self, dir: &str) -> Self {
+        self.env.set_loader(minijinja::path_loader(dir));
+        self
+    }
+
+    fn set_context(&mut self) {
+        self.ctx = minijinja::context!(
+            env => self.env_map.clone(),
+            values => minijinja::Value::from_serialize(&self.values),
+            pod => minijinja::Value::from_serialize(&self.pod),
+            secrets => self.secrets.clone(),
+        );
+    }
+
+    pub fn render(&self, name: &str, input: &str) -> Result<String, String> {
+        let tmpl = self
+            .env
+            .template_from_named_str(name, input)
+            .map_err(|e| format!("parsing template: {}", e))?;
+        tmpl.render(&self.ctx)
+            .map_err(|e| format!("executing template: {}", e))
+    }
+}
+
+/// Load YAML/JSON values files in order and deep-merge them: mappings are
+/// merged key by key, any other value in a later file replaces the earlier one.
+pub fn load_values(paths: &[String]) -> Result<serde_yaml::Value, String> {
+    load_merged(paths, &ListMerge::Replace, "values file")
+}
+
+/// Decode base64 text into raw bytes for binary outputs (keystores, DER
+/// certificates). Whitespace, including the line breaks of wrapped base64, is
+/// ignored.
+pub fn decode_base64(input: &str) -> Result<Vec<u8>, String> {
+    use base64::prelude::*;
+    let compact: String = input.split_ascii_whitespace().collect();
+    BASE64_STANDARD
+        .decode(compact)
+        .map_err(|e| format!("invalid base64: {}", e))
+}
+
+/// Read every file directly inside `dir` into a map keyed by file name, for
+/// exposing mounted Secret volumes as `secrets.<name>`. Kubernetes `..data`
+/// internals and subdirectories are skipped; files must be UTF-8 and at most
+/// [`crate::template_funcs::MAX_FILE_SIZE`] bytes.
+pub fn load_secrets_dir(dir: &str) -> Result<BTreeMap<String, String>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("reading secrets directory {}: {}", dir, e))?;
+    let mut secrets = BTreeMap::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("reading secrets directory {}: {}", dir, e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if name.starts_with("..") || !path.is_file() {
+            continue;
+        }
+        let meta =
+            std::fs::metadata(&path).map_err(|e| format!("reading secret {:?}: {}", path, e))?;
+        if meta.len() > crate::template_funcs::MAX_FILE_SIZE {
+            return Err(format!(
+                "secret {:?} is {} bytes, larger than the {} byte limit",
+                path,
+                meta.len(),
+                crate::template_funcs::MAX_FILE_SIZE
+            ));
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("reading secret {:?}: {}", path, e))?;
+        secrets.insert(name, content);
+    }
+    Ok(secrets)
+}
+
+/// How lists are combined when deep-merging documents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListMerge {
+    /// The later list replaces the earlier one.
+    Replace,
+    /// The later list's items are appended to the earlier one.
+    Append,
+    /// Mapping items sharing the same value for the field are deep-merged;
+    /// other items are appended.
+    ByKey(String),
+}
+
+impl ListMerge {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "replace" => Ok(Self::Replace),
+            "append" => Ok(Self::Append),
+            _ => match s.strip_prefix("key:") {
+                Some(field) if !field.is_empty() => Ok(Self::ByKey(field.to_string())),
+                _ => Err(format!(
+                    "list merge strategy must be replace, append or key:<field>, got {:?}",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Load YAML/JSON mapping documents in order and deep-merge them, combining
+/// lists according to `lists`. `kind` names the files in error messages.
+pub fn load_merged(
+    paths: &[String],
+    lists: &ListMerge,
+    kind: &str,
+) -> Result<serde_yaml::Value, String> {
+    let mut merged = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+    for path in paths {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("reading {} {}: {}", kind, path, e))?;
+        let value: serde_yaml::Value = serde_yaml::from_str(&content)
+            .map_err(|e| format!("parsing {} {}: {}", kind, path, e))?;
+        match value {
+            serde_yaml::Value::Null => {}
+            serde_yaml::Value::Mapping(_) => merge_values(&mut merged, value, lists),
+            _ => return Err(format!("{} {} must contain a mapping", kind, path)),
+        }
+    }
+    Ok(merged)
+}
+
+fn merge_values(base: &mut serde_yaml::Value, overlay: serde_yaml::Value, lists: &ListMerge) {
+    use serde_yaml::Value;
+    match (base, overlay) {
+        (Value::Mapping(base_map), Value::Mapping(overlay_map)) => {
+            for (k, v) in overlay_map {
+                match base_map.get_mut(&k) {
+                    Some(existing) => merge_values(existing, v, lists),
+                    None => {
+                        base_map.insert(k, v);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base_seq), Value::Sequence(overlay_seq))
+            if *lists != ListMerge::Replace =>
+        {
+            for item in overlay_seq {
+                let existing = match lists {
+                    ListMerge::ByKey(field) => item.get(field.as_str()).and_then(|key| {
+                        base_seq
+                            .iter_mut()
+                            .find(|b| b.get(field.as_str()) == Some(key))
+                    }),
+                    _ => None,
+                };
+                match existing {
+                    Some(existing) => merge_values(existing, item, lists),
+                    None => base_seq.push(item),
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn template_render(input: &str) -> Result<String, String> {
+        TemplateRenderer::new().render("t", input)
+    }
+
+    #[test]
+    fn test_load_values_deep_merge() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = dir.path().join("base.yaml");
+        let prod = dir.path().join("prod.yaml");
+        std::fs::write(
+            &base,
+            "db:\n  host: localhost\n  port: 5432\nreplicas: 1\ntags: [a, b]\n",
+        )
+        .unwrap();
+        std::fs::write(&prod, "db:\n  host: db.prod\ntags: [c]\n").unwrap();
+        let values = load_values(&[
+            base.to_str().unwrap().to_string(),
+            prod.to_str().unwrap().to_string(),
+        ])
+        .unwrap();
+        let out = TemplateRenderer::new()
+            .with_values(values)
+            .render(
+                "t",
+                "{{ values.db.host }}:{{ values.db.port }} x{{ values.replicas }} {{ values.tags | join(',') }}",
+            )
+            .unwrap();
+        assert_eq!(out, "db.prod:5432 x1 c");
+    }
+
+    #[test]
+    fn test_template_dir_include_and_import() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("header.j2"), "# managed by initium").unwrap();
+        std::fs::create_dir(dir.path().join("macros")).unwrap();
+        std::fs::write(
+            dir.path().join("macros/kv.j2"),
+            "{% macro kv(k, v) %}{{ k }} = {{ v }}{% endmacro %}",
+        )
+        .unwrap();
+        let out = TemplateRenderer::new()
+            .with_template_dir(dir.path().to_str().unwrap())
+            .render(
+                "app.conf",
+                "{% include 'header.j2' %}\n{% from 'macros/kv.j2' import kv %}{{ kv('port', 8080) }}",
+            )
+            .unwrap();
+        assert_eq!(out, "# managed by initium\nport = 8080");
+    }
+
+    #[test]
+    fn test_template_dir_rejects_traversal() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let sub = dir.path().join("partials");
+        std::fs::create_dir(&sub).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "leaked").unwrap();
+        let result = TemplateRenderer::new()
+            .with_template_dir(sub.to_str().unwrap())
+            .render("t", "{% include '../secret.txt' %}");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_include_without_template_dir_fails() {
+        let result = template_render("{% include 'header.j2' %}");
+        assert!(result.unwrap_err().contains("executing template"));
+    }
+
+    #[test]
+    fn test_strict_rejects_undefined() {
+        let err = TemplateRenderer::new()
+            .with_strict(true)
+            .render("app.conf", "{{ env.NONEXISTENT_STRICT_VAR_XYZ }}")
+            .unwrap_err();
+        assert!(err.contains("undefined"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_strict_allows_defined_and_is_defined_checks() {
+        let _guard = EnvGuard::set("TEST_STRICT_VAR", "ok");
+        let out = TemplateRenderer::new()
+            .with_strict(true)
+            .render(
+                "t",
+                "{{ env.TEST_STRICT_VAR }}{% if env.NONEXISTENT_STRICT_VAR_XYZ is defined %}!{% endif %}",
+            )
+            .unwrap();
+        assert_eq!(out, "ok");
+    }
+
+    #[test]
+    fn test_load_merged_list_strategies() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = dir.path().join("base.yaml");
+        let overlay = dir.path().join("overlay.yaml");
+        std::fs::write(
+            &base,
+            "args: [a]\ncontainers:\n  - name: app\n    image: app:1\n    ports: [80]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &overlay,
+            "args: [b]\ncontainers:\n  - name: app\n    image: app:2\n  - name: proxy\n    image: envoy\n",
+        )
+        .unwrap();
+        let paths = [
+            base.to_str().unwrap().to_string(),
+            overlay.to_str().unwrap().to_string(),
+        ];
+        let load = |lists: &str| {
+            let v = load_merged(&paths, &ListMerge::parse(lists).unwrap(), "merge file").unwrap();
+            serde_yaml::to_string(&v).unwrap()
+        };
+
+        let replaced = load("replace");
+        assert!(replaced.contains("args:\n- b\n"), "{}", replaced);
+        assert!(!replaced.contains("ports"), "{}", replaced);
+
+        let appended = load("append");
+        assert!(appended.contains("args:\n- a\n- b\n"), "{}", appended);
+        assert_eq!(appended.matches("name: app").count(), 2, "{}", appended);
+
+        let keyed = load("key:name");
+        assert_eq!(keyed.matches("name: app").count(), 1, "{}", keyed);
+        assert!(keyed.contains("image: app:2"), "{}", keyed);
+        assert!(keyed.contains("ports:\n  - 80"), "{}", keyed);
+        assert!(keyed.contains("name: proxy"), "{}", keyed);
+    }
+
+    #[test]
+    fn test_list_merge_parse() {
+        assert_eq!(ListMerge::parse("append").unwrap(), ListMerge::Append);
+        assert_eq!(
+            ListMerge::parse("key:name").unwrap(),
+            ListMerge::ByKey("name".into())
+        );
+        assert!(ListMerge::parse("key:").is_err());
+        assert!(ListMerge::parse("union").is_err());
+    }
+
+    #[test]
+    fn test_secrets_dir_context() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("password"), "s3cret").unwrap();
+        std::fs::write(dir.path().join("tls.crt"), "CERT").unwrap();
+        std::fs::create_dir(dir.path().join("..data")).unwrap();
+        std::fs::write(dir.path().join("..data/password"), "dup").unwrap();
+        let secrets = load_secrets_dir(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(secrets.len(), 2);
+        let out = TemplateRenderer::new()
+            .with_secrets(secrets)
+            .render("t", r#"{{ secrets.password }}/{{ secrets["tls.crt"] }}"#)
+            .unwrap();
+        assert_eq!(out, "s3cret/CERT");
+    }
+
+    #[test]
+    fn test_secrets_dir_missing() {
+        let err = load_secrets_dir("/nonexistent/secrets").unwrap_err();
+        assert!(err.contains("reading secrets directory"), "{}", err);
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("AAEC\n/w==\n").unwrap(), vec![0u8, 1, 2, 255]);
+        assert!(decode_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_load_values_rejects_non_mapping() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("list.yaml");
+        std::fs::write(&path, "- a\n- b\n").unwrap();
+        let err = load_values(&[path.to_str().unwrap().to_string()]).unwrap_err();
+        assert!(err.contains("must contain a mapping"));
+    }
+
+    #[test]
+    fn test_load_values_missing_file() {
+        let err = load_values(&["/nonexistent/values.yaml".to_string()]).unwrap_err();
+        assert!(err.contains("reading values file"));
+    }
+
+    #[test]
+    fn test_values_undefined_without_files() {
+        assert_eq!(
+            TemplateRenderer::new()
+                .render("t", "[{{ values.missing }}]")
+                .unwrap(),
+            "[]"
+        );
+    }
+
     use std::ffi::OsString;
 
     struct EnvGuard {
@@ -113,40 +616,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pod_context() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("labels"), "app=\"web\"\n").unwrap();
+        let _d = EnvGuard::set("INITIUM_PODINFO_DIR", dir.path().to_str().unwrap());
+        let _n = EnvGuard::set("POD_NAMESPACE", "staging");
+        let out = template_render("{{ pod.namespace }}/{{ pod.labels.app }}").unwrap();
+        assert_eq!(out, "staging/web");
+    }
+
     #[test]
     fn test_envsubst_basic() {
         let _g = EnvGuard::set("TEST_RENDER_VAR", "hello");
-        assert_eq!(envsubst("say ${TEST_RENDER_VAR}"), "say hello");
-        assert_eq!(envsubst("say $TEST_RENDER_VAR"), "say hello");
+        assert_eq!(envsubst("say ${TEST_RENDER_VAR}").unwrap(), "say hello");
+        assert_eq!(envsubst("say $TEST_RENDER_VAR").unwrap(), "say hello");
     }
     #[test]
     fn test_envsubst_missing() {
         let _g = EnvGuard::remove("MISSING_VAR_XYZ");
-        assert_eq!(envsubst("${MISSING_VAR_XYZ}"), "${MISSING_VAR_XYZ}");
+        assert_eq!(
+            envsubst("${MISSING_VAR_XYZ}").unwrap(),
+            "${MISSING_VAR_XYZ}"
+        );
     }
     #[test]
     fn test_envsubst_empty() {
-        assert_eq!(envsubst(""), "");
+        assert_eq!(envsubst("").unwrap(), "");
     }
     #[test]
     fn test_envsubst_no_vars() {
-        assert_eq!(envsubst("no vars here"), "no vars here");
+        assert_eq!(envsubst("no vars here").unwrap(), "no vars here");
     }
     #[test]
     fn test_envsubst_empty_value() {
         let _g = EnvGuard::set("TEST_EMPTY_VAR", "");
-        assert_eq!(envsubst("${TEST_EMPTY_VAR}"), "");
+        assert_eq!(envsubst("${TEST_EMPTY_VAR}").unwrap(), "");
     }
     #[test]
     fn test_envsubst_special_chars() {
         let _g = EnvGuard::set("TEST_SPECIAL", "a=b&c");
-        assert_eq!(envsubst("${TEST_SPECIAL}"), "a=b&c");
+        assert_eq!(envsubst("${TEST_SPECIAL}").unwrap(), "a=b&c");
     }
     #[test]
     fn test_envsubst_multiline() {
         let _g = EnvGuard::set("TEST_ML", "val");
         let input = "line1 ${TEST_ML}\nline2 $TEST_ML";
-        let output = envsubst(input);
+        let output = envsubst(input).unwrap();
         assert!(output.contains("line1 val"));
         assert!(output.contains("line2 val"));
     }
@@ -154,7 +670,75 @@ mod tests {
     fn test_envsubst_adjacent() {
         let _g1 = EnvGuard::set("TEST_A", "X");
         let _g2 = EnvGuard::set("TEST_B", "Y");
-        assert_eq!(envsubst("${TEST_A}${TEST_B}"), "XY");
+        assert_eq!(envsubst("${TEST_A}${TEST_B}").unwrap(), "XY");
+    }
+    #[test]
+    fn test_envsubst_default_forms() {
+        let _g1 = EnvGuard::remove("TEST_DEF_UNSET");
+        let _g2 = EnvGuard::set("TEST_DEF_EMPTY", "");
+        let _g3 = EnvGuard::set("TEST_DEF_SET", "real");
+        assert_eq!(envsubst("${TEST_DEF_UNSET:-fallback}").unwrap(), "fallback");
+        assert_eq!(envsubst("${TEST_DEF_EMPTY:-fallback}").unwrap(), "fallback");
+        assert_eq!(envsubst("${TEST_DEF_EMPTY-fallback}").unwrap(), "");
+        assert_eq!(envsubst("${TEST_DEF_UNSET-fallback}").unwrap(), "fallback");
+        assert_eq!(envsubst("${TEST_DEF_SET:-fallback}").unwrap(), "real");
+        assert_eq!(envsubst("${TEST_DEF_UNSET:-}").unwrap(), "");
+        assert_eq!(
+            envsubst("url=${TEST_DEF_UNSET:-http://localhost:8080/x}").unwrap(),
+            "url=http://localhost:8080/x"
+        );
+    }
+    #[test]
+    fn test_envsubst_nested_default() {
+        let _g1 = EnvGuard::remove("TEST_NEST_A");
+        let _g2 = EnvGuard::set("TEST_NEST_B", "inner");
+        assert_eq!(envsubst("${TEST_NEST_A:-${TEST_NEST_B}}").unwrap(), "inner");
+        assert_eq!(
+            envsubst("${TEST_NEST_A:-x${TEST_NEST_A:-y}z}").unwrap(),
+            "xyz"
+        );
+    }
+    #[test]
+    fn test_envsubst_required_forms() {
+        let _g1 = EnvGuard::remove("TEST_REQ_UNSET");
+        let _g2 = EnvGuard::set("TEST_REQ_EMPTY", "");
+        let err = envsubst("${TEST_REQ_UNSET:?database host is required}").unwrap_err();
+        assert_eq!(err, "TEST_REQ_UNSET: database host is required");
+        let err = envsubst("${TEST_REQ_EMPTY:?}").unwrap_err();
+        assert_eq!(err, "TEST_REQ_EMPTY: parameter null or not set");
+        assert_eq!(envsubst("${TEST_REQ_EMPTY?must be set}").unwrap(), "");
+        assert!(envsubst("${TEST_REQ_UNSET?must be set}").is_err());
+    }
+    #[test]
+    fn test_envsubst_strict_lists_all_missing() {
+        let _g1 = EnvGuard::remove("TEST_STRICT_A");
+        let _g2 = EnvGuard::remove("TEST_STRICT_B");
+        let _g3 = EnvGuard::set("TEST_STRICT_SET", "ok");
+        let err = envsubst_strict(
+            "${TEST_STRICT_A} $TEST_STRICT_B ${TEST_STRICT_A} ${TEST_STRICT_SET} ${TEST_STRICT_B:-d}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "unset environment variables: TEST_STRICT_A, TEST_STRICT_B"
+        );
+        assert_eq!(
+            envsubst_strict("${TEST_STRICT_SET} ${TEST_STRICT_A:-x}").unwrap(),
+            "ok x"
+        );
+    }
+    #[test]
+    fn test_envsubst_unterminated_left_as_is() {
+        let _g = EnvGuard::remove("TEST_UNTERM");
+        assert_eq!(
+            envsubst("${TEST_UNTERM:-oops").unwrap(),
+            "${TEST_UNTERM:-oops"
+        );
+    }
+    #[test]
+    fn test_envsubst_preserves_utf8() {
+        let _g = EnvGuard::set("TEST_UTF8", "wörld");
+        assert_eq!(envsubst("héllo ${TEST_UTF8} ✓").unwrap(), "héllo wörld ✓");
     }
     #[test]
     fn test_template_basic() {
@@ -183,6 +767,30 @@ mod tests {
         assert!(result.is_err());
     }
     #[test]
+    fn test_template_renderer_shared_across_templates() {
+        let _g = EnvGuard::set("TEST_SHARED_RENDERER", "shared");
+        let renderer = TemplateRenderer::new();
+        assert_eq!(
+            renderer
+                .render("a", "a={{ env.TEST_SHARED_RENDERER }}")
+                .unwrap(),
+            "a=shared"
+        );
+        assert_eq!(
+            renderer
+                .render("b", "b={{ env.TEST_SHARED_RENDERER }}")
+                .unwrap(),
+            "b=shared"
+        );
+    }
+    #[test]
+    fn test_template_renderer_error_names_template() {
+        let err = TemplateRenderer::new()
+            .render("broken.tmpl", "{{ oops %}")
+            .unwrap_err();
+        assert!(err.contains("broken.tmpl"), "{}", err);
+    }
+    #[test]
     fn test_template_conditional() {
         let _g = EnvGuard::set("TEST_COND", "yes");
         let result = template_render("{% if env.TEST_COND %}ok{% endif %}").unwrap();
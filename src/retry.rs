@@ -1,5 +1,6 @@
 use std::time::{Duration, Instant};
 
+#[derive(Clone)]
 pub struct Config {
     pub max_attempts: u32,
     pub initial_delay: Duration,
@@ -1,3 +1,5 @@
+use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 pub fn validate_file_path(workdir: &str, target: &str) -> Result<PathBuf, String> {
     if workdir.is_empty() {
@@ -23,6 +25,93 @@ pub fn validate_file_path(workdir: &str, target: &str) -> Result<PathBuf, String
     }
     Ok(cleaned)
 }
+/// A file written under a temporary name in the target's directory and
+/// renamed into place by [`AtomicFile::commit`], so readers never observe a
+/// partially written file. Dropping it without committing removes the
+/// temporary file.
+pub struct AtomicFile {
+    file: Option<fs::File>,
+    tmp_path: PathBuf,
+    target: PathBuf,
+}
+
+impl AtomicFile {
+    /// Create the temporary file, creating parent directories as needed.
+    pub fn create(target: &Path) -> Result<Self, String> {
+        let parent = match target.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(parent).map_err(|e| format!("creating output directory: {}", e))?;
+        let name = target
+            .file_name()
+            .ok_or_else(|| format!("invalid output path {:?}", target))?
+            .to_string_lossy();
+        // Keep the original name (and extension) as the suffix so tools that
+        // sniff the file type still recognise the temporary file.
+        let tmp_path = parent.join(format!(
+            ".initium-{}-{:08x}.{}",
+            std::process::id(),
+            rand::random::<u32>(),
+            name
+        ));
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+            .map_err(|e| format!("creating temporary file {:?}: {}", tmp_path, e))?;
+        // Keep the permissions of a file being replaced.
+        if let Ok(meta) = fs::metadata(target) {
+            let _ = fs::set_permissions(&tmp_path, meta.permissions());
+        }
+        Ok(Self {
+            file: Some(file),
+            tmp_path,
+            target: target.to_path_buf(),
+        })
+    }
+
+    /// Path of the temporary file holding the content written so far.
+    pub fn path(&self) -> &Path {
+        &self.tmp_path
+    }
+
+    /// Flush to disk and atomically rename over the target.
+    pub fn commit(mut self) -> Result<(), String> {
+        let file = self.file.take().expect("AtomicFile committed twice");
+        file.sync_all()
+            .map_err(|e| format!("syncing output {:?}: {}", self.target, e))?;
+        drop(file);
+        fs::rename(&self.tmp_path, &self.target)
+            .map_err(|e| format!("writing output {:?}: {}", self.target, e))
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file.as_mut().expect("write after commit").write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.as_mut().expect("flush after commit").flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+/// Write `data` to `target` atomically (temp file + rename).
+pub fn write_file_atomic(target: &Path, data: &[u8]) -> Result<(), String> {
+    let mut file = AtomicFile::create(target)?;
+    file.write_all(data)
+        .map_err(|e| format!("writing output {:?}: {}", target, e))?;
+    file.commit()
+}
+
 fn normalize_path(path: &Path) -> PathBuf {
     let mut components = Vec::new();
     for component in path.components() {
@@ -76,4 +165,42 @@ mod tests {
         let result = validate_file_path(dir.path().to_str().unwrap(), ".");
         assert!(result.is_ok());
     }
+    #[test]
+    fn test_write_file_atomic_creates_and_replaces() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("nested/app.conf");
+        write_file_atomic(&target, b"v1").unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "v1");
+        write_file_atomic(&target, b"v2").unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "v2");
+        let leftovers: Vec<_> = fs::read_dir(dir.path().join("nested"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers.len(), 1, "{:?}", leftovers);
+    }
+    #[test]
+    fn test_atomic_file_dropped_without_commit_leaves_target_untouched() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("app.conf");
+        fs::write(&target, "original").unwrap();
+        {
+            let mut f = AtomicFile::create(&target).unwrap();
+            f.write_all(b"partial").unwrap();
+        }
+        assert_eq!(fs::read_to_string(&target).unwrap(), "original");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_write_file_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("secret.conf");
+        fs::write(&target, "old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        write_file_atomic(&target, b"new").unwrap();
+        let mode = fs::metadata(&target).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+    }
 }
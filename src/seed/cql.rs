@@ -0,0 +1,523 @@
+//! Minimal client for the Cassandra native protocol (v4), covering what the
+//! `cassandra` seed driver needs: password authentication, unprepared queries
+//! and decoding of scalar result columns into strings.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const VERSION_REQUEST: u8 = 0x04;
+const HEADER_LEN: usize = 9;
+const MAX_FRAME_LEN: usize = 256 * 1024 * 1024;
+
+const OP_ERROR: u8 = 0x00;
+const OP_STARTUP: u8 = 0x01;
+const OP_READY: u8 = 0x02;
+const OP_AUTHENTICATE: u8 = 0x03;
+const OP_QUERY: u8 = 0x07;
+const OP_RESULT: u8 = 0x08;
+const OP_AUTH_RESPONSE: u8 = 0x0F;
+const OP_AUTH_SUCCESS: u8 = 0x10;
+
+const FLAG_TRACING: u8 = 0x02;
+const FLAG_CUSTOM_PAYLOAD: u8 = 0x04;
+const FLAG_WARNING: u8 = 0x08;
+
+const RESULT_ROWS: i32 = 0x0002;
+
+/// Consistency levels accepted by `options.consistency`.
+pub const CONSISTENCY_LEVELS: [(&str, u16); 10] = [
+    ("any", 0x0000),
+    ("one", 0x0001),
+    ("two", 0x0002),
+    ("three", 0x0003),
+    ("quorum", 0x0004),
+    ("all", 0x0005),
+    ("local_quorum", 0x0006),
+    ("each_quorum", 0x0007),
+    ("local_one", 0x000A),
+    ("serial", 0x0008),
+];
+
+/// Parse a consistency level name such as `local_quorum` (case-insensitive).
+pub fn parse_consistency(name: &str) -> Result<u16, String> {
+    let lower = name.to_ascii_lowercase();
+    CONSISTENCY_LEVELS
+        .iter()
+        .find(|(n, _)| *n == lower)
+        .map(|(_, code)| *code)
+        .ok_or_else(|| {
+            let names: Vec<&str> = CONSISTENCY_LEVELS.iter().map(|(n, _)| *n).collect();
+            format!(
+                "invalid consistency level '{}' (supported: {})",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+/// Rows returned by a query, with every value rendered as a string (`None` for
+/// null).
+#[derive(Debug, Default, PartialEq)]
+pub struct Rows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+pub struct Connection {
+    stream: TcpStream,
+}
+
+impl Connection {
+    /// Open a connection to `host:port` and authenticate with
+    /// `PasswordAuthenticator` credentials when the server asks for them.
+    pub fn connect(
+        host: &str,
+        port: u16,
+        user: &str,
+        password: &str,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        let addr = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| format!("resolving {}:{}: {}", host, port, e))?
+            .next()
+            .ok_or_else(|| format!("resolving {}:{}: no addresses", host, port))?;
+        let stream = TcpStream::connect_timeout(&addr, timeout)
+            .map_err(|e| format!("connecting to {}: {}", addr, e))?;
+        stream
+            .set_read_timeout(Some(timeout))
+            .and_then(|_| stream.set_write_timeout(Some(timeout)))
+            .and_then(|_| stream.set_nodelay(true))
+            .map_err(|e| format!("configuring connection: {}", e))?;
+        let mut conn = Self { stream };
+        conn.startup(user, password)?;
+        Ok(conn)
+    }
+
+    fn startup(&mut self, user: &str, password: &str) -> Result<(), String> {
+        let mut body = Vec::new();
+        put_short(&mut body, 1);
+        put_string(&mut body, "CQL_VERSION");
+        put_string(&mut body, "3.0.0");
+        let (opcode, resp) = self.request(OP_STARTUP, &body)?;
+        match opcode {
+            OP_READY => Ok(()),
+            OP_AUTHENTICATE => {
+                if user.is_empty() {
+                    return Err("server requires authentication but no user was given".into());
+                }
+                let mut token = vec![0u8];
+                token.extend_from_slice(user.as_bytes());
+                token.push(0);
+                token.extend_from_slice(password.as_bytes());
+                let mut body = Vec::new();
+                put_bytes(&mut body, Some(&token));
+                match self.request(OP_AUTH_RESPONSE, &body)? {
+                    (OP_AUTH_SUCCESS, _) => Ok(()),
+                    (op, _) => Err(format!("unexpected authentication response 0x{:02x}", op)),
+                }
+            }
+            _ => Err(format!(
+                "unexpected startup response 0x{:02x} ({} bytes)",
+                opcode,
+                resp.len()
+            )),
+        }
+    }
+
+    /// Run an unprepared query. Returns the rows for `SELECT`s and `None` for
+    /// statements that produce no result set.
+    pub fn query(&mut self, cql: &str, consistency: u16) -> Result<Option<Rows>, String> {
+        let mut body = Vec::new();
+        put_long_string(&mut body, cql);
+        put_short(&mut body, consistency);
+        body.push(0);
+        let (opcode, resp) = self.request(OP_QUERY, &body)?;
+        if opcode != OP_RESULT {
+            return Err(format!("unexpected query response 0x{:02x}", opcode));
+        }
+        let mut r = Reader::new(&resp);
+        if r.int()? != RESULT_ROWS {
+            return Ok(None);
+        }
+        parse_rows(&mut r).map(Some)
+    }
+
+    fn request(&mut self, opcode: u8, body: &[u8]) -> Result<(u8, Vec<u8>), String> {
+        let mut frame = Vec::with_capacity(HEADER_LEN + body.len());
+        frame.extend_from_slice(&[VERSION_REQUEST, 0, 0, 0, opcode]);
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(body);
+        self.stream
+            .write_all(&frame)
+            .map_err(|e| format!("sending request: {}", e))?;
+
+        let mut header = [0u8; HEADER_LEN];
+        self.stream
+            .read_exact(&mut header)
+            .map_err(|e| format!("reading response: {}", e))?;
+        let flags = header[1];
+        let opcode = header[4];
+        let len = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(format!("response frame too large ({} bytes)", len));
+        }
+        let mut resp = vec![0u8; len];
+        self.stream
+            .read_exact(&mut resp)
+            .map_err(|e| format!("reading response: {}", e))?;
+
+        let mut r = Reader::new(&resp);
+        if flags & FLAG_TRACING != 0 {
+            r.take(16)?;
+        }
+        if flags & FLAG_WARNING != 0 {
+            for _ in 0..r.short()? {
+                r.string()?;
+            }
+        }
+        if flags & FLAG_CUSTOM_PAYLOAD != 0 {
+            for _ in 0..r.short()? {
+                r.string()?;
+                r.bytes()?;
+            }
+        }
+        let body = r.rest().to_vec();
+        if opcode == OP_ERROR {
+            let mut r = Reader::new(&body);
+            let code = r.int()?;
+            let message = r.string()?;
+            return Err(format!("server error 0x{:04x}: {}", code, message));
+        }
+        Ok((opcode, body))
+    }
+}
+
+fn parse_rows(r: &mut Reader) -> Result<Rows, String> {
+    let flags = r.int()?;
+    let column_count = r.int()?.max(0) as usize;
+    if flags & 0x0002 != 0 {
+        r.bytes()?;
+    }
+    let global_spec = flags & 0x0001 != 0;
+    if global_spec {
+        r.string()?;
+        r.string()?;
+    }
+    let mut columns = Vec::with_capacity(column_count);
+    let mut types = Vec::with_capacity(column_count);
+    for _ in 0..column_count {
+        if !global_spec {
+            r.string()?;
+            r.string()?;
+        }
+        columns.push(r.string()?);
+        types.push(r.type_id()?);
+    }
+    let row_count = r.int()?.max(0) as usize;
+    let mut rows = Vec::with_capacity(row_count);
+    for _ in 0..row_count {
+        let mut row = Vec::with_capacity(column_count);
+        for type_id in &types {
+            row.push(r.bytes()?.map(|b| decode_value(*type_id, b)));
+        }
+        rows.push(row);
+    }
+    Ok(Rows { columns, rows })
+}
+
+/// Render a serialized value as a string. Scalars use their CQL text form;
+/// collections and other composite types fall back to a `0x`-prefixed hex blob.
+fn decode_value(type_id: u16, b: &[u8]) -> String {
+    fn int<const N: usize>(b: &[u8]) -> Option<[u8; N]> {
+        b.try_into().ok()
+    }
+    let decoded = match type_id {
+        // ascii, varchar/text
+        0x0001 | 0x000D => Some(String::from_utf8_lossy(b).into_owned()),
+        // bigint, counter
+        0x0002 | 0x0005 => int::<8>(b).map(|v| i64::from_be_bytes(v).to_string()),
+        0x0004 => b.first().map(|v| (*v != 0).to_string()),
+        0x0007 => int::<8>(b).map(|v| f64::from_be_bytes(v).to_string()),
+        0x0008 => int::<4>(b).map(|v| f32::from_be_bytes(v).to_string()),
+        0x0009 => int::<4>(b).map(|v| i32::from_be_bytes(v).to_string()),
+        // timestamp: milliseconds since the epoch
+        0x000B => int::<8>(b)
+            .and_then(|v| chrono::DateTime::from_timestamp_millis(i64::from_be_bytes(v)))
+            .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        // uuid, timeuuid
+        0x000C | 0x000F if b.len() == 16 => {
+            let hex = hex_string(b);
+            Some(format!(
+                "{}-{}-{}-{}-{}",
+                &hex[0..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..32]
+            ))
+        }
+        0x0010 => match b.len() {
+            4 => Some(std::net::Ipv4Addr::from(int::<4>(b).unwrap()).to_string()),
+            16 => Some(std::net::Ipv6Addr::from(int::<16>(b).unwrap()).to_string()),
+            _ => None,
+        },
+        // date: days since the epoch, centered at 2^31
+        0x0011 => int::<4>(b)
+            .map(|v| i64::from(u32::from_be_bytes(v)) - (1i64 << 31))
+            .and_then(|days| {
+                chrono::NaiveDate::from_ymd_opt(1970, 1, 1)?
+                    .checked_add_signed(chrono::TimeDelta::try_days(days)?)
+            })
+            .map(|d| d.to_string()),
+        0x0013 => int::<2>(b).map(|v| i16::from_be_bytes(v).to_string()),
+        0x0014 => int::<1>(b).map(|v| i8::from_be_bytes(v).to_string()),
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| format!("0x{}", hex_string(b)))
+}
+
+fn hex_string(b: &[u8]) -> String {
+    b.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn put_short(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_string(buf: &mut Vec<u8>, s: &str) {
+    put_short(buf, s.len() as u16);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn put_long_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn put_bytes(buf: &mut Vec<u8>, b: Option<&[u8]>) {
+    match b {
+        Some(b) => {
+            buf.extend_from_slice(&(b.len() as u32).to_be_bytes());
+            buf.extend_from_slice(b);
+        }
+        None => buf.extend_from_slice(&(-1i32).to_be_bytes()),
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.buf.len() < n {
+            return Err("truncated response".into());
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    fn short(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn int(&mut self) -> Result<i32, String> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.short()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn bytes(&mut self) -> Result<Option<&'a [u8]>, String> {
+        let len = self.int()?;
+        if len < 0 {
+            return Ok(None);
+        }
+        self.take(len as usize).map(Some)
+    }
+
+    /// Read an `[option]` type spec, returning the top-level type id and
+    /// skipping any nested parameters.
+    fn type_id(&mut self) -> Result<u16, String> {
+        let id = self.short()?;
+        match id {
+            0x0000 => {
+                self.string()?;
+            }
+            // list, set
+            0x0020 | 0x0022 => {
+                self.type_id()?;
+            }
+            // map
+            0x0021 => {
+                self.type_id()?;
+                self.type_id()?;
+            }
+            // user-defined type
+            0x0030 => {
+                self.string()?;
+                self.string()?;
+                for _ in 0..self.short()? {
+                    self.string()?;
+                    self.type_id()?;
+                }
+            }
+            // tuple
+            0x0031 => {
+                for _ in 0..self.short()? {
+                    self.type_id()?;
+                }
+            }
+            _ => {}
+        }
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_consistency() {
+        assert_eq!(parse_consistency("LOCAL_QUORUM").unwrap(), 0x0006);
+        assert_eq!(parse_consistency("one").unwrap(), 0x0001);
+        assert!(parse_consistency("most").is_err());
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let mut body = Vec::new();
+        body.extend_from_slice(&1i32.to_be_bytes()); // global table spec
+        body.extend_from_slice(&3i32.to_be_bytes());
+        put_string(&mut body, "ks");
+        put_string(&mut body, "users");
+        for (name, type_id) in [("name", 0x000Du16), ("age", 0x0009), ("tags", 0x0022)] {
+            put_string(&mut body, name);
+            put_short(&mut body, type_id);
+            if type_id == 0x0022 {
+                put_short(&mut body, 0x000D);
+            }
+        }
+        body.extend_from_slice(&1i32.to_be_bytes());
+        put_bytes(&mut body, Some(b"alice"));
+        put_bytes(&mut body, Some(&42i32.to_be_bytes()));
+        put_bytes(&mut body, None);
+
+        let rows = parse_rows(&mut Reader::new(&body)).unwrap();
+        assert_eq!(rows.columns, vec!["name", "age", "tags"]);
+        assert_eq!(
+            rows.rows,
+            vec![vec![Some("alice".into()), Some("42".into()), None]]
+        );
+    }
+
+    fn read_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut header = [0u8; HEADER_LEN];
+        stream.read_exact(&mut header).unwrap();
+        let len = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) as usize;
+        let mut body = vec![0u8; len];
+        stream.read_exact(&mut body).unwrap();
+        (header[4], body)
+    }
+
+    fn write_frame(stream: &mut TcpStream, opcode: u8, body: &[u8]) {
+        let mut frame = vec![0x84, 0, 0, 0, opcode];
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(body);
+        stream.write_all(&frame).unwrap();
+    }
+
+    #[test]
+    fn test_connect_authenticate_and_query() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            assert_eq!(read_frame(&mut stream).0, OP_STARTUP);
+            let mut body = Vec::new();
+            put_string(&mut body, "org.apache.cassandra.auth.PasswordAuthenticator");
+            write_frame(&mut stream, OP_AUTHENTICATE, &body);
+
+            let (op, body) = read_frame(&mut stream);
+            assert_eq!(op, OP_AUTH_RESPONSE);
+            assert_eq!(&body[4..], b"\0cassandra\0s3cret");
+            write_frame(&mut stream, OP_AUTH_SUCCESS, &[0xff, 0xff, 0xff, 0xff]);
+
+            let (op, body) = read_frame(&mut stream);
+            assert_eq!(op, OP_QUERY);
+            let mut r = Reader::new(&body);
+            let len = r.int().unwrap() as usize;
+            assert_eq!(
+                r.take(len).unwrap(),
+                b"SELECT release_version FROM system.local"
+            );
+            assert_eq!(r.short().unwrap(), 0x0006);
+            let mut rows = RESULT_ROWS.to_be_bytes().to_vec();
+            rows.extend_from_slice(&1i32.to_be_bytes());
+            rows.extend_from_slice(&1i32.to_be_bytes());
+            put_string(&mut rows, "system");
+            put_string(&mut rows, "local");
+            put_string(&mut rows, "release_version");
+            put_short(&mut rows, 0x000D);
+            rows.extend_from_slice(&1i32.to_be_bytes());
+            put_bytes(&mut rows, Some(b"4.1.3"));
+            write_frame(&mut stream, OP_RESULT, &rows);
+
+            read_frame(&mut stream);
+            let mut err = 0x2200i32.to_be_bytes().to_vec();
+            put_string(&mut err, "unconfigured table nope");
+            write_frame(&mut stream, OP_ERROR, &err);
+        });
+
+        let mut conn = Connection::connect(
+            "127.0.0.1",
+            port,
+            "cassandra",
+            "s3cret",
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        let rows = conn
+            .query("SELECT release_version FROM system.local", 0x0006)
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows.rows, vec![vec![Some("4.1.3".to_string())]]);
+        let err = conn.query("SELECT * FROM nope", 0x0006).unwrap_err();
+        assert!(err.contains("unconfigured table nope"), "{}", err);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_decode_value_scalars() {
+        assert_eq!(decode_value(0x0004, &[1]), "true");
+        assert_eq!(decode_value(0x0002, &(-7i64).to_be_bytes()), "-7");
+        assert_eq!(
+            decode_value(0x000B, &0i64.to_be_bytes()),
+            "1970-01-01T00:00:00.000Z"
+        );
+        assert_eq!(
+            decode_value(0x0011, &((1u32 << 31) + 1).to_be_bytes()),
+            "1970-01-02"
+        );
+        assert_eq!(
+            decode_value(0x000C, &[0x12; 16]),
+            "12121212-1212-1212-1212-121212121212"
+        );
+        assert_eq!(decode_value(0x0010, &[10, 0, 0, 1]), "10.0.0.1");
+        assert_eq!(decode_value(0x0003, &[0xca, 0xfe]), "0xcafe");
+    }
+}
@@ -1,11 +1,121 @@
 use crate::duration::{format_duration, parse_duration};
+use crate::glob;
 use crate::logging::Logger;
-use crate::seed::db::Database;
-use crate::seed::hash::compute_seed_set_hash;
-use crate::seed::schema::{SeedPhase, SeedPlan, SeedSet, TableSeed, WaitForObject};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::retry;
+use crate::seed::db::{Database, SeedMetadata, SeedValue};
+use crate::seed::hash::{compute_content_hash, compute_seed_set_hash};
+use crate::seed::report::{self, RowCounts, SeedReport, TableStats};
+use crate::seed::schema::{
+    Hook, Lookup, RoutineCall, ScriptsSource, SeedPhase, SeedPlan, SeedSet, SqlStatement,
+    TableSeed, WaitForObject,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::mem;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A `sql:` or hook entry ready to run.
+enum Step<'s> {
+    Sql(String),
+    Call(&'s RoutineCall),
+}
+
+impl Step<'_> {
+    /// The statement as shown by dry runs.
+    fn text(&self) -> String {
+        match self {
+            Step::Sql(sql) => sql.trim().to_string(),
+            Step::Call(call) => call.to_string(),
+        }
+    }
+}
+
+/// Decode the base64 content of a `!binary` or `base64:` value.
+fn decode_binary(encoded: &str) -> Result<SeedValue, String> {
+    crate::render::decode_base64(encoded)
+        .map(SeedValue::Bytes)
+        .map_err(|e| format!("binary value: {}", e))
+}
+
+/// The plan without the rows whose `_when` is false, or `None` when no row
+/// has a `_when`. A `_when` holding a template is rendered first, so rows
+/// read from files can use one too.
+fn select_rows(plan: &SeedPlan) -> Result<Option<SeedPlan>, String> {
+    let conditional = plan.phases.iter().any(|p| {
+        p.seed_sets.iter().any(|ss| {
+            ss.tables
+                .iter()
+                .any(|ts| ts.rows.iter().any(|r| r.contains_key("_when")))
+        })
+    });
+    if !conditional {
+        return Ok(None);
+    }
+    let mut plan = plan.clone();
+    for phase in &mut plan.phases {
+        for ss in &mut phase.seed_sets {
+            for ts in &mut ss.tables {
+                let rows = mem::take(&mut ts.rows);
+                for (idx, mut row) in rows.into_iter().enumerate() {
+                    let Some(when) = row.remove("_when") else {
+                        ts.rows.push(row);
+                        continue;
+                    };
+                    let selected = row_condition(&when, plan.strict).map_err(|e| {
+                        format!(
+                            "seed set '{}': table '{}': row {}: _when {}",
+                            ss.name,
+                            ts.table,
+                            idx + 1,
+                            e
+                        )
+                    })?;
+                    if selected {
+                        ts.rows.push(row);
+                    }
+                }
+            }
+        }
+    }
+    Ok(Some(plan))
+}
+
+fn row_condition(when: &serde_yaml::Value, strict: bool) -> Result<bool, String> {
+    let text = match when {
+        serde_yaml::Value::Bool(b) => return Ok(*b),
+        serde_yaml::Value::String(s) if s.contains("{{") || s.contains("{%") => {
+            super::render_with(super::template_env(strict), s, super::env_context())?
+        }
+        serde_yaml::Value::String(s) => s.clone(),
+        _ => return Err("must be a boolean or a template".into()),
+    };
+    match text.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" | "" => Ok(false),
+        other => Err(format!("must be true or false, got '{}'", other)),
+    }
+}
+
+/// Opens a connection to another database of a connection, for phases with
+/// `database:`: called with the `connection` key and the database name, it
+/// returns `None` when the connection already targets that database.
+pub type Connector<'a> =
+    Box<dyn FnMut(&str, &str) -> Result<Option<Box<dyn Database>>, String> + 'a>;
+
+/// A connection is identified by its `databases:` key (empty for `database:`)
+/// and the phase `database:` it was switched to (empty when unchanged).
+type ConnectionKey = (String, String);
+
+/// A connection that is not in use by the current phase.
+struct Connection {
+    db: Box<dyn Database>,
+    tracking_table: String,
+    tracking_missing: bool,
+    row_tracking_missing: bool,
+}
+
 pub struct SeedExecutor<'a> {
     log: &'a Logger,
     db: Box<dyn Database>,
@@ -13,7 +123,39 @@ pub struct SeedExecutor<'a> {
     reset: bool,
     dry_run: bool,
     reconcile_all: bool,
-    refs: HashMap<String, HashMap<String, String>>,
+    /// `--auto-order`: order the tables of every seed set by dependencies.
+    auto_order: bool,
+    /// `--phase` and `--seed-set` globs; a `!` prefix excludes matches.
+    phase_filter: Vec<String>,
+    seed_set_filter: Vec<String>,
+    /// `--reset-set` globs: seed sets reset without `--reset`.
+    reset_sets: Vec<String>,
+    /// How long to wait for another seeder holding the seed lock.
+    lock_timeout: Duration,
+    base_dir: PathBuf,
+    refs: HashMap<String, HashMap<String, SeedValue>>,
+    /// Dry-run only: the tracking tables have not been created yet, so
+    /// nothing has been applied.
+    tracking_missing: bool,
+    row_tracking_missing: bool,
+    /// The connection in `db`.
+    connection: ConnectionKey,
+    /// The other connections, swapped into `db` by the phases using them.
+    connections: BTreeMap<ConnectionKey, Connection>,
+    connector: Option<Connector<'a>>,
+    /// Connections whose seed lock is held.
+    locked: Vec<ConnectionKey>,
+    /// Set while a phase with `transaction: per_phase` or `none` runs, so
+    /// `in_transaction` runs its work without a transaction of its own.
+    skip_transactions: bool,
+    /// Seed sets with `continue_on_error` that failed.
+    failed_seed_sets: Vec<String>,
+    /// Rows written per seed set table, for the summary and report.
+    stats: Vec<TableStats>,
+    /// How long `execute` ran.
+    duration: Duration,
+    /// Checksum of the spec file, stored with each tracking entry.
+    spec_checksum: Option<String>,
 }
 
 impl<'a> SeedExecutor<'a> {
@@ -30,10 +172,46 @@ impl<'a> SeedExecutor<'a> {
             reset,
             dry_run: false,
             reconcile_all: false,
+            auto_order: false,
+            phase_filter: Vec::new(),
+            seed_set_filter: Vec::new(),
+            reset_sets: Vec::new(),
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            base_dir: PathBuf::from("."),
             refs: HashMap::new(),
+            tracking_missing: false,
+            row_tracking_missing: false,
+            connection: ConnectionKey::default(),
+            connections: BTreeMap::new(),
+            connector: None,
+            locked: Vec::new(),
+            skip_transactions: false,
+            failed_seed_sets: Vec::new(),
+            stats: Vec::new(),
+            duration: Duration::ZERO,
+            spec_checksum: None,
         }
     }
 
+    /// Add a named connection for phases with `connection: <name>`.
+    pub fn with_connection(
+        mut self,
+        name: String,
+        db: Box<dyn Database>,
+        tracking_table: String,
+    ) -> Self {
+        self.connections.insert(
+            (name, String::new()),
+            Connection {
+                db,
+                tracking_table,
+                tracking_missing: false,
+                row_tracking_missing: false,
+            },
+        );
+        self
+    }
+
     pub fn with_dry_run(mut self, dry_run: bool) -> Self {
         self.dry_run = dry_run;
         self
@@ -44,32 +222,419 @@ impl<'a> SeedExecutor<'a> {
         self
     }
 
+    pub fn with_auto_order(mut self, auto_order: bool) -> Self {
+        self.auto_order = auto_order;
+        self
+    }
+
+    pub fn with_phase_filter(mut self, patterns: Vec<String>) -> Self {
+        self.phase_filter = patterns;
+        self
+    }
+
+    pub fn with_seed_set_filter(mut self, patterns: Vec<String>) -> Self {
+        self.seed_set_filter = patterns;
+        self
+    }
+
+    pub fn with_reset_sets(mut self, patterns: Vec<String>) -> Self {
+        self.reset_sets = patterns;
+        self
+    }
+
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Open connections for phases whose `database:` is not the one their
+    /// connection was opened on.
+    pub fn with_connector(mut self, connector: Connector<'a>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    /// Directory that relative file paths in the spec are resolved against.
+    pub fn with_base_dir(mut self, base_dir: PathBuf) -> Self {
+        self.base_dir = base_dir;
+        self
+    }
+
+    /// Checksum of the spec file, recorded in the tracking table.
+    pub fn with_spec_checksum(mut self, checksum: String) -> Self {
+        self.spec_checksum = Some(checksum);
+        self
+    }
+
     pub fn execute(&mut self, plan: &SeedPlan) -> Result<(), String> {
         self.log.info("starting seed execution", &[]);
+        let started = Instant::now();
+        let result = self.execute_plan(plan);
+        self.duration = started.elapsed();
+        self.log_summary();
+        result?;
+
+        if self.failed_seed_sets.is_empty() {
+            self.log.info("seed execution completed", &[]);
+        } else {
+            self.log.warn(
+                "seed execution completed with failed seed sets",
+                &[("failed", &self.failed_seed_sets.join(", "))],
+            );
+        }
+        Ok(())
+    }
+
+    fn execute_plan(&mut self, plan: &SeedPlan) -> Result<(), String> {
+        self.check_filters(plan)?;
+        let selected = select_rows(plan)?;
+        let plan = selected.as_ref().unwrap_or(plan);
+        if self.dry_run {
+            for key in self.connection_keys() {
+                self.switch_connection(&key)?;
+                self.prepare_dry_run()?;
+            }
+            self.execute_phases(plan)
+        } else {
+            let result = self.execute_locked(plan);
+            self.release_locks();
+            result
+        }
+    }
+
+    fn log_summary(&self) {
+        for t in &self.stats {
+            let rows = t.rows.fields();
+            let duration = format_duration(t.duration);
+            let mut fields = vec![
+                ("seed_set", t.seed_set.as_str()),
+                ("table", t.table.as_str()),
+            ];
+            fields.extend(rows.iter().map(|(k, v)| (*k, v.as_str())));
+            fields.push(("duration", &duration));
+            self.log.info("table summary", &fields);
+        }
+        let totals = report::totals(&self.stats).fields();
+        let duration = format_duration(self.duration);
+        let mut fields: Vec<(&str, &str)> = totals.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        fields.push(("duration", &duration));
+        self.log.info("seed summary", &fields);
+    }
+
+    /// The outcome of the last `execute`, given the error it returned.
+    pub fn report(&self, error: Option<&str>) -> SeedReport {
+        SeedReport {
+            success: error.is_none(),
+            error: error.map(String::from),
+            dry_run: self.dry_run,
+            duration: self.duration,
+            totals: report::totals(&self.stats),
+            tables: self.stats.clone(),
+            failed_seed_sets: self.failed_seed_sets.clone(),
+        }
+    }
+
+    /// The default connection first, then the named ones by name, so that
+    /// concurrent seeders take the seed locks in the same order.
+    fn connection_keys(&self) -> Vec<ConnectionKey> {
+        let mut keys: Vec<ConnectionKey> = self.connections.keys().cloned().collect();
+        keys.push(self.connection.clone());
+        keys.sort();
+        keys
+    }
+
+    /// Make `key` the connection used by `db` and the tracking helpers.
+    fn switch_connection(&mut self, key: &ConnectionKey) -> Result<(), String> {
+        if &self.connection == key {
+            return Ok(());
+        }
+        let next = self
+            .connections
+            .remove(key)
+            .ok_or_else(|| format!("unknown connection '{}'", key.0))?;
+        let prev = Connection {
+            db: mem::replace(&mut self.db, next.db),
+            tracking_table: mem::replace(&mut self.tracking_table, next.tracking_table),
+            tracking_missing: mem::replace(&mut self.tracking_missing, next.tracking_missing),
+            row_tracking_missing: mem::replace(
+                &mut self.row_tracking_missing,
+                next.row_tracking_missing,
+            ),
+        };
+        let prev_key = mem::replace(&mut self.connection, key.clone());
+        self.connections.insert(prev_key, prev);
+        Ok(())
+    }
+
+    /// Switch to the database named by the phase's `database:`, connecting to
+    /// it on first use. Returns false when a dry run cannot reach it, e.g.
+    /// because the phase would create it.
+    fn switch_database(&mut self, phase: &SeedPhase) -> Result<bool, String> {
+        let key = (phase.connection.clone(), phase.database.clone());
+        if self.connection == key || self.connections.contains_key(&key) {
+            self.switch_connection(&key)?;
+            return Ok(true);
+        }
+        let Some(connector) = self.connector.as_mut() else {
+            return Ok(true);
+        };
+        let db = match connector(&phase.connection, &phase.database) {
+            Ok(Some(db)) => db,
+            Ok(None) => return Ok(true),
+            Err(e) if self.dry_run => {
+                self.log.info(
+                    "dry-run: cannot connect to phase database, skipping its seed sets",
+                    &[("database", phase.database.as_str()), ("error", e.as_str())],
+                );
+                return Ok(false);
+            }
+            Err(e) => {
+                return Err(format!(
+                    "connecting to database '{}': {}",
+                    phase.database, e
+                ))
+            }
+        };
+        self.log.info(
+            "connected to phase database",
+            &[("database", phase.database.as_str())],
+        );
+        let tracking_table = self.tracking_table.clone();
+        self.connections.insert(
+            key.clone(),
+            Connection {
+                db,
+                tracking_table,
+                tracking_missing: false,
+                row_tracking_missing: false,
+            },
+        );
+        self.switch_connection(&key)?;
+        if self.dry_run {
+            self.prepare_dry_run()?;
+        } else {
+            self.prepare_connection()?;
+        }
+        Ok(true)
+    }
+
+    fn lock_name(&self) -> String {
+        format!("initium_seed:{}", self.tracking_table)
+    }
+
+    fn release_locks(&mut self) {
+        for key in mem::take(&mut self.locked) {
+            let released = self
+                .switch_connection(&key)
+                .and_then(|_| self.db.release_lock(&self.lock_name()));
+            if let Err(e) = released {
+                self.log.warn(
+                    "failed to release seed lock",
+                    &[("lock", self.lock_name().as_str()), ("error", e.as_str())],
+                );
+            }
+        }
+    }
+
+    /// Serialize seeders sharing a tracking table, e.g. replicas starting at
+    /// the same time, so they do not both apply the same seed set.
+    fn acquire_lock(&mut self, lock: &str) -> Result<bool, String> {
+        let timeout = format_duration(self.lock_timeout);
+        self.log.debug(
+            "acquiring seed lock",
+            &[("lock", lock), ("timeout", timeout.as_str())],
+        );
+        let start = Instant::now();
+        let locked = self.db.acquire_lock(lock, self.lock_timeout)?;
+        if locked {
+            let waited = format_duration(start.elapsed());
+            self.log.info(
+                "acquired seed lock",
+                &[("lock", lock), ("waited", waited.as_str())],
+            );
+        } else {
+            self.log.debug(
+                "driver has no seed lock, concurrent runs are not serialized",
+                &[("driver", self.db.driver_name())],
+            );
+        }
+        Ok(locked)
+    }
+
+    fn execute_locked(&mut self, plan: &SeedPlan) -> Result<(), String> {
+        for key in self.connection_keys() {
+            self.switch_connection(&key)?;
+            self.prepare_connection()?;
+        }
+        self.execute_phases(plan)
+    }
+
+    /// Take the seed lock of the current connection and create its tracking
+    /// tables.
+    fn prepare_connection(&mut self) -> Result<(), String> {
+        if self.acquire_lock(&self.lock_name())? {
+            self.locked.push(self.connection.clone());
+        }
         self.db.ensure_tracking_table(&self.tracking_table)?;
         self.db.migrate_tracking_table(&self.tracking_table)?;
-        self.db.ensure_row_tracking_table(&self.tracking_table)?;
+        self.db.ensure_row_tracking_table(&self.tracking_table)
+    }
+
+    /// Switch the session to read-only where the driver supports it and look
+    /// up the tracking tables instead of creating them.
+    fn prepare_dry_run(&mut self) -> Result<(), String> {
+        self.db.set_read_only()?;
+        let tt = self.tracking_table.clone();
+        // Drivers that cannot answer are assumed to have the tables.
+        let exists =
+            |exec: &mut Self, table: &str| exec.db.object_exists("table", table).unwrap_or(true);
+        self.tracking_missing = !exists(self, &tt);
+        self.row_tracking_missing = !exists(self, &format!("{}_rows", tt));
+        if self.tracking_missing {
+            self.log.info(
+                "dry-run: tracking table does not exist, every seed set is new",
+                &[("tracking_table", tt.as_str())],
+            );
+        }
+        Ok(())
+    }
+
+    fn is_applied(&mut self, key: &str) -> Result<bool, String> {
+        if self.tracking_missing {
+            return Ok(false);
+        }
+        self.db.is_seed_applied(&self.tracking_table, key)
+    }
 
-        self.execute_phases(plan)?;
+    fn stored_hash(&mut self, key: &str) -> Result<Option<String>, String> {
+        if self.tracking_missing {
+            return Ok(None);
+        }
+        self.db.get_seed_hash(&self.tracking_table, key)
+    }
+
+    fn tracked_rows(
+        &mut self,
+        seed_set: &str,
+        table: &str,
+    ) -> Result<Vec<(String, String)>, String> {
+        if self.row_tracking_missing {
+            return Ok(Vec::new());
+        }
+        self.db
+            .get_tracked_rows(&self.tracking_table, seed_set, table)
+    }
 
-        self.log.info("seed execution completed", &[]);
+    /// Reject `--phase`, `--seed-set` and `--reset-set` patterns that select
+    /// nothing, which are almost always typos.
+    fn check_filters(&self, plan: &SeedPlan) -> Result<(), String> {
+        let includes = |patterns: &[String]| -> Vec<String> {
+            patterns
+                .iter()
+                .filter(|p| !p.starts_with('!'))
+                .cloned()
+                .collect()
+        };
+        for pattern in includes(&self.phase_filter) {
+            if !plan.phases.iter().any(|p| glob::matches(&pattern, &p.name)) {
+                return Err(format!(
+                    "--phase '{}' matches no phase in the spec",
+                    pattern
+                ));
+            }
+        }
+        let seed_set_patterns = includes(&self.seed_set_filter)
+            .into_iter()
+            .map(|p| ("--seed-set", p))
+            .chain(self.reset_sets.iter().map(|p| ("--reset-set", p.clone())));
+        for (flag, pattern) in seed_set_patterns {
+            let found = plan
+                .phases
+                .iter()
+                .flat_map(|p| &p.seed_sets)
+                .any(|ss| glob::matches(&pattern, &ss.name));
+            if !found {
+                return Err(format!(
+                    "{} '{}' matches no seed set in the spec",
+                    flag, pattern
+                ));
+            }
+        }
         Ok(())
     }
 
+    /// With `--seed-set`, only phases holding a selected seed set run.
+    fn phase_selected(&self, phase: &SeedPhase) -> bool {
+        filter_selects(&self.phase_filter, &phase.name)
+            && (self.seed_set_filter.is_empty()
+                || phase
+                    .seed_sets
+                    .iter()
+                    .any(|ss| filter_selects(&self.seed_set_filter, &ss.name)))
+    }
+
+    /// A seed set left out by the filters still provides its `_ref` rows to
+    /// the seed sets that run, read from the database when it was applied
+    /// earlier.
+    fn skip_seed_set(&mut self, ss: &SeedSet) {
+        self.log.info(
+            "skipping seed set (not selected)",
+            &[("seed_set", ss.name.as_str())],
+        );
+        if let Err(e) = self.populate_refs_from_db(ss) {
+            self.log.debug(
+                "could not load references of skipped seed set",
+                &[("seed_set", ss.name.as_str()), ("error", &e)],
+            );
+        }
+    }
+
     fn execute_phases(&mut self, plan: &SeedPlan) -> Result<(), String> {
         let mut phases: Vec<&SeedPhase> = plan.phases.iter().collect();
         phases.sort_by_key(|p| p.order);
         for phase in &phases {
+            self.switch_connection(&(phase.connection.clone(), String::new()))?;
+            if !self.phase_selected(phase) {
+                self.log.info(
+                    "skipping phase (not selected)",
+                    &[("phase", phase.name.as_str())],
+                );
+                let mut seed_sets: Vec<&SeedSet> = phase.seed_sets.iter().collect();
+                seed_sets.sort_by_key(|s| s.order);
+                for ss in seed_sets {
+                    self.skip_seed_set(ss);
+                }
+                continue;
+            }
             self.execute_phase(phase)?;
         }
         Ok(())
     }
 
     fn execute_phase(&mut self, phase: &SeedPhase) -> Result<(), String> {
-        self.log
-            .info("executing phase", &[("phase", phase.name.as_str())]);
+        if phase.connection.is_empty() {
+            self.log
+                .info("executing phase", &[("phase", phase.name.as_str())]);
+        } else {
+            self.log.info(
+                "executing phase",
+                &[
+                    ("phase", phase.name.as_str()),
+                    ("connection", phase.connection.as_str()),
+                ],
+            );
+        }
 
-        if phase.create_if_missing {
+        if phase.create_if_missing && self.dry_run {
+            for (kind, name) in [("database", &phase.database), ("schema", &phase.schema)] {
+                if !name.is_empty() {
+                    self.log.info(
+                        &format!("dry-run: would create {} if missing", kind),
+                        &[(kind, name.as_str())],
+                    );
+                }
+            }
+        } else if phase.create_if_missing {
             if !phase.database.is_empty() {
                 self.log.info(
                     "creating database if missing",
@@ -86,28 +651,92 @@ impl<'a> SeedExecutor<'a> {
             }
         }
 
+        if !phase.database.is_empty() && !self.switch_database(phase)? {
+            return Ok(());
+        }
+        if !self.db.set_schema(&phase.schema)? && !phase.schema.is_empty() {
+            self.log.warn(
+                "driver cannot switch the current schema, qualify table names as schema.table",
+                &[
+                    ("phase", phase.name.as_str()),
+                    ("driver", self.db.driver_name()),
+                ],
+            );
+        }
+
         let phase_timeout =
             parse_duration(&phase.timeout).map_err(|e| format!("invalid phase timeout: {}", e))?;
         for wf in &phase.wait_for {
             self.wait_for_object(wf, &phase_timeout)?;
         }
 
+        match phase.transaction.as_str() {
+            "per_phase" if !self.dry_run => self.in_transaction(&phase.name, |exec| {
+                exec.skip_transactions = true;
+                let result = exec.apply_phase(phase);
+                exec.skip_transactions = false;
+                result
+            })?,
+            "none" => {
+                self.skip_transactions = true;
+                let result = self.apply_phase(phase);
+                self.skip_transactions = false;
+                result?
+            }
+            _ => self.apply_phase(phase)?,
+        }
+
+        self.log
+            .info("phase completed", &[("phase", phase.name.as_str())]);
+        Ok(())
+    }
+
+    /// Everything in a phase after `wait_for`: hooks, `sql:`, resets and the
+    /// seed sets.
+    fn apply_phase(&mut self, phase: &SeedPhase) -> Result<(), String> {
+        self.run_hooks(&phase.before, "before", "phase", &phase.name)?;
+        if !phase.sql.is_empty() {
+            self.execute_phase_sql(phase)?;
+        }
+
         let mut seed_sets: Vec<&SeedSet> = phase.seed_sets.iter().collect();
         seed_sets.sort_by_key(|s| s.order);
 
-        if self.reset {
-            for ss in seed_sets.iter().rev() {
+        for ss in seed_sets.iter().rev() {
+            if !filter_selects(&self.seed_set_filter, &ss.name) || !self.resets(ss) {
+                continue;
+            }
+            if self.dry_run {
+                self.log.info(
+                    "dry-run: would clear seed set data (reset)",
+                    &[("seed_set", ss.name.as_str())],
+                );
+            } else {
                 self.reset_seed_set(ss)?;
             }
         }
 
         for ss in &seed_sets {
-            self.execute_seed_set(ss)?;
+            if !filter_selects(&self.seed_set_filter, &ss.name) {
+                self.skip_seed_set(ss);
+                continue;
+            }
+            let result = self
+                .run_hooks(&ss.before, "before", "seed_set", &ss.name)
+                .and_then(|()| self.execute_seed_set(ss))
+                .and_then(|()| self.run_hooks(&ss.after, "after", "seed_set", &ss.name));
+            match result {
+                Err(e) if ss.continue_on_error => {
+                    self.log.error(
+                        "seed set failed, continuing (continue_on_error)",
+                        &[("seed_set", ss.name.as_str()), ("error", &e)],
+                    );
+                    self.failed_seed_sets.push(ss.name.clone());
+                }
+                result => result?,
+            }
         }
-
-        self.log
-            .info("phase completed", &[("phase", phase.name.as_str())]);
-        Ok(())
+        self.run_hooks(&phase.after, "after", "phase", &phase.name)
     }
 
     fn wait_for_object(
@@ -120,6 +749,23 @@ impl<'a> SeedExecutor<'a> {
             None => *phase_timeout,
         };
         let timeout_str = format_duration(timeout_dur);
+        let target = wf.target();
+
+        if self.dry_run {
+            // Report the current state once instead of waiting.
+            let exists = self.object_ready(wf)?;
+            self.log.info(
+                "dry-run: would wait for object",
+                &[
+                    ("type", wf.obj_type.as_str()),
+                    ("name", target.as_str()),
+                    ("timeout", &timeout_str),
+                    ("exists", if exists { "true" } else { "false" }),
+                ],
+            );
+            return Ok(());
+        }
+
         let deadline = Instant::now() + timeout_dur;
         let poll_interval = Duration::from_millis(500);
 
@@ -127,17 +773,17 @@ impl<'a> SeedExecutor<'a> {
             "waiting for object",
             &[
                 ("type", wf.obj_type.as_str()),
-                ("name", wf.name.as_str()),
+                ("name", target.as_str()),
                 ("timeout", &timeout_str),
             ],
         );
 
         loop {
-            match self.db.object_exists(&wf.obj_type, &wf.name) {
+            match self.object_ready(wf) {
                 Ok(true) => {
                     self.log.info(
                         "object found",
-                        &[("type", wf.obj_type.as_str()), ("name", wf.name.as_str())],
+                        &[("type", wf.obj_type.as_str()), ("name", target.as_str())],
                     );
                     return Ok(());
                 }
@@ -146,7 +792,7 @@ impl<'a> SeedExecutor<'a> {
                     return Err(format!(
                         "error checking {} '{}' on {} driver: {}",
                         wf.obj_type,
-                        wf.name,
+                        target,
                         self.db.driver_name(),
                         e
                     ));
@@ -156,7 +802,7 @@ impl<'a> SeedExecutor<'a> {
             if Instant::now() >= deadline {
                 return Err(format!(
                     "timeout after {} waiting for {} '{}'",
-                    timeout_str, wf.obj_type, wf.name
+                    timeout_str, wf.obj_type, target
                 ));
             }
 
@@ -164,93 +810,549 @@ impl<'a> SeedExecutor<'a> {
         }
     }
 
-    fn reset_seed_set(&mut self, ss: &SeedSet) -> Result<(), String> {
-        let name = &ss.name;
-        let tt = self.tracking_table.clone();
-        self.log
-            .info("reset mode: clearing seed set data", &[("seed_set", name)]);
-        let mut tables: Vec<&TableSeed> = ss.tables.iter().collect();
-        tables.sort_by_key(|t| std::cmp::Reverse(t.order));
-        for ts in &tables {
-            let count = self.db.delete_rows(&ts.table)?;
-            self.log.info(
-                "deleted rows",
-                &[("table", &ts.table), ("count", &count.to_string())],
-            );
+    /// Whether a `wait_for` object exists. A row of a table that does not
+    /// exist yet is not ready rather than an error, so waiting may start
+    /// before the migration creates the table.
+    fn object_ready(&mut self, wf: &WaitForObject) -> Result<bool, String> {
+        if wf.is_column() {
+            return self.db.column_exists(&wf.table, &wf.column);
+        }
+        if !wf.is_row() {
+            return self.db.object_exists(&wf.obj_type, &wf.name);
+        }
+        let mut columns = Vec::new();
+        let mut values = Vec::new();
+        for (column, value) in &wf.conditions {
+            columns.push(column.clone());
+            values.push(self.resolve_value(value)?);
+        }
+        match self.db.row_exists(&wf.table, &columns, &values) {
+            Err(_) if !self.db.object_exists("table", &wf.table).unwrap_or(true) => Ok(false),
+            result => result,
         }
-        self.db.delete_all_tracked_rows(&tt, name)?;
-        self.db.remove_seed_mark(&self.tracking_table, name)?;
-        Ok(())
     }
 
-    fn execute_seed_set(&mut self, ss: &SeedSet) -> Result<(), String> {
-        let name = &ss.name;
-        let is_reconcile = ss.is_reconcile() || self.reconcile_all;
-        self.log.info(
-            "processing seed set",
-            &[
-                ("seed_set", name),
-                ("mode", if is_reconcile { "reconcile" } else { "once" }),
-            ],
-        );
-
-        if is_reconcile {
-            // Guard: reconcile requires unique_key on every table.
-            // Schema validation catches this for mode: reconcile, but --reconcile-all
-            // can force reconcile on mode: once seed sets that lack unique_key.
-            for ts in &ss.tables {
-                if ts.unique_key.is_empty() {
-                    return Err(format!(
-                        "cannot reconcile seed set '{}': table '{}' has no unique_key (required for reconcile mode)",
-                        name, ts.table
-                    ));
-                }
-            }
-            return self.reconcile_seed_set(ss);
+    /// Run the phase's `sql:` entries in order, in one transaction. They run
+    /// on every execution, so the statements should be idempotent.
+    fn execute_phase_sql(&mut self, phase: &SeedPhase) -> Result<(), String> {
+        let mut statements = Vec::with_capacity(phase.sql.len());
+        for (i, stmt) in phase.sql.iter().enumerate() {
+            statements.push(self.load_sql(stmt, || format!("sql[{}]", i + 1))?);
         }
 
-        if self.db.is_seed_applied(&self.tracking_table, name)? {
-            self.log
-                .info("seed set already applied, skipping", &[("seed_set", name)]);
+        if self.dry_run {
+            for (label, step) in &statements {
+                self.log.info(
+                    "dry-run: would execute SQL",
+                    &[
+                        ("phase", phase.name.as_str()),
+                        ("sql", label),
+                        ("statement", &step.text()),
+                    ],
+                );
+            }
             return Ok(());
         }
 
-        if self.dry_run {
-            self.log.info(
-                "dry-run: seed set would be applied (new)",
-                &[("seed_set", name)],
-            );
+        self.in_transaction(&phase.name, |exec| {
+            for (label, step) in &statements {
+                exec.log.info(
+                    "executing SQL",
+                    &[("phase", phase.name.as_str()), ("sql", label)],
+                );
+                exec.run_step(step)
+                    .map_err(|e| format!("{}: {}", label, e))?;
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("phase '{}' SQL failed: {}", phase.name, e))
+    }
+
+    /// Returns a label for logs and errors together with the step to run,
+    /// reading `file:` entries relative to the spec file.
+    fn load_sql<'s>(
+        &self,
+        stmt: &'s SqlStatement,
+        inline_label: impl FnOnce() -> String,
+    ) -> Result<(String, Step<'s>), String> {
+        match stmt {
+            SqlStatement::Inline(sql) => Ok((inline_label(), Step::Sql(sql.clone()))),
+            SqlStatement::File { file } => {
+                let path = self.base_dir.join(file);
+                let sql = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("reading SQL file '{}': {}", path.display(), e))?;
+                Ok((file.clone(), Step::Sql(sql)))
+            }
+            SqlStatement::Call { call } => Ok((inline_label(), Step::Call(call))),
+        }
+    }
+
+    fn run_step(&mut self, step: &Step) -> Result<(), String> {
+        match step {
+            Step::Sql(sql) => self.db.execute_sql(sql),
+            Step::Call(call) => {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|a| self.resolve_value(a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.db.call_routine(call.name(), &args, call.is_function())
+            }
+        }
+    }
+
+    /// Runs the `before:` or `after:` hooks of a phase or seed set in order.
+    /// Each SQL hook commits on its own; a command hook fails the run when it
+    /// exits non-zero.
+    fn run_hooks(
+        &mut self,
+        hooks: &[Hook],
+        stage: &str,
+        kind: &str,
+        name: &str,
+    ) -> Result<(), String> {
+        for (i, hook) in hooks.iter().enumerate() {
+            let result = match hook {
+                Hook::Sql(stmt) => self
+                    .load_sql(stmt, || format!("{}[{}]", stage, i + 1))
+                    .and_then(|(label, step)| self.run_sql_hook(&label, &step, stage, kind, name)),
+                Hook::Command { command } => self.run_command_hook(command, stage, kind, name),
+            };
+            result.map_err(|e| format!("{} '{}' {} hook failed: {}", kind, name, stage, e))?;
+        }
+        Ok(())
+    }
+
+    fn run_sql_hook(
+        &mut self,
+        label: &str,
+        step: &Step,
+        stage: &str,
+        kind: &str,
+        name: &str,
+    ) -> Result<(), String> {
+        let fields = [(kind, name), ("hook", stage), ("sql", label)];
+        if self.dry_run {
+            self.log.info(
+                "dry-run: would run SQL hook",
+                &[fields[0], fields[1], fields[2], ("statement", &step.text())],
+            );
             return Ok(());
         }
+        self.log.info("running SQL hook", &fields);
+        self.in_transaction(name, |exec| {
+            exec.run_step(step).map_err(|e| format!("{}: {}", label, e))
+        })
+    }
+
+    fn run_command_hook(
+        &mut self,
+        command: &[String],
+        stage: &str,
+        kind: &str,
+        name: &str,
+    ) -> Result<(), String> {
+        let display = command.join(" ");
+        let fields = [(kind, name), ("hook", stage), ("command", display.as_str())];
+        if self.dry_run {
+            self.log.info("dry-run: would run command hook", &fields);
+            return Ok(());
+        }
+        self.log.info("running command hook", &fields);
+        let dir = self.base_dir.to_str();
+        match crate::cmd::run_command_in_dir(self.log, command, dir)? {
+            0 => Ok(()),
+            code => Err(format!(
+                "command {:?} exited with code {}",
+                command[0], code
+            )),
+        }
+    }
+
+    /// Whether the seed set is cleared before it is applied: by `--reset`,
+    /// a matching `--reset-set`, or `reset: true` in the spec.
+    fn resets(&self, ss: &SeedSet) -> bool {
+        self.reset || ss.reset || glob::matches_any(&self.reset_sets, &ss.name)
+    }
+
+    fn reset_seed_set(&mut self, ss: &SeedSet) -> Result<(), String> {
+        let name = &ss.name;
+        let tt = self.tracking_table.clone();
+        self.log
+            .info("reset mode: clearing seed set data", &[("seed_set", name)]);
+        if let Some(scripts) = &ss.scripts {
+            // Scripts cannot be undone; clearing their marks makes them run again.
+            for (file, _) in self.list_scripts(scripts)? {
+                self.db
+                    .remove_seed_mark(&tt, &format!("{}/{}", name, file))?;
+            }
+        }
+        let mut tables = self.ordered_tables(ss)?;
+        tables.reverse();
+        for ts in &tables {
+            let started = Instant::now();
+            let strategy = ss.reset_strategy(ts);
+            let count = match strategy {
+                "delete" => self.db.delete_rows(&ts.table)?,
+                _ => self
+                    .db
+                    .truncate_table(&ts.table, strategy == "truncate_cascade")?,
+            };
+            self.log.info(
+                "deleted rows",
+                &[
+                    ("table", &ts.table),
+                    ("count", &count.to_string()),
+                    ("strategy", strategy),
+                ],
+            );
+            let mut stats = TableStats::new(name, &ts.table);
+            stats.rows.deleted = count;
+            stats.duration = started.elapsed();
+            report::merge(&mut self.stats, stats);
+        }
+        self.db.delete_all_tracked_rows(&tt, name)?;
+        self.db.remove_seed_mark(&self.tracking_table, name)?;
+        Ok(())
+    }
+
+    fn execute_seed_set(&mut self, ss: &SeedSet) -> Result<(), String> {
+        let name = &ss.name;
+        let is_reconcile = (ss.is_reconcile() || self.reconcile_all) && ss.scripts.is_none();
+        self.log.info(
+            "processing seed set",
+            &[
+                ("seed_set", name),
+                ("mode", if is_reconcile { "reconcile" } else { "once" }),
+            ],
+        );
+
+        if let Some(scripts) = &ss.scripts {
+            return self.execute_scripts(ss, scripts);
+        }
+
+        if is_reconcile {
+            // Guard: reconcile requires unique_key on every table.
+            // Schema validation catches this for mode: reconcile, but --reconcile-all
+            // can force reconcile on mode: once seed sets that lack unique_key.
+            for ts in &ss.tables {
+                if ts.unique_key.is_empty() {
+                    return Err(format!(
+                        "cannot reconcile seed set '{}': table '{}' has no unique_key (required for reconcile mode)",
+                        name, ts.table
+                    ));
+                }
+            }
+            return self.reconcile_seed_set(ss);
+        }
+
+        // The hash is stored alongside the applied mark, so later runs can
+        // tell when the content changed.
+        let current_hash =
+            compute_seed_set_hash(ss, &|val| self.resolve_value(val).map(|v| v.to_text()))?;
+        let tt = self.tracking_table.clone();
+        let applied = self.is_applied(name)?;
+        if applied {
+            match self.stored_hash(name)? {
+                Some(stored) if stored == current_hash => {
+                    self.log
+                        .info("seed set already applied, skipping", &[("seed_set", name)]);
+                    return Ok(());
+                }
+                // Applied before content hashes were recorded: adopt the
+                // current content as the applied one.
+                None if !ss.reapplies_on_change() => {
+                    if !self.dry_run {
+                        self.db.update_seed_entry(&tt, name, &current_hash)?;
+                    }
+                    self.log
+                        .info("seed set already applied, skipping", &[("seed_set", name)]);
+                    return Ok(());
+                }
+                _ => {
+                    let subject = format!("seed set '{}'", name);
+                    if !self.reapply_changed(ss, "seed set", &subject, &[("seed_set", name)])? {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        if self.dry_run {
+            self.log.info(
+                if applied {
+                    "dry-run: seed set would be re-applied (changed)"
+                } else {
+                    "dry-run: seed set would be applied (new)"
+                },
+                &[("seed_set", name)],
+            );
+            return self.dry_run_preview_tables(ss);
+        }
+
+        let started = Instant::now();
+        let mut stats = Vec::new();
+        self.in_transaction(name, |exec| {
+            stats = exec.apply_seed_set_tables(ss)?;
+            exec.db.mark_seed_applied(&tt, name)?;
+            exec.db.update_seed_entry(&tt, name, &current_hash)?;
+            exec.record_metadata(name, started)
+        })
+        .map_err(|e| format!("seed set '{}' failed: {}", name, e))?;
+        for t in stats {
+            report::merge(&mut self.stats, t);
+        }
+        self.sync_sequences(ss)?;
+        self.log
+            .info("seed set applied successfully", &[("seed_set", name)]);
+        Ok(())
+    }
 
-        self.db.begin_transaction()?;
-        let result = self.apply_seed_set_tables(ss);
-        match result {
-            Ok(()) => {
-                self.db.mark_seed_applied(&self.tracking_table, &ss.name)?;
-                self.db.commit_transaction()?;
+    /// Apply the seed set's `on_change` policy to applied content whose hash
+    /// changed. Returns whether the content should be applied again.
+    fn reapply_changed(
+        &self,
+        ss: &SeedSet,
+        kind: &str,
+        subject: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<bool, String> {
+        match ss.on_change_policy() {
+            Some("reapply") => {
                 self.log
-                    .info("seed set applied successfully", &[("seed_set", name)]);
-                Ok(())
+                    .info(&format!("{} changed, re-applying", kind), fields);
+                Ok(true)
             }
-            Err(e) => {
-                self.db.rollback_transaction()?;
-                Err(format!("seed set '{}' failed: {}", name, e))
+            Some("error") => Err(format!(
+                "{} changed after it was applied (on_change: error)",
+                subject
+            )),
+            Some(_) => {
+                self.log.info(
+                    &format!("{} changed, ignoring (on_change: ignore)", kind),
+                    fields,
+                );
+                Ok(false)
+            }
+            None => {
+                self.log.warn(
+                    &format!(
+                        "{} changed after it was applied, skipping; set on_change to reapply or error",
+                        kind
+                    ),
+                    fields,
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Store the audit details of the tracking entry `key`, applied since
+    /// `started`.
+    fn record_metadata(&mut self, key: &str, started: Instant) -> Result<(), String> {
+        let metadata = SeedMetadata::new(self.spec_checksum.clone(), started.elapsed());
+        self.db
+            .record_seed_metadata(&self.tracking_table, key, &metadata)
+    }
+
+    /// Runs after the seed set commits: on MySQL the ALTER TABLE behind
+    /// `sync_sequence` would otherwise commit the transaction early.
+    fn sync_sequences(&mut self, ss: &SeedSet) -> Result<(), String> {
+        for ts in &ss.tables {
+            if let Some(column) = &ts.sync_sequence {
+                self.db
+                    .sync_sequence(&ts.table, column)
+                    .map_err(|e| format!("seed set '{}': table '{}': {}", ss.name, ts.table, e))?;
+                self.log.info(
+                    "synchronized sequence",
+                    &[("table", ts.table.as_str()), ("column", column.as_str())],
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Files in the scripts directory matching the pattern, in lexical order.
+    fn list_scripts(&self, scripts: &ScriptsSource) -> Result<Vec<(String, PathBuf)>, String> {
+        let dir = self.base_dir.join(&scripts.dir);
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|e| format!("reading scripts dir '{}': {}", dir.display(), e))?;
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry =
+                entry.map_err(|e| format!("reading scripts dir '{}': {}", dir.display(), e))?;
+            let path = entry.path();
+            let file = entry.file_name().to_string_lossy().into_owned();
+            if path.is_file() && glob::matches(&scripts.pattern, &file) {
+                files.push((file, path));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Apply each pending script in its own transaction, tracked as
+    /// `<seed_set>/<file>` with the content hash of the file.
+    fn execute_scripts(&mut self, ss: &SeedSet, scripts: &ScriptsSource) -> Result<(), String> {
+        let name = &ss.name;
+        let tt = self.tracking_table.clone();
+        let files = self.list_scripts(scripts)?;
+        if files.is_empty() {
+            self.log.warn(
+                "no scripts matched",
+                &[("seed_set", name), ("pattern", &scripts.pattern)],
+            );
+        }
+        for (file, path) in files {
+            let sql = std::fs::read_to_string(&path)
+                .map_err(|e| format!("reading script '{}': {}", path.display(), e))?;
+            let key = format!("{}/{}", name, file);
+            let hash = compute_content_hash(sql.as_bytes());
+            if self.is_applied(&key)? {
+                let changed = self.stored_hash(&key)?.is_some_and(|stored| stored != hash);
+                if !changed {
+                    self.log.info(
+                        "script already applied, skipping",
+                        &[("seed_set", name), ("script", &file)],
+                    );
+                    continue;
+                }
+                let subject = format!("script '{}' in seed set '{}'", file, name);
+                let fields = [("seed_set", name.as_str()), ("script", file.as_str())];
+                if !self.reapply_changed(ss, "script", &subject, &fields)? {
+                    continue;
+                }
+            }
+            if self.dry_run {
+                self.log.info(
+                    "dry-run: script would be applied",
+                    &[("seed_set", name), ("script", &file)],
+                );
+                continue;
+            }
+            let started = Instant::now();
+            self.in_transaction(&key, |exec| {
+                exec.db.execute_sql(&sql)?;
+                exec.db.mark_seed_applied(&tt, &key)?;
+                exec.db.update_seed_entry(&tt, &key, &hash)?;
+                exec.record_metadata(&key, started)
+            })
+            .map_err(|e| format!("script '{}' in seed set '{}' failed: {}", file, name, e))?;
+            self.log
+                .info("script applied", &[("seed_set", name), ("script", &file)]);
+        }
+        Ok(())
+    }
+
+    /// Run `apply` inside a transaction and commit it. When the driver reports
+    /// a retryable failure (e.g. a CockroachDB serialization conflict), the
+    /// transaction is rolled back and `apply` runs again after a backoff.
+    /// Inside a `per_phase` or `none` phase, `apply` just runs.
+    fn in_transaction<F>(&mut self, seed_set: &str, mut apply: F) -> Result<(), String>
+    where
+        F: FnMut(&mut Self) -> Result<(), String>,
+    {
+        if self.skip_transactions {
+            return apply(self);
+        }
+        let retry_cfg = transaction_retry_config();
+        let mut attempt = 0;
+        loop {
+            self.db.begin_transaction()?;
+            let result = apply(self).and_then(|()| self.db.commit_transaction());
+            let err = match result {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            self.db.rollback_transaction()?;
+            if attempt + 1 >= retry_cfg.max_attempts || !self.db.is_retryable_error(&err) {
+                return Err(err);
             }
+            let delay = retry::delay(&retry_cfg, attempt);
+            self.log.warn(
+                "retryable transaction error, retrying seed set",
+                &[
+                    ("seed_set", seed_set),
+                    ("attempt", &(attempt + 1).to_string()),
+                    ("delay", &format_duration(delay)),
+                    ("error", &err),
+                ],
+            );
+            std::thread::sleep(delay);
+            attempt += 1;
         }
     }
 
-    fn apply_seed_set_tables(&mut self, ss: &SeedSet) -> Result<(), String> {
+    /// The tables of a seed set in the order they are written: by `order`,
+    /// or with `auto_order` after the tables they reference through foreign
+    /// keys or `@ref:`, `order` then only breaking ties.
+    fn ordered_tables<'s>(&mut self, ss: &'s SeedSet) -> Result<Vec<&'s TableSeed>, String> {
         let mut tables: Vec<&TableSeed> = ss.tables.iter().collect();
         tables.sort_by_key(|t| t.order);
+        if !self.auto_order && !ss.auto_order {
+            return Ok(tables);
+        }
+
+        let ref_owners: HashMap<&str, usize> = tables
+            .iter()
+            .enumerate()
+            .flat_map(|(i, ts)| {
+                ts.rows
+                    .iter()
+                    .filter_map(move |row| Some((row.get("_ref")?.as_str()?, i)))
+            })
+            .collect();
+        let mut foreign_keys_known = true;
+        let mut deps = Vec::with_capacity(tables.len());
+        for (i, ts) in tables.iter().enumerate() {
+            let mut on: BTreeSet<usize> = ts
+                .rows
+                .iter()
+                .flat_map(|row| row.values())
+                .filter_map(|v| v.as_str()?.strip_prefix("@ref:"))
+                .filter_map(|expr| ref_owners.get(expr.split('.').next()?).copied())
+                .collect();
+            match self.db.referenced_tables(&ts.table)? {
+                Some(referenced) => on.extend(
+                    referenced
+                        .iter()
+                        .filter_map(|r| tables.iter().position(|t| same_table(r, &t.table))),
+                ),
+                None => foreign_keys_known = false,
+            }
+            on.remove(&i);
+            deps.push(on);
+        }
+        if !foreign_keys_known {
+            self.log.warn(
+                "driver cannot read foreign keys; ordering tables by @ref: values only",
+                &[("seed_set", &ss.name), ("driver", self.db.driver_name())],
+            );
+        }
+        sort_by_dependencies(tables, &deps).map_err(|cycle| {
+            format!(
+                "seed set '{}': tables {} reference each other; order them by hand with 'order'",
+                ss.name, cycle
+            )
+        })
+    }
+
+    fn apply_seed_set_tables(&mut self, ss: &SeedSet) -> Result<Vec<TableStats>, String> {
+        let tables = self.ordered_tables(ss)?;
+        let mut stats = Vec::with_capacity(tables.len());
         for ts in &tables {
-            self.apply_table_seed(ts)?;
+            let started = Instant::now();
+            let mut t = TableStats::new(&ss.name, &ts.table);
+            t.rows = self.apply_table_seed(&ss.name, ts)?;
+            t.duration = started.elapsed();
+            stats.push(t);
         }
-        Ok(())
+        Ok(stats)
     }
 
-    fn apply_table_seed(&mut self, ts: &TableSeed) -> Result<(), String> {
+    fn apply_table_seed(&mut self, ss_name: &str, ts: &TableSeed) -> Result<RowCounts, String> {
         let table = &ts.table;
+        let tt = self.tracking_table.clone();
         self.log.info(
             "seeding table",
             &[
@@ -259,90 +1361,255 @@ impl<'a> SeedExecutor<'a> {
             ],
         );
 
+        // Plain inserts are grouped into multi-row INSERTs. Rows that need a
+        // database-generated id or an upsert are written one at a time.
+        let batching =
+            ts.batch_size > 1 && ts.auto_id.as_ref().is_none_or(|a| a.is_uuid()) && !ts.upserts();
+        let auto_id_col = db_generated_id_column(ts);
+        let mut batch = PendingBatch::default();
+        let mut counts = RowCounts::default();
+        // `managed` tables remember the rows they write, so rows removed from
+        // the spec can be deleted.
+        let mut seen_keys = HashSet::new();
+
         for (idx, row) in ts.rows.iter().enumerate() {
             let ref_name = row
                 .get("_ref")
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
 
-            let mut columns = Vec::new();
-            let mut values = Vec::new();
-            let mut unique_columns = Vec::new();
-            let mut unique_values = Vec::new();
-
+            let mut resolved = Vec::new();
             for (key, val) in row {
                 if key == "_ref" {
                     continue;
                 }
-                let resolved = self.resolve_value(val)?;
-                columns.push(key.clone());
-                values.push(resolved.clone());
-
-                if ts.unique_key.contains(key) {
-                    unique_columns.push(key.clone());
-                    unique_values.push(resolved);
-                }
+                resolved.push((key.clone(), self.resolve_row_value(val)?));
             }
-
-            if let Some(ref auto_id) = ts.auto_id {
-                if !columns.contains(&auto_id.column) {
-                    // Skip auto_id column; let the database generate it
-                }
+            if batching {
+                // Rows of a batch must list their columns in the same order.
+                resolved.sort_by(|a, b| a.0.cmp(&b.0));
             }
-
-            if !ts.unique_key.is_empty()
-                && self.db.row_exists(table, &unique_columns, &unique_values)?
-            {
-                self.log.info(
-                    "row already exists, skipping",
-                    &[("table", table.as_str()), ("row", &(idx + 1).to_string())],
-                );
-                continue;
+            let (mut columns, mut values): (Vec<String>, Vec<SeedValue>) =
+                resolved.into_iter().unzip();
+            let (unique_columns, unique_values): (Vec<String>, Vec<SeedValue>) = columns
+                .iter()
+                .zip(&values)
+                .filter(|(c, _)| ts.unique_key.contains(c))
+                .map(|(c, v)| (c.clone(), v.clone()))
+                .unzip();
+            let row_key = build_row_key(&ts.unique_key, &columns, &values);
+            if ts.managed {
+                seen_keys.insert(row_key.clone());
             }
 
-            let auto_id_col = ts.auto_id.as_ref().map(|a| a.column.as_str());
-            let generated_id = self.db.insert_row(table, &columns, &values, auto_id_col)?;
+            let generated_id = if ts.upserts() {
+                self.add_uuid_auto_id(ts, &mut columns, &mut values)?;
+                self.db
+                    .upsert_row(table, &columns, &values, &ts.unique_key, auto_id_col)?
+            } else {
+                if !ts.unique_key.is_empty()
+                    && (batch.keys.contains(&row_key)
+                        || self.db.row_exists(table, &unique_columns, &unique_values)?)
+                {
+                    if ts.on_conflict == "error" {
+                        return Err(format!(
+                            "row {} in table '{}' conflicts with an existing row on unique_key ({})",
+                            idx + 1,
+                            table,
+                            ts.unique_key.join(", ")
+                        ));
+                    }
+                    self.log.info(
+                        "row already exists, skipping",
+                        &[("table", table.as_str()), ("row", &(idx + 1).to_string())],
+                    );
+                    counts.skipped += 1;
+                    continue;
+                }
+                self.add_uuid_auto_id(ts, &mut columns, &mut values)?;
+                if batching {
+                    if batch.columns != columns || batch.rows.len() >= ts.batch_size {
+                        self.flush_batch(table, &mut batch)?;
+                        batch.columns = columns.clone();
+                    }
+                    if batch.rows.is_empty() {
+                        batch.first = idx + 1;
+                    }
+                    batch.last = idx + 1;
+                    if !ts.unique_key.is_empty() {
+                        batch.keys.insert(row_key.clone());
+                    }
+                    batch.rows.push(values.clone());
+                    None
+                } else {
+                    self.db.insert_row(table, &columns, &values, auto_id_col)?
+                }
+            };
 
             if let Some(ref_key) = ref_name {
                 let mut ref_map = HashMap::new();
                 for (i, col) in columns.iter().enumerate() {
                     ref_map.insert(col.clone(), values[i].clone());
                 }
-                if let (Some(ref auto_id), Some(id)) = (&ts.auto_id, generated_id) {
-                    ref_map.insert(auto_id.column.clone(), id.to_string());
+                if let (Some(col), Some(id)) = (auto_id_col, generated_id) {
+                    ref_map.insert(col.to_string(), SeedValue::Int(id));
                 }
                 self.refs.insert(ref_key, ref_map);
             }
+            if ts.managed {
+                let row_values = build_row_values_excluding(&columns, &values, &ts.ignore_columns);
+                self.db
+                    .store_tracked_row(&tt, ss_name, table, &row_key, &row_values)?;
+            }
 
-            self.log.info(
-                "inserted row",
-                &[("table", table.as_str()), ("row", &(idx + 1).to_string())],
-            );
+            if ts.upserts() {
+                counts.upserted += 1;
+            } else {
+                counts.inserted += 1;
+            }
+            if !batching {
+                self.log.info(
+                    if ts.upserts() {
+                        "upserted row"
+                    } else {
+                        "inserted row"
+                    },
+                    &[("table", table.as_str()), ("row", &(idx + 1).to_string())],
+                );
+            }
+        }
+        self.flush_batch(table, &mut batch)?;
+        if ts.managed {
+            let tracked = self.tracked_rows(ss_name, table)?;
+            let orphans = tracked
+                .into_iter()
+                .map(|(key, _)| key)
+                .filter(|key| !seen_keys.contains(key));
+            counts.deleted = self.delete_orphans(ss_name, table, orphans)?;
+        }
+        Ok(counts)
+    }
+
+    /// Append the id column for a `uuid` auto_id unless the row sets it: the
+    /// id of the row an upsert will update, or a new UUIDv4. Unlike integer
+    /// ids, the value is known before the write, so it reaches `@ref:` via the
+    /// row's columns.
+    fn add_uuid_auto_id(
+        &mut self,
+        ts: &TableSeed,
+        columns: &mut Vec<String>,
+        values: &mut Vec<SeedValue>,
+    ) -> Result<(), String> {
+        let auto_id = match &ts.auto_id {
+            Some(a) if a.is_uuid() && !columns.contains(&a.column) => a,
+            _ => return Ok(()),
+        };
+        let mut existing = None;
+        if ts.upserts() {
+            let (key_columns, key_values): (Vec<String>, Vec<SeedValue>) = columns
+                .iter()
+                .zip(values.iter())
+                .filter(|(c, _)| ts.unique_key.contains(c))
+                .map(|(c, v)| (c.clone(), v.clone()))
+                .unzip();
+            existing = self
+                .db
+                .get_row_columns(
+                    &ts.table,
+                    &key_columns,
+                    &key_values,
+                    std::slice::from_ref(&auto_id.column),
+                )?
+                .and_then(|vals| vals.into_iter().next());
         }
+        let id =
+            existing.unwrap_or_else(|| crate::template_funcs::func_uuid4(&mut rand::thread_rng()));
+        columns.push(auto_id.column.clone());
+        values.push(SeedValue::Text(id));
+        Ok(())
+    }
 
+    fn flush_batch(&mut self, table: &str, batch: &mut PendingBatch) -> Result<(), String> {
+        if batch.rows.is_empty() {
+            return Ok(());
+        }
+        let range = format!("{}-{}", batch.first, batch.last);
+        self.db
+            .insert_rows(table, &batch.columns, &batch.rows)
+            .map_err(|e| format!("rows {}: {}", range, e))?;
+        self.log.info(
+            "inserted rows",
+            &[
+                ("table", table),
+                ("rows", &range),
+                ("count", &batch.rows.len().to_string()),
+            ],
+        );
+        batch.rows.clear();
+        batch.keys.clear();
         Ok(())
     }
 
-    fn resolve_value(&self, val: &serde_yaml::Value) -> Result<String, String> {
+    fn resolve_value(&self, val: &serde_yaml::Value) -> Result<SeedValue, String> {
         match val {
             serde_yaml::Value::String(s) => {
                 if let Some(ref_expr) = s.strip_prefix("@ref:") {
                     self.resolve_reference(ref_expr)
                 } else if let Some(env_expr) = s.strip_prefix("$env:") {
                     std::env::var(env_expr)
+                        .map(SeedValue::Text)
                         .map_err(|_| format!("environment variable '{}' not set", env_expr))
+                } else if let Some(encoded) = s.strip_prefix("base64:") {
+                    decode_binary(encoded)
                 } else {
-                    Ok(s.clone())
+                    Ok(SeedValue::Text(s.clone()))
                 }
             }
-            serde_yaml::Value::Number(n) => Ok(n.to_string()),
-            serde_yaml::Value::Bool(b) => Ok(b.to_string()),
-            serde_yaml::Value::Null => Ok(String::new()),
-            _ => Ok(format!("{:?}", val)),
+            serde_yaml::Value::Number(n) => Ok(SeedValue::from_yaml_number(n)),
+            serde_yaml::Value::Bool(b) => Ok(SeedValue::Bool(*b)),
+            serde_yaml::Value::Null => Ok(SeedValue::Null),
+            serde_yaml::Value::Sequence(_) | serde_yaml::Value::Mapping(_) => {
+                serde_json::to_value(val)
+                    .map(SeedValue::Json)
+                    .map_err(|e| format!("converting value to JSON: {}", e))
+            }
+            serde_yaml::Value::Tagged(t) if t.tag == "binary" => match &t.value {
+                serde_yaml::Value::String(encoded) => decode_binary(encoded),
+                _ => Err("!binary value must be a base64 string".into()),
+            },
+            serde_yaml::Value::Tagged(t) => Err(format!("unsupported YAML tag '{}'", t.tag)),
+        }
+    }
+
+    /// Resolve a row value, including `@lookup:` values, which query the
+    /// database.
+    fn resolve_row_value(&mut self, val: &serde_yaml::Value) -> Result<SeedValue, String> {
+        match val.as_str().and_then(|s| s.strip_prefix("@lookup:")) {
+            Some(expr) => self.resolve_lookup(expr),
+            None => self.resolve_value(val),
+        }
+    }
+
+    fn resolve_lookup(&mut self, expr: &str) -> Result<SeedValue, String> {
+        let lookup = Lookup::parse(expr)?;
+        let mut key_columns = Vec::new();
+        let mut key_values = Vec::new();
+        for (column, value) in &lookup.conditions {
+            key_columns.push(column.clone());
+            key_values.push(self.resolve_value(value)?);
         }
+        let row = self.db.get_row_columns(
+            &lookup.table,
+            &key_columns,
+            &key_values,
+            std::slice::from_ref(&lookup.column),
+        )?;
+        row.and_then(|vals| vals.into_iter().next())
+            .map(SeedValue::Text)
+            .ok_or_else(|| format!("lookup '{}' matched no row", expr))
     }
 
-    fn resolve_reference(&self, expr: &str) -> Result<String, String> {
+    fn resolve_reference(&self, expr: &str) -> Result<SeedValue, String> {
         let parts: Vec<&str> = expr.splitn(2, '.').collect();
         if parts.len() != 2 {
             return Err(format!(
@@ -370,19 +1637,23 @@ impl<'a> SeedExecutor<'a> {
         let name = &ss.name;
 
         // Compute hash of current spec (resolve env vars, keep @ref: as literals)
-        let current_hash = compute_seed_set_hash(ss, &|val| self.resolve_value(val))?;
+        let current_hash =
+            compute_seed_set_hash(ss, &|val| self.resolve_value(val).map(|v| v.to_text()))?;
 
         // Check stored hash for quick skip.
-        // Only skip if the seed set has no @ref: expressions, because
-        // compute_seed_set_hash treats @ref: values as literals. Resolved
+        // Only skip if the seed set has no @ref: or @lookup: expressions,
+        // because compute_seed_set_hash treats them as literals. Resolved
         // reference targets can change without affecting the hash (e.g.,
         // upstream auto_id row deleted/reinserted), and skipping could leave
         // stale foreign keys.
-        let stored_hash = self.db.get_seed_hash(&self.tracking_table, name)?;
+        let stored_hash = self.stored_hash(name)?;
         let has_refs = ss.tables.iter().any(|ts| {
             ts.rows.iter().any(|row| {
-                row.values()
-                    .any(|v| v.as_str().map(|s| s.starts_with("@ref:")).unwrap_or(false))
+                row.values().any(|v| {
+                    v.as_str()
+                        .map(|s| s.starts_with("@ref:") || s.starts_with("@lookup:"))
+                        .unwrap_or(false)
+                })
             })
         });
         if !has_refs && stored_hash.as_deref() == Some(current_hash.as_str()) {
@@ -406,35 +1677,39 @@ impl<'a> SeedExecutor<'a> {
 
         self.log.info("reconciling seed set", &[("seed_set", name)]);
 
-        self.db.begin_transaction()?;
-        let result = self.reconcile_tables(ss, &current_hash);
-        match result {
-            Ok(()) => {
-                let tt = self.tracking_table.clone();
-                self.db.update_seed_entry(&tt, name, &current_hash)?;
-                self.db.commit_transaction()?;
-                self.log
-                    .info("seed set reconciled successfully", &[("seed_set", name)]);
-                Ok(())
-            }
-            Err(e) => {
-                self.db.rollback_transaction()?;
-                Err(format!("reconciling seed set '{}' failed: {}", name, e))
-            }
+        let tt = self.tracking_table.clone();
+        let started = Instant::now();
+        let mut stats = Vec::new();
+        self.in_transaction(name, |exec| {
+            stats = exec.reconcile_tables(ss)?;
+            exec.db.update_seed_entry(&tt, name, &current_hash)?;
+            exec.record_metadata(name, started)
+        })
+        .map_err(|e| format!("reconciling seed set '{}' failed: {}", name, e))?;
+        for t in stats {
+            report::merge(&mut self.stats, t);
         }
+        self.sync_sequences(ss)?;
+        self.log
+            .info("seed set reconciled successfully", &[("seed_set", name)]);
+        Ok(())
     }
 
-    fn reconcile_tables(&mut self, ss: &SeedSet, _hash: &str) -> Result<(), String> {
-        let mut tables: Vec<&TableSeed> = ss.tables.iter().collect();
-        tables.sort_by_key(|t| t.order);
+    fn reconcile_tables(&mut self, ss: &SeedSet) -> Result<Vec<TableStats>, String> {
+        let tables = self.ordered_tables(ss)?;
 
+        let mut stats = Vec::with_capacity(tables.len());
         for ts in &tables {
-            self.reconcile_table(ss, ts)?;
+            let started = Instant::now();
+            let mut t = TableStats::new(&ss.name, &ts.table);
+            t.rows = self.reconcile_table(ss, ts)?;
+            t.duration = started.elapsed();
+            stats.push(t);
         }
-        Ok(())
+        Ok(stats)
     }
 
-    fn reconcile_table(&mut self, ss: &SeedSet, ts: &TableSeed) -> Result<(), String> {
+    fn reconcile_table(&mut self, ss: &SeedSet, ts: &TableSeed) -> Result<RowCounts, String> {
         let table = &ts.table;
         let tt = self.tracking_table.clone();
         let ss_name = ss.name.clone();
@@ -448,11 +1723,12 @@ impl<'a> SeedExecutor<'a> {
         );
 
         // Get currently tracked rows for this seed_set + table
-        let tracked = self.db.get_tracked_rows(&tt, &ss_name, table)?;
+        let tracked = self.tracked_rows(&ss_name, table)?;
         let tracked_keys: HashSet<String> = tracked.iter().map(|(k, _)| k.clone()).collect();
         let tracked_values: HashMap<String, String> = tracked.into_iter().collect();
 
         let mut seen_keys = HashSet::new();
+        let mut counts = RowCounts::default();
 
         for (idx, row) in ts.rows.iter().enumerate() {
             let ref_name = row
@@ -469,7 +1745,7 @@ impl<'a> SeedExecutor<'a> {
                 if key == "_ref" {
                     continue;
                 }
-                let resolved = self.resolve_value(val)?;
+                let resolved = self.resolve_row_value(val)?;
                 columns.push(key.clone());
                 values.push(resolved.clone());
 
@@ -497,6 +1773,7 @@ impl<'a> SeedExecutor<'a> {
                         "row unchanged, skipping",
                         &[("table", table.as_str()), ("row", &(idx + 1).to_string())],
                     );
+                    counts.skipped += 1;
                     continue;
                 }
 
@@ -506,7 +1783,7 @@ impl<'a> SeedExecutor<'a> {
                     .filter(|c| !ts.unique_key.contains(c) && !ts.ignore_columns.contains(c))
                     .cloned()
                     .collect();
-                let non_key_values: Vec<String> = columns
+                let non_key_values: Vec<SeedValue> = columns
                     .iter()
                     .zip(values.iter())
                     .filter(|(c, _)| !ts.unique_key.contains(c) && !ts.ignore_columns.contains(c))
@@ -530,18 +1807,25 @@ impl<'a> SeedExecutor<'a> {
                     "updated row",
                     &[("table", table.as_str()), ("row", &(idx + 1).to_string())],
                 );
+                counts.updated += 1;
             } else {
-                // New row — INSERT
-                let auto_id_col = ts.auto_id.as_ref().map(|a| a.column.as_str());
-                let generated_id = self.db.insert_row(table, &columns, &values, auto_id_col)?;
+                // New row — INSERT (or adopt a matching untracked row when upserting)
+                self.add_uuid_auto_id(ts, &mut columns, &mut values)?;
+                let auto_id_col = db_generated_id_column(ts);
+                let generated_id = if ts.upserts() {
+                    self.db
+                        .upsert_row(table, &columns, &values, &ts.unique_key, auto_id_col)?
+                } else {
+                    self.db.insert_row(table, &columns, &values, auto_id_col)?
+                };
 
                 if let Some(ref_key) = &ref_name {
                     let mut ref_map = HashMap::new();
                     for (i, col) in columns.iter().enumerate() {
                         ref_map.insert(col.clone(), values[i].clone());
                     }
-                    if let (Some(ref auto_id), Some(id)) = (&ts.auto_id, generated_id) {
-                        ref_map.insert(auto_id.column.clone(), id.to_string());
+                    if let (Some(col), Some(id)) = (auto_id_col, generated_id) {
+                        ref_map.insert(col.to_string(), SeedValue::Int(id));
                     }
                     self.refs.insert(ref_key.clone(), ref_map);
                 }
@@ -552,29 +1836,48 @@ impl<'a> SeedExecutor<'a> {
                     "inserted row",
                     &[("table", table.as_str()), ("row", &(idx + 1).to_string())],
                 );
+                if ts.upserts() {
+                    counts.upserted += 1;
+                } else {
+                    counts.inserted += 1;
+                }
             }
         }
 
         // Delete orphaned rows (in tracking but not in current spec)
         let orphaned_keys: Vec<String> = tracked_keys.difference(&seen_keys).cloned().collect();
+        counts.deleted = self.delete_orphans(&ss_name, table, orphaned_keys)?;
 
-        for orphan_key in &orphaned_keys {
-            // Parse the row_key JSON to get column names + values
-            let key_map: BTreeMap<String, String> = serde_json::from_str(orphan_key)
+        Ok(counts)
+    }
+
+    /// Delete tracked rows whose keys are no longer in the spec, with their
+    /// tracking entries. Returns how many were deleted.
+    fn delete_orphans(
+        &mut self,
+        ss_name: &str,
+        table: &str,
+        orphaned_keys: impl IntoIterator<Item = String>,
+    ) -> Result<u64, String> {
+        let tt = self.tracking_table.clone();
+        let mut deleted = 0;
+        for orphan_key in orphaned_keys {
+            // Parse the row_key JSON to get column names + values
+            let key_map: BTreeMap<String, String> = serde_json::from_str(&orphan_key)
                 .map_err(|e| format!("parsing orphan row key: {}", e))?;
             let key_cols: Vec<String> = key_map.keys().cloned().collect();
-            let key_vals: Vec<String> = key_map.values().cloned().collect();
+            let key_vals: Vec<SeedValue> = key_map.into_values().map(SeedValue::Text).collect();
 
             self.db.delete_row_by_key(table, &key_cols, &key_vals)?;
             self.db
-                .delete_tracked_row(&tt, &ss_name, table, orphan_key)?;
+                .delete_tracked_row(&tt, ss_name, table, &orphan_key)?;
             self.log.info(
                 "deleted orphaned row",
-                &[("table", table.as_str()), ("row_key", orphan_key)],
+                &[("table", table), ("row_key", &orphan_key)],
             );
+            deleted += 1;
         }
-
-        Ok(())
+        Ok(deleted)
     }
 
     /// Populate refs from an existing (unchanged) row, fetching auto_id from DB if needed.
@@ -583,7 +1886,7 @@ impl<'a> SeedExecutor<'a> {
         ts: &TableSeed,
         ref_name: &Option<String>,
         columns: &[String],
-        values: &[String],
+        values: &[SeedValue],
     ) -> Result<(), String> {
         if let Some(ref_key) = ref_name {
             let mut ref_map = HashMap::new();
@@ -594,7 +1897,7 @@ impl<'a> SeedExecutor<'a> {
             // If there's an auto_id, fetch the actual ID from the DB
             if let Some(ref auto_id) = ts.auto_id {
                 let unique_cols: Vec<String> = ts.unique_key.clone();
-                let unique_vals: Vec<String> = ts
+                let unique_vals: Vec<SeedValue> = ts
                     .unique_key
                     .iter()
                     .filter_map(|uk| {
@@ -612,7 +1915,7 @@ impl<'a> SeedExecutor<'a> {
                     std::slice::from_ref(&auto_id.column),
                 )? {
                     if let Some(id_val) = row_vals.first() {
-                        ref_map.insert(auto_id.column.clone(), id_val.clone());
+                        ref_map.insert(auto_id.column.clone(), SeedValue::Text(id_val.clone()));
                     }
                 }
             }
@@ -623,8 +1926,7 @@ impl<'a> SeedExecutor<'a> {
 
     /// Populate refs for a skipped (hash-matched) seed set by reading from DB.
     fn populate_refs_from_db(&mut self, ss: &SeedSet) -> Result<(), String> {
-        let mut tables: Vec<&TableSeed> = ss.tables.iter().collect();
-        tables.sort_by_key(|t| t.order);
+        let tables = self.ordered_tables(ss)?;
 
         for ts in &tables {
             for row in &ts.rows {
@@ -644,7 +1946,7 @@ impl<'a> SeedExecutor<'a> {
                     if key == "_ref" {
                         continue;
                     }
-                    let resolved = self.resolve_value(val)?;
+                    let resolved = self.resolve_row_value(val)?;
                     columns.push(key.clone());
                     values.push(resolved);
                 }
@@ -657,22 +1959,77 @@ impl<'a> SeedExecutor<'a> {
 
     /// Resolve a value for dry-run: treats `@ref:` as literals to avoid failures
     /// when refs haven't been populated (common with auto_id + refs in same seed set).
-    fn resolve_value_dry_run(&self, val: &serde_yaml::Value) -> Result<String, String> {
+    fn resolve_value_dry_run(&self, val: &serde_yaml::Value) -> Result<SeedValue, String> {
         match val {
-            serde_yaml::Value::String(s) if s.starts_with("@ref:") => Ok(s.clone()),
+            serde_yaml::Value::String(s) if s.starts_with("@ref:") => {
+                Ok(SeedValue::Text(s.clone()))
+            }
             _ => self.resolve_value(val),
         }
     }
 
+    /// Dry-run: list the rows each table of a `once` seed set would write.
+    fn dry_run_preview_tables(&mut self, ss: &SeedSet) -> Result<(), String> {
+        let tables = self.ordered_tables(ss)?;
+        for ts in &tables {
+            self.log.info(
+                "dry-run: would seed table",
+                &[
+                    ("table", ts.table.as_str()),
+                    ("rows", &ts.rows.len().to_string()),
+                    ("on_conflict", ts.on_conflict.as_str()),
+                ],
+            );
+            for (idx, row) in ts.rows.iter().enumerate() {
+                let mut preview = BTreeMap::new();
+                for (key, val) in row {
+                    if key != "_ref" {
+                        preview.insert(key.as_str(), self.resolve_value_dry_run(val)?.to_text());
+                    }
+                }
+                let preview = serde_json::to_string(&preview)
+                    .map_err(|e| format!("serializing row preview: {}", e))?;
+                self.log.info(
+                    "dry-run: would write row",
+                    &[
+                        ("table", ts.table.as_str()),
+                        ("row", &(idx + 1).to_string()),
+                        ("values", &preview),
+                    ],
+                );
+            }
+            if ts.managed {
+                let mut seen_keys = HashSet::new();
+                for row in &ts.rows {
+                    let (mut columns, mut values) = (Vec::new(), Vec::new());
+                    for (key, val) in row {
+                        if key != "_ref" {
+                            columns.push(key.clone());
+                            values.push(self.resolve_value_dry_run(val)?);
+                        }
+                    }
+                    seen_keys.insert(build_row_key(&ts.unique_key, &columns, &values));
+                }
+                for (key, _) in self.tracked_rows(&ss.name, &ts.table)? {
+                    if !seen_keys.contains(&key) {
+                        self.log.info(
+                            "dry-run: would delete row",
+                            &[("table", ts.table.as_str()), ("row_key", &key)],
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Dry-run: compute what reconciliation would do without modifying the DB.
     fn dry_run_reconcile_tables(&mut self, ss: &SeedSet) -> Result<(), String> {
-        let mut tables: Vec<&TableSeed> = ss.tables.iter().collect();
-        tables.sort_by_key(|t| t.order);
-        let tt = self.tracking_table.clone();
+        let tables = self.ordered_tables(ss)?;
         let ss_name = ss.name.clone();
 
         for ts in &tables {
-            let tracked = self.db.get_tracked_rows(&tt, &ss_name, &ts.table)?;
+            let tracked = self.tracked_rows(&ss_name, &ts.table)?;
             let tracked_keys: HashSet<String> = tracked.iter().map(|(k, _)| k.clone()).collect();
             let tracked_values: HashMap<String, String> = tracked.into_iter().collect();
 
@@ -730,12 +2087,91 @@ impl<'a> SeedExecutor<'a> {
     }
 }
 
+/// Whether `name` passes a `--phase`/`--seed-set` filter: it must match one
+/// of the plain patterns, if there are any, and none of the `!` patterns.
+fn filter_selects(patterns: &[String], name: &str) -> bool {
+    let (excludes, includes): (Vec<&String>, Vec<&String>) =
+        patterns.iter().partition(|p| p.starts_with('!'));
+    (includes.is_empty() || includes.iter().any(|p| glob::matches(p, name)))
+        && !excludes.iter().any(|p| glob::matches(&p[1..], name))
+}
+
+/// Order `tables` so each comes after the tables it depends on, `deps`
+/// holding the indexes each depends on, keeping the given order where no
+/// dependency decides. A cycle fails with the tables in it.
+fn sort_by_dependencies<'s>(
+    tables: Vec<&'s TableSeed>,
+    deps: &[BTreeSet<usize>],
+) -> Result<Vec<&'s TableSeed>, String> {
+    let mut placed = vec![false; tables.len()];
+    let mut order = Vec::with_capacity(tables.len());
+    while order.len() < tables.len() {
+        let next = (0..tables.len())
+            .find(|&i| !placed[i] && deps[i].iter().all(|&d| placed[d]))
+            .ok_or_else(|| {
+                let waiting: Vec<&str> = (0..tables.len())
+                    .filter(|&i| !placed[i])
+                    .map(|i| tables[i].table.as_str())
+                    .collect();
+                waiting.join(", ")
+            })?;
+        placed[next] = true;
+        order.push(tables[next]);
+    }
+    Ok(order)
+}
+
+/// Whether a table name read from the database's foreign keys names `table`.
+/// Unless both are schema-qualified, only the table names are compared, as
+/// the database may qualify names the spec leaves to the default schema.
+fn same_table(referenced: &str, table: &str) -> bool {
+    let strip = |s: &str| s.replace(['"', '`', '[', ']'], "");
+    let (referenced, table) = (strip(referenced), strip(table));
+    match (referenced.rsplit_once('.'), table.rsplit_once('.')) {
+        (Some(_), Some(_)) | (None, None) => referenced.eq_ignore_ascii_case(&table),
+        (Some((_, a)), None) => a.eq_ignore_ascii_case(&table),
+        (None, Some((_, b))) => referenced.eq_ignore_ascii_case(b),
+    }
+}
+
+/// The auto_id column whose value the database generates and returns, i.e.
+/// an `integer` auto_id.
+fn db_generated_id_column(ts: &TableSeed) -> Option<&str> {
+    ts.auto_id
+        .as_ref()
+        .filter(|a| !a.is_uuid())
+        .map(|a| a.column.as_str())
+}
+
+/// Rows queued for a multi-row INSERT, all with the same columns.
+#[derive(Default)]
+struct PendingBatch {
+    columns: Vec<String>,
+    rows: Vec<Vec<SeedValue>>,
+    /// 1-based indexes of the first and last queued rows, for logs and errors.
+    first: usize,
+    last: usize,
+    /// Unique keys of the queued rows, which `row_exists` cannot see yet.
+    keys: HashSet<String>,
+}
+
+/// Backoff for retrying a seed set transaction after a retryable error.
+fn transaction_retry_config() -> retry::Config {
+    retry::Config {
+        max_attempts: 5,
+        initial_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(5),
+        backoff_factor: 2.0,
+        jitter_fraction: 0.5,
+    }
+}
+
 /// Build a canonical JSON key from unique key columns (sorted by column name).
-fn build_row_key(unique_key_spec: &[String], columns: &[String], values: &[String]) -> String {
+fn build_row_key(unique_key_spec: &[String], columns: &[String], values: &[SeedValue]) -> String {
     let mut map = BTreeMap::new();
     for uk in unique_key_spec {
         if let Some(idx) = columns.iter().position(|c| c == uk) {
-            map.insert(uk.clone(), values[idx].clone());
+            map.insert(uk.clone(), values[idx].to_text());
         }
     }
     serde_json::to_string(&map).unwrap_or_default()
@@ -743,11 +2179,16 @@ fn build_row_key(unique_key_spec: &[String], columns: &[String], values: &[Strin
 
 /// Build a canonical JSON representation of row values, excluding specified columns.
 /// Ignored columns are excluded from tracking so changes to them don't trigger reconciliation.
-fn build_row_values_excluding(columns: &[String], values: &[String], exclude: &[String]) -> String {
+/// Values are stored in text form, so tracking written by earlier versions still matches.
+fn build_row_values_excluding(
+    columns: &[String],
+    values: &[SeedValue],
+    exclude: &[String],
+) -> String {
     let mut map = BTreeMap::new();
     for (i, col) in columns.iter().enumerate() {
         if !exclude.contains(col) {
-            map.insert(col.clone(), values[i].clone());
+            map.insert(col.clone(), values[i].to_text());
         }
     }
     serde_json::to_string(&map).unwrap_or_default()
@@ -991,6 +2432,141 @@ phases:
         );
     }
 
+    #[test]
+    fn test_uuid_auto_id_and_refs() {
+        let spec = |mode: &str, name: &str| {
+            format!(
+                r#"
+database:
+  driver: sqlite
+phases:
+  - name: phase1
+    seed_sets:
+      - name: tenants
+        mode: {mode}
+        tables:
+          - table: tenants
+            order: 1
+            unique_key: [slug]
+            on_conflict: update
+            auto_id:
+              column: id
+              id_type: uuid
+            rows:
+              - _ref: acme
+                slug: acme
+                name: {name}
+              - slug: globex
+                name: Globex
+          - table: members
+            order: 2
+            unique_key: [email]
+            on_conflict: update
+            rows:
+              - email: alice@example.com
+                tenant_id: "@ref:acme.id"
+"#
+            )
+        };
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            db.conn
+                .execute_batch(
+                    "CREATE TABLE tenants (id TEXT PRIMARY KEY, slug TEXT UNIQUE, name TEXT);
+                     CREATE TABLE members (email TEXT UNIQUE, tenant_id TEXT);",
+                )
+                .unwrap();
+        }
+        let log = test_logger();
+        let run = |yaml: &str| {
+            let plan = SeedPlan::from_yaml(yaml).unwrap();
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(db), "initium_seed".into(), false)
+                .execute(&plan)
+                .unwrap();
+        };
+        let read = || -> (String, String, String, String) {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            db.conn
+                .query_row(
+                    "SELECT t.id, t.name, m.tenant_id, g.id FROM tenants t, members m, tenants g \
+                     WHERE t.slug = 'acme' AND g.slug = 'globex'",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+                )
+                .unwrap()
+        };
+
+        run(&spec("once", "Acme"));
+        let (acme_id, _, member_tenant, globex_id) = read();
+        assert_eq!(acme_id.len(), 36);
+        assert_eq!(&acme_id[14..15], "4");
+        assert_ne!(acme_id, globex_id);
+        assert_eq!(member_tenant, acme_id);
+
+        // Re-applying the changed spec updates the row but keeps its id.
+        run(&spec("once", "Acme Corp"));
+        assert_eq!(
+            read(),
+            (
+                acme_id.clone(),
+                "Acme Corp".into(),
+                acme_id.clone(),
+                globex_id.clone()
+            )
+        );
+
+        // Reconcile mode adopts the rows and resolves refs from the stored ids.
+        run(&spec("reconcile", "Acme Inc"));
+        assert_eq!(
+            read(),
+            (acme_id.clone(), "Acme Inc".into(), acme_id, globex_id)
+        );
+    }
+
+    #[test]
+    fn test_sync_sequence_after_explicit_ids() {
+        let yaml = r#"
+database:
+  driver: sqlite
+phases:
+  - name: phase1
+    seed_sets:
+      - name: plans
+        tables:
+          - table: plans
+            sync_sequence: id
+            rows:
+              - id: 42
+                name: pro
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute(
+                "CREATE TABLE plans (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)",
+                [],
+            )
+            .unwrap();
+        let log = test_logger();
+        SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+            .execute(&plan)
+            .unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        db.conn
+            .execute("INSERT INTO plans (name) VALUES ('team')", [])
+            .unwrap();
+        assert_eq!(db.conn.last_insert_rowid(), 43);
+    }
+
     #[test]
     fn test_multiple_references_same_table() {
         let yaml = r#"
@@ -1337,7 +2913,7 @@ phases:
     }
 
     #[test]
-    fn test_numeric_and_boolean_values() {
+    fn test_lookup_existing_rows() {
         let yaml = r#"
 database:
   driver: sqlite
@@ -1345,24 +2921,32 @@ database:
 phases:
   - name: phase1
     seed_sets:
-      - name: types
+      - name: employees
+        mode: reconcile
         tables:
-          - table: config
+          - table: employees
+            unique_key: [email]
             rows:
-              - key: max_retries
-                value: 5
-              - key: debug
-                value: true
+              - name: Alice
+                email: alice@example.com
+                department_id: "@lookup:departments.id WHERE name='R&D' AND id=7"
+              - name: Bob
+                email: bob@example.com
+                department_id: "@lookup:departments.id WHERE name='Bob''s team'"
 "#;
         let plan = SeedPlan::from_yaml(yaml).unwrap();
         let dir = tempfile::TempDir::new().unwrap();
         let db_path = dir.path().join("test.db");
         let db_path_str = db_path.to_str().unwrap();
 
+        // Rows created outside the seed spec, e.g. by a migration.
         let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        setup_db_with_tables(&sqlite);
         sqlite
             .conn
-            .execute("CREATE TABLE config (key TEXT, value TEXT)", [])
+            .execute_batch(
+                "INSERT INTO departments (id, name) VALUES (7, 'R&D'), (9, 'Bob''s team');",
+            )
             .unwrap();
 
         let log = test_logger();
@@ -1370,26 +2954,52 @@ phases:
         executor.execute(&plan).unwrap();
 
         let db = SqliteDb::connect(db_path_str).unwrap();
-        let count: i64 = db
-            .conn
-            .query_row("SELECT COUNT(*) FROM config", [], |r| r.get(0))
-            .unwrap();
-        assert_eq!(count, 2);
-
-        let rows: Vec<(String, String)> = db
+        let dept_ids: Vec<i64> = db
             .conn
-            .prepare("SELECT key, value FROM config ORDER BY key")
+            .prepare("SELECT department_id FROM employees ORDER BY name")
             .unwrap()
-            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .query_map([], |r| r.get(0))
             .unwrap()
             .map(|r| r.unwrap())
             .collect();
-        assert_eq!(rows[0], ("debug".to_string(), "true".to_string()));
-        assert_eq!(rows[1], ("max_retries".to_string(), "5".to_string()));
+        assert_eq!(dept_ids, vec![7, 9]);
+
+        // A moved target is picked up although the spec did not change.
+        db.conn
+            .execute_batch("UPDATE departments SET id = 8 WHERE name = 'Bob''s team';")
+            .unwrap();
+        let mut executor = SeedExecutor::new(&log, Box::new(db), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let bob_dept: i64 = db
+            .conn
+            .query_row(
+                "SELECT department_id FROM employees WHERE name = 'Bob'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(bob_dept, 8);
     }
 
     #[test]
-    fn test_basic_phase_execution() {
+    fn test_lookup_without_match() {
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: phase1
+    seed_sets:
+      - name: employees
+        tables:
+          - table: employees
+            rows:
+              - name: Alice
+                department_id: "@lookup:departments.id WHERE name='Missing'"
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
         let dir = tempfile::TempDir::new().unwrap();
         let db_path = dir.path().join("test.db");
         let db_path_str = db_path.to_str().unwrap();
@@ -1397,6 +3007,14 @@ phases:
         let sqlite = SqliteDb::connect(db_path_str).unwrap();
         setup_db_with_tables(&sqlite);
 
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(err.contains("matched no row"), "{}", err);
+    }
+
+    #[test]
+    fn test_numeric_and_boolean_values() {
         let yaml = r#"
 database:
   driver: sqlite
@@ -1404,83 +3022,676 @@ database:
 phases:
   - name: phase1
     seed_sets:
-      - name: initial
+      - name: types
         tables:
-          - table: departments
+          - table: config
             rows:
-              - name: Engineering
-              - name: Sales
+              - key: max_retries
+                value: 5
+              - key: debug
+                value: true
+              - key: ratio
+                value: 0.5
+              - key: version
+                value: "5"
+              - key: owner
+                value: ~
 "#;
         let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute("CREATE TABLE config (key TEXT, value)", [])
+            .unwrap();
+
         let log = test_logger();
         let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
         executor.execute(&plan).unwrap();
 
+        // `value` has no declared type, so SQLite keeps the bound storage class.
         let db = SqliteDb::connect(db_path_str).unwrap();
-        let count: i64 = db
+        let rows: Vec<(String, String, String)> = db
             .conn
-            .query_row("SELECT COUNT(*) FROM departments", [], |r| r.get(0))
-            .unwrap();
-        assert_eq!(count, 2);
+            .prepare("SELECT key, typeof(value), CAST(value AS TEXT) FROM config ORDER BY key")
+            .unwrap()
+            .query_map([], |r| {
+                Ok((
+                    r.get(0)?,
+                    r.get(1)?,
+                    r.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                ))
+            })
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        let row = |k: &str, t: &str, v: &str| (k.to_string(), t.to_string(), v.to_string());
+        assert_eq!(
+            rows,
+            vec![
+                row("debug", "integer", "1"),
+                row("max_retries", "integer", "5"),
+                row("owner", "null", ""),
+                row("ratio", "real", "0.5"),
+                row("version", "text", "5"),
+            ]
+        );
     }
 
     #[test]
-    fn test_multiple_phases() {
-        let dir = tempfile::TempDir::new().unwrap();
-        let db_path = dir.path().join("test.db");
-        let db_path_str = db_path.to_str().unwrap();
-
-        let sqlite = SqliteDb::connect(db_path_str).unwrap();
-        setup_db_with_tables(&sqlite);
-
+    fn test_structured_values_stored_as_json() {
         let yaml = r#"
 database:
   driver: sqlite
   url: ":memory:"
 phases:
   - name: phase1
-    order: 1
-    seed_sets:
-      - name: depts
-        tables:
-          - table: departments
-            auto_id:
-              column: id
-            rows:
-              - _ref: dept_eng
-                name: Engineering
-  - name: phase2
-    order: 2
     seed_sets:
-      - name: employees
+      - name: json
         tables:
-          - table: employees
+          - table: services
             rows:
-              - name: Alice
-                email: alice@example.com
-                department_id: "@ref:dept_eng.id"
+              - name: api
+                settings:
+                  replicas: 3
+                  ports: [80, 443]
+                  tls: { enabled: true, issuer: ~ }
+                tags: [web, "v2"]
+                owner: null
 "#;
         let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute(
+                "CREATE TABLE services (name TEXT, settings TEXT, tags TEXT, owner TEXT)",
+                [],
+            )
+            .unwrap();
+
         let log = test_logger();
         let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
         executor.execute(&plan).unwrap();
 
         let db = SqliteDb::connect(db_path_str).unwrap();
-        let dept_count: i64 = db
-            .conn
-            .query_row("SELECT COUNT(*) FROM departments", [], |r| r.get(0))
-            .unwrap();
-        assert_eq!(dept_count, 1);
-
-        let emp_count: i64 = db
+        let (settings, tags, port, owner_null): (String, String, i64, bool) = db
             .conn
-            .query_row("SELECT COUNT(*) FROM employees", [], |r| r.get(0))
+            .query_row(
+                "SELECT settings, tags, json_extract(settings, '$.ports[1]'), owner IS NULL FROM services",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
             .unwrap();
-        assert_eq!(emp_count, 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&settings).unwrap(),
+            serde_json::json!({"replicas": 3, "ports": [80, 443], "tls": {"enabled": true, "issuer": null}})
+        );
+        assert_eq!(tags, r#"["web","v2"]"#);
+        assert_eq!(port, 443);
+        assert!(owner_null);
+    }
 
-        let dept_id: i64 = db
-            .conn
-            .query_row("SELECT id FROM departments", [], |r| r.get(0))
+    #[test]
+    fn test_on_conflict_update_upserts_changed_rows() {
+        let spec = |price: u32| {
+            format!(
+                r#"
+database:
+  driver: sqlite
+phases:
+  - name: phase1
+    seed_sets:
+      - name: pricing
+        tables:
+          - table: prices
+            unique_key: [sku]
+            on_conflict: update
+            auto_id:
+              column: id
+            rows:
+              - _ref: basic
+                sku: basic
+                price: {price}
+          - table: plans
+            order: 1
+            unique_key: [name]
+            rows:
+              - name: starter
+                price_id: "@ref:basic.id"
+"#
+            )
+        };
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        {
+            let sqlite = SqliteDb::connect(db_path_str).unwrap();
+            sqlite
+                .conn
+                .execute_batch(
+                    "CREATE TABLE prices (id INTEGER PRIMARY KEY, sku TEXT UNIQUE, price INTEGER);
+                     INSERT INTO prices (sku, price) VALUES ('legacy', 0), ('basic', 1);
+                     CREATE TABLE plans (name TEXT UNIQUE, price_id INTEGER);",
+                )
+                .unwrap();
+        }
+
+        let log = test_logger();
+        let run = |price: u32| {
+            let plan = SeedPlan::from_yaml(&spec(price)).unwrap();
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(db), "initium_seed".into(), false)
+                .execute(&plan)
+                .unwrap();
+        };
+        let read = || {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            db.conn
+                .query_row(
+                    "SELECT p.id, p.price, (SELECT COUNT(*) FROM prices), pl.price_id \
+                     FROM prices p, plans pl WHERE p.sku = 'basic'",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+                )
+                .unwrap()
+        };
+
+        // The existing row is updated in place and its id is used for @ref.
+        run(5);
+        let state: (i64, i64, i64, i64) = read();
+        assert_eq!(state, (2, 5, 2, 2));
+
+        // Unchanged spec: the applied seed set is skipped.
+        {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            db.conn
+                .execute("UPDATE prices SET price = 99 WHERE sku = 'basic'", [])
+                .unwrap();
+        }
+        run(5);
+        assert_eq!(read().1, 99);
+
+        // Changed spec: the seed set is re-applied and the row updated.
+        run(7);
+        assert_eq!(read(), (2, 7, 2, 2));
+    }
+
+    #[test]
+    fn test_rows_inserted_in_batches() {
+        let yaml = r#"
+database:
+  driver: sqlite
+phases:
+  - name: phase1
+    seed_sets:
+      - name: items
+        tables:
+          - table: items
+            unique_key: [name]
+            batch_size: 2
+            rows:
+              - name: a
+              - name: a
+              - name: b
+              - name: c
+                qty: 1
+              - name: d
+                qty: 2
+              - name: e
+                qty: 3
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute("CREATE TABLE items (name TEXT UNIQUE, qty INTEGER)", [])
+            .unwrap();
+
+        #[derive(Clone)]
+        struct SharedBuf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl Write for SharedBuf {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let buf = SharedBuf(Default::default());
+        let log = Logger::new(Box::new(buf.clone()), true, Level::Info);
+        SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+            .execute(&plan)
+            .unwrap();
+
+        let output = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let batches: Vec<serde_json::Value> = output
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .filter(|v: &serde_json::Value| v["msg"] == "inserted rows")
+            .collect();
+        let ranges: Vec<&str> = batches
+            .iter()
+            .map(|b| b["rows"].as_str().unwrap())
+            .collect();
+        // The duplicate row 2 is skipped and row 4 starts a batch with new columns.
+        assert_eq!(ranges, vec!["1-3", "4-5", "6-6"]);
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let total: i64 = db
+            .conn
+            .query_row(
+                "SELECT COALESCE(SUM(qty), 0) + COUNT(*) FROM items",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(total, 6 + 5);
+    }
+
+    #[test]
+    fn test_on_conflict_error_fails_on_existing_row() {
+        let yaml = r#"
+database:
+  driver: sqlite
+phases:
+  - name: phase1
+    seed_sets:
+      - name: users
+        tables:
+          - table: users
+            unique_key: [email]
+            on_conflict: error
+            rows:
+              - email: alice@example.com
+                name: Alice
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let sqlite = SqliteDb::connect(":memory:").unwrap();
+        sqlite
+            .conn
+            .execute_batch(
+                "CREATE TABLE users (email TEXT UNIQUE, name TEXT);
+                 INSERT INTO users VALUES ('alice@example.com', 'Old');",
+            )
+            .unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(
+            err.contains(
+                "row 1 in table 'users' conflicts with an existing row on unique_key (email)"
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_phase_sql_runs_before_seed_sets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("sql")).unwrap();
+        std::fs::write(
+            dir.path().join("sql/views.sql"),
+            "CREATE VIEW IF NOT EXISTS active_users AS SELECT name FROM users WHERE active = 1;",
+        )
+        .unwrap();
+        let yaml = r#"
+database:
+  driver: sqlite
+phases:
+  - name: schema
+    sql:
+      - CREATE TABLE IF NOT EXISTS users (name TEXT UNIQUE, active INTEGER)
+      - file: sql/views.sql
+    seed_sets:
+      - name: users
+        tables:
+          - table: users
+            unique_key: [name]
+            rows:
+              - name: alice
+                active: true
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let log = test_logger();
+        for _ in 0..2 {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(db), "initium_seed".into(), false)
+                .with_base_dir(dir.path().to_path_buf())
+                .execute(&plan)
+                .unwrap();
+        }
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let names: Vec<String> = db
+            .conn
+            .prepare("SELECT name FROM active_users")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(names, vec!["alice"]);
+    }
+
+    #[test]
+    fn test_scripts_applied_once_in_lexical_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let sql_dir = dir.path().join("sql");
+        std::fs::create_dir(&sql_dir).unwrap();
+        std::fs::write(
+            sql_dir.join("002_data.sql"),
+            "INSERT INTO events VALUES ('002');",
+        )
+        .unwrap();
+        std::fs::write(
+            sql_dir.join("001_schema.sql"),
+            "CREATE TABLE events (name TEXT); INSERT INTO events VALUES ('001');",
+        )
+        .unwrap();
+        std::fs::write(sql_dir.join("README.md"), "not a script").unwrap();
+        let yaml = r#"
+database:
+  driver: sqlite
+phases:
+  - name: legacy
+    seed_sets:
+      - name: scripts
+        scripts:
+          dir: sql
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let log = test_logger();
+        let run = || {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(db), "initium_seed".into(), false)
+                .with_base_dir(dir.path().to_path_buf())
+                .execute(&plan)
+                .unwrap();
+        };
+        let events = || -> Vec<String> {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            let mut stmt = db
+                .conn
+                .prepare("SELECT name FROM events ORDER BY rowid")
+                .unwrap();
+            let names = stmt
+                .query_map([], |r| r.get(0))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect();
+            names
+        };
+
+        run();
+        assert_eq!(events(), vec!["001", "002"]);
+
+        // Applied scripts are skipped, even if edited; new scripts run.
+        std::fs::write(
+            sql_dir.join("002_data.sql"),
+            "INSERT INTO events VALUES ('002-edited');",
+        )
+        .unwrap();
+        std::fs::write(
+            sql_dir.join("003_more.sql"),
+            "INSERT INTO events VALUES ('003');",
+        )
+        .unwrap();
+        run();
+        assert_eq!(events(), vec!["001", "002", "003"]);
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let tracked: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM initium_seed WHERE seed_set LIKE 'scripts/%'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(tracked, 3);
+
+        // With on_change: reapply, the edited script runs again once.
+        let plan = SeedPlan::from_yaml(
+            &yaml.replace("scripts:\n", "on_change: reapply\n        scripts:\n"),
+        )
+        .unwrap();
+        for _ in 0..2 {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(db), "initium_seed".into(), false)
+                .with_base_dir(dir.path().to_path_buf())
+                .execute(&plan)
+                .unwrap();
+        }
+        assert_eq!(events(), vec!["001", "002", "003", "002-edited"]);
+    }
+
+    #[test]
+    fn test_on_change_policies() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        setup_db_with_tables(&SqliteDb::connect(db_path_str).unwrap());
+
+        let spec = |on_change: &str, names: &[&str]| {
+            let rows: String = names
+                .iter()
+                .map(|n| format!("\n              - name: {}", n))
+                .collect();
+            SeedPlan::from_yaml(&format!(
+                r#"
+database:
+  driver: sqlite
+phases:
+  - name: phase1
+    seed_sets:
+      - name: depts
+        {}
+        tables:
+          - table: departments
+            unique_key: [name]
+            rows:{}
+"#,
+                on_change, rows
+            ))
+            .unwrap()
+        };
+        let log = test_logger();
+        let run = |plan: &SeedPlan| {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(db), "initium_seed".into(), false).execute(plan)
+        };
+        let names = || -> Vec<String> {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            let mut stmt = db
+                .conn
+                .prepare("SELECT name FROM departments ORDER BY id")
+                .unwrap();
+            let names = stmt
+                .query_map([], |r| r.get(0))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect();
+            names
+        };
+
+        run(&spec("", &["Engineering"])).unwrap();
+        let changed = &["Engineering", "Sales"];
+
+        // Without on_change, a changed seed set is skipped with a warning.
+        run(&spec("", changed)).unwrap();
+        run(&spec("on_change: ignore", changed)).unwrap();
+        assert_eq!(names(), vec!["Engineering"]);
+
+        let err = run(&spec("on_change: error", changed)).unwrap_err();
+        assert!(
+            err.contains("seed set 'depts' changed after it was applied (on_change: error)"),
+            "{}",
+            err
+        );
+
+        run(&spec("on_change: reapply", changed)).unwrap();
+        assert_eq!(names(), vec!["Engineering", "Sales"]);
+        // The new content is recorded, so an unchanged run is a no-op.
+        run(&spec("on_change: error", changed)).unwrap();
+
+        // Entries applied before hashes were recorded adopt the current content.
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        db.conn
+            .execute("UPDATE initium_seed SET content_hash = NULL", [])
+            .unwrap();
+        run(&spec("on_change: error", &["Marketing"])).unwrap();
+        let err = run(&spec("on_change: error", changed)).unwrap_err();
+        assert!(err.contains("changed after it was applied"), "{}", err);
+        assert_eq!(names(), vec!["Engineering", "Sales"]);
+    }
+
+    #[test]
+    fn test_phase_sql_failure_rolls_back() {
+        let yaml = r#"
+database:
+  driver: sqlite
+phases:
+  - name: setup
+    sql:
+      - INSERT INTO audit VALUES ('first')
+      - INSERT INTO missing_table VALUES (1)
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute("CREATE TABLE audit (event TEXT)", [])
+            .unwrap();
+
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(
+            err.contains("phase 'setup' SQL failed: sql[2]") && err.contains("missing_table"),
+            "{}",
+            err
+        );
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM audit", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_basic_phase_execution() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        setup_db_with_tables(&sqlite);
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: phase1
+    seed_sets:
+      - name: initial
+        tables:
+          - table: departments
+            rows:
+              - name: Engineering
+              - name: Sales
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM departments", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_multiple_phases() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        setup_db_with_tables(&sqlite);
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: phase1
+    order: 1
+    seed_sets:
+      - name: depts
+        tables:
+          - table: departments
+            auto_id:
+              column: id
+            rows:
+              - _ref: dept_eng
+                name: Engineering
+  - name: phase2
+    order: 2
+    seed_sets:
+      - name: employees
+        tables:
+          - table: employees
+            rows:
+              - name: Alice
+                email: alice@example.com
+                department_id: "@ref:dept_eng.id"
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let dept_count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM departments", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(dept_count, 1);
+
+        let emp_count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM employees", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(emp_count, 1);
+
+        let dept_id: i64 = db
+            .conn
+            .query_row("SELECT id FROM departments", [], |r| r.get(0))
             .unwrap();
         let emp_dept_id: i64 = db
             .conn
@@ -1490,104 +3701,827 @@ phases:
     }
 
     #[test]
-    fn test_wait_for_existing_table() {
+    fn test_wait_for_existing_table() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        setup_db_with_tables(&sqlite);
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: wait_and_seed
+    timeout: 2
+    wait_for:
+      - type: table
+        name: departments
+    seed_sets:
+      - name: data
+        tables:
+          - table: departments
+            rows:
+              - name: Engineering
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM departments", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_wait_for_timeout() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: will_timeout
+    timeout: 1
+    wait_for:
+      - type: table
+        name: nonexistent_table
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let result = executor.execute(&plan);
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(
+            err.contains("timeout"),
+            "error should mention timeout: {}",
+            err
+        );
+        assert!(err.contains("nonexistent_table"));
+    }
+
+    #[test]
+    fn test_wait_for_per_object_timeout() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: per_obj_timeout
+    timeout: 60
+    wait_for:
+      - type: table
+        name: missing_table
+        timeout: 1
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let result = executor.execute(&plan);
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(err.contains("timeout after 1s"));
+    }
+
+    #[test]
+    fn test_wait_for_row() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap().to_string();
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: after_migration
+    wait_for:
+      - type: row
+        table: schema_migrations
+        where:
+          version: "2024_10_01"
+          dirty: false
+        timeout: 10
+    seed_sets:
+      - name: s
+        tables:
+          - table: schema_migrations
+            rows:
+              - version: seeded
+                dirty: false
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+
+        // The migration creates the table and records the version later.
+        let migrator_path = db_path_str.clone();
+        let migrator = std::thread::spawn(move || {
+            let db = SqliteDb::connect(&migrator_path).unwrap();
+            std::thread::sleep(Duration::from_millis(600));
+            db.conn
+                .execute_batch(
+                    "CREATE TABLE schema_migrations (version TEXT, dirty BOOLEAN);
+                     INSERT INTO schema_migrations VALUES ('2024_09_01', 0);",
+                )
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(600));
+            db.conn
+                .execute_batch("INSERT INTO schema_migrations VALUES ('2024_10_01', 0);")
+                .unwrap();
+        });
+
+        let sqlite = SqliteDb::connect(&db_path_str).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let started = Instant::now();
+        executor.execute(&plan).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(1200));
+        migrator.join().unwrap();
+
+        let missing = yaml
+            .replace("2024_10_01", "2025_01_01")
+            .replace("timeout: 10", "timeout: 1");
+        let plan = SeedPlan::from_yaml(&missing).unwrap();
+        let sqlite = SqliteDb::connect(&db_path_str).unwrap();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(
+            err.contains("timeout after 1s waiting for row 'schema_migrations WHERE dirty=false AND version='2025_01_01''"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_wait_for_column() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap().to_string();
+        let sqlite = SqliteDb::connect(&db_path_str).unwrap();
+        setup_db_with_tables(&sqlite);
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: after_migration
+    wait_for:
+      - type: column
+        table: main.employees
+        column: department_id
+      - type: column
+        table: employees
+        column: title
+        timeout: 10
+    seed_sets:
+      - name: s
+        tables:
+          - table: employees
+            rows:
+              - name: Alice
+                title: Engineer
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+
+        let migrator_path = db_path_str.clone();
+        let migrator = std::thread::spawn(move || {
+            let db = SqliteDb::connect(&migrator_path).unwrap();
+            std::thread::sleep(Duration::from_millis(600));
+            db.conn
+                .execute_batch("ALTER TABLE employees ADD COLUMN title TEXT;")
+                .unwrap();
+        });
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
+        migrator.join().unwrap();
+
+        let missing = yaml
+            .replace("column: title", "column: nickname")
+            .replace("timeout: 10", "timeout: 1");
+        let plan = SeedPlan::from_yaml(&missing).unwrap();
+        let sqlite = SqliteDb::connect(&db_path_str).unwrap();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(
+            err.contains("waiting for column 'employees.nickname'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_hooks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        std::fs::write(
+            dir.path().join("audit.sql"),
+            "CREATE TABLE IF NOT EXISTS audit (event TEXT);",
+        )
+        .unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        setup_db_with_tables(&sqlite);
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: setup
+    before:
+      - file: audit.sql
+      - "INSERT INTO audit (event) VALUES ('phase before')"
+    after:
+      - "INSERT INTO audit (event) VALUES ('phase after')"
+      - command: ["touch", "done.marker"]
+    seed_sets:
+      - name: departments
+        before:
+          - "INSERT INTO audit (event) VALUES ('set before')"
+        after:
+          - "INSERT INTO audit (event) SELECT 'set after: ' || COUNT(*) FROM departments"
+        tables:
+          - table: departments
+            rows:
+              - name: Engineering
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+            .with_base_dir(dir.path().to_path_buf());
+        executor.execute(&plan).unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let mut stmt = db.conn.prepare("SELECT event FROM audit").unwrap();
+        let events: Vec<String> = stmt
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec!["phase before", "set before", "set after: 1", "phase after"]
+        );
+        assert!(dir.path().join("done.marker").exists());
+
+        let failing = yaml.replace("[\"touch\", \"done.marker\"]", "[\"false\"]");
+        let plan = SeedPlan::from_yaml(&failing).unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+            .with_base_dir(dir.path().to_path_buf());
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(
+            err.contains("phase 'setup' after hook failed: command \"false\" exited with code 1"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_transaction_scopes_and_continue_on_error() {
+        let spec = |transaction: &str, continue_on_error: bool| {
+            format!(
+                r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: setup
+    transaction: {}
+    seed_sets:
+      - name: departments
+        order: 1
+        tables:
+          - table: departments
+            rows:
+              - name: Engineering
+      - name: broken
+        order: 2
+        continue_on_error: {}
+        tables:
+          - table: departments
+            rows:
+              - name: Sales
+          - table: missing_table
+            order: 1
+            rows:
+              - name: x
+  - name: later
+    order: 1
+    seed_sets:
+      - name: employees
+        tables:
+          - table: employees
+            rows:
+              - name: Alice
+                email: alice@example.com
+"#,
+                transaction, continue_on_error
+            )
+        };
+        let run = |yaml: &str| {
+            let dir = tempfile::TempDir::new().unwrap();
+            let db_path = dir.path().join("test.db");
+            let db_path_str = db_path.to_str().unwrap();
+            let sqlite = SqliteDb::connect(db_path_str).unwrap();
+            setup_db_with_tables(&sqlite);
+            let plan = SeedPlan::from_yaml(yaml).unwrap();
+            let log = test_logger();
+            let mut executor =
+                SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+            let result = executor.execute(&plan);
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            let mut stmt = db
+                .conn
+                .prepare("SELECT name FROM departments UNION ALL SELECT name FROM employees")
+                .unwrap();
+            let names: Vec<String> = stmt
+                .query_map([], |r| r.get(0))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect();
+            (result, names)
+        };
+
+        let (result, names) = run(&spec("per_set", false));
+        assert!(
+            result.unwrap_err().contains("seed set 'broken' failed"),
+            "expected the failing seed set to abort the plan"
+        );
+        assert_eq!(names, vec!["Engineering"]);
+
+        let (result, names) = run(&spec("per_set", true));
+        result.unwrap();
+        assert_eq!(names, vec!["Engineering", "Alice"]);
+
+        let (result, names) = run(&spec("none", true));
+        result.unwrap();
+        assert_eq!(names, vec!["Engineering", "Sales", "Alice"]);
+
+        let (result, names) = run(&spec("per_phase", false));
+        assert!(result.is_err());
+        assert!(names.is_empty(), "{:?}", names);
+    }
+
+    #[test]
+    fn test_binary_values() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute_batch("CREATE TABLE icons (name TEXT PRIMARY KEY, data BLOB);")
+            .unwrap();
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: setup
+    seed_sets:
+      - name: icons
+        tables:
+          - table: icons
+            unique_key: [name]
+            rows:
+              - name: tagged
+                data: !binary |
+                  AAEC/w==
+              - name: prefixed
+                data: "base64:aGk="
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let blob = |name: &str| -> (String, Vec<u8>) {
+            db.conn
+                .query_row(
+                    "SELECT typeof(data), data FROM icons WHERE name = ?1",
+                    [name],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )
+                .unwrap()
+        };
+        assert_eq!(blob("tagged"), ("blob".into(), vec![0, 1, 2, 255]));
+        assert_eq!(blob("prefixed"), ("blob".into(), b"hi".to_vec()));
+
+        let plan = SeedPlan::from_yaml(&yaml.replace("base64:aGk=", "base64:not base64!")).unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), true);
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(err.contains("binary value: invalid base64"), "{}", err);
+    }
+
+    #[test]
+    fn test_call_steps() {
         let dir = tempfile::TempDir::new().unwrap();
         let db_path = dir.path().join("test.db");
         let db_path_str = db_path.to_str().unwrap();
 
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: setup
+    sql:
+      - call:
+          function: abs
+          args: [-3]
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
         let sqlite = SqliteDb::connect(db_path_str).unwrap();
-        setup_db_with_tables(&sqlite);
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
 
-        let yaml = r#"
+        let plan =
+            SeedPlan::from_yaml(&yaml.replace("function: abs", "procedure: refresh")).unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(
+            err.contains("sql[1]: sqlite has no stored procedures"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_auto_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let spec = |auto_order: bool| {
+            format!(
+                r#"
 database:
   driver: sqlite
   url: ":memory:"
 phases:
-  - name: wait_and_seed
-    timeout: 2
-    wait_for:
-      - type: table
-        name: departments
+  - name: schema
+    sql:
+      - CREATE TABLE IF NOT EXISTS orgs (id INTEGER PRIMARY KEY, name TEXT UNIQUE)
+      - CREATE TABLE IF NOT EXISTS teams (id INTEGER PRIMARY KEY, name TEXT UNIQUE, org_id INTEGER REFERENCES orgs(id))
+      - CREATE TABLE IF NOT EXISTS projects (id INTEGER PRIMARY KEY, name TEXT UNIQUE, team_id INTEGER)
     seed_sets:
-      - name: data
+      - name: all
+        auto_order: {}
         tables:
-          - table: departments
+          - table: projects
+            unique_key: [name]
             rows:
-              - name: Engineering
-"#;
-        let plan = SeedPlan::from_yaml(yaml).unwrap();
+              - name: initium
+                team_id: "@ref:core.id"
+          - table: teams
+            unique_key: [name]
+            rows:
+              - _ref: core
+                id: 7
+                name: core
+                org_id: 1
+          - table: orgs
+            unique_key: [name]
+            rows:
+              - id: 1
+                name: KitStream
+"#,
+                auto_order
+            )
+        };
         let log = test_logger();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
         let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
-        executor.execute(&plan).unwrap();
+        let err = executor
+            .execute(&SeedPlan::from_yaml(&spec(false)).unwrap())
+            .unwrap_err();
+        assert!(err.contains("reference 'core' not found"), "{}", err);
 
-        let db = SqliteDb::connect(db_path_str).unwrap();
-        let count: i64 = db
+        // Teams follow orgs through the foreign key, projects follow teams
+        // through @ref:.
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor
+            .execute(&SeedPlan::from_yaml(&spec(true)).unwrap())
+            .unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        let team: i64 = sqlite
             .conn
-            .query_row("SELECT COUNT(*) FROM departments", [], |r| r.get(0))
+            .query_row("SELECT team_id FROM projects", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(team, 7);
+
+        // --auto-order applies to every seed set; reset clears dependents first.
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), true)
+            .with_auto_order(true)
+            .execute(&SeedPlan::from_yaml(&spec(false)).unwrap())
             .unwrap();
-        assert_eq!(count, 1);
     }
 
     #[test]
-    fn test_wait_for_timeout() {
+    fn test_reset_strategy_truncate() {
         let dir = tempfile::TempDir::new().unwrap();
         let db_path = dir.path().join("test.db");
         let db_path_str = db_path.to_str().unwrap();
 
-        let sqlite = SqliteDb::connect(db_path_str).unwrap();
-
-        let yaml = r#"
+        let spec = |strategy: &str| {
+            format!(
+                r#"
 database:
   driver: sqlite
   url: ":memory:"
 phases:
-  - name: will_timeout
-    timeout: 1
-    wait_for:
-      - type: table
-        name: nonexistent_table
-"#;
-        let plan = SeedPlan::from_yaml(yaml).unwrap();
+  - name: schema
+    sql:
+      - CREATE TABLE IF NOT EXISTS tags (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT UNIQUE)
+    seed_sets:
+      - name: tags
+        reset_strategy: {}
+        tables:
+          - table: tags
+            unique_key: [name]
+            auto_id:
+              column: id
+            rows:
+              - name: a
+              - name: b
+"#,
+                strategy
+            )
+        };
         let log = test_logger();
-        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
-        let result = executor.execute(&plan);
-        assert!(result.is_err());
-        let err = result.unwrap_err();
+        let run = |strategy: &str, reset: bool| {
+            let sqlite = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), reset)
+                .execute(&SeedPlan::from_yaml(&spec(strategy)).unwrap())
+        };
+        let max_id = || -> i64 {
+            let sqlite = SqliteDb::connect(db_path_str).unwrap();
+            sqlite
+                .conn
+                .query_row("SELECT MAX(id) FROM tags", [], |r| r.get(0))
+                .unwrap()
+        };
+
+        run("delete", false).unwrap();
+        run("delete", true).unwrap();
+        assert_eq!(max_id(), 4, "delete keeps the AUTOINCREMENT counter");
+        run("truncate", true).unwrap();
+        assert_eq!(max_id(), 2, "truncate restarts the counter");
+
+        let err = run("truncate_cascade", true).unwrap_err();
         assert!(
-            err.contains("timeout"),
-            "error should mention timeout: {}",
+            err.contains("sqlite has no TRUNCATE ... CASCADE"),
+            "{}",
             err
         );
-        assert!(err.contains("nonexistent_table"));
     }
 
     #[test]
-    fn test_wait_for_per_object_timeout() {
+    fn test_row_when() {
+        std::env::set_var("TEST_ROW_WHEN_ENVIRONMENT", "staging");
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute("CREATE TABLE users (name TEXT PRIMARY KEY)", [])
+            .unwrap();
+        drop(sqlite);
+
+        let plan = SeedPlan::from_yaml(
+            r#"
+database:
+  driver: sqlite
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        tables:
+          - table: users
+            rows:
+              - name: admin
+              - name: demo
+                _when: "{{ env.TEST_ROW_WHEN_ENVIRONMENT == 'staging' }}"
+              - name: tester
+                _when: "{{ env.TEST_ROW_WHEN_ENVIRONMENT == 'production' }}"
+              - name: disabled
+                _when: false
+"#,
+        )
+        .unwrap();
+        let log = test_logger();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+            .execute(&plan)
+            .unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        let names: Vec<String> = sqlite
+            .conn
+            .prepare("SELECT name FROM users ORDER BY name")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(names, vec!["admin", "demo"]);
+
+        let mut plan = plan;
+        plan.phases[0].seed_sets[0].tables[0].rows[1]
+            .insert("_when".into(), serde_yaml::Value::from("maybe"));
+        let sqlite = SqliteDb::connect(":memory:").unwrap();
+        let err = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+            .execute(&plan)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "seed set 's': table 'users': row 2: _when must be true or false, got 'maybe'"
+        );
+    }
+
+    #[test]
+    fn test_managed_table() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute_batch(
+                "CREATE TABLE roles (name TEXT PRIMARY KEY);
+                 INSERT INTO roles VALUES ('legacy');",
+            )
+            .unwrap();
+        drop(sqlite);
+
+        let log = test_logger();
+        let run = |roles: &[&str]| {
+            let rows: String = roles
+                .iter()
+                .map(|r| format!("\n              - name: {}", r))
+                .collect();
+            let plan = SeedPlan::from_yaml(&format!(
+                r#"
+database:
+  driver: sqlite
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        tables:
+          - table: roles
+            unique_key: [name]
+            managed: true
+            rows:{}
+"#,
+                rows
+            ))
+            .unwrap();
+            let sqlite = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+                .execute(&plan)
+                .unwrap();
+            let sqlite = SqliteDb::connect(db_path_str).unwrap();
+            let mut stmt = sqlite
+                .conn
+                .prepare("SELECT name FROM roles ORDER BY name")
+                .unwrap();
+            stmt.query_map([], |r| r.get::<_, String>(0))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        assert_eq!(run(&["admin", "viewer"]), vec!["admin", "legacy", "viewer"]);
+        // Removed rows written by initium are deleted; rows it found in the
+        // table are kept.
+        assert_eq!(run(&["admin", "editor"]), vec!["admin", "editor", "legacy"]);
+        assert_eq!(run(&[]), vec!["legacy"]);
+    }
+
+    #[test]
+    fn test_tracking_metadata() {
         let dir = tempfile::TempDir::new().unwrap();
         let db_path = dir.path().join("test.db");
         let db_path_str = db_path.to_str().unwrap();
+        // A tracking table from before metadata was recorded.
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        sqlite
+            .conn
+            .execute_batch(
+                "CREATE TABLE initium_seed (seed_set TEXT PRIMARY KEY, applied_at TEXT);
+                 CREATE TABLE t (id INTEGER PRIMARY KEY)",
+            )
+            .unwrap();
+        drop(sqlite);
+
+        let plan = SeedPlan::from_yaml(
+            r#"
+database:
+  driver: sqlite
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        tables:
+          - table: t
+            rows:
+              - id: 1
+"#,
+        )
+        .unwrap();
+        let log = test_logger();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+            .with_spec_checksum("abc123".into())
+            .execute(&plan)
+            .unwrap();
 
         let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        let (version, checksum, duration): (String, String, i64) = sqlite
+            .conn
+            .query_row(
+                "SELECT initium_version, spec_checksum, duration_ms FROM initium_seed WHERE seed_set = 's'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(checksum, "abc123");
+        assert!(duration >= 0);
+    }
 
+    #[test]
+    fn test_auto_order_cycle() {
         let yaml = r#"
 database:
   driver: sqlite
   url: ":memory:"
 phases:
-  - name: per_obj_timeout
-    timeout: 60
-    wait_for:
-      - type: table
-        name: missing_table
-        timeout: 1
+  - name: schema
+    sql:
+      - CREATE TABLE a (id INTEGER PRIMARY KEY, b_id INTEGER REFERENCES b(id))
+      - CREATE TABLE b (id INTEGER PRIMARY KEY, a_id INTEGER REFERENCES a(id))
+    seed_sets:
+      - name: cyclic
+        auto_order: true
+        tables:
+          - table: a
+            rows:
+              - id: 1
+          - table: b
+            rows:
+              - id: 1
 "#;
         let plan = SeedPlan::from_yaml(yaml).unwrap();
         let log = test_logger();
+        let sqlite = SqliteDb::connect(":memory:").unwrap();
         let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
-        let result = executor.execute(&plan);
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(err.contains("timeout after 1s"));
+        let err = executor.execute(&plan).unwrap_err();
+        assert!(
+            err.contains("seed set 'cyclic': tables a, b reference each other"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_same_table() {
+        assert!(same_table("users", "users"));
+        assert!(same_table("public.users", "users"));
+        assert!(same_table("users", "\"app\".\"Users\""));
+        assert!(same_table("dbo.users", "dbo.users"));
+        assert!(!same_table("audit.users", "app.users"));
+        assert!(!same_table("users", "user"));
     }
 
     #[test]
@@ -1626,6 +4560,131 @@ phases:
         );
     }
 
+    #[test]
+    fn test_phase_database_uses_its_own_connection() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let app_path = dir.path().join("app.db");
+        let other_path = dir.path().join("other.db");
+        setup_db_with_tables(&SqliteDb::connect(app_path.to_str().unwrap()).unwrap());
+        setup_db_with_tables(&SqliteDb::connect(other_path.to_str().unwrap()).unwrap());
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: app
+    seed_sets:
+      - name: app_departments
+        tables:
+          - table: departments
+            rows:
+              - name: Engineering
+  - name: other
+    order: 1
+    database: other
+    seed_sets:
+      - name: other_departments
+        tables:
+          - table: departments
+            rows:
+              - name: Sales
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        for _ in 0..2 {
+            let calls = std::cell::RefCell::new(Vec::new());
+            let other = other_path.to_str().unwrap().to_string();
+            let sqlite = SqliteDb::connect(app_path.to_str().unwrap()).unwrap();
+            let mut executor =
+                SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false)
+                    .with_connector(Box::new(|connection, database| {
+                        calls
+                            .borrow_mut()
+                            .push(format!("{}/{}", connection, database));
+                        Ok(Some(Box::new(SqliteDb::connect(&other)?)))
+                    }));
+            executor.execute(&plan).unwrap();
+            drop(executor);
+            assert_eq!(calls.into_inner(), vec!["/other"]);
+        }
+
+        let names = |path: &std::path::Path, sql: &str| -> Vec<String> {
+            let db = SqliteDb::connect(path.to_str().unwrap()).unwrap();
+            let mut stmt = db.conn.prepare(sql).unwrap();
+            stmt.query_map([], |r| r.get(0))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        };
+        let departments = "SELECT name FROM departments ORDER BY name";
+        let tracked = "SELECT seed_set FROM initium_seed ORDER BY seed_set";
+        assert_eq!(names(&app_path, departments), vec!["Engineering"]);
+        assert_eq!(names(&other_path, departments), vec!["Sales"]);
+        assert_eq!(names(&app_path, tracked), vec!["app_departments"]);
+        assert_eq!(names(&other_path, tracked), vec!["other_departments"]);
+    }
+
+    #[test]
+    fn test_schema_qualified_tables() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let main_path = dir.path().join("main.db");
+        let aux_path = dir.path().join("aux.db");
+        setup_db_with_tables(&SqliteDb::connect(aux_path.to_str().unwrap()).unwrap());
+        let sqlite = SqliteDb::connect(main_path.to_str().unwrap()).unwrap();
+        sqlite
+            .conn
+            .execute("ATTACH DATABASE ?1 AS aux", [aux_path.to_str().unwrap()])
+            .unwrap();
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: aux
+    wait_for:
+      - type: table
+        name: aux.departments
+    seed_sets:
+      - name: aux_departments
+        tables:
+          - table: aux.departments
+            unique_key: [name]
+            auto_id:
+              column: id
+            rows:
+              - _ref: eng
+                name: Engineering
+          - table: aux.employees
+            order: 1
+            unique_key: [email]
+            rows:
+              - name: Alice
+                email: alice@example.com
+                department_id: "@ref:eng.id"
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
+        drop(executor);
+
+        let aux = SqliteDb::connect(aux_path.to_str().unwrap()).unwrap();
+        let (name, department): (String, String) = aux
+            .conn
+            .query_row(
+                "SELECT e.name, d.name FROM employees e JOIN departments d ON d.id = e.department_id",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (name.as_str(), department.as_str()),
+            ("Alice", "Engineering")
+        );
+    }
+
     #[test]
     fn test_phase_without_seed_sets() {
         let dir = tempfile::TempDir::new().unwrap();
@@ -2224,6 +5283,283 @@ phases:
         assert_eq!(count, 0, "dry-run should not modify the database");
     }
 
+    #[test]
+    fn test_dry_run_leaves_fresh_database_untouched() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: schema
+    order: 1
+    sql:
+      - CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)
+  - name: data
+    order: 2
+    seed_sets:
+      - name: items
+        tables:
+          - table: items
+            auto_id:
+              column: id
+            rows:
+              - _ref: first
+                name: one
+      - name: managed
+        mode: reconcile
+        tables:
+          - table: items
+            unique_key: [name]
+            rows:
+              - name: two
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+
+        let db1 = SqliteDb::connect(db_path_str).unwrap();
+        let mut exec =
+            SeedExecutor::new(&log, Box::new(db1), "initium_seed".into(), true).with_dry_run(true);
+        exec.execute(&plan).unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let tables: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(tables, 0, "dry-run should not create any table");
+    }
+
+    #[test]
+    fn test_dry_run_reset_keeps_data() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        setup_db_with_tables(&sqlite);
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: phase1
+    seed_sets:
+      - name: depts
+        tables:
+          - table: departments
+            rows:
+              - name: Engineering
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+
+        let db1 = SqliteDb::connect(db_path_str).unwrap();
+        let mut exec = SeedExecutor::new(&log, Box::new(db1), "initium_seed".into(), false);
+        exec.execute(&plan).unwrap();
+
+        let db2 = SqliteDb::connect(db_path_str).unwrap();
+        let mut exec =
+            SeedExecutor::new(&log, Box::new(db2), "initium_seed".into(), true).with_dry_run(true);
+        exec.execute(&plan).unwrap();
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM departments", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 1, "dry-run reset should not delete rows");
+        let marks: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM initium_seed", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(marks, 1, "dry-run reset should keep the tracking entry");
+    }
+
+    #[test]
+    fn test_filter_selects() {
+        let f = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        assert!(filter_selects(&[], "demo"));
+        assert!(filter_selects(&f(&["demo_*"]), "demo_users"));
+        assert!(!filter_selects(&f(&["demo_*"]), "users"));
+        assert!(filter_selects(&f(&["!demo_*"]), "users"));
+        assert!(!filter_selects(&f(&["!demo_*"]), "demo_users"));
+        assert!(!filter_selects(&f(&["*users", "!demo_*"]), "demo_users"));
+        assert!(filter_selects(&f(&["core", "*users"]), "core"));
+    }
+
+    #[test]
+    fn test_phase_and_seed_set_filters() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        setup_db_with_tables(&SqliteDb::connect(db_path_str).unwrap());
+
+        let yaml = r#"
+database:
+  driver: sqlite
+phases:
+  - name: base
+    order: 1
+    seed_sets:
+      - name: departments
+        tables:
+          - table: departments
+            unique_key: [name]
+            auto_id:
+              column: id
+            rows:
+              - _ref: eng
+                name: Engineering
+  - name: people
+    order: 2
+    seed_sets:
+      - name: core_employees
+        tables:
+          - table: employees
+            unique_key: [email]
+            rows:
+              - name: Alice
+                email: alice@example.com
+                department_id: "@ref:eng.id"
+  - name: demo
+    order: 3
+    seed_sets:
+      - name: demo_employees
+        tables:
+          - table: employees
+            unique_key: [email]
+            rows:
+              - name: Demo
+                email: demo@example.com
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let log = test_logger();
+        let run = |phases: &[&str], seed_sets: &[&str]| {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            SeedExecutor::new(&log, Box::new(db), "initium_seed".into(), false)
+                .with_phase_filter(phases.iter().map(|p| p.to_string()).collect())
+                .with_seed_set_filter(seed_sets.iter().map(|p| p.to_string()).collect())
+                .execute(&plan)
+        };
+        let emails = || -> Vec<(String, Option<i64>)> {
+            let db = SqliteDb::connect(db_path_str).unwrap();
+            let mut stmt = db
+                .conn
+                .prepare("SELECT email, department_id FROM employees ORDER BY id")
+                .unwrap();
+            let rows = stmt
+                .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect();
+            rows
+        };
+
+        run(&["base"], &[]).unwrap();
+        assert!(emails().is_empty());
+
+        // The skipped departments seed set still provides @ref:eng.
+        run(&[], &["core_*"]).unwrap();
+        assert_eq!(emails(), vec![("alice@example.com".to_string(), Some(1))]);
+
+        run(&["!demo"], &[]).unwrap();
+        assert_eq!(emails().len(), 1);
+        run(&["demo"], &[]).unwrap();
+        assert_eq!(emails().len(), 2);
+
+        let err = run(&["bsae"], &[]).unwrap_err();
+        assert!(err.contains("--phase 'bsae' matches no phase"), "{}", err);
+        let err = run(&[], &["missing"]).unwrap_err();
+        assert!(
+            err.contains("--seed-set 'missing' matches no seed set"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_scoped_reset() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        setup_db_with_tables(&SqliteDb::connect(db_path_str).unwrap());
+
+        let spec = |reset: &str| {
+            SeedPlan::from_yaml(&format!(
+                r#"
+database:
+  driver: sqlite
+phases:
+  - name: phase1
+    seed_sets:
+      - name: departments
+        {}
+        tables:
+          - table: departments
+            rows:
+              - name: Engineering
+      - name: employees
+        tables:
+          - table: employees
+            rows:
+              - name: Alice
+"#,
+                reset
+            ))
+            .unwrap()
+        };
+        let log = test_logger();
+        let db = || SqliteDb::connect(db_path_str).unwrap();
+        let count = |table: &str| -> i64 {
+            db().conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+                .unwrap()
+        };
+
+        let plan = spec("");
+        SeedExecutor::new(&log, Box::new(db()), "initium_seed".into(), false)
+            .execute(&plan)
+            .unwrap();
+        // Rows added outside the spec show which tables were cleared.
+        db().conn
+            .execute_batch(
+                "INSERT INTO departments (name) VALUES ('Manual');
+                 INSERT INTO employees (name) VALUES ('Manual');",
+            )
+            .unwrap();
+
+        SeedExecutor::new(&log, Box::new(db()), "initium_seed".into(), false)
+            .with_reset_sets(vec!["depart*".into()])
+            .execute(&plan)
+            .unwrap();
+        assert_eq!(count("departments"), 1);
+        assert_eq!(count("employees"), 2);
+
+        db().conn
+            .execute("INSERT INTO departments (name) VALUES ('Manual')", [])
+            .unwrap();
+        SeedExecutor::new(&log, Box::new(db()), "initium_seed".into(), false)
+            .execute(&spec("reset: true"))
+            .unwrap();
+        assert_eq!(count("departments"), 1);
+        assert_eq!(count("employees"), 2);
+
+        let err = SeedExecutor::new(&log, Box::new(db()), "initium_seed".into(), false)
+            .with_reset_sets(vec!["missing".into()])
+            .execute(&plan)
+            .unwrap_err();
+        assert!(
+            err.contains("--reset-set 'missing' matches no seed set"),
+            "{}",
+            err
+        );
+    }
+
     #[test]
     fn test_reconcile_cross_seed_set_refs() {
         let dir = tempfile::TempDir::new().unwrap();
@@ -2642,4 +5978,236 @@ phases:
         assert_eq!(value, "new_value"); // Non-ignored column updated
         assert_eq!(updated_at, "2026-01-01"); // Ignored column preserved
     }
+
+    /// Wraps a SQLite database and fails the first `failures` commits with a
+    /// retryable error, like a CockroachDB serialization conflict. Seed lock
+    /// calls are recorded in `locks`.
+    struct FlakyDb {
+        inner: SqliteDb,
+        failures: std::sync::Arc<std::sync::atomic::AtomicU32>,
+        retryable: bool,
+        locks: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl Database for FlakyDb {
+        fn ensure_tracking_table(&mut self, t: &str) -> Result<(), String> {
+            self.inner.ensure_tracking_table(t)
+        }
+        fn is_seed_applied(&mut self, t: &str, s: &str) -> Result<bool, String> {
+            self.inner.is_seed_applied(t, s)
+        }
+        fn mark_seed_applied(&mut self, t: &str, s: &str) -> Result<(), String> {
+            self.inner.mark_seed_applied(t, s)
+        }
+        fn remove_seed_mark(&mut self, t: &str, s: &str) -> Result<(), String> {
+            self.inner.remove_seed_mark(t, s)
+        }
+        fn insert_row(
+            &mut self,
+            t: &str,
+            c: &[String],
+            v: &[SeedValue],
+            a: Option<&str>,
+        ) -> Result<Option<i64>, String> {
+            self.inner.insert_row(t, c, v, a)
+        }
+        fn row_exists(&mut self, t: &str, c: &[String], v: &[SeedValue]) -> Result<bool, String> {
+            self.inner.row_exists(t, c, v)
+        }
+        fn delete_rows(&mut self, t: &str) -> Result<u64, String> {
+            self.inner.delete_rows(t)
+        }
+        fn begin_transaction(&mut self) -> Result<(), String> {
+            self.inner.begin_transaction()
+        }
+        fn commit_transaction(&mut self) -> Result<(), String> {
+            use std::sync::atomic::Ordering;
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err("committing transaction: restart transaction (SQLSTATE 40001)".into());
+            }
+            self.inner.commit_transaction()
+        }
+        fn rollback_transaction(&mut self) -> Result<(), String> {
+            self.inner.rollback_transaction()
+        }
+        fn create_database(&mut self, n: &str) -> Result<(), String> {
+            self.inner.create_database(n)
+        }
+        fn create_schema(&mut self, n: &str) -> Result<(), String> {
+            self.inner.create_schema(n)
+        }
+        fn object_exists(&mut self, o: &str, n: &str) -> Result<bool, String> {
+            self.inner.object_exists(o, n)
+        }
+        fn driver_name(&self) -> &str {
+            "flaky"
+        }
+        fn migrate_tracking_table(&mut self, t: &str) -> Result<(), String> {
+            self.inner.migrate_tracking_table(t)
+        }
+        fn ensure_row_tracking_table(&mut self, t: &str) -> Result<(), String> {
+            self.inner.ensure_row_tracking_table(t)
+        }
+        fn get_seed_hash(&mut self, t: &str, s: &str) -> Result<Option<String>, String> {
+            self.inner.get_seed_hash(t, s)
+        }
+        fn update_seed_entry(&mut self, t: &str, s: &str, h: &str) -> Result<(), String> {
+            self.inner.update_seed_entry(t, s, h)
+        }
+        fn record_seed_metadata(
+            &mut self,
+            t: &str,
+            s: &str,
+            m: &crate::seed::db::SeedMetadata,
+        ) -> Result<(), String> {
+            self.inner.record_seed_metadata(t, s, m)
+        }
+        fn store_tracked_row(
+            &mut self,
+            tt: &str,
+            s: &str,
+            t: &str,
+            k: &str,
+            v: &str,
+        ) -> Result<(), String> {
+            self.inner.store_tracked_row(tt, s, t, k, v)
+        }
+        fn get_tracked_rows(
+            &mut self,
+            tt: &str,
+            s: &str,
+            t: &str,
+        ) -> Result<Vec<(String, String)>, String> {
+            self.inner.get_tracked_rows(tt, s, t)
+        }
+        fn delete_tracked_row(
+            &mut self,
+            tt: &str,
+            s: &str,
+            t: &str,
+            k: &str,
+        ) -> Result<(), String> {
+            self.inner.delete_tracked_row(tt, s, t, k)
+        }
+        fn delete_all_tracked_rows(&mut self, tt: &str, s: &str) -> Result<(), String> {
+            self.inner.delete_all_tracked_rows(tt, s)
+        }
+        fn update_row(
+            &mut self,
+            t: &str,
+            sc: &[String],
+            sv: &[SeedValue],
+            wc: &[String],
+            wv: &[SeedValue],
+        ) -> Result<u64, String> {
+            self.inner.update_row(t, sc, sv, wc, wv)
+        }
+        fn get_row_columns(
+            &mut self,
+            t: &str,
+            kc: &[String],
+            kv: &[SeedValue],
+            fc: &[String],
+        ) -> Result<Option<Vec<String>>, String> {
+            self.inner.get_row_columns(t, kc, kv, fc)
+        }
+        fn delete_row_by_key(
+            &mut self,
+            t: &str,
+            kc: &[String],
+            kv: &[SeedValue],
+        ) -> Result<u64, String> {
+            self.inner.delete_row_by_key(t, kc, kv)
+        }
+        fn is_retryable_error(&self, error: &str) -> bool {
+            self.retryable && error.contains("(SQLSTATE 40001)")
+        }
+        fn acquire_lock(&mut self, name: &str, _timeout: Duration) -> Result<bool, String> {
+            self.locks.lock().unwrap().push(format!("acquire {}", name));
+            Ok(true)
+        }
+        fn release_lock(&mut self, name: &str) -> Result<(), String> {
+            self.locks.lock().unwrap().push(format!("release {}", name));
+            Ok(())
+        }
+    }
+
+    fn run_flaky_seed(
+        failures: u32,
+        retryable: bool,
+    ) -> (Result<(), String>, i64, u32, Vec<String>) {
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: phase1
+    seed_sets:
+      - name: basic
+        tables:
+          - table: departments
+            unique_key: [name]
+            auto_id:
+              column: id
+            rows:
+              - name: Engineering
+              - name: Sales
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path_str = db_path.to_str().unwrap();
+        let sqlite = SqliteDb::connect(db_path_str).unwrap();
+        setup_db_with_tables(&sqlite);
+        let remaining = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(failures));
+        let locks = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let flaky = FlakyDb {
+            inner: sqlite,
+            failures: remaining.clone(),
+            retryable,
+            locks: locks.clone(),
+        };
+
+        let log = test_logger();
+        let mut executor = SeedExecutor::new(&log, Box::new(flaky), "initium_seed".into(), false);
+        let result = executor.execute(&plan);
+
+        let db = SqliteDb::connect(db_path_str).unwrap();
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM departments", [], |r| r.get(0))
+            .unwrap();
+        let locks = locks.lock().unwrap().clone();
+        (
+            result,
+            count,
+            remaining.load(std::sync::atomic::Ordering::SeqCst),
+            locks,
+        )
+    }
+
+    #[test]
+    fn test_retryable_commit_error_is_retried() {
+        let (result, count, remaining, _) = run_flaky_seed(2, true);
+        result.unwrap();
+        assert_eq!(remaining, 0);
+        assert_eq!(count, 2, "rows must be written exactly once");
+    }
+
+    #[test]
+    fn test_non_retryable_commit_error_fails() {
+        let (result, count, remaining, locks) = run_flaky_seed(1, false);
+        assert!(result.unwrap_err().contains("SQLSTATE 40001"));
+        assert_eq!(remaining, 0);
+        assert_eq!(count, 0, "failed transaction must be rolled back");
+        assert_eq!(
+            locks,
+            vec![
+                "acquire initium_seed:initium_seed",
+                "release initium_seed:initium_seed"
+            ],
+            "the seed lock must be released when seeding fails"
+        );
+    }
 }
@@ -0,0 +1,296 @@
+use crate::logging::Logger;
+use crate::seed::db::{self, SeedValue, TableExport};
+use crate::seed::schema::DatabaseConfig;
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Options of `seed export`.
+pub struct Config {
+    pub driver: String,
+    /// Database URL; empty falls back to `DATABASE_URL`.
+    pub url: String,
+    pub tables: Vec<String>,
+    /// Spec file to write, `-` for stdout.
+    pub output: String,
+}
+
+/// Read `cfg.tables` from the database and write them as a seed spec.
+pub fn run(log: &Logger, cfg: &Config) -> Result<(), String> {
+    if cfg.tables.is_empty() {
+        return Err("no tables to export: set --tables".into());
+    }
+    let config = DatabaseConfig {
+        driver: cfg.driver.clone(),
+        url: cfg.url.clone(),
+        ..Default::default()
+    };
+    log.info("connecting to database", &[("driver", cfg.driver.as_str())]);
+    let mut db = db::connect(&config)?;
+    db.set_read_only()?;
+
+    let mut tables = Vec::with_capacity(cfg.tables.len());
+    for table in &cfg.tables {
+        let export = db.export_table(table)?;
+        log.info(
+            "exported table",
+            &[("table", table), ("rows", &export.rows.len().to_string())],
+        );
+        tables.push((table.clone(), export));
+    }
+    let yaml = serde_yaml::to_string(&build_spec(&cfg.driver, &tables))
+        .map_err(|e| format!("encoding seed spec: {}", e))?;
+    if cfg.output == "-" {
+        print!("{}", yaml);
+    } else {
+        crate::safety::write_file_atomic(Path::new(&cfg.output), yaml.as_bytes())
+            .map_err(|e| format!("writing seed spec '{}': {}", cfg.output, e))?;
+        log.info("wrote seed spec", &[("path", cfg.output.as_str())]);
+    }
+    Ok(())
+}
+
+/// How an exported table is written back.
+struct TableKeys<'t> {
+    unique_key: &'t [String],
+    /// Integer primary key left to the database, with rows matched by
+    /// `unique_key` instead.
+    auto_id: Option<&'t str>,
+}
+
+/// Choose the keys of a table: a UNIQUE constraint identifies rows when
+/// there is one, so a single integer primary key can become an `auto_id`;
+/// otherwise rows keep their primary key values.
+fn table_keys(export: &TableExport) -> TableKeys<'_> {
+    let natural = export
+        .unique_keys
+        .iter()
+        .find(|k| **k != export.primary_key);
+    let integer_pk = match export.primary_key.as_slice() {
+        [pk] => export.columns.iter().position(|c| c == pk).filter(|&i| {
+            export
+                .rows
+                .iter()
+                .all(|r| matches!(r[i], SeedValue::Int(_)))
+        }),
+        _ => None,
+    };
+    match (natural, integer_pk) {
+        (Some(natural), Some(_)) => TableKeys {
+            unique_key: natural,
+            auto_id: Some(export.primary_key[0].as_str()),
+        },
+        (Some(natural), None) if export.primary_key.is_empty() => TableKeys {
+            unique_key: natural,
+            auto_id: None,
+        },
+        _ => TableKeys {
+            unique_key: &export.primary_key,
+            auto_id: None,
+        },
+    }
+}
+
+/// The `_ref` name of an exported row, from its table and primary key.
+fn ref_name(table: &str, id: i64) -> String {
+    let table = table.rsplit('.').next().unwrap_or(table);
+    format!("{}_{}", table, id)
+}
+
+/// Build a seed spec with one seed set holding `tables`. Foreign keys to a
+/// table whose ids become `auto_id` are written as `@ref:` values, and
+/// `auto_order` puts referenced tables first.
+pub fn build_spec(driver: &str, tables: &[(String, TableExport)]) -> Value {
+    let keys: Vec<TableKeys> = tables.iter().map(|(_, e)| table_keys(e)).collect();
+
+    // For each foreign key column that becomes a @ref:, the exported table
+    // it points at; the referenced rows get a `_ref`.
+    let mut refs: HashMap<(usize, &str), usize> = HashMap::new();
+    let mut referenced: HashSet<(usize, i64)> = HashSet::new();
+    for (i, (_, export)) in tables.iter().enumerate() {
+        for fk in &export.foreign_keys {
+            let target = tables.iter().position(|(name, _)| {
+                name == &fk.table || name.rsplit('.').next() == Some(fk.table.as_str())
+            });
+            let Some(t) = target else { continue };
+            let Some(auto_id) = keys[t].auto_id else {
+                continue;
+            };
+            if !fk.referenced_column.is_empty() && fk.referenced_column != auto_id {
+                continue;
+            }
+            let Some(col) = export.columns.iter().position(|c| c == &fk.column) else {
+                continue;
+            };
+            refs.insert((i, fk.column.as_str()), t);
+            for row in &export.rows {
+                if let SeedValue::Int(id) = row[col] {
+                    referenced.insert((t, id));
+                }
+            }
+        }
+    }
+
+    let mut table_specs = Vec::with_capacity(tables.len());
+    for (i, (name, export)) in tables.iter().enumerate() {
+        let mut spec = Mapping::new();
+        spec.insert("table".into(), name.as_str().into());
+        if !keys[i].unique_key.is_empty() {
+            spec.insert("unique_key".into(), keys[i].unique_key.to_vec().into());
+        }
+        match keys[i].auto_id {
+            Some(column) => {
+                let mut auto_id = Mapping::new();
+                auto_id.insert("column".into(), column.into());
+                spec.insert("auto_id".into(), auto_id.into());
+            }
+            None => {
+                if let [pk] = export.primary_key.as_slice() {
+                    let col = export.columns.iter().position(|c| c == pk);
+                    let integer = col.is_some_and(|c| {
+                        !export.rows.is_empty()
+                            && export
+                                .rows
+                                .iter()
+                                .all(|r| matches!(r[c], SeedValue::Int(_)))
+                    });
+                    if integer {
+                        spec.insert("sync_sequence".into(), pk.as_str().into());
+                    }
+                }
+            }
+        }
+
+        let mut rows = Vec::with_capacity(export.rows.len());
+        for values in &export.rows {
+            let mut row = Mapping::new();
+            for (column, value) in export.columns.iter().zip(values) {
+                if keys[i].auto_id == Some(column.as_str()) {
+                    if let SeedValue::Int(id) = value {
+                        if referenced.contains(&(i, *id)) {
+                            row.insert("_ref".into(), ref_name(name, *id).into());
+                        }
+                    }
+                    continue;
+                }
+                let value = match (refs.get(&(i, column.as_str())), value) {
+                    (Some(&t), SeedValue::Int(id)) => format!(
+                        "@ref:{}.{}",
+                        ref_name(&tables[t].0, *id),
+                        keys[t].auto_id.unwrap_or_default()
+                    )
+                    .into(),
+                    _ => to_yaml(value),
+                };
+                row.insert(column.as_str().into(), value);
+            }
+            rows.push(Value::Mapping(row));
+        }
+        spec.insert("rows".into(), rows.into());
+        table_specs.push(Value::Mapping(spec));
+    }
+
+    let mut seed_set = Mapping::new();
+    seed_set.insert("name".into(), "exported".into());
+    seed_set.insert("auto_order".into(), true.into());
+    seed_set.insert("tables".into(), table_specs.into());
+    let mut phase = Mapping::new();
+    phase.insert("name".into(), "exported".into());
+    phase.insert("seed_sets".into(), vec![Value::Mapping(seed_set)].into());
+    let mut database = Mapping::new();
+    database.insert("driver".into(), driver.into());
+    let mut spec = Mapping::new();
+    spec.insert("database".into(), database.into());
+    spec.insert("phases".into(), vec![Value::Mapping(phase)].into());
+    Value::Mapping(spec)
+}
+
+fn to_yaml(value: &SeedValue) -> Value {
+    match value {
+        SeedValue::Null => Value::Null,
+        SeedValue::Bool(b) => (*b).into(),
+        SeedValue::Int(i) => (*i).into(),
+        SeedValue::Float(f) => (*f).into(),
+        SeedValue::Text(s) => s.as_str().into(),
+        SeedValue::Json(v) => serde_yaml::to_value(v).unwrap_or_default(),
+        SeedValue::Bytes(_) => Value::Tagged(Box::new(serde_yaml::value::TaggedValue {
+            tag: serde_yaml::value::Tag::new("binary"),
+            value: value.to_text().into(),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::{Level, Logger};
+    use crate::seed::db::SqliteDb;
+    use crate::seed::executor::SeedExecutor;
+    use crate::seed::schema::SeedPlan;
+
+    const SCHEMA: &str = "CREATE TABLE departments (id INTEGER PRIMARY KEY, name TEXT UNIQUE);
+         CREATE TABLE employees (id INTEGER PRIMARY KEY, email TEXT UNIQUE, badge BLOB,
+             department_id INTEGER REFERENCES departments(id));
+         CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT);";
+
+    #[test]
+    fn test_export_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = dir.path().join("source.db");
+        let sqlite = SqliteDb::connect(source.to_str().unwrap()).unwrap();
+        sqlite
+            .conn
+            .execute_batch(&format!(
+                "{}
+                 INSERT INTO departments VALUES (3, 'Engineering'), (5, 'Sales');
+                 INSERT INTO employees VALUES (1, 'alice@example.com', x'0102', 5);
+                 INSERT INTO settings VALUES ('theme', 'dark');",
+                SCHEMA
+            ))
+            .unwrap();
+
+        let mut db: Box<dyn db::Database> = Box::new(sqlite);
+        let tables: Vec<(String, TableExport)> = ["employees", "departments", "settings"]
+            .iter()
+            .map(|t| (t.to_string(), db.export_table(t).unwrap()))
+            .collect();
+        let yaml = serde_yaml::to_string(&build_spec("sqlite", &tables)).unwrap();
+        let plan = SeedPlan::from_yaml(&yaml).unwrap();
+        let ss = &plan.phases[0].seed_sets[0];
+        assert!(ss.auto_order);
+        assert_eq!(ss.tables[0].unique_key, vec!["email".to_string()]);
+        assert_eq!(
+            ss.tables[0].rows[0]["department_id"],
+            Value::from("@ref:departments_5.id")
+        );
+        assert_eq!(ss.tables[1].auto_id.as_ref().unwrap().column, "id");
+        assert_eq!(ss.tables[2].unique_key, vec!["key".to_string()]);
+        assert!(yaml.contains("badge: !binary AQI="), "{}", yaml);
+
+        let target = dir.path().join("target.db");
+        let sqlite = SqliteDb::connect(target.to_str().unwrap()).unwrap();
+        sqlite.conn.execute_batch(SCHEMA).unwrap();
+        let log = Logger::new(Box::new(std::io::sink()), false, Level::Info);
+        let mut executor = SeedExecutor::new(&log, Box::new(sqlite), "initium_seed".into(), false);
+        executor.execute(&plan).unwrap();
+
+        let sqlite = SqliteDb::connect(target.to_str().unwrap()).unwrap();
+        let (department, badge): (String, Vec<u8>) = sqlite
+            .conn
+            .query_row(
+                "SELECT d.name, e.badge FROM employees e JOIN departments d ON d.id = e.department_id",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(department, "Sales");
+        assert_eq!(badge, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_export_missing_table() {
+        let mut db: Box<dyn db::Database> = Box::new(SqliteDb::connect(":memory:").unwrap());
+        let err = db.export_table("missing").unwrap_err();
+        assert_eq!(err, "table 'missing' not found");
+    }
+}
@@ -51,9 +51,9 @@ pub fn compute_seed_set_hash(
                 hasher.update(key.as_bytes());
                 hasher.update(b"=");
 
-                // Keep @ref: literals as-is; resolve everything else
+                // Keep @ref: and @lookup: literals as-is; resolve everything else
                 let val_str = match val.as_str() {
-                    Some(s) if s.starts_with("@ref:") => s.to_string(),
+                    Some(s) if s.starts_with("@ref:") || s.starts_with("@lookup:") => s.to_string(),
                     _ => resolver(val)?,
                 };
                 hasher.update(val_str.as_bytes());
@@ -67,7 +67,12 @@ pub fn compute_seed_set_hash(
     Ok(hex_encode(&hash))
 }
 
-fn hex_encode(bytes: &[u8]) -> String {
+/// SHA-256 of a tracked file's content, e.g. a seed script.
+pub fn compute_content_hash(content: &[u8]) -> String {
+    hex_encode(&Sha256::digest(content))
+}
+
+pub fn hex_encode(bytes: &[u8]) -> String {
     use std::fmt::Write;
     let mut s = String::with_capacity(bytes.len() * 2);
     for b in bytes {
@@ -76,6 +81,19 @@ fn hex_encode(bytes: &[u8]) -> String {
     s
 }
 
+pub fn hex_decode(hex: &str) -> Result<Vec<u8>, String> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(format!("invalid hex string '{}'", hex));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| format!("invalid hex string '{}'", hex))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,7 +1,13 @@
+#[cfg(feature = "cassandra")]
+pub mod cql;
 pub mod db;
 pub mod executor;
+pub mod export;
 pub mod hash;
+pub mod report;
 pub mod schema;
+pub mod source;
+pub mod validate;
 
 use crate::logging::Logger;
 
@@ -14,72 +20,171 @@ fn bootstrap_database(config: &schema::DatabaseConfig) -> String {
         // guaranteed to exist on every cluster.
         "postgres" | "postgresql" => "postgres".into(),
         // MySQL can connect without selecting a database, which avoids needing
-        // access to the `mysql` system schema.
+        // access to the `mysql` system schema. SQL Server falls back to the
+        // login's default database.
         _ => String::new(),
     }
 }
 
-fn render_template(content: &str) -> Result<String, String> {
+fn render_template(content: &str, strict: bool) -> Result<String, String> {
+    render_with(template_env(strict), content, env_context())
+}
+
+/// The process environment, as the `env` template variable.
+fn env_context() -> minijinja::Value {
     let env_map: std::collections::HashMap<String, String> = std::env::vars().collect();
+    minijinja::Value::from_serialize(&env_map)
+}
+
+fn template_env<'a>(strict: bool) -> minijinja::Environment<'a> {
     let mut jinja_env = minijinja::Environment::new();
-    jinja_env.set_undefined_behavior(minijinja::UndefinedBehavior::Lenient);
+    jinja_env.set_undefined_behavior(if strict {
+        minijinja::UndefinedBehavior::Strict
+    } else {
+        minijinja::UndefinedBehavior::Lenient
+    });
     crate::template_funcs::register(&mut jinja_env);
+    crate::fake::register(&mut jinja_env, crate::template_funcs::random_seed());
+    jinja_env
+}
+
+/// Render a seed spec with `env` as the `env` context variable.
+fn render_with<'a>(
+    mut jinja_env: minijinja::Environment<'a>,
+    content: &'a str,
+    env: minijinja::Value,
+) -> Result<String, String> {
     jinja_env
         .add_template("seed", content)
         .map_err(|e| format!("parsing seed template: {}", e))?;
     let tmpl = jinja_env
         .get_template("seed")
         .map_err(|e| format!("getting seed template: {}", e))?;
-    tmpl.render(minijinja::context!(env => env_map))
+    tmpl.render(minijinja::context!(env => env, pod => crate::pod::discover()))
         .map_err(|e| format!("rendering seed template: {}", e))
 }
 
-pub fn run(
-    log: &Logger,
-    spec_file: &str,
-    reset: bool,
-    dry_run: bool,
-    reconcile_all: bool,
-) -> Result<(), String> {
-    let content = std::fs::read_to_string(spec_file)
-        .map_err(|e| format!("reading seed spec '{}': {}", spec_file, e))?;
-
-    let rendered = render_template(&content)?;
+/// Options of the `seed` command besides the spec file.
+#[derive(Default)]
+pub struct Config {
+    pub reset: bool,
+    /// `--reset-set` globs: seed sets to reset without `--reset`.
+    pub reset_sets: Vec<String>,
+    pub dry_run: bool,
+    pub reconcile_all: bool,
+    /// `--auto-order`: order every seed set's tables by their dependencies.
+    pub auto_order: bool,
+    /// `--phase` globs; a `!` prefix skips matching phases.
+    pub phases: Vec<String>,
+    /// `--seed-set` globs; a `!` prefix skips matching seed sets.
+    pub seed_sets: Vec<String>,
+    /// Wait for the seed lock; `None` uses the executor default.
+    pub lock_timeout: Option<std::time::Duration>,
+    /// `--report-file`: where to write the JSON run report.
+    pub report_file: Option<String>,
+    /// `--leader-elect`: seed on the pod holding this Kubernetes Lease only.
+    pub leader_elect: Option<crate::lease::Config>,
+}
 
-    let plan = if spec_file.ends_with(".json") {
-        schema::SeedPlan::from_json(&rendered)?
-    } else {
-        schema::SeedPlan::from_yaml(&rendered)?
+/// `db::connect`, retried with backoff when the database has a
+/// `connect_retry` block, so a database that is still starting or not yet
+/// resolvable does not fail the run.
+fn connect_with_retry(
+    log: &Logger,
+    config: &schema::DatabaseConfig,
+) -> Result<Box<dyn db::Database>, String> {
+    let Some(retry) = &config.connect_retry else {
+        return db::connect(config);
     };
+    connect_retrying(log, config, &retry.config()?)
+}
 
-    let tracking_table = plan.database.tracking_table.clone();
-    let driver = plan.database.driver.clone();
+/// `db::connect`, retried with the backoff of `retry_cfg`.
+pub(crate) fn connect_retrying(
+    log: &Logger,
+    config: &schema::DatabaseConfig,
+    retry_cfg: &crate::retry::Config,
+) -> Result<Box<dyn db::Database>, String> {
+    let mut attempt = 0;
+    loop {
+        let err = match db::connect(config) {
+            Ok(db) => return Ok(db),
+            Err(e) => e,
+        };
+        if attempt + 1 >= retry_cfg.max_attempts {
+            return Err(format!(
+                "{} (gave up after {} attempts)",
+                err, retry_cfg.max_attempts
+            ));
+        }
+        let delay = crate::retry::delay(retry_cfg, attempt);
+        log.warn(
+            "database connection failed, retrying",
+            &[
+                ("driver", config.driver.as_str()),
+                ("attempt", &(attempt + 1).to_string()),
+                ("delay", &crate::duration::format_duration(delay)),
+                ("error", &err),
+            ],
+        );
+        std::thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// Connect to the database of the phases whose `connection` is `name`.
+fn connect(
+    log: &Logger,
+    name: &str,
+    config: &schema::DatabaseConfig,
+    plan: &schema::SeedPlan,
+) -> Result<Box<dyn db::Database>, String> {
+    let driver = config.driver.as_str();
+    let phases: Vec<&schema::SeedPhase> = plan
+        .phases
+        .iter()
+        .filter(|p| p.connection == name)
+        .collect();
 
     // When using structured config and a phase needs to create a database that
     // matches the configured name, we try the normal connection first. If it
     // fails, we fall back to connecting to a bootstrap database, create the
     // target, then reconnect. See https://github.com/KitStream/initium/issues/50
-    let may_need_bootstrap = plan.database.has_structured_config()
-        && plan.phases.iter().any(|p| {
-            p.create_if_missing && !p.database.is_empty() && p.database == plan.database.name
-        });
+    let may_need_bootstrap = config.has_structured_config()
+        && phases
+            .iter()
+            .any(|p| p.create_if_missing && !p.database.is_empty() && p.database == config.name);
 
-    log.info("connecting to database", &[("driver", driver.as_str())]);
+    if name.is_empty() {
+        log.info("connecting to database", &[("driver", driver)]);
+    } else {
+        log.info(
+            "connecting to database",
+            &[("connection", name), ("driver", driver)],
+        );
+    }
 
-    let db = match db::connect(&plan.database) {
-        Ok(db) => db,
+    // A missing target database fails at once and is created below; the
+    // bootstrap connection is the one retried then.
+    let connected = if may_need_bootstrap {
+        db::connect(config)
+    } else {
+        connect_with_retry(log, config)
+    };
+    match connected {
+        Ok(db) => Ok(db),
         Err(err) if may_need_bootstrap => {
             log.info(
                 "target database not reachable, bootstrapping via default database",
-                &[("driver", driver.as_str())],
+                &[("driver", driver)],
             );
 
-            let mut admin_config = plan.database.clone();
-            admin_config.name = bootstrap_database(&plan.database);
+            let mut admin_config = config.clone();
+            admin_config.name = bootstrap_database(config);
 
-            let mut admin_db = db::connect(&admin_config)?;
+            let mut admin_db = connect_with_retry(log, &admin_config)?;
 
-            for phase in &plan.phases {
+            for phase in &phases {
                 if phase.create_if_missing && !phase.database.is_empty() {
                     log.info(
                         "creating database if missing",
@@ -93,14 +198,102 @@ pub fn run(
             }
             drop(admin_db);
 
-            db::connect(&plan.database).map_err(|_| err)?
+            db::connect(config).map_err(|_| err)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+pub fn run(log: &Logger, spec_file: &str, cfg: &Config) -> Result<(), String> {
+    if cfg.dry_run && cfg.leader_elect.is_some() {
+        // A dry run would mark the Lease completed for the real runs.
+        return Err("--leader-elect cannot be combined with --dry-run".into());
+    }
+    let content = std::fs::read_to_string(spec_file)
+        .map_err(|e| format!("reading seed spec '{}': {}", spec_file, e))?;
+
+    let parse = |rendered: &str| {
+        if spec_file.ends_with(".json") {
+            schema::SeedPlan::parse_json(rendered)
+        } else {
+            schema::SeedPlan::parse_yaml(rendered)
         }
-        Err(err) => return Err(err),
     };
-    let mut exec = executor::SeedExecutor::new(log, db, tracking_table, reset)
-        .with_dry_run(dry_run)
-        .with_reconcile_all(reconcile_all);
-    exec.execute(&plan)
+
+    let mut plan = parse(&render_template(&content, false)?)?;
+    // `strict` lives inside the spec, so it is only known after a lenient
+    // render. Re-render strictly so undefined variables fail the run.
+    if plan.strict {
+        plan = parse(&render_template(&content, true)?)?;
+    }
+    let strict = plan.strict;
+    source::load_includes(&mut plan, std::path::Path::new(spec_file), &|_, c| {
+        render_template(c, strict)
+    })?;
+    let base_dir = std::path::Path::new(spec_file)
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_default();
+    // Row files are read as-is, after templating. The plan is validated once
+    // includes and row files are merged.
+    source::load_external_rows(&mut plan, &base_dir)?;
+    source::generate_rows(&mut plan, &template_env(strict), &env_context())?;
+
+    let seed = || execute(log, &plan, cfg, &content, base_dir);
+    match &cfg.leader_elect {
+        Some(lease) => crate::lease::run_as_leader(log, lease, seed),
+        None => seed(),
+    }
+}
+
+/// Connect to the databases of `plan` and apply it.
+fn execute(
+    log: &Logger,
+    plan: &schema::SeedPlan,
+    cfg: &Config,
+    content: &str,
+    base_dir: std::path::PathBuf,
+) -> Result<(), String> {
+    let db = connect(log, "", &plan.database, plan)?;
+    let mut exec =
+        executor::SeedExecutor::new(log, db, plan.database.qualified_tracking_table(), cfg.reset)
+            .with_dry_run(cfg.dry_run)
+            .with_reconcile_all(cfg.reconcile_all)
+            .with_auto_order(cfg.auto_order)
+            .with_phase_filter(cfg.phases.clone())
+            .with_seed_set_filter(cfg.seed_sets.clone())
+            .with_reset_sets(cfg.reset_sets.clone())
+            .with_base_dir(base_dir)
+            .with_spec_checksum(hash::compute_content_hash(content.as_bytes()));
+    for (name, config) in &plan.databases {
+        if !plan.phases.iter().any(|p| &p.connection == name) {
+            continue;
+        }
+        let db = connect(log, name, config, plan)?;
+        exec = exec.with_connection(name.clone(), db, config.qualified_tracking_table());
+    }
+    if let Some(timeout) = cfg.lock_timeout {
+        exec = exec.with_lock_timeout(timeout);
+    }
+    let (default_config, configs) = (plan.database.clone(), plan.databases.clone());
+    exec = exec.with_connector(Box::new(move |connection, database| {
+        let config = configs.get(connection).unwrap_or(&default_config);
+        match db::retarget(config, database)? {
+            Some(config) => connect_with_retry(log, &config).map(Some),
+            None => Ok(None),
+        }
+    }));
+    let result = exec.execute(plan);
+    let Some(path) = &cfg.report_file else {
+        return result;
+    };
+    let written = exec
+        .report(result.as_ref().err().map(String::as_str))
+        .write(path);
+    if written.is_ok() {
+        log.info("wrote seed report", &[("path", path.as_str())]);
+    }
+    result.and(written)
 }
 
 #[cfg(test)]
@@ -122,7 +315,7 @@ phases:
             rows:
               - a: b
 "#;
-        let rendered = render_template(input).unwrap();
+        let rendered = render_template(input, false).unwrap();
         assert!(rendered.contains("phases:"));
     }
 
@@ -142,7 +335,7 @@ phases:
             rows:
               - a: b
 "#;
-        let rendered = render_template(input).unwrap();
+        let rendered = render_template(input, false).unwrap();
         assert!(rendered.contains("driver: sqlite"));
         std::env::remove_var("TEST_SEED_RENDER_DRIVER");
     }
@@ -172,7 +365,7 @@ phases:
               - c: d
 {% endif %}
 "#;
-        let rendered = render_template(input).unwrap();
+        let rendered = render_template(input, false).unwrap();
         assert!(rendered.contains("phase2"));
         std::env::remove_var("TEST_SEED_ENABLE_PHASE2");
     }
@@ -195,7 +388,7 @@ phases:
                 value: val_{{ i }}
 {% endfor %}
 "#;
-        let rendered = render_template(input).unwrap();
+        let rendered = render_template(input, false).unwrap();
         assert!(rendered.contains("item_0"));
         assert!(rendered.contains("item_1"));
         assert!(rendered.contains("item_2"));
@@ -204,7 +397,7 @@ phases:
     #[test]
     fn test_render_template_invalid() {
         let input = "{% invalid %}";
-        let result = render_template(input);
+        let result = render_template(input, false);
         assert!(result.is_err());
     }
 
@@ -223,7 +416,285 @@ phases:
             rows:
               - a: b
 "#;
-        let rendered = render_template(input).unwrap();
+        let rendered = render_template(input, false).unwrap();
         assert!(rendered.contains("driver:"));
     }
+
+    #[test]
+    fn test_render_template_missing_env_strict() {
+        let input = "driver: {{ env.NONEXISTENT_SEED_VAR_XYZ }}\n";
+        let err = render_template(input, true).unwrap_err();
+        assert!(err.contains("rendering seed template"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_run_strict_spec_fails_on_undefined() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let spec = dir.path().join("seed.yaml");
+        std::fs::write(
+            &spec,
+            r#"
+strict: true
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: setup
+    seed_sets:
+      - name: s1
+        tables:
+          - table: t
+            rows:
+              - a: "{{ env.NONEXISTENT_SEED_VAR_XYZ }}"
+"#,
+        )
+        .unwrap();
+        let log = Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        );
+        let err = run(&log, spec.to_str().unwrap(), &Config::default()).unwrap_err();
+        assert!(err.contains("undefined"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_run_merges_includes_and_rows_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("seed.db");
+        std::fs::create_dir(dir.path().join("seeds")).unwrap();
+        let spec = dir.path().join("seed.yaml");
+        std::fs::write(
+            &spec,
+            format!(
+                r#"
+database:
+  driver: sqlite
+  url: "{}"
+include:
+  - seeds/users.yaml
+phases:
+  - name: setup
+    sql:
+      - CREATE TABLE users (name TEXT UNIQUE, role TEXT)
+"#,
+                db_path.display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("seeds/users.yaml"),
+            r#"
+phases:
+  - name: setup
+    seed_sets:
+      - name: users
+        tables:
+          - table: users
+            unique_key: [name]
+            rows_file: users.rows.yaml
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("seeds/users.rows.yaml"),
+            "- name: alice\n  role: admin\n- name: bob\n  role: viewer\n",
+        )
+        .unwrap();
+        let log = Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        );
+        run(&log, spec.to_str().unwrap(), &Config::default()).unwrap();
+
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM users", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_run_with_named_connections() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let app_db = dir.path().join("app.db");
+        let report_db = dir.path().join("report.db");
+        let spec = dir.path().join("seed.yaml");
+        std::fs::write(
+            &spec,
+            format!(
+                r#"
+database:
+  driver: sqlite
+  url: "{}"
+databases:
+  reporting:
+    driver: sqlite
+    url: "{}"
+    tracking_table: report_seed
+phases:
+  - name: app
+    sql:
+      - CREATE TABLE IF NOT EXISTS users (id INTEGER PRIMARY KEY, name TEXT UNIQUE)
+    seed_sets:
+      - name: users
+        tables:
+          - table: users
+            unique_key: [name]
+            auto_id:
+              column: id
+            rows:
+              - _ref: alice
+                name: alice
+  - name: reporting
+    order: 1
+    connection: reporting
+    sql:
+      - CREATE TABLE IF NOT EXISTS user_stats (user_id INTEGER UNIQUE, logins INTEGER)
+    seed_sets:
+      - name: stats
+        tables:
+          - table: user_stats
+            unique_key: [user_id]
+            rows:
+              - user_id: "@ref:alice.id"
+                logins: 0
+"#,
+                app_db.display(),
+                report_db.display()
+            ),
+        )
+        .unwrap();
+        let log = Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        );
+        run(&log, spec.to_str().unwrap(), &Config::default()).unwrap();
+
+        let app = rusqlite::Connection::open(&app_db).unwrap();
+        let report = rusqlite::Connection::open(&report_db).unwrap();
+        let alice: i64 = app
+            .query_row("SELECT id FROM users WHERE name = 'alice'", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        let user_id: i64 = report
+            .query_row("SELECT user_id FROM user_stats", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(user_id, alice, "references resolve across connections");
+        let tracked = |conn: &rusqlite::Connection, table: &str| -> Vec<String> {
+            let mut stmt = conn
+                .prepare(&format!("SELECT seed_set FROM {} ORDER BY seed_set", table))
+                .unwrap();
+            stmt.query_map([], |r| r.get(0))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        };
+        assert_eq!(tracked(&app, "initium_seed"), vec!["users"]);
+        assert_eq!(tracked(&report, "report_seed"), vec!["stats"]);
+        let stray: i64 = app
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'user_stats'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(stray, 0, "reporting phase must not touch the app database");
+
+        // A second run finds both seed sets applied in their own databases.
+        run(&log, spec.to_str().unwrap(), &Config::default()).unwrap();
+    }
+
+    #[test]
+    fn test_report_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("seed.db");
+        let spec = dir.path().join("seed.yaml");
+        let report_path = dir.path().join("report.json");
+        let write_spec = |rows: &str| {
+            std::fs::write(
+                &spec,
+                format!(
+                    r#"
+database:
+  driver: sqlite
+  url: "{}"
+phases:
+  - name: setup
+    sql:
+      - CREATE TABLE IF NOT EXISTS users (name TEXT UNIQUE, role TEXT)
+    seed_sets:
+      - name: users
+        mode: reconcile
+        tables:
+          - table: users
+            unique_key: [name]
+            rows:
+{}
+"#,
+                    db_path.display(),
+                    rows
+                ),
+            )
+            .unwrap();
+        };
+        let log = Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        );
+        let cfg = Config {
+            report_file: Some(report_path.to_str().unwrap().into()),
+            ..Config::default()
+        };
+        let read_report = || -> serde_json::Value {
+            serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap()
+        };
+
+        write_spec(
+            "              - {name: alice, role: admin}\n              - {name: bob, role: viewer}",
+        );
+        run(&log, spec.to_str().unwrap(), &cfg).unwrap();
+        let report = read_report();
+        assert_eq!(report["success"], true);
+        assert_eq!(report["totals"]["inserted"], 2);
+        assert_eq!(report["tables"][0]["seed_set"], "users");
+        assert_eq!(report["tables"][0]["table"], "users");
+
+        write_spec("              - {name: alice, role: owner}\n              - {name: carol, role: viewer}");
+        run(&log, spec.to_str().unwrap(), &cfg).unwrap();
+        let totals = &read_report()["totals"];
+        assert_eq!(
+            (&totals["inserted"], &totals["updated"], &totals["deleted"]),
+            (&1.into(), &1.into(), &1.into())
+        );
+
+        write_spec("              - {name: dave, missing: x}");
+        let err = run(&log, spec.to_str().unwrap(), &cfg).unwrap_err();
+        let report = read_report();
+        assert_eq!(report["success"], false);
+        assert_eq!(report["error"], err.as_str());
+    }
+
+    #[test]
+    fn test_connect_retry_gives_up() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config: schema::DatabaseConfig = serde_yaml::from_str(&format!(
+            "driver: sqlite\nurl: {}/missing/seed.db\nconnect_retry: {{max_attempts: 3, initial_delay: 10ms, max_delay: 20ms}}",
+            dir.path().display()
+        ))
+        .unwrap();
+        let log = Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        );
+        let started = std::time::Instant::now();
+        let err = connect_with_retry(&log, &config).err().unwrap();
+        assert!(err.contains("gave up after 3 attempts"), "{}", err);
+        assert!(started.elapsed() >= std::time::Duration::from_millis(20));
+    }
 }
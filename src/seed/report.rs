@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+/// Rows a seed run wrote, per table or in total.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct RowCounts {
+    pub inserted: u64,
+    /// Rows changed by reconcile mode.
+    pub updated: u64,
+    /// Rows written by `on_conflict: update`, which may insert or update.
+    pub upserted: u64,
+    /// Rows that already existed or were unchanged.
+    pub skipped: u64,
+    /// Rows removed by reset or as reconcile orphans.
+    pub deleted: u64,
+}
+
+impl RowCounts {
+    fn add(&mut self, other: &RowCounts) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.upserted += other.upserted;
+        self.skipped += other.skipped;
+        self.deleted += other.deleted;
+    }
+
+    /// Log fields in a fixed order.
+    pub fn fields(&self) -> [(&'static str, String); 5] {
+        [
+            ("inserted", self.inserted.to_string()),
+            ("updated", self.updated.to_string()),
+            ("upserted", self.upserted.to_string()),
+            ("skipped", self.skipped.to_string()),
+            ("deleted", self.deleted.to_string()),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableStats {
+    pub seed_set: String,
+    pub table: String,
+    #[serde(flatten)]
+    pub rows: RowCounts,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+}
+
+impl TableStats {
+    pub fn new(seed_set: &str, table: &str) -> Self {
+        Self {
+            seed_set: seed_set.into(),
+            table: table.into(),
+            rows: RowCounts::default(),
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+/// The outcome of a seed run, written as JSON by `--report-file`.
+#[derive(Debug, Serialize)]
+pub struct SeedReport {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub dry_run: bool,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+    pub totals: RowCounts,
+    pub tables: Vec<TableStats>,
+    /// Seed sets with `continue_on_error` that failed.
+    pub failed_seed_sets: Vec<String>,
+}
+
+impl SeedReport {
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("encoding seed report: {}", e))?;
+        crate::safety::write_file_atomic(Path::new(path), (json + "\n").as_bytes())
+            .map_err(|e| format!("writing seed report '{}': {}", path, e))
+    }
+}
+
+/// Add `stats` to the entry of the same seed set and table, so a reset and
+/// the re-seed that follows it are reported together.
+pub fn merge(tables: &mut Vec<TableStats>, stats: TableStats) {
+    match tables
+        .iter_mut()
+        .find(|t| t.seed_set == stats.seed_set && t.table == stats.table)
+    {
+        Some(t) => {
+            t.rows.add(&stats.rows);
+            t.duration += stats.duration;
+        }
+        None => tables.push(stats),
+    }
+}
+
+pub fn totals(tables: &[TableStats]) -> RowCounts {
+    let mut totals = RowCounts::default();
+    for t in tables {
+        totals.add(&t.rows);
+    }
+    totals
+}
+
+fn millis<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u64(d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_and_serialize() {
+        let mut tables = Vec::new();
+        let mut reset = TableStats::new("users", "users");
+        reset.rows.deleted = 3;
+        reset.duration = Duration::from_millis(5);
+        merge(&mut tables, reset);
+        let mut seeded = TableStats::new("users", "users");
+        seeded.rows.inserted = 2;
+        seeded.rows.skipped = 1;
+        seeded.duration = Duration::from_millis(20);
+        merge(&mut tables, seeded);
+        let mut roles = TableStats::new("users", "roles");
+        roles.rows.upserted = 4;
+        merge(&mut tables, roles);
+
+        assert_eq!(tables.len(), 2);
+        let report = SeedReport {
+            success: true,
+            error: None,
+            dry_run: false,
+            duration: Duration::from_millis(1500),
+            totals: totals(&tables),
+            tables,
+            failed_seed_sets: Vec::new(),
+        };
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "success": true,
+                "dry_run": false,
+                "duration_ms": 1500,
+                "totals": {"inserted": 2, "updated": 0, "upserted": 4, "skipped": 1, "deleted": 3},
+                "tables": [
+                    {"seed_set": "users", "table": "users", "inserted": 2, "updated": 0,
+                     "upserted": 0, "skipped": 1, "deleted": 3, "duration_ms": 25},
+                    {"seed_set": "users", "table": "roles", "inserted": 0, "updated": 0,
+                     "upserted": 4, "skipped": 0, "deleted": 0, "duration_ms": 0}
+                ],
+                "failed_seed_sets": []
+            })
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use serde::de::{self, Deserializer};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 fn deserialize_string_or_number<'de, D>(deserializer: D) -> Result<String, D::Error>
@@ -70,7 +70,27 @@ where
 pub struct SeedPlan {
     #[serde(default)]
     pub database: DatabaseConfig,
+    /// Further connections, used by phases that name them in `connection`.
+    #[serde(default)]
+    pub databases: BTreeMap<String, DatabaseConfig>,
+    #[serde(default)]
     pub phases: Vec<SeedPhase>,
+    #[serde(default)]
+    pub strict: bool,
+    /// Spec files, relative to this one, whose phases are merged into the plan.
+    #[serde(default)]
+    pub include: Vec<String>,
+}
+
+/// A spec file listed in `include`. It may only contribute phases and
+/// further includes; the database config belongs to the main spec.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpecInclude {
+    #[serde(default)]
+    pub phases: Vec<SeedPhase>,
+    #[serde(default)]
+    pub include: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Clone, Default)]
@@ -97,6 +117,21 @@ pub struct DatabaseConfig {
     pub options: HashMap<String, String>,
     #[serde(default = "default_tracking_table")]
     pub tracking_table: String,
+    /// Schema holding the tracking tables, created if missing.
+    #[serde(default)]
+    pub tracking_schema: String,
+    /// Retry failed connection attempts with backoff.
+    #[serde(default)]
+    pub connect_retry: Option<ConnectRetry>,
+    /// PostgreSQL session settings, applied after connecting.
+    #[serde(default, deserialize_with = "deserialize_optional_string_or_number")]
+    pub statement_timeout: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_string_or_number")]
+    pub lock_timeout: Option<String>,
+    #[serde(default)]
+    pub application_name: String,
+    #[serde(default)]
+    pub search_path: String,
 }
 
 impl DatabaseConfig {
@@ -114,8 +149,128 @@ impl DatabaseConfig {
                 "database config must use either structured fields (host, port, user, password, name) or url/url_env, not both".into(),
             );
         }
+        if let Some(retry) = &self.connect_retry {
+            retry
+                .config()
+                .map_err(|e| format!("invalid connect_retry: {}", e))?;
+        }
+        let settings = self.session_settings()?;
+        if !settings.is_empty() && !matches!(self.driver.as_str(), "postgres" | "postgresql") {
+            let names: Vec<&str> = settings.iter().map(|(name, _)| *name).collect();
+            return Err(format!(
+                "database {} only supported by the postgres driver, not '{}'",
+                if names.len() == 1 {
+                    format!("setting {} is", names[0])
+                } else {
+                    format!("settings {} are", names.join(", "))
+                },
+                self.driver
+            ));
+        }
+        if !self.tracking_schema.is_empty()
+            && !matches!(self.driver.as_str(), "postgres" | "postgresql")
+        {
+            return Err(format!(
+                "database setting tracking_schema is only supported by the postgres driver, not '{}'",
+                self.driver
+            ));
+        }
         Ok(())
     }
+
+    /// The tracking table, qualified with `tracking_schema` when set.
+    pub fn qualified_tracking_table(&self) -> String {
+        if self.tracking_schema.is_empty() {
+            self.tracking_table.clone()
+        } else {
+            format!("{}.{}", self.tracking_schema, self.tracking_table)
+        }
+    }
+
+    /// The session settings to apply after connecting, with timeouts in
+    /// milliseconds.
+    pub fn session_settings(&self) -> Result<Vec<(&'static str, String)>, String> {
+        let mut settings = Vec::new();
+        for (name, value) in [
+            ("statement_timeout", &self.statement_timeout),
+            ("lock_timeout", &self.lock_timeout),
+        ] {
+            if let Some(value) = value {
+                let timeout = crate::duration::parse_duration(value)
+                    .map_err(|e| format!("invalid {}: {}", name, e))?;
+                settings.push((name, timeout.as_millis().to_string()));
+            }
+        }
+        for (name, value) in [
+            ("application_name", &self.application_name),
+            ("search_path", &self.search_path),
+        ] {
+            if !value.is_empty() {
+                settings.push((name, value.clone()));
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// Backoff for connecting to a database that may still be starting, using
+/// the same settings as `wait-for`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectRetry {
+    #[serde(default = "default_connect_attempts")]
+    pub max_attempts: u32,
+    #[serde(
+        default = "default_connect_initial_delay",
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub initial_delay: String,
+    #[serde(
+        default = "default_connect_max_delay",
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub max_delay: String,
+    #[serde(default = "default_connect_backoff_factor")]
+    pub backoff_factor: f64,
+    #[serde(default = "default_connect_jitter")]
+    pub jitter: f64,
+}
+
+impl ConnectRetry {
+    pub fn config(&self) -> Result<crate::retry::Config, String> {
+        let duration = |name: &str, value: &str| {
+            crate::duration::parse_duration(value).map_err(|e| format!("{}: {}", name, e))
+        };
+        let config = crate::retry::Config {
+            max_attempts: self.max_attempts,
+            initial_delay: duration("initial_delay", &self.initial_delay)?,
+            max_delay: duration("max_delay", &self.max_delay)?,
+            backoff_factor: self.backoff_factor,
+            jitter_fraction: self.jitter,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn default_connect_attempts() -> u32 {
+    10
+}
+
+fn default_connect_initial_delay() -> String {
+    "1s".into()
+}
+
+fn default_connect_max_delay() -> String {
+    "30s".into()
+}
+
+fn default_connect_backoff_factor() -> f64 {
+    2.0
+}
+
+fn default_connect_jitter() -> f64 {
+    0.1
 }
 
 fn default_driver() -> String {
@@ -133,19 +288,84 @@ pub struct SeedSet {
     pub order: i32,
     #[serde(default = "default_seed_mode")]
     pub mode: String,
+    #[serde(default)]
     pub tables: Vec<TableSeed>,
+    #[serde(default)]
+    pub scripts: Option<ScriptsSource>,
+    /// What an applied `once` seed set does when its content changes:
+    /// `reapply`, `error` or `ignore`.
+    #[serde(default)]
+    pub on_change: Option<String>,
+    /// Clear and re-apply the seed set on every run, as `--reset` does.
+    #[serde(default)]
+    pub reset: bool,
+    /// How reset clears the tables: `delete`, `truncate` or
+    /// `truncate_cascade`. Tables may override it.
+    #[serde(default)]
+    pub reset_strategy: Option<String>,
+    /// Write tables after the tables they reference through foreign keys or
+    /// `@ref:`, as `--auto-order` does, rather than by `order` alone.
+    #[serde(default)]
+    pub auto_order: bool,
+    #[serde(default)]
+    pub before: Vec<Hook>,
+    #[serde(default)]
+    pub after: Vec<Hook>,
+    /// Log a failure of this seed set and go on with the rest of the plan.
+    #[serde(default)]
+    pub continue_on_error: bool,
 }
 
 fn default_seed_mode() -> String {
     "once".into()
 }
 
+/// A directory of SQL files applied in lexical order, each tracked separately.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ScriptsSource {
+    /// Directory, relative to the spec file.
+    pub dir: String,
+    #[serde(default = "default_scripts_pattern")]
+    pub pattern: String,
+}
+
+fn default_scripts_pattern() -> String {
+    "*.sql".into()
+}
+
 impl SeedSet {
     pub fn is_reconcile(&self) -> bool {
         self.mode == "reconcile"
     }
+
+    /// The effective `on_change` policy. Without one, seed sets with
+    /// upserting or managed tables are re-applied, so updated and removed
+    /// rows reach the database, and other changed seed sets are skipped with
+    /// a warning.
+    pub fn on_change_policy(&self) -> Option<&str> {
+        match &self.on_change {
+            Some(policy) => Some(policy),
+            None if self.tables.iter().any(|t| t.upserts() || t.managed) => Some("reapply"),
+            None => None,
+        }
+    }
+
+    pub fn reapplies_on_change(&self) -> bool {
+        !self.is_reconcile() && self.on_change_policy() == Some("reapply")
+    }
+
+    /// How reset clears `ts`: its own `reset_strategy`, the seed set's, or
+    /// `delete`.
+    pub fn reset_strategy<'s>(&'s self, ts: &'s TableSeed) -> &'s str {
+        ts.reset_strategy
+            .as_deref()
+            .or(self.reset_strategy.as_deref())
+            .unwrap_or("delete")
+    }
 }
 
+const RESET_STRATEGIES: [&str; 3] = ["delete", "truncate", "truncate_cascade"];
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct TableSeed {
     pub table: String,
@@ -157,18 +377,97 @@ pub struct TableSeed {
     pub ignore_columns: Vec<String>,
     #[serde(default)]
     pub auto_id: Option<AutoIdConfig>,
+    #[serde(default = "default_on_conflict")]
+    pub on_conflict: String,
+    /// Overrides the seed set's `reset_strategy` for this table.
+    #[serde(default)]
+    pub reset_strategy: Option<String>,
+    /// Track the rows written by a `once` seed set and delete them once they
+    /// are removed from the spec, as reconcile mode does.
+    #[serde(default)]
+    pub managed: bool,
+    /// Rows per multi-row INSERT when the table has no `auto_id`.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default)]
     pub rows: Vec<HashMap<String, serde_yaml::Value>>,
+    /// Column whose sequence or identity counter is moved past the seeded
+    /// ids once the seed set is applied.
+    #[serde(default)]
+    pub sync_sequence: Option<String>,
+    /// YAML file holding a list of rows, relative to the spec file.
+    #[serde(default)]
+    pub rows_file: Option<String>,
+    #[serde(default)]
+    pub rows_from: Option<RowsFrom>,
+    #[serde(default)]
+    pub generate: Option<GenerateRows>,
+}
+
+fn default_on_conflict() -> String {
+    "skip".into()
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+/// External file whose records are appended to a table's `rows`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RowsFrom {
+    /// CSV file path, relative to the spec file.
+    pub csv: String,
+    #[serde(default = "default_csv_delimiter")]
+    pub delimiter: String,
+    #[serde(default = "default_true")]
+    pub header: bool,
+    /// Column names for a CSV without a header row.
+    #[serde(default)]
+    pub columns: Vec<String>,
+}
+
+/// Rows generated from MiniJinja expressions and appended to `rows`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct GenerateRows {
+    pub count: usize,
+    /// Seed of the random and `fake_*` functions, so every run generates
+    /// the same rows.
+    #[serde(default)]
+    pub seed: u64,
+    /// Expression per column, evaluated once per row with `index` (from 1).
+    pub columns: BTreeMap<String, String>,
+}
+
+fn default_csv_delimiter() -> String {
+    ",".into()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl TableSeed {
+    /// Whether rows matching an existing `unique_key` are updated in place.
+    pub fn upserts(&self) -> bool {
+        self.on_conflict == "update"
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct AutoIdConfig {
     pub column: String,
-    /// Reserved for future use (e.g. UUID generation); parsed from spec for forward compatibility.
+    /// `integer` (generated by the database) or `uuid` (a UUIDv4 generated
+    /// by initium and written with the row).
     #[serde(default = "default_auto_id_type")]
-    #[allow(dead_code)]
     pub id_type: String,
 }
 
+impl AutoIdConfig {
+    pub fn is_uuid(&self) -> bool {
+        self.id_type == "uuid"
+    }
+}
+
 fn default_auto_id_type() -> String {
     "integer".into()
 }
@@ -178,6 +477,9 @@ pub struct SeedPhase {
     pub name: String,
     #[serde(default)]
     pub order: i32,
+    /// Key of `databases` to run this phase on; empty for `database`.
+    #[serde(default)]
+    pub connection: String,
     #[serde(default)]
     pub database: String,
     #[serde(default)]
@@ -192,6 +494,18 @@ pub struct SeedPhase {
     )]
     pub timeout: String,
     #[serde(default)]
+    pub sql: Vec<SqlStatement>,
+    /// Run after `wait_for`, before `sql` and the seed sets.
+    #[serde(default)]
+    pub before: Vec<Hook>,
+    /// Run once the phase's seed sets are applied.
+    #[serde(default)]
+    pub after: Vec<Hook>,
+    /// What commits together: `per_set` (default), `per_phase`, or `none`
+    /// to commit every statement as it runs.
+    #[serde(default = "default_transaction")]
+    pub transaction: String,
+    #[serde(default)]
     pub seed_sets: Vec<SeedSet>,
 }
 
@@ -199,32 +513,269 @@ fn default_phase_timeout() -> String {
     "30s".into()
 }
 
+fn default_transaction() -> String {
+    "per_set".into()
+}
+
+/// An entry of a phase's `sql:` list: inline SQL, `file:` with a path
+/// relative to the spec file, or a `call:` of a stored routine.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SqlStatement {
+    File { file: String },
+    Call { call: RoutineCall },
+    Inline(String),
+}
+
+impl SqlStatement {
+    fn is_empty(&self) -> bool {
+        match self {
+            SqlStatement::Inline(sql) => sql.trim().is_empty(),
+            SqlStatement::File { file } => file.trim().is_empty(),
+            SqlStatement::Call { call } => call.name().trim().is_empty(),
+        }
+    }
+}
+
+/// A `call:` step: a stored procedure run with `CALL`, or a function
+/// selected for its side effects. Arguments are resolved like row values.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RoutineCall {
+    #[serde(default)]
+    pub procedure: String,
+    #[serde(default)]
+    pub function: String,
+    #[serde(default)]
+    pub args: Vec<serde_yaml::Value>,
+}
+
+impl RoutineCall {
+    pub fn is_function(&self) -> bool {
+        !self.function.is_empty()
+    }
+
+    pub fn name(&self) -> &str {
+        if self.is_function() {
+            &self.function
+        } else {
+            &self.procedure
+        }
+    }
+}
+
+impl std::fmt::Display for RoutineCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|a| serde_json::to_string(a).unwrap_or_default())
+            .collect();
+        let verb = if self.is_function() { "SELECT" } else { "CALL" };
+        write!(f, "{} {}({})", verb, self.name(), args.join(", "))
+    }
+}
+
+/// An entry of a `before:` or `after:` list: SQL like a `sql:` entry, or a
+/// `command:` run in the spec file's directory.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Hook {
+    Command { command: Vec<String> },
+    Sql(SqlStatement),
+}
+
+impl Hook {
+    fn is_empty(&self) -> bool {
+        match self {
+            Hook::Command { command } => command.first().is_none_or(|c| c.trim().is_empty()),
+            Hook::Sql(stmt) => stmt.is_empty(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct WaitForObject {
     #[serde(rename = "type")]
     pub obj_type: String,
+    #[serde(default)]
     pub name: String,
+    /// Table of a `row` or `column` wait.
+    #[serde(default)]
+    pub table: String,
+    /// Column of a `column` wait.
+    #[serde(default)]
+    pub column: String,
+    /// Column values the awaited row must have, for `row`.
+    #[serde(default, rename = "where")]
+    pub conditions: BTreeMap<String, serde_yaml::Value>,
     #[serde(default, deserialize_with = "deserialize_optional_string_or_number")]
     pub timeout: Option<String>,
 }
 
+impl WaitForObject {
+    pub fn is_row(&self) -> bool {
+        self.obj_type == "row"
+    }
+
+    pub fn is_column(&self) -> bool {
+        self.obj_type == "column"
+    }
+
+    /// What is waited for, for logs and errors: the name, `table.column`,
+    /// or for a row its table and conditions.
+    pub fn target(&self) -> String {
+        if self.is_column() {
+            return format!("{}.{}", self.table, self.column);
+        }
+        if !self.is_row() {
+            return self.name.clone();
+        }
+        let conditions: Vec<String> = self
+            .conditions
+            .iter()
+            .map(|(column, value)| match value {
+                serde_yaml::Value::String(s) => format!("{}='{}'", column, s),
+                other => format!(
+                    "{}={}",
+                    column,
+                    serde_yaml::to_string(other).unwrap_or_default().trim_end()
+                ),
+            })
+            .collect();
+        format!("{} WHERE {}", self.table, conditions.join(" AND "))
+    }
+}
+
+/// A `@lookup:table.column WHERE col=value [AND ...]` value: the column of an
+/// existing row, found by equality conditions rather than by `_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lookup {
+    pub table: String,
+    pub column: String,
+    /// Condition values are YAML scalars, resolved like any row value.
+    pub conditions: Vec<(String, serde_yaml::Value)>,
+}
+
+impl Lookup {
+    /// Parse the expression after `@lookup:`.
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| {
+            format!(
+                "invalid lookup '{}': {} (expected 'table.column WHERE column=value')",
+                expr, reason
+            )
+        };
+        let upper = expr.to_ascii_uppercase();
+        let where_at = upper
+            .find(" WHERE ")
+            .ok_or_else(|| invalid("missing WHERE"))?;
+        let target = expr[..where_at].trim();
+        let (table, column) = target
+            .rsplit_once('.')
+            .filter(|(t, c)| !t.trim().is_empty() && !c.trim().is_empty())
+            .ok_or_else(|| invalid("target must be 'table.column'"))?;
+
+        let mut conditions = Vec::new();
+        let mut rest = expr[where_at + " WHERE ".len()..].trim_start();
+        loop {
+            let (name, after) = rest
+                .split_once('=')
+                .ok_or_else(|| invalid("condition must be 'column=value'"))?;
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(invalid("condition must be 'column=value'"));
+            }
+            let after = after.trim_start();
+            let (value, remainder) = if let Some(quoted) = after.strip_prefix('\'') {
+                // '' inside a quoted value is a literal quote, as in SQL.
+                let mut text = String::new();
+                let mut chars = quoted.char_indices().peekable();
+                let mut end = None;
+                while let Some((i, c)) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek().map(|(_, n)| *n) == Some('\'') {
+                            chars.next();
+                            text.push('\'');
+                        } else {
+                            end = Some(i + 1);
+                            break;
+                        }
+                    } else {
+                        text.push(c);
+                    }
+                }
+                let end = end.ok_or_else(|| invalid("unterminated quoted value"))?;
+                (serde_yaml::Value::String(text), &quoted[end..])
+            } else {
+                let len = after.find(char::is_whitespace).unwrap_or(after.len());
+                let raw = &after[..len];
+                let value = match serde_yaml::from_str::<serde_yaml::Value>(raw) {
+                    Ok(v @ (serde_yaml::Value::Number(_) | serde_yaml::Value::Bool(_))) => v,
+                    _ => return Err(invalid("values must be numbers, booleans or 'quoted'")),
+                };
+                (value, &after[len..])
+            };
+            conditions.push((name.to_string(), value));
+
+            let remainder = remainder.trim_start();
+            if remainder.is_empty() {
+                break;
+            }
+            rest = match remainder.get(..3) {
+                Some(and)
+                    if and.eq_ignore_ascii_case("AND")
+                        && remainder[3..].starts_with(char::is_whitespace) =>
+                {
+                    remainder[3..].trim_start()
+                }
+                _ => return Err(invalid("conditions must be joined with AND")),
+            };
+        }
+
+        Ok(Lookup {
+            table: table.trim().to_string(),
+            column: column.trim().to_string(),
+            conditions,
+        })
+    }
+}
+
 impl SeedPlan {
+    /// Parse and validate a self-contained spec. `seed::run` parses with
+    /// `parse_yaml`/`parse_json` and validates after merging includes.
+    #[cfg(test)]
     pub fn from_yaml(content: &str) -> Result<Self, String> {
-        let plan: SeedPlan =
-            serde_yaml::from_str(content).map_err(|e| format!("parsing seed YAML: {}", e))?;
+        let plan = Self::parse_yaml(content)?;
         plan.validate()?;
         Ok(plan)
     }
 
+    #[cfg(test)]
     pub fn from_json(content: &str) -> Result<Self, String> {
-        let plan: SeedPlan =
-            serde_json::from_str(content).map_err(|e| format!("parsing seed JSON: {}", e))?;
+        let plan = Self::parse_json(content)?;
         plan.validate()?;
         Ok(plan)
     }
 
+    /// Parse without validating, for plans completed by includes and row files.
+    pub fn parse_yaml(content: &str) -> Result<Self, String> {
+        serde_yaml::from_str(content).map_err(|e| format!("parsing seed YAML: {}", e))
+    }
+
+    pub fn parse_json(content: &str) -> Result<Self, String> {
+        serde_json::from_str(content).map_err(|e| format!("parsing seed JSON: {}", e))
+    }
+
     pub fn validate(&self) -> Result<(), String> {
         self.database.validate()?;
+        for (name, db) in &self.databases {
+            if name.is_empty() {
+                return Err("databases entry name must not be empty".into());
+            }
+            db.validate()
+                .map_err(|e| format!("databases entry '{}': {}", name, e))?;
+        }
         if self.phases.is_empty() {
             return Err("seed plan must contain at least one phase".into());
         }
@@ -232,11 +783,88 @@ impl SeedPlan {
             if phase.name.is_empty() {
                 return Err("phase name must not be empty".into());
             }
+            if !phase.connection.is_empty() && !self.databases.contains_key(&phase.connection) {
+                return Err(format!(
+                    "phase '{}' uses connection '{}', which is not defined in databases",
+                    phase.name, phase.connection
+                ));
+            }
             for wf in &phase.wait_for {
                 Self::validate_wait_for(wf)?;
             }
+            let calls = phase.sql.iter().chain(
+                phase
+                    .before
+                    .iter()
+                    .chain(&phase.after)
+                    .chain(
+                        phase
+                            .seed_sets
+                            .iter()
+                            .flat_map(|ss| ss.before.iter().chain(&ss.after)),
+                    )
+                    .filter_map(|hook| match hook {
+                        Hook::Sql(stmt) => Some(stmt),
+                        Hook::Command { .. } => None,
+                    }),
+            );
+            for stmt in calls {
+                if let SqlStatement::Call { call } = stmt {
+                    if call.procedure.is_empty() == call.function.is_empty() {
+                        return Err(format!(
+                            "phase '{}': call must set exactly one of procedure or function",
+                            phase.name
+                        ));
+                    }
+                }
+            }
+            for (i, stmt) in phase.sql.iter().enumerate() {
+                if stmt.is_empty() {
+                    return Err(format!(
+                        "phase '{}' has an empty sql entry ({})",
+                        phase.name,
+                        i + 1
+                    ));
+                }
+            }
+            Self::validate_hooks("phase", &phase.name, &phase.before, &phase.after)?;
+            let valid_scopes = ["per_set", "per_phase", "none"];
+            if !valid_scopes.contains(&phase.transaction.as_str()) {
+                return Err(format!(
+                    "phase '{}' has invalid transaction '{}' (supported: {})",
+                    phase.name,
+                    phase.transaction,
+                    valid_scopes.join(", ")
+                ));
+            }
             for ss in &phase.seed_sets {
                 Self::validate_seed_set(ss)?;
+                if ss.continue_on_error && phase.transaction == "per_phase" {
+                    return Err(format!(
+                        "seed_set '{}' sets continue_on_error, which cannot be used in phase '{}' with transaction 'per_phase'",
+                        ss.name, phase.name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_hooks(
+        kind: &str,
+        name: &str,
+        before: &[Hook],
+        after: &[Hook],
+    ) -> Result<(), String> {
+        for (stage, hooks) in [("before", before), ("after", after)] {
+            if let Some(i) = hooks.iter().position(Hook::is_empty) {
+                return Err(format!(
+                    "{} '{}' has an empty {} entry ({})",
+                    kind,
+                    name,
+                    stage,
+                    i + 1
+                ));
             }
         }
         Ok(())
@@ -246,6 +874,7 @@ impl SeedPlan {
         if ss.name.is_empty() {
             return Err("seed_set name must not be empty".into());
         }
+        Self::validate_hooks("seed_set", &ss.name, &ss.before, &ss.after)?;
         let valid_modes = ["once", "reconcile"];
         if !valid_modes.contains(&ss.mode.as_str()) {
             return Err(format!(
@@ -255,6 +884,60 @@ impl SeedPlan {
                 valid_modes.join(", ")
             ));
         }
+        if let Some(on_change) = &ss.on_change {
+            let valid_policies = ["reapply", "error", "ignore"];
+            if !valid_policies.contains(&on_change.as_str()) {
+                return Err(format!(
+                    "seed_set '{}' has invalid on_change '{}' (supported: {})",
+                    ss.name,
+                    on_change,
+                    valid_policies.join(", ")
+                ));
+            }
+            if ss.is_reconcile() {
+                return Err(format!(
+                    "seed_set '{}' sets on_change, which only applies to mode 'once'",
+                    ss.name
+                ));
+            }
+        }
+        if let Some(strategy) = &ss.reset_strategy {
+            if !RESET_STRATEGIES.contains(&strategy.as_str()) {
+                return Err(format!(
+                    "seed_set '{}' has invalid reset_strategy '{}' (supported: {})",
+                    ss.name,
+                    strategy,
+                    RESET_STRATEGIES.join(", ")
+                ));
+            }
+        }
+        if ss.reset && ss.is_reconcile() {
+            return Err(format!(
+                "seed_set '{}' sets reset, which only applies to mode 'once'",
+                ss.name
+            ));
+        }
+        if let Some(scripts) = &ss.scripts {
+            if !ss.tables.is_empty() {
+                return Err(format!(
+                    "seed_set '{}' cannot have both tables and scripts",
+                    ss.name
+                ));
+            }
+            if ss.is_reconcile() {
+                return Err(format!(
+                    "seed_set '{}' uses scripts, which only support mode 'once'",
+                    ss.name
+                ));
+            }
+            if scripts.dir.trim().is_empty() || scripts.pattern.trim().is_empty() {
+                return Err(format!(
+                    "seed_set '{}' scripts need a non-empty dir and pattern",
+                    ss.name
+                ));
+            }
+            return Ok(());
+        }
         if ss.tables.is_empty() {
             return Err(format!(
                 "seed_set '{}' must contain at least one table",
@@ -268,6 +951,123 @@ impl SeedPlan {
                     ss.name
                 ));
             }
+            let valid_conflicts = ["skip", "update", "error"];
+            if !valid_conflicts.contains(&ts.on_conflict.as_str()) {
+                return Err(format!(
+                    "table '{}' in seed_set '{}' has invalid on_conflict '{}' (supported: {})",
+                    ts.table,
+                    ss.name,
+                    ts.on_conflict,
+                    valid_conflicts.join(", ")
+                ));
+            }
+            if let Some(strategy) = &ts.reset_strategy {
+                if !RESET_STRATEGIES.contains(&strategy.as_str()) {
+                    return Err(format!(
+                        "table '{}' in seed_set '{}' has invalid reset_strategy '{}' (supported: {})",
+                        ts.table,
+                        ss.name,
+                        strategy,
+                        RESET_STRATEGIES.join(", ")
+                    ));
+                }
+            }
+            if ts.on_conflict != "skip" && ts.unique_key.is_empty() {
+                return Err(format!(
+                    "table '{}' in seed_set '{}' must have unique_key when on_conflict is '{}'",
+                    ts.table, ss.name, ts.on_conflict
+                ));
+            }
+            if ts.managed && ts.unique_key.is_empty() {
+                return Err(format!(
+                    "table '{}' in seed_set '{}' must have unique_key when managed is true",
+                    ts.table, ss.name
+                ));
+            }
+            if ss.reapplies_on_change() && ts.unique_key.is_empty() {
+                return Err(format!(
+                    "table '{}' in seed_set '{}' must have unique_key: the seed set is re-applied when it changes because {}",
+                    ts.table,
+                    ss.name,
+                    if ss.on_change.is_some() {
+                        "on_change is 'reapply'"
+                    } else if ss.tables.iter().any(TableSeed::upserts) {
+                        "it uses on_conflict 'update'"
+                    } else {
+                        "it has managed tables"
+                    }
+                ));
+            }
+            if let Some(auto_id) = &ts.auto_id {
+                let valid_id_types = ["integer", "uuid"];
+                if !valid_id_types.contains(&auto_id.id_type.as_str()) {
+                    return Err(format!(
+                        "table '{}' in seed_set '{}' has invalid auto_id.id_type '{}' (supported: {})",
+                        ts.table,
+                        ss.name,
+                        auto_id.id_type,
+                        valid_id_types.join(", ")
+                    ));
+                }
+            }
+            if ts.batch_size == 0 {
+                return Err(format!(
+                    "table '{}' in seed_set '{}': batch_size must be at least 1",
+                    ts.table, ss.name
+                ));
+            }
+            if ts
+                .sync_sequence
+                .as_ref()
+                .is_some_and(|c| c.trim().is_empty())
+            {
+                return Err(format!(
+                    "table '{}' in seed_set '{}': sync_sequence must name a column",
+                    ts.table, ss.name
+                ));
+            }
+            if ts.rows_file.as_ref().is_some_and(|f| f.trim().is_empty()) {
+                return Err(format!(
+                    "table '{}' in seed_set '{}': rows_file must not be empty",
+                    ts.table, ss.name
+                ));
+            }
+            if let Some(rf) = &ts.rows_from {
+                Self::validate_rows_from(rf, ts, ss)?;
+            }
+            if let Some(generate) = &ts.generate {
+                if generate.columns.is_empty() {
+                    return Err(format!(
+                        "table '{}' in seed_set '{}': generate.columns must not be empty",
+                        ts.table, ss.name
+                    ));
+                }
+                if let Some((column, _)) = generate
+                    .columns
+                    .iter()
+                    .find(|(c, e)| c.trim().is_empty() || e.trim().is_empty())
+                {
+                    return Err(format!(
+                        "table '{}' in seed_set '{}': generate.columns entry '{}' needs a column name and an expression",
+                        ts.table, ss.name, column
+                    ));
+                }
+            }
+            for (row_idx, row) in ts.rows.iter().enumerate() {
+                for val in row.values() {
+                    if let Some(expr) = val.as_str().and_then(|s| s.strip_prefix("@lookup:")) {
+                        Lookup::parse(expr).map_err(|e| {
+                            format!(
+                                "table '{}' in seed_set '{}': row {}: {}",
+                                ts.table,
+                                ss.name,
+                                row_idx + 1,
+                                e
+                            )
+                        })?;
+                    }
+                }
+            }
             if ss.is_reconcile() && ts.unique_key.is_empty() {
                 return Err(format!(
                     "table '{}' in seed_set '{}' must have unique_key when mode is 'reconcile'",
@@ -281,7 +1081,7 @@ impl SeedPlan {
                         ts.table, ss.name
                     ));
                 }
-                let reserved_keys = ["_ref"];
+                let reserved_keys = ["_ref", "_when"];
                 if let Some(reserved) = ts
                     .unique_key
                     .iter()
@@ -321,8 +1121,36 @@ impl SeedPlan {
         Ok(())
     }
 
+    fn validate_rows_from(rf: &RowsFrom, ts: &TableSeed, ss: &SeedSet) -> Result<(), String> {
+        if rf.csv.trim().is_empty() {
+            return Err(format!(
+                "table '{}' in seed_set '{}': rows_from.csv must not be empty",
+                ts.table, ss.name
+            ));
+        }
+        if rf.delimiter.len() != 1 {
+            return Err(format!(
+                "table '{}' in seed_set '{}': rows_from.delimiter must be a single ASCII character, got '{}'",
+                ts.table, ss.name, rf.delimiter
+            ));
+        }
+        if rf.header && !rf.columns.is_empty() {
+            return Err(format!(
+                "table '{}' in seed_set '{}': rows_from.columns can only be set when header is false",
+                ts.table, ss.name
+            ));
+        }
+        if !rf.header && rf.columns.is_empty() {
+            return Err(format!(
+                "table '{}' in seed_set '{}': rows_from.columns is required when header is false",
+                ts.table, ss.name
+            ));
+        }
+        Ok(())
+    }
+
     fn validate_wait_for(wf: &WaitForObject) -> Result<(), String> {
-        let valid_types = ["table", "view", "schema", "database"];
+        let valid_types = ["table", "view", "schema", "database", "column", "row"];
         if !valid_types.contains(&wf.obj_type.as_str()) {
             return Err(format!(
                 "unsupported wait_for type '{}' (supported: {})",
@@ -330,6 +1158,43 @@ impl SeedPlan {
                 valid_types.join(", ")
             ));
         }
+        if wf.is_column() {
+            if wf.table.trim().is_empty() || wf.column.trim().is_empty() {
+                return Err("wait_for type 'column' needs a table and a column".into());
+            }
+            if !wf.conditions.is_empty() {
+                return Err("wait_for type 'column' does not take where".into());
+            }
+            return Ok(());
+        }
+        if !wf.column.is_empty() {
+            return Err(format!(
+                "wait_for type '{}' does not take column; use type 'column'",
+                wf.obj_type
+            ));
+        }
+        if wf.is_row() {
+            if wf.table.trim().is_empty() || wf.conditions.is_empty() {
+                return Err("wait_for type 'row' needs a table and where conditions".into());
+            }
+            if let Some((column, _)) = wf
+                .conditions
+                .iter()
+                .find(|(_, v)| v.is_mapping() || v.is_sequence() || v.is_null())
+            {
+                return Err(format!(
+                    "wait_for row in '{}': where '{}' must be a string, number or boolean",
+                    wf.table, column
+                ));
+            }
+            return Ok(());
+        }
+        if !wf.table.is_empty() || !wf.conditions.is_empty() {
+            return Err(format!(
+                "wait_for type '{}' does not take table or where; use type 'row'",
+                wf.obj_type
+            ));
+        }
         if wf.name.is_empty() {
             return Err(format!(
                 "wait_for name must not be empty for type '{}'",
@@ -610,6 +1475,150 @@ phases:
         assert!(err.contains("not both"));
     }
 
+    #[test]
+    fn test_connect_retry() {
+        let spec = |retry: &str| {
+            format!(
+                "database:\n  driver: postgres\n  connect_retry:\n{}\nphases:\n  - name: p\n",
+                retry
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("    max_attempts: 5\n    max_delay: 10")).unwrap();
+        let retry = plan.database.connect_retry.unwrap().config().unwrap();
+        assert_eq!(retry.max_attempts, 5);
+        assert_eq!(retry.initial_delay, std::time::Duration::from_secs(1));
+        assert_eq!(retry.max_delay, std::time::Duration::from_secs(10));
+
+        let err = SeedPlan::from_yaml(&spec("    initial_delay: 1m")).unwrap_err();
+        assert!(err.contains("invalid connect_retry: max-delay"), "{}", err);
+        let err = SeedPlan::from_yaml(&spec("    initial_delay: soon")).unwrap_err();
+        assert!(
+            err.contains("invalid connect_retry: initial_delay"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_session_settings() {
+        let spec = |driver: &str, settings: &str| {
+            format!(
+                "database:\n  driver: {}\n{}\nphases:\n  - name: p\n",
+                driver, settings
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec(
+            "postgres",
+            "  statement_timeout: 5m\n  lock_timeout: 10\n  application_name: seeder\n  search_path: app, public",
+        ))
+        .unwrap();
+        assert_eq!(
+            plan.database.session_settings().unwrap(),
+            vec![
+                ("statement_timeout", "300000".to_string()),
+                ("lock_timeout", "10000".to_string()),
+                ("application_name", "seeder".to_string()),
+                ("search_path", "app, public".to_string()),
+            ]
+        );
+
+        let err = SeedPlan::from_yaml(&spec("postgres", "  lock_timeout: never")).unwrap_err();
+        assert!(err.contains("invalid lock_timeout"), "{}", err);
+        let err = SeedPlan::from_yaml(&spec("mysql", "  application_name: seeder")).unwrap_err();
+        assert_eq!(
+            err,
+            "database setting application_name is only supported by the postgres driver, not 'mysql'"
+        );
+    }
+
+    #[test]
+    fn test_reset_strategy() {
+        let spec = |set: &str, table: &str| {
+            format!(
+                r#"
+database:
+  driver: postgres
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        reset_strategy: {}
+        tables:
+          - table: parents
+            rows: []
+          - table: children
+            reset_strategy: {}
+            rows: []
+"#,
+                set, table
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("truncate_cascade", "delete")).unwrap();
+        let ss = &plan.phases[0].seed_sets[0];
+        assert_eq!(ss.reset_strategy(&ss.tables[0]), "truncate_cascade");
+        assert_eq!(ss.reset_strategy(&ss.tables[1]), "delete");
+
+        let err = SeedPlan::from_yaml(&spec("wipe", "delete")).unwrap_err();
+        assert!(err.contains("seed_set 's' has invalid reset_strategy 'wipe' (supported: delete, truncate, truncate_cascade)"), "{}", err);
+        let err = SeedPlan::from_yaml(&spec("delete", "drop")).unwrap_err();
+        assert!(
+            err.contains("table 'children' in seed_set 's' has invalid reset_strategy 'drop'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_managed_table() {
+        let spec = |unique_key: &str| {
+            format!(
+                r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        tables:
+          - table: roles
+            managed: true
+            {}
+            rows:
+              - name: admin
+"#,
+                unique_key
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("unique_key: [name]")).unwrap();
+        let ss = &plan.phases[0].seed_sets[0];
+        assert!(ss.tables[0].managed);
+        assert!(ss.reapplies_on_change());
+
+        let err = SeedPlan::from_yaml(&spec("")).unwrap_err();
+        assert_eq!(
+            err,
+            "table 'roles' in seed_set 's' must have unique_key when managed is true"
+        );
+    }
+
+    #[test]
+    fn test_tracking_schema() {
+        let spec = |driver: &str| {
+            format!(
+                "database:\n  driver: {}\n  tracking_schema: audit\nphases:\n  - name: p\n",
+                driver
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("postgres")).unwrap();
+        assert_eq!(
+            plan.database.qualified_tracking_table(),
+            "audit.initium_seed"
+        );
+        let err = SeedPlan::from_yaml(&spec("sqlite")).unwrap_err();
+        assert_eq!(
+            err,
+            "database setting tracking_schema is only supported by the postgres driver, not 'sqlite'"
+        );
+    }
+
     #[test]
     fn test_default_tracking_table() {
         let yaml = r#"
@@ -656,6 +1665,59 @@ phases:
         assert_eq!(dept_id.as_str().unwrap(), "@ref:dept_eng.id");
     }
 
+    #[test]
+    fn test_parse_lookup() {
+        let lookup = Lookup::parse(
+            "hr.departments.id where name = 'O''Brien team' AND active=true and floor=3",
+        )
+        .unwrap();
+        assert_eq!(lookup.table, "hr.departments");
+        assert_eq!(lookup.column, "id");
+        assert_eq!(
+            lookup.conditions,
+            vec![
+                (
+                    "name".into(),
+                    serde_yaml::Value::String("O'Brien team".into())
+                ),
+                ("active".into(), serde_yaml::Value::Bool(true)),
+                ("floor".into(), serde_yaml::Value::Number(3.into())),
+            ]
+        );
+
+        for (expr, reason) in [
+            ("departments.id", "missing WHERE"),
+            ("departments WHERE name='x'", "table.column"),
+            ("departments.id WHERE name", "column=value"),
+            ("departments.id WHERE name='x", "unterminated"),
+            ("departments.id WHERE name=Engineering", "numbers, booleans"),
+            ("departments.id WHERE name='x' OR id=1", "joined with AND"),
+        ] {
+            let err = Lookup::parse(expr).unwrap_err();
+            assert!(err.contains(reason), "{}: {}", expr, err);
+        }
+
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: phase1
+    seed_sets:
+      - name: refs
+        tables:
+          - table: employees
+            rows:
+              - department_id: "@lookup:departments.id"
+"#;
+        let err = SeedPlan::from_yaml(yaml).unwrap_err();
+        assert!(
+            err.contains("employees") && err.contains("row 1"),
+            "{}",
+            err
+        );
+    }
+
     #[test]
     fn test_parse_phases() {
         let yaml = r#"
@@ -824,6 +1886,69 @@ phases:
         assert_eq!(wf[1].timeout, None);
     }
 
+    #[test]
+    fn test_wait_for_row_and_column() {
+        let spec = |wait_for: &str| {
+            format!(
+                r#"
+database:
+  driver: sqlite
+  url: ":memory:"
+phases:
+  - name: p
+    wait_for:
+      - {}
+"#,
+                wait_for
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec(
+            "{type: row, table: schema_migrations, where: {version: '2024_10_01', step: 3}}",
+        ))
+        .unwrap();
+        let wf = &plan.phases[0].wait_for[0];
+        assert!(wf.is_row());
+        assert_eq!(
+            wf.target(),
+            "schema_migrations WHERE step=3 AND version='2024_10_01'"
+        );
+
+        let plan =
+            SeedPlan::from_yaml(&spec("{type: column, table: app.users, column: email}")).unwrap();
+        assert_eq!(plan.phases[0].wait_for[0].target(), "app.users.email");
+
+        for (wait_for, expected) in [
+            (
+                "{type: row, table: schema_migrations}",
+                "needs a table and where",
+            ),
+            (
+                "{type: row, where: {version: 1}}",
+                "needs a table and where",
+            ),
+            (
+                "{type: row, table: t, where: {version: null}}",
+                "must be a string, number or boolean",
+            ),
+            (
+                "{type: table, name: t, where: {version: 1}}",
+                "does not take table or where",
+            ),
+            ("{type: column, table: t}", "needs a table and a column"),
+            (
+                "{type: column, table: t, column: c, where: {version: 1}}",
+                "does not take where",
+            ),
+            (
+                "{type: row, table: t, column: c, where: {v: 1}}",
+                "does not take column",
+            ),
+        ] {
+            let err = SeedPlan::from_yaml(&spec(wait_for)).unwrap_err();
+            assert!(err.contains(expected), "{}: {}", wait_for, err);
+        }
+    }
+
     #[test]
     fn test_phase_without_seed_sets() {
         let yaml = r#"
@@ -968,4 +2093,431 @@ phases:
 "#;
         assert!(SeedPlan::from_yaml(yaml).is_ok());
     }
+
+    #[test]
+    fn test_on_conflict_validation() {
+        let spec = |on_conflict: &str, unique_key: &str| {
+            format!(
+                r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        tables:
+          - table: prices
+            unique_key: {unique_key}
+            on_conflict: {on_conflict}
+            rows:
+              - sku: a
+                price: 1
+          - table: notes
+            rows:
+              - text: hi
+"#
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("skip", "[sku]")).unwrap();
+        let ss = &plan.phases[0].seed_sets[0];
+        assert_eq!(ss.tables[1].on_conflict, "skip");
+        assert!(!ss.reapplies_on_change());
+
+        let err = SeedPlan::from_yaml(&spec("replace", "[sku]")).unwrap_err();
+        assert!(err.contains("invalid on_conflict 'replace'"), "{}", err);
+        let err = SeedPlan::from_yaml(&spec("error", "[]")).unwrap_err();
+        assert!(err.contains("must have unique_key when on_conflict is 'error'"));
+        let err = SeedPlan::from_yaml(&spec("update", "[sku]")).unwrap_err();
+        assert!(
+            err.contains("table 'notes' in seed_set 's' must have unique_key"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_phase_sql() {
+        let yaml = r#"
+phases:
+  - name: setup
+    sql:
+      - CREATE TABLE IF NOT EXISTS audit (id INTEGER)
+      - file: sql/grants.sql
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        assert_eq!(
+            plan.phases[0].sql,
+            vec![
+                SqlStatement::Inline("CREATE TABLE IF NOT EXISTS audit (id INTEGER)".into()),
+                SqlStatement::File {
+                    file: "sql/grants.sql".into()
+                },
+            ]
+        );
+
+        let yaml = r#"
+phases:
+  - name: setup
+    sql: [" "]
+"#;
+        let err = SeedPlan::from_yaml(yaml).unwrap_err();
+        assert!(
+            err.contains("phase 'setup' has an empty sql entry (1)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_scripts_seed_set() {
+        let yaml = r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: legacy
+        scripts:
+          dir: ./sql
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let scripts = plan.phases[0].seed_sets[0].scripts.as_ref().unwrap();
+        assert_eq!(scripts.dir, "./sql");
+        assert_eq!(scripts.pattern, "*.sql");
+
+        let yaml = r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: legacy
+        mode: reconcile
+        scripts: { dir: sql, pattern: "*.pgsql" }
+"#;
+        let err = SeedPlan::from_yaml(yaml).unwrap_err();
+        assert!(err.contains("only support mode 'once'"), "{}", err);
+
+        let yaml = r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: legacy
+        scripts: { dir: sql }
+        tables:
+          - table: t
+            rows: []
+"#;
+        let err = SeedPlan::from_yaml(yaml).unwrap_err();
+        assert!(
+            err.contains("cannot have both tables and scripts"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_rows_from_validation() {
+        let spec = |rows_from: &str| {
+            format!(
+                r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        tables:
+          - table: t
+            rows_from: {}
+"#,
+                rows_from
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("{ csv: data/t.csv }")).unwrap();
+        let rf = plan.phases[0].seed_sets[0].tables[0]
+            .rows_from
+            .as_ref()
+            .unwrap();
+        assert_eq!(rf.delimiter, ",");
+        assert!(rf.header);
+
+        let err = SeedPlan::from_yaml(&spec(r#"{ csv: t.csv, delimiter: "||" }"#)).unwrap_err();
+        assert!(err.contains("single ASCII character"), "{}", err);
+        let err = SeedPlan::from_yaml(&spec("{ csv: t.csv, header: false }")).unwrap_err();
+        assert!(err.contains("columns is required"), "{}", err);
+    }
+
+    #[test]
+    fn test_auto_id_type_validation() {
+        let spec = |id_type: &str| {
+            format!(
+                r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        tables:
+          - table: t
+            auto_id:
+              column: id
+              id_type: {}
+            rows: []
+"#,
+                id_type
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("uuid")).unwrap();
+        let auto_id = plan.phases[0].seed_sets[0].tables[0]
+            .auto_id
+            .as_ref()
+            .unwrap();
+        assert!(auto_id.is_uuid());
+        let err = SeedPlan::from_yaml(&spec("ulid")).unwrap_err();
+        assert!(err.contains("invalid auto_id.id_type 'ulid'"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_sync_sequence() {
+        let spec = |column: &str| {
+            format!(
+                r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        tables:
+          - table: plans
+            sync_sequence: "{}"
+            rows:
+              - id: 10
+                name: basic
+"#,
+                column
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("id")).unwrap();
+        assert_eq!(
+            plan.phases[0].seed_sets[0].tables[0]
+                .sync_sequence
+                .as_deref(),
+            Some("id")
+        );
+        let err = SeedPlan::from_yaml(&spec(" ")).unwrap_err();
+        assert!(err.contains("sync_sequence must name a column"), "{}", err);
+    }
+
+    #[test]
+    fn test_on_change_validation() {
+        let spec = |mode: &str, on_change: &str, unique_key: &str| {
+            format!(
+                r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: s
+        mode: {}
+        on_change: {}
+        tables:
+          - table: t
+            unique_key: {}
+            rows:
+              - name: a
+"#,
+                mode, on_change, unique_key
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("once", "error", "[]")).unwrap();
+        let ss = &plan.phases[0].seed_sets[0];
+        assert_eq!(ss.on_change_policy(), Some("error"));
+        assert!(!ss.reapplies_on_change());
+
+        let err = SeedPlan::from_yaml(&spec("once", "rerun", "[name]")).unwrap_err();
+        assert!(err.contains("invalid on_change 'rerun'"), "{}", err);
+        let err = SeedPlan::from_yaml(&spec("reconcile", "ignore", "[name]")).unwrap_err();
+        assert!(err.contains("only applies to mode 'once'"), "{}", err);
+        let err = SeedPlan::from_yaml(
+            &spec("reconcile", "ignore", "[name]").replace("on_change: ignore", "reset: true"),
+        )
+        .unwrap_err();
+        assert!(err.contains("sets reset, which only applies"), "{}", err);
+        let err = SeedPlan::from_yaml(&spec("once", "reapply", "[]")).unwrap_err();
+        assert!(
+            err.contains("re-applied when it changes because on_change is 'reapply'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_phase_connection_validation() {
+        let spec = |connection: &str| {
+            format!(
+                r#"
+databases:
+  reporting:
+    driver: sqlite
+    url: "report.db"
+    tracking_table: report_seed
+phases:
+  - name: p
+    connection: {}
+"#,
+                connection
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("reporting")).unwrap();
+        assert_eq!(plan.phases[0].connection, "reporting");
+        assert_eq!(plan.databases["reporting"].tracking_table, "report_seed");
+
+        let err = SeedPlan::from_yaml(&spec("audit")).unwrap_err();
+        assert!(
+            err.contains("phase 'p' uses connection 'audit', which is not defined in databases"),
+            "{}",
+            err
+        );
+        let err = SeedPlan::from_yaml(
+            &spec("reporting").replace("    url: \"report.db\"", "    url: x\n    host: h"),
+        )
+        .unwrap_err();
+        assert!(err.starts_with("databases entry 'reporting': "), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let yaml = r#"
+database:
+  driver: sqlite
+  url: "test.db"
+phases:
+  - name: p
+    before:
+      - "ALTER TABLE users DISABLE TRIGGER ALL"
+      - file: hooks/prepare.sql
+    after:
+      - command: ["./refresh.sh", "--all"]
+    seed_sets:
+      - name: s
+        after:
+          - "REFRESH MATERIALIZED VIEW user_stats"
+        tables:
+          - table: users
+            rows:
+              - name: alice
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let phase = &plan.phases[0];
+        assert_eq!(
+            phase.before,
+            vec![
+                Hook::Sql(SqlStatement::Inline(
+                    "ALTER TABLE users DISABLE TRIGGER ALL".into()
+                )),
+                Hook::Sql(SqlStatement::File {
+                    file: "hooks/prepare.sql".into()
+                }),
+            ]
+        );
+        assert_eq!(
+            phase.after,
+            vec![Hook::Command {
+                command: vec!["./refresh.sh".into(), "--all".into()]
+            }]
+        );
+        assert!(phase.seed_sets[0].before.is_empty());
+        assert_eq!(phase.seed_sets[0].after.len(), 1);
+
+        let err =
+            SeedPlan::from_yaml(&yaml.replace("[\"./refresh.sh\", \"--all\"]", "[]")).unwrap_err();
+        assert!(
+            err.contains("phase 'p' has an empty after entry (1)"),
+            "{}",
+            err
+        );
+        let err =
+            SeedPlan::from_yaml(&yaml.replace("\"REFRESH MATERIALIZED VIEW user_stats\"", "\" \""))
+                .unwrap_err();
+        assert!(
+            err.contains("seed_set 's' has an empty after entry (1)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_transaction_validation() {
+        let spec = |transaction: &str| {
+            format!(
+                r#"
+database:
+  driver: sqlite
+  url: "test.db"
+phases:
+  - name: p
+    transaction: {}
+    seed_sets:
+      - name: s
+        continue_on_error: true
+        tables:
+          - table: t
+            rows:
+              - id: 1
+"#,
+                transaction
+            )
+        };
+        let plan = SeedPlan::from_yaml(&spec("none")).unwrap();
+        assert_eq!(plan.phases[0].transaction, "none");
+        assert!(plan.phases[0].seed_sets[0].continue_on_error);
+
+        let err = SeedPlan::from_yaml(&spec("per_table")).unwrap_err();
+        assert!(
+            err.contains("phase 'p' has invalid transaction 'per_table' (supported: per_set, per_phase, none)"),
+            "{}",
+            err
+        );
+        let err = SeedPlan::from_yaml(&spec("per_phase")).unwrap_err();
+        assert!(
+            err.contains("seed_set 's' sets continue_on_error, which cannot be used in phase 'p'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_call_steps() {
+        let yaml = r#"
+database:
+  driver: postgres
+  url: "postgres://localhost/app"
+phases:
+  - name: p
+    sql:
+      - call:
+          procedure: reporting.refresh_rollups
+          args: [daily, 7, "$env:REGION"]
+    after:
+      - call: {function: bump_version}
+"#;
+        let plan = SeedPlan::from_yaml(yaml).unwrap();
+        let SqlStatement::Call { call } = &plan.phases[0].sql[0] else {
+            panic!("expected a call step");
+        };
+        assert_eq!(call.name(), "reporting.refresh_rollups");
+        assert!(!call.is_function());
+        assert_eq!(
+            call.to_string(),
+            "CALL reporting.refresh_rollups(\"daily\", 7, \"$env:REGION\")"
+        );
+        assert_eq!(
+            plan.phases[0].after,
+            vec![Hook::Sql(SqlStatement::Call {
+                call: RoutineCall {
+                    procedure: String::new(),
+                    function: "bump_version".into(),
+                    args: Vec::new(),
+                }
+            })]
+        );
+
+        let err = SeedPlan::from_yaml(&yaml.replace("{function: bump_version}", "{args: [1]}"))
+            .unwrap_err();
+        assert!(
+            err.contains("phase 'p': call must set exactly one of procedure or function"),
+            "{}",
+            err
+        );
+    }
 }
@@ -0,0 +1,534 @@
+use crate::seed::schema::{
+    GenerateRows, Hook, RowsFrom, SeedPhase, SeedPlan, SpecInclude, SqlStatement,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+type Row = HashMap<String, serde_yaml::Value>;
+
+/// Merge the phases of every spec listed in `plan.include`, recursively, into
+/// the plan. Included specs are templated with `render`, which also receives
+/// the file's path, like the main spec. A
+/// phase whose name is already defined adds its `wait_for`, `sql` and seed
+/// sets to the existing phase.
+pub fn load_includes(
+    plan: &mut SeedPlan,
+    spec_file: &Path,
+    render: &dyn Fn(&Path, &str) -> Result<String, String>,
+) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    if let Ok(path) = std::fs::canonicalize(spec_file) {
+        seen.insert(path);
+    }
+    let includes = std::mem::take(&mut plan.include);
+    let dir = spec_file.parent().unwrap_or(Path::new(""));
+    let mut phases = Vec::new();
+    collect_includes(dir, &includes, render, &mut seen, &mut phases)?;
+    for phase in phases {
+        match plan.phases.iter_mut().find(|p| p.name == phase.name) {
+            Some(existing) => {
+                existing.wait_for.extend(phase.wait_for);
+                existing.sql.extend(phase.sql);
+                existing.seed_sets.extend(phase.seed_sets);
+            }
+            None => plan.phases.push(phase),
+        }
+    }
+    Ok(())
+}
+
+fn collect_includes(
+    dir: &Path,
+    includes: &[String],
+    render: &dyn Fn(&Path, &str) -> Result<String, String>,
+    seen: &mut HashSet<PathBuf>,
+    phases: &mut Vec<SeedPhase>,
+) -> Result<(), String> {
+    for include in includes {
+        let path = dir.join(include);
+        let path = std::fs::canonicalize(&path)
+            .map_err(|e| format!("reading include '{}': {}", path.display(), e))?;
+        if !seen.insert(path.clone()) {
+            return Err(format!(
+                "spec '{}' is included more than once",
+                path.display()
+            ));
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("reading include '{}': {}", path.display(), e))?;
+        let rendered = render(&path, &content)?;
+        let spec: SpecInclude = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&rendered).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str(&rendered).map_err(|e| e.to_string())
+        }
+        .map_err(|e| format!("parsing include '{}': {}", path.display(), e))?;
+
+        // Paths inside an included spec are relative to that spec.
+        let include_dir = path.parent().unwrap_or(Path::new("/"));
+        for mut phase in spec.phases {
+            rebase_paths(&mut phase, include_dir);
+            phases.push(phase);
+        }
+        collect_includes(include_dir, &spec.include, render, seen, phases)?;
+    }
+    Ok(())
+}
+
+fn rebase_paths(phase: &mut SeedPhase, dir: &Path) {
+    let rebase = |p: &mut String| *p = dir.join(&*p).to_string_lossy().into_owned();
+    let hook_files = phase
+        .before
+        .iter_mut()
+        .chain(&mut phase.after)
+        .chain(
+            phase
+                .seed_sets
+                .iter_mut()
+                .flat_map(|ss| ss.before.iter_mut().chain(&mut ss.after)),
+        )
+        .filter_map(|hook| match hook {
+            Hook::Sql(stmt) => Some(stmt),
+            Hook::Command { .. } => None,
+        });
+    for stmt in phase.sql.iter_mut().chain(hook_files) {
+        if let SqlStatement::File { file } = stmt {
+            rebase(file);
+        }
+    }
+    for ss in &mut phase.seed_sets {
+        if let Some(scripts) = &mut ss.scripts {
+            rebase(&mut scripts.dir);
+        }
+        for ts in &mut ss.tables {
+            if let Some(file) = &mut ts.rows_file {
+                rebase(file);
+            }
+            if let Some(rf) = &mut ts.rows_from {
+                rebase(&mut rf.csv);
+            }
+        }
+    }
+}
+
+/// Append rows from each table's external source, resolving paths against
+/// `base_dir`, and re-validate the plan with the full set of rows.
+pub fn load_external_rows(plan: &mut SeedPlan, base_dir: &Path) -> Result<(), String> {
+    for phase in &mut plan.phases {
+        for ss in &mut phase.seed_sets {
+            for ts in &mut ss.tables {
+                let context = |e: String| {
+                    format!(
+                        "loading rows for table '{}' in seed_set '{}': {}",
+                        ts.table, ss.name, e
+                    )
+                };
+                let mut rows = Vec::new();
+                if let Some(file) = &ts.rows_file {
+                    rows.extend(read_yaml_rows(&base_dir.join(file)).map_err(context)?);
+                }
+                if let Some(rf) = &ts.rows_from {
+                    rows.extend(read_csv_rows(&base_dir.join(&rf.csv), rf).map_err(context)?);
+                }
+                ts.rows.extend(rows);
+            }
+        }
+    }
+    plan.validate()
+}
+
+/// Append the rows of each table's `generate`, evaluating its column
+/// expressions in `jinja_env` with `env` and the row's `index` in the
+/// context, and re-validate the plan.
+pub fn generate_rows(
+    plan: &mut SeedPlan,
+    jinja_env: &minijinja::Environment<'_>,
+    env: &minijinja::Value,
+) -> Result<(), String> {
+    for phase in &mut plan.phases {
+        for ss in &mut phase.seed_sets {
+            for ts in &mut ss.tables {
+                if let Some(generate) = &ts.generate {
+                    let rows = evaluate_generate(generate, jinja_env, env).map_err(|e| {
+                        format!(
+                            "generating rows for table '{}' in seed_set '{}': {}",
+                            ts.table, ss.name, e
+                        )
+                    })?;
+                    ts.rows.extend(rows);
+                }
+            }
+        }
+    }
+    plan.validate()
+}
+
+fn evaluate_generate(
+    generate: &GenerateRows,
+    jinja_env: &minijinja::Environment<'_>,
+    env: &minijinja::Value,
+) -> Result<Vec<Row>, String> {
+    // A generator per table, seeded from the spec, so reruns produce the
+    // same rows and unchanged seed sets stay unchanged.
+    let mut jinja_env = jinja_env.clone();
+    crate::template_funcs::register_random_funcs(&mut jinja_env, Some(generate.seed));
+    crate::fake::register(&mut jinja_env, Some(generate.seed));
+    let columns = generate
+        .columns
+        .iter()
+        .map(|(column, expr)| {
+            jinja_env
+                .compile_expression(expr)
+                .map(|compiled| (column, compiled))
+                .map_err(|e| format!("column '{}': {}", column, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut rows = Vec::with_capacity(generate.count);
+    for index in 1..=generate.count {
+        let ctx = minijinja::context!(env => env, index => index);
+        let mut row = Row::new();
+        for (column, expr) in &columns {
+            let value = expr
+                .eval(&ctx)
+                .and_then(|v| {
+                    serde_yaml::to_value(&v).map_err(|e| {
+                        minijinja::Error::new(minijinja::ErrorKind::BadSerialization, e.to_string())
+                    })
+                })
+                .map_err(|e| format!("row {}: column '{}': {}", index, column, e))?;
+            row.insert(column.to_string(), value);
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn read_yaml_rows(path: &Path) -> Result<Vec<Row>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("reading rows file '{}': {}", path.display(), e))?;
+    serde_yaml::from_str(&content)
+        .map_err(|e| format!("parsing rows file '{}': {}", path.display(), e))
+}
+
+fn read_csv_rows(path: &Path, rf: &RowsFrom) -> Result<Vec<Row>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(rf.delimiter.as_bytes()[0])
+        .has_headers(rf.header)
+        .from_path(path)
+        .map_err(|e| format!("opening CSV '{}': {}", path.display(), e))?;
+    let columns: Vec<String> = if rf.header {
+        reader
+            .headers()
+            .map_err(|e| format!("reading CSV header in '{}': {}", path.display(), e))?
+            .iter()
+            .map(|h| h.trim().to_string())
+            .collect()
+    } else {
+        rf.columns.clone()
+    };
+
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("reading CSV '{}': {}", path.display(), e))?;
+        if record.len() != columns.len() {
+            return Err(format!(
+                "CSV '{}' record {} has {} fields, expected {}",
+                path.display(),
+                i + 1,
+                record.len(),
+                columns.len()
+            ));
+        }
+        let row = columns
+            .iter()
+            .zip(record.iter())
+            .map(|(col, field)| (col.clone(), serde_yaml::Value::String(field.to_string())))
+            .collect();
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan_with_csv(rows_from: &str) -> SeedPlan {
+        let yaml = format!(
+            r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: countries
+        mode: reconcile
+        tables:
+          - table: countries
+            unique_key: [code]
+            rows:
+              - code: XX
+                name: Unknown
+            rows_from: {}
+"#,
+            rows_from
+        );
+        SeedPlan::from_yaml(&yaml).unwrap()
+    }
+
+    #[test]
+    fn test_csv_rows_appended_with_header() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("countries.csv"),
+            "code,name\nDE,Germany\nFR,\"France, Republic of\"\n",
+        )
+        .unwrap();
+        let mut plan = plan_with_csv("{ csv: countries.csv }");
+        load_external_rows(&mut plan, dir.path()).unwrap();
+
+        let rows = &plan.phases[0].seed_sets[0].tables[0].rows;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1]["code"], serde_yaml::Value::from("DE"));
+        assert_eq!(
+            rows[2]["name"],
+            serde_yaml::Value::from("France, Republic of")
+        );
+    }
+
+    #[test]
+    fn test_csv_without_header_uses_columns() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("countries.csv"), "DE;Germany\nFR;France\n").unwrap();
+        let mut plan = plan_with_csv(
+            r#"{ csv: countries.csv, delimiter: ";", header: false, columns: [code, name] }"#,
+        );
+        load_external_rows(&mut plan, dir.path()).unwrap();
+
+        let rows = &plan.phases[0].seed_sets[0].tables[0].rows;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2]["name"], serde_yaml::Value::from("France"));
+    }
+
+    #[test]
+    fn test_csv_rows_are_validated() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("countries.csv"), "name\nGermany\n").unwrap();
+        let mut plan = plan_with_csv("{ csv: countries.csv }");
+        let err = load_external_rows(&mut plan, dir.path()).unwrap_err();
+        assert!(err.contains("missing unique_key column 'code'"), "{}", err);
+
+        std::fs::write(dir.path().join("countries.csv"), "code,name\nDE\n").unwrap();
+        let mut plan = plan_with_csv("{ csv: countries.csv }");
+        let err = load_external_rows(&mut plan, dir.path()).unwrap_err();
+        assert!(err.contains("countries.csv"), "{}", err);
+    }
+
+    fn render(_path: &Path, content: &str) -> Result<String, String> {
+        Ok(content.to_string())
+    }
+
+    #[test]
+    fn test_includes_merge_phases_and_rebase_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("seeds/more")).unwrap();
+        std::fs::write(
+            dir.path().join("seeds/users.yaml"),
+            r#"
+include: [more/audit.yaml]
+phases:
+  - name: setup
+    sql:
+      - file: views.sql
+    seed_sets:
+      - name: users
+        tables:
+          - table: users
+            rows_file: users.rows.yaml
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("seeds/more/audit.yaml"),
+            r#"
+phases:
+  - name: audit
+    order: 2
+    seed_sets:
+      - name: audit
+        tables:
+          - table: audit
+            rows: []
+"#,
+        )
+        .unwrap();
+        let mut plan = SeedPlan::parse_yaml(
+            r#"
+include: [seeds/users.yaml]
+phases:
+  - name: setup
+    seed_sets:
+      - name: config
+        tables:
+          - table: config
+            rows: []
+"#,
+        )
+        .unwrap();
+        load_includes(&mut plan, &dir.path().join("seed.yaml"), &render).unwrap();
+        plan.validate().unwrap();
+
+        assert_eq!(plan.phases.len(), 2);
+        let setup = &plan.phases[0];
+        let names: Vec<&str> = setup.seed_sets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["config", "users"]);
+        let seeds_dir = std::fs::canonicalize(dir.path().join("seeds")).unwrap();
+        match &setup.sql[0] {
+            SqlStatement::File { file } => {
+                assert_eq!(Path::new(file), seeds_dir.join("views.sql"))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let rows_file = setup.seed_sets[1].tables[0].rows_file.as_ref().unwrap();
+        assert_eq!(Path::new(rows_file), seeds_dir.join("users.rows.yaml"));
+        assert_eq!(plan.phases[1].name, "audit");
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("db.yaml"), "database:\n  driver: sqlite\n").unwrap();
+        let mut plan = SeedPlan::parse_yaml("include: [db.yaml]").unwrap();
+        let err = load_includes(&mut plan, &dir.path().join("seed.yaml"), &render).unwrap_err();
+        assert!(
+            err.contains("parsing include") && err.contains("database"),
+            "{}",
+            err
+        );
+
+        std::fs::write(dir.path().join("loop.yaml"), "include: [loop.yaml]\n").unwrap();
+        let mut plan = SeedPlan::parse_yaml("include: [loop.yaml]").unwrap();
+        let err = load_includes(&mut plan, &dir.path().join("seed.yaml"), &render).unwrap_err();
+        assert!(err.contains("included more than once"), "{}", err);
+    }
+
+    #[test]
+    fn test_rows_file_rows_come_before_csv_rows() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("countries.yaml"),
+            "- code: DE\n  name: Germany\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("countries.csv"), "code,name\nFR,France\n").unwrap();
+        let mut plan = SeedPlan::parse_yaml(
+            r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: countries
+        tables:
+          - table: countries
+            rows_file: countries.yaml
+            rows_from: { csv: countries.csv }
+"#,
+        )
+        .unwrap();
+        load_external_rows(&mut plan, dir.path()).unwrap();
+
+        let rows = &plan.phases[0].seed_sets[0].tables[0].rows;
+        let codes: Vec<&str> = rows.iter().map(|r| r["code"].as_str().unwrap()).collect();
+        assert_eq!(codes, vec!["DE", "FR"]);
+    }
+
+    fn generate_plan(extra: &str) -> SeedPlan {
+        SeedPlan::parse_yaml(&format!(
+            r#"
+phases:
+  - name: p
+    seed_sets:
+      - name: demo
+        mode: reconcile
+        tables:
+          - table: users
+            unique_key: [email]
+            rows:
+              - email: admin@example.com
+            generate:
+              count: 3
+              columns:
+                email: "'user' ~ index ~ '@example.com'"
+                name: fake_name()
+                age: fake_int(18, 65)
+                active: "index is odd"
+                team: env.TEAM{}
+"#,
+            extra
+        ))
+        .unwrap()
+    }
+
+    fn generate(plan: &mut SeedPlan) -> Result<Vec<Row>, String> {
+        let jinja_env = crate::seed::template_env(false);
+        let env = minijinja::context!(TEAM => "core");
+        generate_rows(plan, &jinja_env, &env)?;
+        Ok(plan.phases[0].seed_sets[0].tables[0].rows.clone())
+    }
+
+    #[test]
+    fn test_generate_rows_appended_with_seeded_values() {
+        let rows = generate(&mut generate_plan("")).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0]["email"].as_str(), Some("admin@example.com"));
+        assert_eq!(rows[1]["email"].as_str(), Some("user1@example.com"));
+        assert_eq!(rows[3]["email"].as_str(), Some("user3@example.com"));
+        assert_eq!(rows[1]["active"], serde_yaml::Value::Bool(true));
+        assert_eq!(rows[2]["active"], serde_yaml::Value::Bool(false));
+        assert_eq!(rows[1]["team"].as_str(), Some("core"));
+        let age = rows[2]["age"].as_i64().unwrap();
+        assert!((18..=65).contains(&age));
+
+        // The same seed generates the same rows; another seed other rows.
+        assert_eq!(rows, generate(&mut generate_plan("")).unwrap());
+        assert_ne!(
+            rows,
+            generate(&mut generate_plan("\n              seed: 7")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generate_rows_errors() {
+        let mut plan = generate_plan("");
+        plan.phases[0].seed_sets[0].tables[0]
+            .generate
+            .as_mut()
+            .unwrap()
+            .columns
+            .insert("age".into(), "fake_int(65, 18)".into());
+        let err = generate(&mut plan).unwrap_err();
+        assert!(
+            err.contains("table 'users'") && err.contains("row 1: column 'age'"),
+            "{}",
+            err
+        );
+
+        let mut plan = generate_plan("");
+        let columns = &mut plan.phases[0].seed_sets[0].tables[0]
+            .generate
+            .as_mut()
+            .unwrap()
+            .columns;
+        columns.insert("name".into(), "fake_name(".into());
+        let err = generate(&mut plan).unwrap_err();
+        assert!(err.contains("column 'name'"), "{}", err);
+
+        // Generated rows are validated like any others.
+        let mut plan = generate_plan("");
+        plan.phases[0].seed_sets[0].tables[0]
+            .generate
+            .as_mut()
+            .unwrap()
+            .columns
+            .remove("email");
+        let err = generate(&mut plan).unwrap_err();
+        assert!(err.contains("missing unique_key column 'email'"), "{}", err);
+    }
+}
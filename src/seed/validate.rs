@@ -0,0 +1,427 @@
+use crate::logging::Logger;
+use crate::seed::schema::SeedPlan;
+use crate::seed::source;
+use minijinja::value::{Object, ObjectRepr};
+use minijinja::Value;
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// JSON Schema for seed specs, published at `schemas/seed-spec.schema.json`.
+const SPEC_SCHEMA: &str = include_str!("../../schemas/seed-spec.schema.json");
+
+/// Check a seed spec without connecting to a database: render it with
+/// placeholder env vars, check the main spec and every include against the
+/// published JSON Schema, then run the semantic checks `seed` applies.
+/// Violations are logged as `file:line:column: message`.
+pub fn run(log: &Logger, spec_file: &str) -> Result<(), String> {
+    let content = std::fs::read_to_string(spec_file)
+        .map_err(|e| format!("reading seed spec '{}': {}", spec_file, e))?;
+    let (spec_schema, include_schema) = validators()?;
+    let errors = RefCell::new(Vec::new());
+
+    let rendered = render_placeholder(&content, false)?;
+    errors
+        .borrow_mut()
+        .extend(check_document(spec_file, &rendered, &spec_schema)?);
+    if errors.borrow().is_empty() {
+        let mut plan =
+            SeedPlan::parse_yaml(&rendered).map_err(|e| format!("{}: {}", spec_file, e))?;
+        if plan.strict {
+            plan = SeedPlan::parse_yaml(&render_placeholder(&content, true)?)
+                .map_err(|e| format!("{}: {}", spec_file, e))?;
+        }
+        let strict = plan.strict;
+        let spec_path = Path::new(spec_file);
+        source::load_includes(&mut plan, spec_path, &|path, c| {
+            let rendered = render_placeholder(c, strict)?;
+            let violations =
+                check_document(&path.display().to_string(), &rendered, &include_schema)?;
+            if violations.is_empty() {
+                Ok(rendered)
+            } else {
+                errors.borrow_mut().extend(violations);
+                Err(format!(
+                    "include '{}' does not match the schema",
+                    path.display()
+                ))
+            }
+        })
+        .or_else(|e| {
+            if errors.borrow().is_empty() {
+                Err(e)
+            } else {
+                Ok(())
+            }
+        })?;
+        if errors.borrow().is_empty() {
+            let base_dir = spec_path
+                .parent()
+                .map(|p| p.to_path_buf())
+                .unwrap_or_default();
+            source::load_external_rows(&mut plan, &base_dir)
+                .map_err(|e| format!("{}: {}", spec_file, e))?;
+            source::generate_rows(
+                &mut plan,
+                &super::template_env(strict),
+                &Value::from_object(PlaceholderEnv),
+            )
+            .map_err(|e| format!("{}: {}", spec_file, e))?;
+            let seed_sets: usize = plan.phases.iter().map(|p| p.seed_sets.len()).sum();
+            log.info(
+                "seed spec is valid",
+                &[
+                    ("spec", spec_file),
+                    ("phases", &plan.phases.len().to_string()),
+                    ("seed_sets", &seed_sets.to_string()),
+                ],
+            );
+            return Ok(());
+        }
+    }
+
+    let errors = errors.into_inner();
+    for e in &errors {
+        log.error(e, &[]);
+    }
+    Err(format!(
+        "seed spec '{}' is invalid: {} schema violation(s)",
+        spec_file,
+        errors.len()
+    ))
+}
+
+/// Validators for a main spec and for an included spec, which may only hold
+/// `phases` and `include`.
+fn validators() -> Result<(jsonschema::Validator, jsonschema::Validator), String> {
+    let schema: serde_json::Value = serde_json::from_str(SPEC_SCHEMA)
+        .map_err(|e| format!("parsing seed spec schema: {}", e))?;
+    let mut include = schema.clone();
+    if let Some(obj) = include.as_object_mut() {
+        obj.remove("properties");
+        obj.remove("additionalProperties");
+        obj.insert("$ref".into(), "#/$defs/include".into());
+    }
+    let compile = |s: &serde_json::Value| {
+        jsonschema::validator_for(s).map_err(|e| format!("compiling seed spec schema: {}", e))
+    };
+    Ok((compile(&schema)?, compile(&include)?))
+}
+
+/// Parse a rendered spec and return its schema violations. A document that
+/// does not parse is an error on its own.
+fn check_document(
+    file: &str,
+    rendered: &str,
+    schema: &jsonschema::Validator,
+) -> Result<Vec<String>, String> {
+    // YAML is a superset of JSON, so one parser covers both.
+    let instance: serde_json::Value =
+        serde_yaml::from_str(rendered).map_err(|e| match e.location() {
+            Some(loc) => format!("{}:{}:{}: {}", file, loc.line(), loc.column(), e),
+            None => format!("{}: {}", file, e),
+        })?;
+    Ok(schema
+        .iter_errors(&instance)
+        .map(|e| {
+            let path = e.instance_path.as_str();
+            let (line, column) = locate(rendered, path);
+            format!(
+                "{}:{}:{}: {}: {}",
+                file,
+                line,
+                column,
+                if path.is_empty() { "/" } else { path },
+                e
+            )
+        })
+        .collect())
+}
+
+/// Render a spec with an `env` that resolves unset variables to `${NAME}`
+/// placeholders, so specs can be checked without their runtime environment.
+fn render_placeholder(content: &str, strict: bool) -> Result<String, String> {
+    let mut jinja_env = super::template_env(strict);
+    jinja_env.add_filter("default", filter_default);
+    jinja_env.add_filter("d", filter_default);
+    super::render_with(jinja_env, content, Value::from_object(PlaceholderEnv))
+}
+
+#[derive(Debug)]
+struct PlaceholderEnv;
+
+impl Object for PlaceholderEnv {
+    fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+        let name = key.as_str()?;
+        Some(match std::env::var(name) {
+            Ok(value) => Value::from(value),
+            Err(_) => Value::from_object(Placeholder(name.to_string())),
+        })
+    }
+}
+
+/// An unset env var. It renders as `${NAME}` but counts as undefined for
+/// `default`, so fallback values still apply.
+#[derive(Debug)]
+struct Placeholder(String);
+
+impl Object for Placeholder {
+    fn repr(self: &Arc<Self>) -> ObjectRepr {
+        ObjectRepr::Plain
+    }
+
+    fn render(self: &Arc<Self>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${{{}}}", self.0)
+    }
+}
+
+fn filter_default(value: Value, other: Option<Value>, lax: Option<bool>) -> Value {
+    if value.downcast_object_ref::<Placeholder>().is_some() {
+        return other.unwrap_or_else(|| Value::from(""));
+    }
+    minijinja::filters::default(&value, other, lax)
+}
+
+/// A non-blank, non-comment line of a YAML document.
+struct Line<'a> {
+    number: usize,
+    column: usize,
+    text: &'a str,
+}
+
+/// Best-effort 1-based line and column of the node at JSON pointer `pointer`
+/// in a block-style YAML document. Flow collections and JSON documents are
+/// not descended into; the position of the deepest node found is returned.
+fn locate(doc: &str, pointer: &str) -> (usize, usize) {
+    let mut scope: Vec<Line> = doc
+        .lines()
+        .enumerate()
+        .filter_map(|(i, raw)| {
+            let text = raw.trim_start();
+            if text.is_empty() || text.starts_with('#') || text == "---" {
+                return None;
+            }
+            Some(Line {
+                number: i + 1,
+                column: raw.len() - text.len(),
+                text,
+            })
+        })
+        .collect();
+    let mut pos = (1, 1);
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        let Some(indent) = scope.first().map(|l| l.column) else {
+            break;
+        };
+        let at_indent = |l: &Line| l.column == indent;
+        let is_item = |l: &Line| l.text == "-" || l.text.starts_with("- ");
+        let found = if scope.first().is_some_and(is_item) {
+            segment.parse::<usize>().ok().and_then(|idx| {
+                scope
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, l)| at_indent(l) && is_item(l))
+                    .nth(idx)
+                    .map(|(i, _)| i)
+            })
+        } else {
+            scope
+                .iter()
+                .position(|l| at_indent(l) && mapping_key(l.text) == Some(segment.as_str()))
+        };
+        let Some(i) = found else {
+            break;
+        };
+        let line = &scope[i];
+        pos = (line.number, line.column + 1);
+        let mut next = Vec::new();
+        if is_item(line) {
+            // The item's first entry shares the line with the dash.
+            let rest = line.text[1..].trim_start();
+            if !rest.is_empty() {
+                next.push(Line {
+                    number: line.number,
+                    column: line.column + line.text.len() - rest.len(),
+                    text: rest,
+                });
+            }
+        }
+        let column = line.column;
+        let nested_item = !is_item(line);
+        next.extend(
+            scope
+                .drain(i + 1..)
+                // A mapping value may be a sequence at the key's own indent.
+                .take_while(|l| {
+                    l.column > column || (nested_item && l.column == column && is_item(l))
+                }),
+        );
+        scope = next;
+    }
+    pos
+}
+
+/// The key of a `key: value` or `key:` line, unquoted.
+fn mapping_key(text: &str) -> Option<&str> {
+    if let Some(quote) = text.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let end = text[1..].find(quote)? + 1;
+        return text[end + 1..]
+            .trim_start()
+            .starts_with(':')
+            .then(|| &text[1..end]);
+    }
+    let end = text
+        .find(": ")
+        .or_else(|| text.strip_suffix(':').map(str::len))?;
+    Some(text[..end].trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_logger() -> Logger {
+        Logger::new(
+            Box::new(std::io::sink()),
+            false,
+            crate::logging::Level::Info,
+        )
+    }
+
+    fn write_spec(dir: &Path, name: &str, content: &str) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_valid_spec_with_unset_env() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let spec = write_spec(
+            dir.path(),
+            "seed.yaml",
+            r#"
+strict: true
+database:
+  driver: postgres
+  url: "{{ env.UNSET_VALIDATE_URL_XYZ }}"
+  port: {{ env.UNSET_VALIDATE_PORT_XYZ | default(5432) }}
+phases:
+  - name: setup
+    seed_sets:
+      - name: users
+        tables:
+          - table: users
+            rows:
+              - email: "{{ env.UNSET_VALIDATE_EMAIL_XYZ }}"
+"#,
+        );
+        run(&test_logger(), &spec).unwrap();
+    }
+
+    #[test]
+    fn test_schema_violations_report_positions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let spec = write_spec(
+            dir.path(),
+            "seed.yaml",
+            r#"database:
+  driver: sqlite
+phases:
+  - name: setup
+    seed_sets:
+      - name: users
+        mode: sometimes
+        tables:
+          - table: users
+            on_conflit: skip
+"#,
+        );
+        let rendered = std::fs::read_to_string(&spec).unwrap();
+        let (schema, _) = validators().unwrap();
+        let errors = check_document("seed.yaml", &rendered, &schema).unwrap();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("seed.yaml:7:9: /phases/0/seed_sets/0/mode:")),
+            "{:?}",
+            errors
+        );
+        assert!(
+            errors.iter().any(
+                |e| e.starts_with("seed.yaml:9:11: /phases/0/seed_sets/0/tables/0:")
+                    && e.contains("on_conflit")
+            ),
+            "{:?}",
+            errors
+        );
+        let err = run(&test_logger(), &spec).unwrap_err();
+        assert!(err.contains("2 schema violation(s)"), "{}", err);
+    }
+
+    #[test]
+    fn test_include_checked_against_include_schema() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_spec(
+            dir.path(),
+            "more.yaml",
+            "database:\n  driver: sqlite\nphases: []\n",
+        );
+        let spec = write_spec(
+            dir.path(),
+            "seed.yaml",
+            "database:\n  driver: sqlite\ninclude: [more.yaml]\n",
+        );
+        let err = run(&test_logger(), &spec).unwrap_err();
+        assert!(err.contains("1 schema violation(s)"), "{}", err);
+    }
+
+    #[test]
+    fn test_semantic_and_parse_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let spec = write_spec(
+            dir.path(),
+            "seed.yaml",
+            r#"
+phases:
+  - name: setup
+    seed_sets:
+      - name: s
+        mode: reconcile
+        tables:
+          - table: t
+            rows:
+              - a: b
+"#,
+        );
+        let err = run(&test_logger(), &spec).unwrap_err();
+        assert!(err.contains("must have unique_key"), "{}", err);
+
+        let spec = write_spec(dir.path(), "broken.yaml", "phases:\n  - name: [\n");
+        let err = run(&test_logger(), &spec).unwrap_err();
+        assert!(err.contains("broken.yaml:"), "{}", err);
+    }
+
+    #[test]
+    fn test_examples_are_valid() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/seed");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            run(&test_logger(), path.to_str().unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        }
+    }
+
+    #[test]
+    fn test_locate() {
+        let doc = "a:\n  b:\n  - x: 1\n    y: 2\n  - x: 3\n# note\n  c: {d: 1}\n";
+        assert_eq!(locate(doc, ""), (1, 1));
+        assert_eq!(locate(doc, "/a/b/1"), (5, 3));
+        assert_eq!(locate(doc, "/a/b/0/y"), (4, 5));
+        assert_eq!(locate(doc, "/a/c/d"), (7, 3));
+        assert_eq!(locate("\"k\": 1\n", "/k"), (1, 1));
+    }
+}
@@ -1,21 +1,393 @@
 use base64::prelude::*;
+use hmac::{Hmac, Mac};
+use md5::Md5;
 use minijinja::value::Value;
-use sha2::{Digest, Sha256};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Largest file `file()` will inline into a template.
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024;
 
 /// Register all custom template filters on the given MiniJinja environment.
 pub fn register(env: &mut minijinja::Environment<'_>) {
     env.add_filter("sha256", filter_sha256);
+    env.add_filter("sha512", filter_sha512);
+    env.add_filter("sha1", filter_sha1);
+    env.add_filter("md5", filter_md5);
+    env.add_filter("hmac_sha256", filter_hmac_sha256);
     env.add_filter("base64_encode", filter_base64_encode);
     env.add_filter("base64_decode", filter_base64_decode);
+    // Replaces the MiniJinja builtin, which leaves `/` unencoded and so
+    // corrupts credentials embedded in connection URLs.
+    env.add_filter("urlencode", filter_urlencode);
+    env.add_filter("urldecode", filter_urldecode);
+    env.add_filter("to_json", filter_to_json);
+    env.add_filter("from_json", filter_from_json);
+    env.add_filter("to_yaml", filter_to_yaml);
+    env.add_filter("from_yaml", filter_from_yaml);
+    env.add_filter("bcrypt", filter_bcrypt);
+    env.add_filter("argon2", filter_argon2);
+    env.add_filter("htpasswd", filter_htpasswd);
+    register_random_funcs(env, random_seed());
+    env.add_filter("regex_match", filter_regex_match);
+    env.add_filter("regex_replace", filter_regex_replace);
+    env.add_filter("regex_find_all", filter_regex_find_all);
+    env.add_function("now", func_now);
+    env.add_function("timestamp", func_timestamp);
+    env.add_function("date_format", func_date_format);
+    env.add_filter("date_format", func_date_format);
+    // Helm/Sprig compatibility. `default`, `trim`, `replace`, `split`, `join`,
+    // `upper`, `lower`, `indent` and `dict()` are MiniJinja builtins.
+    env.add_filter("nindent", filter_nindent);
+    env.add_filter("quote", filter_quote);
+    env.add_filter("squote", filter_squote);
+    env.add_filter("ternary", filter_ternary);
+    env.add_filter("merge", filter_merge);
+    env.add_filter("merge_overwrite", filter_merge_overwrite);
+}
+
+fn bcrypt_hash(
+    value: &str,
+    cost: Option<u32>,
+    version: bcrypt::Version,
+    filter: &str,
+) -> Result<String, minijinja::Error> {
+    bcrypt::hash_with_result(value, cost.unwrap_or(bcrypt::DEFAULT_COST))
+        .map(|h| h.format_for_version(version))
+        .map_err(|e| {
+            minijinja::Error::new(
+                minijinja::ErrorKind::InvalidOperation,
+                format!("{}: {}", filter, e),
+            )
+        })
+}
+
+/// bcrypt hash in `$2b$` format; `cost` defaults to 12.
+fn filter_bcrypt(value: String, cost: Option<u32>) -> Result<String, minijinja::Error> {
+    bcrypt_hash(&value, cost, bcrypt::Version::TwoB, "bcrypt")
+}
+
+/// Argon2id PHC string with the crate's recommended parameters and a random
+/// salt.
+fn filter_argon2(value: String) -> Result<String, minijinja::Error> {
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    let salt_bytes: [u8; 16] = rand::random();
+    let err = |e: argon2::password_hash::Error| {
+        minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!("argon2: {}", e),
+        )
+    };
+    let salt = SaltString::encode_b64(&salt_bytes).map_err(err)?;
+    argon2::Argon2::default()
+        .hash_password(value.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(err)
+}
+
+/// Apache htpasswd line `user:$2y$...` (bcrypt, as written by `htpasswd -B`).
+fn filter_htpasswd(
+    value: String,
+    user: String,
+    cost: Option<u32>,
+) -> Result<String, minijinja::Error> {
+    if user.is_empty() || user.contains(':') {
+        return Err(minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            "htpasswd: user must be non-empty and must not contain ':'",
+        ));
+    }
+    let hash = bcrypt_hash(&value, cost, bcrypt::Version::TwoY, "htpasswd")?;
+    Ok(format!("{}:{}", user, hash))
+}
+
+fn compile_regex(filter: &str, pattern: &str) -> Result<regex::Regex, minijinja::Error> {
+    regex::Regex::new(pattern).map_err(|e| {
+        minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!("{}: invalid pattern: {}", filter, e),
+        )
+    })
+}
+
+/// True if `pattern` matches anywhere in the input (anchor with `^…$` for a
+/// full match).
+fn filter_regex_match(value: String, pattern: String) -> Result<bool, minijinja::Error> {
+    Ok(compile_regex("regex_match", &pattern)?.is_match(&value))
+}
+
+/// Replace every match; `$1`/`${name}` in the replacement refer to groups.
+fn filter_regex_replace(
+    value: String,
+    pattern: String,
+    replacement: String,
+) -> Result<String, minijinja::Error> {
+    Ok(compile_regex("regex_replace", &pattern)?
+        .replace_all(&value, replacement.as_str())
+        .into_owned())
+}
+
+/// All non-overlapping matches, optionally limited to the first `n`.
+fn filter_regex_find_all(
+    value: String,
+    pattern: String,
+    n: Option<usize>,
+) -> Result<Value, minijinja::Error> {
+    let re = compile_regex("regex_find_all", &pattern)?;
+    Ok(re
+        .find_iter(&value)
+        .take(n.unwrap_or(usize::MAX))
+        .map(|m| Value::from(m.as_str()))
+        .collect())
+}
+
+/// Current UTC time shifted by an optional signed duration (`"24h"`, `"-30m"`).
+fn offset_now(offset: Option<&str>) -> Result<chrono::DateTime<chrono::Utc>, minijinja::Error> {
+    let now = chrono::Utc::now();
+    let Some(offset) = offset else {
+        return Ok(now);
+    };
+    let (negative, magnitude) = match offset.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, offset.trim().trim_start_matches('+')),
+    };
+    let invalid = |e: String| {
+        minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!("invalid time offset '{}': {}", offset, e),
+        )
+    };
+    let dur = crate::duration::parse_duration(magnitude).map_err(invalid)?;
+    let dur = chrono::Duration::from_std(dur).map_err(|e| invalid(e.to_string()))?;
+    let shifted = if negative {
+        now.checked_sub_signed(dur)
+    } else {
+        now.checked_add_signed(dur)
+    };
+    shifted.ok_or_else(|| invalid("out of range".into()))
+}
+
+/// RFC 3339 UTC timestamp with second precision, e.g. `2026-01-02T03:04:05Z`.
+fn func_now(offset: Option<String>) -> Result<String, minijinja::Error> {
+    Ok(offset_now(offset.as_deref())?.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+}
+
+/// Unix timestamp in seconds.
+fn func_timestamp(offset: Option<String>) -> Result<i64, minijinja::Error> {
+    Ok(offset_now(offset.as_deref())?.timestamp())
+}
+
+/// Format a unix timestamp or RFC 3339 string with a strftime pattern
+/// (default RFC 3339). The result is always in UTC.
+fn func_date_format(value: Value, format: Option<String>) -> Result<String, minijinja::Error> {
+    use chrono::format::{Item, StrftimeItems};
+    use std::fmt::Write;
+    let invalid = |msg: String| minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, msg);
+    let dt = if let Ok(secs) = i64::try_from(value.clone()) {
+        chrono::DateTime::from_timestamp(secs, 0)
+            .ok_or_else(|| invalid(format!("date_format: timestamp {} out of range", secs)))?
+    } else if let Some(s) = value.as_str() {
+        chrono::DateTime::parse_from_rfc3339(s)
+            .map_err(|e| invalid(format!("date_format: invalid RFC 3339 date '{}': {}", s, e)))?
+            .with_timezone(&chrono::Utc)
+    } else {
+        return Err(invalid(format!(
+            "date_format: expected a unix timestamp or RFC 3339 string, got {}",
+            value.kind()
+        )));
+    };
+    let format = format.as_deref().unwrap_or("%Y-%m-%dT%H:%M:%SZ");
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.iter().any(|i| matches!(i, Item::Error)) {
+        return Err(invalid(format!("date_format: invalid format '{}'", format)));
+    }
+    let mut out = String::new();
+    write!(out, "{}", dt.format_with_items(items.into_iter()))
+        .map_err(|_| invalid(format!("date_format: cannot format with '{}'", format)))?;
+    Ok(out)
+}
+
+/// Environment variable holding a fixed seed for the random template
+/// functions, so tests and reproducible builds get stable output.
+pub const RANDOM_SEED_ENV: &str = "INITIUM_RANDOM_SEED";
+
+/// Longest string `random_string()` will generate.
+const MAX_RANDOM_STRING_LEN: usize = 4096;
+
+pub(crate) fn random_seed() -> Option<u64> {
+    std::env::var(RANDOM_SEED_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Register `uuid4()`, `random_string(n, charset)` and `random_int(min, max)`,
+/// drawing from a single generator that is deterministic when `seed` is set.
+pub(crate) fn register_random_funcs(env: &mut minijinja::Environment<'_>, seed: Option<u64>) {
+    let rng = Arc::new(Mutex::new(match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }));
+    let r = rng.clone();
+    env.add_function("uuid4", move || func_uuid4(&mut *r.lock().unwrap()));
+    let r = rng.clone();
+    env.add_function("random_string", move |n: usize, charset: Option<String>| {
+        func_random_string(&mut r.lock().unwrap(), n, charset.as_deref())
+    });
+    env.add_function("random_int", move |min: i64, max: i64| {
+        func_random_int(&mut rng.lock().unwrap(), min, max)
+    });
+}
+
+/// A random (version 4) UUID; also used for `auto_id.id_type: uuid` seeds.
+pub(crate) fn func_uuid4(rng: &mut impl Rng) -> String {
+    let mut b: [u8; 16] = rng.gen();
+    b[6] = (b[6] & 0x0f) | 0x40; // version 4
+    b[8] = (b[8] & 0x3f) | 0x80; // RFC 4122 variant
+    let h = hex_encode(&b);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &h[0..8],
+        &h[8..12],
+        &h[12..16],
+        &h[16..20],
+        &h[20..32]
+    )
+}
+
+/// `charset` is a named set (`alnum`, `alpha`, `lower`, `upper`, `numeric`,
+/// `hex`) or a literal list of characters; defaults to `alnum`.
+fn func_random_string(
+    rng: &mut StdRng,
+    n: usize,
+    charset: Option<&str>,
+) -> Result<String, minijinja::Error> {
+    const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+    const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const DIGITS: &str = "0123456789";
+    if n > MAX_RANDOM_STRING_LEN {
+        return Err(minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!(
+                "random_string: length {} exceeds limit of {}",
+                n, MAX_RANDOM_STRING_LEN
+            ),
+        ));
+    }
+    let chars: Vec<char> = match charset.unwrap_or("alnum") {
+        "alnum" => format!("{}{}{}", LOWER, UPPER, DIGITS).chars().collect(),
+        "alpha" => format!("{}{}", LOWER, UPPER).chars().collect(),
+        "lower" => LOWER.chars().collect(),
+        "upper" => UPPER.chars().collect(),
+        "numeric" => DIGITS.chars().collect(),
+        "hex" => "0123456789abcdef".chars().collect(),
+        literal => literal.chars().collect(),
+    };
+    if chars.is_empty() {
+        return Err(minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            "random_string: charset must not be empty",
+        ));
+    }
+    Ok((0..n)
+        .map(|_| chars[rng.gen_range(0..chars.len())])
+        .collect())
+}
+
+/// Random integer in `[min, max)`, matching Sprig's `randInt`.
+fn func_random_int(rng: &mut StdRng, min: i64, max: i64) -> Result<i64, minijinja::Error> {
+    if min >= max {
+        return Err(minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!("random_int: min ({}) must be less than max ({})", min, max),
+        ));
+    }
+    Ok(rng.gen_range(min..max))
+}
+
+/// Register `file(path)` and `fileExists(path)`. Paths are resolved against
+/// `root`; absolute paths must lie under it and traversal is rejected.
+pub fn register_file_funcs(env: &mut minijinja::Environment<'_>, root: &str) {
+    let file_root = root.to_string();
+    env.add_function("file", move |path: String| func_file(&file_root, &path));
+    let exists_root = root.to_string();
+    env.add_function("fileExists", move |path: String| {
+        func_file_exists(&exists_root, &path)
+    });
+}
+
+fn resolve_file(root: &str, path: &str) -> Result<PathBuf, minijinja::Error> {
+    let invalid = |msg: String| minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, msg);
+    let rel = if Path::new(path).is_absolute() {
+        Path::new(path)
+            .strip_prefix(root)
+            .map_err(|_| invalid(format!("file: {:?} is outside file root {:?}", path, root)))?
+            .to_string_lossy()
+            .to_string()
+    } else {
+        path.to_string()
+    };
+    crate::safety::validate_file_path(root, &rel).map_err(|e| invalid(format!("file: {}", e)))
+}
+
+fn func_file(root: &str, path: &str) -> Result<String, minijinja::Error> {
+    let resolved = resolve_file(root, path)?;
+    let invalid = |msg: String| minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, msg);
+    let meta = std::fs::metadata(&resolved)
+        .map_err(|e| invalid(format!("file: reading {:?}: {}", path, e)))?;
+    if meta.len() > MAX_FILE_SIZE {
+        return Err(invalid(format!(
+            "file: {:?} is {} bytes, larger than the {} byte limit",
+            path,
+            meta.len(),
+            MAX_FILE_SIZE
+        )));
+    }
+    std::fs::read_to_string(&resolved)
+        .map_err(|e| invalid(format!("file: reading {:?}: {}", path, e)))
+}
+
+fn func_file_exists(root: &str, path: &str) -> Result<bool, minijinja::Error> {
+    Ok(resolve_file(root, path)?.is_file())
 }
 
 fn filter_sha256(value: String, mode: Option<String>) -> Result<Value, minijinja::Error> {
-    let mut hasher = Sha256::new();
-    hasher.update(value.as_bytes());
-    let hash = hasher.finalize();
+    digest_output("sha256", &Sha256::digest(value.as_bytes()), mode)
+}
 
+fn filter_sha512(value: String, mode: Option<String>) -> Result<Value, minijinja::Error> {
+    digest_output("sha512", &Sha512::digest(value.as_bytes()), mode)
+}
+
+/// SHA-1 is cryptographically broken; provided only for legacy formats.
+fn filter_sha1(value: String, mode: Option<String>) -> Result<Value, minijinja::Error> {
+    digest_output("sha1", &Sha1::digest(value.as_bytes()), mode)
+}
+
+/// MD5 is cryptographically broken; provided only for legacy formats.
+fn filter_md5(value: String, mode: Option<String>) -> Result<Value, minijinja::Error> {
+    digest_output("md5", &Md5::digest(value.as_bytes()), mode)
+}
+
+fn filter_hmac_sha256(
+    value: String,
+    key: String,
+    mode: Option<String>,
+) -> Result<Value, minijinja::Error> {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(value.as_bytes());
+    digest_output("hmac_sha256", &mac.finalize().into_bytes(), mode)
+}
+
+/// Format a digest as a lowercase hex string (default) or a list of byte
+/// values, shared by every hashing filter.
+fn digest_output(name: &str, hash: &[u8], mode: Option<String>) -> Result<Value, minijinja::Error> {
     match mode.as_deref().unwrap_or("hex") {
-        "hex" => Ok(Value::from(hex_encode(&hash))),
+        "hex" => Ok(Value::from(hex_encode(hash))),
         "bytes" => {
             let list: Vec<Value> = hash.iter().map(|b| Value::from(*b as i64)).collect();
             Ok(Value::from(list))
@@ -23,8 +395,8 @@ fn filter_sha256(value: String, mode: Option<String>) -> Result<Value, minijinja
         other => Err(minijinja::Error::new(
             minijinja::ErrorKind::InvalidOperation,
             format!(
-                "sha256: unsupported mode '{}' (expected 'hex' or 'bytes')",
-                other
+                "{}: unsupported mode '{}' (expected 'hex' or 'bytes')",
+                name, other
             ),
         )),
     }
@@ -79,6 +451,213 @@ fn filter_base64_decode(value: String) -> Result<String, minijinja::Error> {
     })
 }
 
+/// Percent-encode everything except RFC 3986 unreserved characters. Maps are
+/// rendered as `k=v&...` query strings, skipping none/undefined values.
+fn filter_urlencode(value: Value) -> Result<String, minijinja::Error> {
+    if value.kind() == minijinja::value::ValueKind::Map {
+        let mut pairs = Vec::new();
+        for key in value.try_iter()? {
+            let v = value.get_item(&key)?;
+            if v.is_none() || v.is_undefined() {
+                continue;
+            }
+            pairs.push(format!(
+                "{}={}",
+                percent_encode(&key.to_string()),
+                percent_encode(&v.to_string())
+            ));
+        }
+        return Ok(pairs.join("&"));
+    }
+    if value.is_none() || value.is_undefined() {
+        return Ok(String::new());
+    }
+    Ok(percent_encode(&value.to_string()))
+}
+
+fn percent_encode(s: &str) -> String {
+    use std::fmt::Write;
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            let _ = write!(out, "%{:02X}", b);
+        }
+    }
+    out
+}
+
+fn filter_urldecode(value: String) -> Result<String, minijinja::Error> {
+    let invalid = |msg: String| minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, msg);
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| invalid(format!("urldecode: invalid escape at position {}", i)))?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out)
+        .map_err(|e| invalid(format!("urldecode: result is not valid UTF-8: {}", e)))
+}
+
+/// Serialize to JSON. Unlike the builtin `tojson`, no HTML escaping is
+/// applied; `indent` pretty-prints with that many spaces.
+fn filter_to_json(value: Value, indent: Option<usize>) -> Result<String, minijinja::Error> {
+    let err = |e: serde_json::Error| {
+        minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!("to_json: {}", e),
+        )
+    };
+    match indent {
+        None => serde_json::to_string(&value).map_err(err),
+        Some(n) => {
+            let pad = " ".repeat(n);
+            let mut buf = Vec::new();
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(pad.as_bytes());
+            let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
+            serde::Serialize::serialize(&value, &mut ser).map_err(err)?;
+            // serde_json only emits valid UTF-8.
+            Ok(String::from_utf8(buf).unwrap_or_default())
+        }
+    }
+}
+
+fn filter_from_json(value: String) -> Result<Value, minijinja::Error> {
+    let parsed: serde_json::Value = serde_json::from_str(&value).map_err(|e| {
+        minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!("from_json: invalid JSON: {}", e),
+        )
+    })?;
+    Ok(Value::from_serialize(&parsed))
+}
+
+/// Serialize to YAML without the trailing newline, so the result composes with
+/// `nindent`/`indent`.
+fn filter_to_yaml(value: Value) -> Result<String, minijinja::Error> {
+    let yaml = serde_yaml::to_string(&value).map_err(|e| {
+        minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!("to_yaml: {}", e),
+        )
+    })?;
+    Ok(yaml.trim_end_matches('\n').to_string())
+}
+
+fn filter_from_yaml(value: String) -> Result<Value, minijinja::Error> {
+    let parsed: serde_yaml::Value = serde_yaml::from_str(&value).map_err(|e| {
+        minijinja::Error::new(
+            minijinja::ErrorKind::InvalidOperation,
+            format!("from_yaml: invalid YAML: {}", e),
+        )
+    })?;
+    Ok(Value::from_serialize(&parsed))
+}
+
+/// Sprig `nindent`: a newline followed by the value with every line indented.
+fn filter_nindent(value: String, width: usize) -> String {
+    let pad = " ".repeat(width);
+    format!("\n{}{}", pad, value.replace('\n', &format!("\n{}", pad)))
+}
+
+fn filter_quote(value: Value) -> String {
+    let s = if value.is_undefined() || value.is_none() {
+        String::new()
+    } else {
+        value.to_string()
+    };
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn filter_squote(value: Value) -> String {
+    if value.is_undefined() || value.is_none() {
+        return "''".into();
+    }
+    format!("'{}'", value)
+}
+
+/// Sprig `ternary`: `cond | ternary(if_true, if_false)`.
+fn filter_ternary(cond: Value, if_true: Value, if_false: Value) -> Value {
+    if cond.is_true() {
+        if_true
+    } else {
+        if_false
+    }
+}
+
+/// Sprig `merge`: deep-merge maps where keys already present in the input
+/// (or an earlier argument) win.
+fn filter_merge(
+    base: Value,
+    rest: minijinja::value::Rest<Value>,
+) -> Result<Value, minijinja::Error> {
+    let mut acc = base;
+    for other in rest.iter() {
+        acc = deep_merge(other, &acc)?;
+    }
+    Ok(acc)
+}
+
+/// Sprig `mergeOverwrite`: deep-merge maps where later arguments win.
+fn filter_merge_overwrite(
+    base: Value,
+    rest: minijinja::value::Rest<Value>,
+) -> Result<Value, minijinja::Error> {
+    let mut acc = base;
+    for other in rest.iter() {
+        acc = deep_merge(&acc, other)?;
+    }
+    Ok(acc)
+}
+
+/// Merge `overlay` on top of `base`; nested maps are merged recursively and any
+/// other overlay value replaces the base value.
+fn deep_merge(base: &Value, overlay: &Value) -> Result<Value, minijinja::Error> {
+    use minijinja::value::ValueKind;
+    for v in [base, overlay] {
+        if v.kind() != ValueKind::Map && !v.is_undefined() && !v.is_none() {
+            return Err(minijinja::Error::new(
+                minijinja::ErrorKind::InvalidOperation,
+                format!("merge: expected a map, got {}", v.kind()),
+            ));
+        }
+    }
+    if overlay.kind() != ValueKind::Map {
+        return Ok(base.clone());
+    }
+    if base.kind() != ValueKind::Map {
+        return Ok(overlay.clone());
+    }
+    let mut merged: std::collections::BTreeMap<Value, Value> = std::collections::BTreeMap::new();
+    for key in base.try_iter()? {
+        let v = base.get_item(&key)?;
+        merged.insert(key, v);
+    }
+    for key in overlay.try_iter()? {
+        let v = overlay.get_item(&key)?;
+        let v = match merged.get(&key) {
+            Some(existing) if existing.kind() == ValueKind::Map && v.kind() == ValueKind::Map => {
+                deep_merge(existing, &v)?
+            }
+            _ => v,
+        };
+        merged.insert(key, v);
+    }
+    Ok(Value::from_iter(merged))
+}
+
 fn hex_encode(bytes: &[u8]) -> String {
     use std::fmt::Write;
     let mut s = String::with_capacity(bytes.len() * 2);
@@ -278,4 +857,465 @@ mod tests {
         let result = tmpl.render(minijinja::context!()).unwrap();
         assert_eq!(result, "secret");
     }
+
+    fn file_env(root: &Path) -> minijinja::Environment<'static> {
+        let mut env = minijinja::Environment::new();
+        register_file_funcs(&mut env, root.to_str().unwrap());
+        env
+    }
+
+    #[test]
+    fn test_file_reads_relative_and_absolute() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("certs")).unwrap();
+        std::fs::write(dir.path().join("certs/ca.crt"), "-----BEGIN CERT-----").unwrap();
+        let env = file_env(dir.path());
+        let rel = env
+            .render_str("{{ file('certs/ca.crt') }}", minijinja::context!())
+            .unwrap();
+        assert_eq!(rel, "-----BEGIN CERT-----");
+        let abs = env
+            .render_str(
+                "{{ file(p) }}",
+                minijinja::context!(p => dir.path().join("certs/ca.crt").to_str().unwrap()),
+            )
+            .unwrap();
+        assert_eq!(abs, "-----BEGIN CERT-----");
+    }
+
+    #[test]
+    fn test_file_rejects_traversal_and_outside_root() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let env = file_env(dir.path());
+        let err = env
+            .render_str("{{ file('../../etc/passwd') }}", minijinja::context!())
+            .unwrap_err();
+        assert!(err.to_string().contains("path traversal"), "{}", err);
+        let err = env
+            .render_str("{{ file('/etc/passwd') }}", minijinja::context!())
+            .unwrap_err();
+        assert!(err.to_string().contains("outside file root"), "{}", err);
+    }
+
+    #[test]
+    fn test_file_size_limit() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("big.bin"),
+            vec![b'a'; MAX_FILE_SIZE as usize + 1],
+        )
+        .unwrap();
+        let env = file_env(dir.path());
+        let err = env
+            .render_str("{{ file('big.bin') }}", minijinja::context!())
+            .unwrap_err();
+        assert!(err.to_string().contains("byte limit"), "{}", err);
+    }
+
+    #[test]
+    fn test_file_exists() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("present.txt"), "x").unwrap();
+        let env = file_env(dir.path());
+        let out = env
+            .render_str(
+                "{{ fileExists('present.txt') }} {{ fileExists('missing.txt') }}",
+                minijinja::context!(),
+            )
+            .unwrap();
+        assert_eq!(out, "true false");
+    }
+
+    fn render(tmpl: &str) -> String {
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        env.render_str(tmpl, minijinja::context!()).unwrap()
+    }
+
+    #[test]
+    fn test_nindent() {
+        assert_eq!(render("{{ 'a\nb' | nindent(2) }}"), "\n  a\n  b");
+    }
+
+    #[test]
+    fn test_quote_and_squote() {
+        assert_eq!(render(r#"{{ 'say "hi"' | quote }}"#), r#""say \"hi\"""#);
+        assert_eq!(render("{{ 8080 | quote }}"), "\"8080\"");
+        assert_eq!(render("{{ none | quote }}"), "\"\"");
+        assert_eq!(render("{{ 'x' | squote }}"), "'x'");
+    }
+
+    #[test]
+    fn test_ternary() {
+        assert_eq!(render("{{ true | ternary('yes', 'no') }}"), "yes");
+        assert_eq!(render("{{ '' | ternary('yes', 'no') }}"), "no");
+    }
+
+    #[test]
+    fn test_merge_first_wins() {
+        let out = render(
+            "{% set m = dict(a=1, nested=dict(x=1)) | merge(dict(a=2, b=2, nested=dict(x=2, y=2))) %}\
+             {{ m.a }} {{ m.b }} {{ m.nested.x }} {{ m.nested.y }}",
+        );
+        assert_eq!(out, "1 2 1 2");
+    }
+
+    #[test]
+    fn test_merge_overwrite_later_wins() {
+        let out = render(
+            "{% set m = dict(a=1, nested=dict(x=1)) | merge_overwrite(dict(a=2, nested=dict(y=2)), dict(a=3)) %}\
+             {{ m.a }} {{ m.nested.x }} {{ m.nested.y }}",
+        );
+        assert_eq!(out, "3 1 2");
+    }
+
+    #[test]
+    fn test_merge_rejects_non_map() {
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        let err = env
+            .render_str("{{ dict(a=1) | merge([1, 2]) }}", minijinja::context!())
+            .unwrap_err();
+        assert!(err.to_string().contains("expected a map"), "{}", err);
+    }
+
+    #[test]
+    fn test_sprig_builtins_available() {
+        assert_eq!(
+            render("{{ ' a,b ' | trim | split(',') | join('-') | upper }}"),
+            "A-B"
+        );
+        assert_eq!(render("{{ '' | default('fallback', true) }}"), "fallback");
+    }
+
+    #[test]
+    fn test_urlencode_reserved_characters() {
+        assert_eq!(
+            filter_urlencode(Value::from("p@ss/w:rd?#%&= é~")).unwrap(),
+            "p%40ss%2Fw%3Ard%3F%23%25%26%3D%20%C3%A9~"
+        );
+        assert_eq!(filter_urlencode(Value::from(())).unwrap(), "");
+    }
+
+    #[test]
+    fn test_urlencode_map_query_string() {
+        assert_eq!(
+            render("{{ dict(a='x y', b=none, c='1/2') | urlencode }}"),
+            "a=x%20y&c=1%2F2"
+        );
+    }
+
+    #[test]
+    fn test_urldecode() {
+        assert_eq!(
+            filter_urldecode("p%40ss%2Fw%3Ard+%C3%A9".into()).unwrap(),
+            "p@ss/w:rd+é"
+        );
+        assert!(filter_urldecode("bad%zz".into()).is_err());
+        assert!(filter_urldecode("trailing%4".into()).is_err());
+        assert!(filter_urldecode("%ff".into()).is_err());
+    }
+
+    #[test]
+    fn test_urlencode_roundtrip() {
+        let original = "user:p@ss/w0rd%+!";
+        let encoded = filter_urlencode(Value::from(original)).unwrap();
+        assert_eq!(filter_urldecode(encoded).unwrap(), original);
+    }
+
+    #[test]
+    fn test_to_json_escapes_strings() {
+        assert_eq!(
+            render(r#"{{ dict(msg='say "hi" <b>') | to_json }}"#),
+            r#"{"msg":"say \"hi\" <b>"}"#
+        );
+        assert_eq!(render("{{ [1, 2] | to_json(2) }}"), "[\n  1,\n  2\n]");
+    }
+
+    #[test]
+    fn test_from_json() {
+        assert_eq!(
+            render(
+                r#"{% set c = '{"hosts": ["a", "b"], "port": 5432}' | from_json %}{{ c.hosts | join(',') }}:{{ c.port }}"#
+            ),
+            "a,b:5432"
+        );
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        let err = env
+            .render_str("{{ '{bad' | from_json }}", minijinja::context!())
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("from_json: invalid JSON"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        assert_eq!(
+            render("{{ dict(name='app', ports=[80, 443]) | to_yaml }}"),
+            "name: app\nports:\n- 80\n- 443"
+        );
+        assert_eq!(render("{{ 'true' | to_yaml }}"), "'true'");
+    }
+
+    #[test]
+    fn test_from_yaml_roundtrip() {
+        assert_eq!(
+            render("{% set v = 'a:\n  b: [1, 2]' | from_yaml %}{{ v.a.b[1] }} {{ v | to_json }}"),
+            r#"2 {"a":{"b":[1,2]}}"#
+        );
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        assert!(env
+            .render_str("{{ 'a: [' | from_yaml }}", minijinja::context!())
+            .is_err());
+    }
+
+    fn seeded_env(seed: u64) -> minijinja::Environment<'static> {
+        let mut env = minijinja::Environment::new();
+        register_random_funcs(&mut env, Some(seed));
+        env
+    }
+
+    #[test]
+    fn test_uuid4_format() {
+        let env = seeded_env(1);
+        let id = env
+            .render_str("{{ uuid4() }}", minijinja::context!())
+            .unwrap();
+        assert_eq!(id.len(), 36);
+        let parts: Vec<&str> = id.split('-').collect();
+        assert_eq!(
+            parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            [8, 4, 4, 4, 12]
+        );
+        assert!(parts[2].starts_with('4'));
+        assert!(matches!(&parts[3][..1], "8" | "9" | "a" | "b"));
+    }
+
+    #[test]
+    fn test_random_functions_deterministic_with_seed() {
+        let tmpl = "{{ uuid4() }} {{ random_string(12) }} {{ random_int(0, 1000) }}";
+        let a = seeded_env(42)
+            .render_str(tmpl, minijinja::context!())
+            .unwrap();
+        let b = seeded_env(42)
+            .render_str(tmpl, minijinja::context!())
+            .unwrap();
+        let c = seeded_env(43)
+            .render_str(tmpl, minijinja::context!())
+            .unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_random_string_charsets() {
+        let env = seeded_env(7);
+        let render = |t: &str| env.render_str(t, minijinja::context!()).unwrap();
+        let s = render("{{ random_string(64, 'numeric') }}");
+        assert_eq!(s.len(), 64);
+        assert!(s.chars().all(|c| c.is_ascii_digit()));
+        let s = render("{{ random_string(32, 'hex') }}");
+        assert!(s
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        let s = render("{{ random_string(20, 'xy') }}");
+        assert!(s.chars().all(|c| c == 'x' || c == 'y'));
+        assert_eq!(render("{{ random_string(0) }}"), "");
+    }
+
+    #[test]
+    fn test_random_string_limits() {
+        let env = seeded_env(7);
+        assert!(env
+            .render_str("{{ random_string(5000) }}", minijinja::context!())
+            .is_err());
+        assert!(env
+            .render_str("{{ random_string(5, '') }}", minijinja::context!())
+            .is_err());
+    }
+
+    #[test]
+    fn test_random_int_range() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let n = func_random_int(&mut rng, -5, 5).unwrap();
+            assert!((-5..5).contains(&n));
+        }
+        assert!(func_random_int(&mut rng, 5, 5).is_err());
+    }
+
+    #[test]
+    fn test_bcrypt_verifies() {
+        let hash = render("{{ 's3cret' | bcrypt(4) }}");
+        assert!(hash.starts_with("$2b$04$"), "{}", hash);
+        assert!(bcrypt::verify("s3cret", &hash).unwrap());
+        assert!(!bcrypt::verify("other", &hash).unwrap());
+    }
+
+    #[test]
+    fn test_bcrypt_invalid_cost() {
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        let err = env
+            .render_str("{{ 'x' | bcrypt(2) }}", minijinja::context!())
+            .unwrap_err();
+        assert!(err.to_string().contains("bcrypt:"), "{}", err);
+    }
+
+    #[test]
+    fn test_argon2_verifies() {
+        use argon2::password_hash::{PasswordHash, PasswordVerifier};
+        let hash = render("{{ 's3cret' | argon2 }}");
+        assert!(hash.starts_with("$argon2id$"), "{}", hash);
+        let parsed = PasswordHash::new(&hash).unwrap();
+        assert!(argon2::Argon2::default()
+            .verify_password(b"s3cret", &parsed)
+            .is_ok());
+        // Salts are random, so hashing twice differs.
+        assert_ne!(hash, render("{{ 's3cret' | argon2 }}"));
+    }
+
+    #[test]
+    fn test_htpasswd() {
+        let line = render("{{ 's3cret' | htpasswd('admin', 4) }}");
+        let (user, hash) = line.split_once(':').unwrap();
+        assert_eq!(user, "admin");
+        assert!(hash.starts_with("$2y$04$"), "{}", hash);
+        assert!(bcrypt::verify("s3cret", hash).unwrap());
+
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        assert!(env
+            .render_str("{{ 'x' | htpasswd('a:b') }}", minijinja::context!())
+            .is_err());
+    }
+
+    #[test]
+    fn test_additional_digests() {
+        assert_eq!(
+            filter_sha512("hello".into(), None).unwrap().to_string(),
+            "9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca72323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043"
+        );
+        assert_eq!(
+            filter_sha1("hello".into(), None).unwrap().to_string(),
+            "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"
+        );
+        assert_eq!(
+            filter_md5("hello".into(), None).unwrap().to_string(),
+            "5d41402abc4b2a76b9719d911017c592"
+        );
+        let bytes = filter_md5("hello".into(), Some("bytes".into())).unwrap();
+        assert_eq!(bytes.len(), Some(16));
+        assert!(filter_sha1("hello".into(), Some("raw".into())).is_err());
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            render("{{ 'what do ya want for nothing?' | hmac_sha256('Jefe') }}"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            render("{{ 'msg' | hmac_sha256('k', 'bytes') | base64_encode }}").len(),
+            44
+        );
+    }
+
+    #[test]
+    fn test_now_and_timestamp() {
+        let before = chrono::Utc::now().timestamp();
+        let ts: i64 = render("{{ timestamp() }}").parse().unwrap();
+        assert!(ts >= before && ts <= before + 5);
+        let later: i64 = render("{{ timestamp('1h') }}").parse().unwrap();
+        assert!((later - ts - 3600).abs() <= 5);
+        let earlier: i64 = render("{{ timestamp('-30m') }}").parse().unwrap();
+        assert!((ts - earlier - 1800).abs() <= 5);
+
+        let now = render("{{ now() }}");
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(&now).is_ok(),
+            "{}",
+            now
+        );
+        assert!(now.ends_with('Z'));
+    }
+
+    #[test]
+    fn test_date_format() {
+        assert_eq!(render("{{ 0 | date_format }}"), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            render("{{ 86400 | date_format('%Y/%m/%d') }}"),
+            "1970/01/02"
+        );
+        assert_eq!(
+            render("{{ date_format('2026-03-14T12:30:00+02:00', '%H:%M') }}"),
+            "10:30"
+        );
+    }
+
+    #[test]
+    fn test_date_format_errors() {
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        let render_err = |t: &str| {
+            env.render_str(t, minijinja::context!())
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(render_err("{{ 'yesterday' | date_format }}").contains("invalid RFC 3339"));
+        assert!(render_err("{{ 0 | date_format('%Q') }}").contains("invalid format"));
+        assert!(render_err("{{ now('soon') }}").contains("invalid time offset"));
+    }
+
+    #[test]
+    fn test_regex_match() {
+        assert_eq!(
+            render(r"{{ 'v1.2.3' | regex_match('^v\\d+\\.\\d+') }}"),
+            "true"
+        );
+        assert_eq!(render("{{ 'abc' | regex_match('^b') }}"), "false");
+    }
+
+    #[test]
+    fn test_regex_replace_extracts_host() {
+        assert_eq!(
+            render(
+                r"{{ 'postgres://user:pw@db.prod:5432/app' | regex_replace('^.*@([^:/]+).*$', '$1') }}"
+            ),
+            "db.prod"
+        );
+        assert_eq!(render(r"{{ 'V1.2' | regex_replace('^[vV]', '') }}"), "1.2");
+    }
+
+    #[test]
+    fn test_regex_find_all() {
+        assert_eq!(
+            render(r"{{ 'a1 b22 c333' | regex_find_all('\\d+') | join(',') }}"),
+            "1,22,333"
+        );
+        assert_eq!(
+            render(r"{{ 'a1 b22 c333' | regex_find_all('\\d+', 2) | join(',') }}"),
+            "1,22"
+        );
+    }
+
+    #[test]
+    fn test_regex_invalid_pattern() {
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        let err = env
+            .render_str("{{ 'x' | regex_match('(') }}", minijinja::context!())
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("regex_match: invalid pattern"),
+            "{}",
+            err
+        );
+    }
 }
@@ -0,0 +1,158 @@
+/// Formats accepted by `render --validate`.
+pub const FORMATS: &[&str] = &["yaml", "json", "toml", "ini"];
+
+/// Check that `content` parses as `format`.
+pub fn check_format(format: &str, content: &str) -> Result<(), String> {
+    match format {
+        "yaml" => serde_yaml::from_str::<serde_yaml::Value>(content)
+            .map(|_| ())
+            .map_err(|e| format!("invalid YAML: {}", e)),
+        "json" => serde_json::from_str::<serde_json::Value>(content)
+            .map(|_| ())
+            .map_err(|e| format!("invalid JSON: {}", e)),
+        "toml" => content
+            .parse::<toml::Table>()
+            .map(|_| ())
+            .map_err(|e| format!("invalid TOML: {}", e.message())),
+        "ini" => check_ini(content).map_err(|e| format!("invalid INI: {}", e)),
+        _ => Err(format!("unsupported validation format {:?}", format)),
+    }
+}
+
+/// Load and compile a JSON Schema from a JSON or YAML file.
+pub fn load_schema(path: &str) -> Result<jsonschema::Validator, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("reading schema {}: {}", path, e))?;
+    // YAML is a superset of JSON, so one parser covers both.
+    let schema: serde_json::Value =
+        serde_yaml::from_str(&content).map_err(|e| format!("parsing schema {}: {}", path, e))?;
+    jsonschema::validator_for(&schema).map_err(|e| format!("invalid schema {}: {}", path, e))
+}
+
+/// Check that JSON or YAML `content` satisfies `schema`, reporting every
+/// violation with its JSON pointer.
+pub fn check_schema(schema: &jsonschema::Validator, content: &str) -> Result<(), String> {
+    let instance: serde_json::Value =
+        serde_yaml::from_str(content).map_err(|e| format!("parsing output: {}", e))?;
+    let errors: Vec<String> = schema
+        .iter_errors(&instance)
+        .map(|e| {
+            let path = e.instance_path.as_str();
+            format!("{}: {}", if path.is_empty() { "/" } else { path }, e)
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("schema violations: {}", errors.join("; ")))
+    }
+}
+
+/// INI has no formal grammar; accept `[section]` headers, `key = value` or
+/// `key: value` entries, and `;`/`#` comments, which covers what php.ini,
+/// my.cnf, systemd and git config style files use.
+fn check_ini(content: &str) -> Result<(), String> {
+    for (i, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        let lineno = i + 1;
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('[') {
+            let name = rest
+                .strip_suffix(']')
+                .ok_or_else(|| format!("line {}: unterminated section header", lineno))?;
+            if name.trim().is_empty() {
+                return Err(format!("line {}: empty section name", lineno));
+            }
+            continue;
+        }
+        let key = match line.find(['=', ':']) {
+            Some(pos) => &line[..pos],
+            // my.cnf style boolean flags (`skip-name-resolve`) have no value.
+            None => line,
+        };
+        if key.trim().is_empty() {
+            return Err(format!("line {}: missing key", lineno));
+        }
+        if key.contains(['[', ']']) {
+            return Err(format!("line {}: malformed section header", lineno));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml() {
+        assert!(check_format("yaml", "a: 1\nb: [x, y]\n").is_ok());
+        let err = check_format("yaml", "a: [1, 2\n").unwrap_err();
+        assert!(err.starts_with("invalid YAML"), "{}", err);
+    }
+
+    #[test]
+    fn test_json() {
+        assert!(check_format("json", r#"{"a": 1}"#).is_ok());
+        let err = check_format("json", r#"{"a": 1,}"#).unwrap_err();
+        assert!(err.starts_with("invalid JSON"), "{}", err);
+    }
+
+    #[test]
+    fn test_toml() {
+        assert!(check_format("toml", "[server]\nport = 8080\n").is_ok());
+        let err = check_format("toml", "port = \n").unwrap_err();
+        assert!(err.starts_with("invalid TOML"), "{}", err);
+    }
+
+    #[test]
+    fn test_ini() {
+        let ok = "; comment\n[mysqld]\nport = 3306\nbind-address: 0.0.0.0\nskip-name-resolve\n";
+        assert!(check_format("ini", ok).is_ok());
+        assert!(check_format("ini", "[mysqld\nport=1\n")
+            .unwrap_err()
+            .contains("line 1: unterminated section header"));
+        assert!(check_format("ini", "[]\n").is_err());
+        assert!(check_format("ini", "a=1\n= 2\n")
+            .unwrap_err()
+            .contains("line 2: missing key"));
+    }
+
+    #[test]
+    fn test_check_schema() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("schema.yaml");
+        std::fs::write(
+            &path,
+            "type: object\nrequired: [port]\nproperties:\n  port: {type: integer}\n",
+        )
+        .unwrap();
+        let schema = load_schema(path.to_str().unwrap()).unwrap();
+        assert!(check_schema(&schema, "port: 8080\n").is_ok());
+        assert!(check_schema(&schema, r#"{"port": 8080}"#).is_ok());
+        let err = check_schema(&schema, "port: http\n").unwrap_err();
+        assert!(err.contains("/port: "), "{}", err);
+        let err = check_schema(&schema, "host: db\n").unwrap_err();
+        assert!(
+            err.contains("/: \"port\" is a required property"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_load_schema_invalid() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("schema.json");
+        std::fs::write(&path, r#"{"type": "no-such-type"}"#).unwrap();
+        let err = load_schema(path.to_str().unwrap()).err().unwrap();
+        assert!(err.starts_with("invalid schema"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert!(check_format("xml", "<a/>").is_err());
+    }
+}
@@ -271,6 +271,38 @@ fn test_sidecar_flag_on_failure_exits_immediately() {
     );
 }
 
+#[test]
+fn test_render_diff_exits_two_without_sidecar_sleep() {
+    // --diff reports differences with exit code 2 and, like a failure,
+    // does not enter sidecar mode.
+    let dir = tempfile::TempDir::new().unwrap();
+    let template = dir.path().join("app.conf.tmpl");
+    std::fs::write(&template, "port=8080\n").unwrap();
+    std::fs::write(dir.path().join("app.conf"), "port=9090\n").unwrap();
+    let start = Instant::now();
+    let output = Command::new(initium_bin())
+        .args([
+            "--sidecar",
+            "render",
+            "--diff",
+            "--template",
+            template.to_str().unwrap(),
+            "--output",
+            "app.conf",
+            "--workdir",
+            dir.path().to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        start.elapsed() < Duration::from_secs(10),
+        "sidecar should not sleep after --diff found differences"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("+port=8080"), "stdout: {}", stdout);
+}
+
 #[test]
 fn test_sidecar_flag_on_success_sleeps() {
     // --sidecar on a successful command should keep the process alive.
@@ -359,3 +391,29 @@ fn test_without_sidecar_exits_on_success() {
         elapsed
     );
 }
+
+#[cfg(unix)]
+#[test]
+fn test_sleep_exits_zero_on_sigterm() {
+    // `sleep` and sidecar mode end successfully on SIGTERM.
+    for args in [&["sleep"][..], &["--sidecar", "exec", "--", "true"][..]] {
+        let mut child = Command::new(initium_bin())
+            .args(args)
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stderr = std::io::BufReader::new(child.stderr.take().unwrap());
+        let mut line = String::new();
+        while !line.contains("sleeping") && !line.contains("sidecar mode") {
+            line.clear();
+            std::io::BufRead::read_line(&mut stderr, &mut line).unwrap();
+        }
+        let started = std::time::Instant::now();
+        unsafe {
+            libc::kill(child.id() as i32, libc::SIGTERM);
+        }
+        let status = child.wait().unwrap();
+        assert!(status.success(), "{:?} exited with {:?}", args, status);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -266,6 +266,46 @@ fn test_render_template() {
     );
 }
 
+// ---------------------------------------------------------------------------
+// render: --output - writes the result to stdout
+// ---------------------------------------------------------------------------
+#[test]
+fn test_render_to_stdout() {
+    if !integration_enabled() {
+        return;
+    }
+    let workdir = tempfile::TempDir::new().expect("failed to create tempdir");
+    let template = format!("{}/template.conf.tmpl", input_dir());
+
+    let out = Command::new(initium_bin())
+        .args([
+            "render",
+            "--template",
+            &template,
+            "--output",
+            "-",
+            "--workdir",
+            workdir.path().to_str().unwrap(),
+        ])
+        .env("DB_HOST", "postgres.stdout")
+        .output()
+        .expect("failed to run initium");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "render should succeed: {}", stderr);
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("host = postgres.stdout"),
+        "expected rendered output on stdout: {}",
+        stdout
+    );
+    assert_eq!(
+        std::fs::read_dir(workdir.path()).unwrap().count(),
+        0,
+        "no files should be written"
+    );
+}
+
 // ---------------------------------------------------------------------------
 // fetch: from HTTP server writes response to file
 // ---------------------------------------------------------------------------
@@ -338,6 +378,72 @@ fn test_exec_failing_command() {
     assert_eq!(code, 1, "expected exit code 1, got {}", code);
 }
 
+#[test]
+fn test_exec_env_file() {
+    if !integration_enabled() {
+        return;
+    }
+    let dir = tempfile::TempDir::new().expect("tempdir");
+    let first = dir.path().join("first.env");
+    let second = dir.path().join("second.env");
+    std::fs::write(&first, "DB_USER=app\nDB_PASSWORD=old\n").unwrap();
+    std::fs::write(&second, "export DB_PASSWORD='s3cret value'\n").unwrap();
+    let out = Command::new(initium_bin())
+        .args([
+            "exec",
+            "--env-file",
+            first.to_str().unwrap(),
+            "--env-file",
+            second.to_str().unwrap(),
+            "--",
+            "sh",
+            "-c",
+            "echo \"creds=$DB_USER:$DB_PASSWORD\"",
+        ])
+        .output()
+        .expect("failed to run initium");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "exec should succeed: {}", stderr);
+    assert!(stderr.contains("creds=app:s3cret value"), "{}", stderr);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_forwards_sigterm() {
+    if !integration_enabled() {
+        return;
+    }
+    let dir = tempfile::TempDir::new().expect("tempdir");
+    let marker = dir.path().join("terminated");
+    let script = format!(
+        "trap 'echo > {}; exit 0' TERM; echo started; sleep 30 & wait",
+        marker.display()
+    );
+    let mut child = Command::new(initium_bin())
+        .args(["exec", "--", "sh", "-c", &script])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run initium");
+    let started = std::time::Instant::now();
+    let mut stderr = std::io::BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    while !line.contains("started") {
+        line.clear();
+        std::io::BufRead::read_line(&mut stderr, &mut line).unwrap();
+    }
+    unsafe {
+        libc::kill(child.id() as i32, libc::SIGTERM);
+    }
+    let mut rest = String::new();
+    std::io::Read::read_to_string(&mut stderr, &mut rest).unwrap();
+    let status = child.wait().unwrap();
+    assert!(!status.success(), "{}", rest);
+    assert!(rest.contains("interrupted by SIGTERM"), "{}", rest);
+    assert!(marker.exists(), "the command should have received SIGTERM");
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+}
+
 // ---------------------------------------------------------------------------
 // seed: PostgreSQL — create tables, seed, verify
 // ---------------------------------------------------------------------------
@@ -836,147 +942,235 @@ phases:
 }
 
 // ---------------------------------------------------------------------------
-// seed: MySQL — structured config with special-character password
+// seed: Postgres typed values are bound as parameters
 // ---------------------------------------------------------------------------
-#[cfg(feature = "mysql")]
+#[cfg(feature = "postgres")]
 #[test]
-fn test_seed_mysql_structured_special_password() {
+fn test_seed_postgres_typed_values() {
     if !integration_enabled() {
         return;
     }
-    use mysql::prelude::Queryable;
 
-    let special_password = "p@ss:w0rd/h#sh?k=v&a=b%20";
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS typed_values;
+             DROP TABLE IF EXISTS initium_seed_typed;
+             DROP TABLE IF EXISTS initium_seed_typed_rows;
+             CREATE TABLE typed_values (id SERIAL PRIMARY KEY, code UUID UNIQUE, qty INTEGER, day DATE, path TEXT, active BOOLEAN, note TEXT, settings JSONB);",
+        )
+        .expect("failed to create postgres tables");
 
-    let mut root_conn = mysql_root_conn();
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    let write_spec = |qty: u32| {
+        std::fs::write(
+            &spec_path,
+            format!(
+                r#"database:
+  driver: postgres
+  url: "{PG_URL}?options=-c%20standard_conforming_strings%3Doff"
+  tracking_table: initium_seed_typed
 
-    // Create user with the special password
-    let _ = root_conn.query_drop("DROP USER IF EXISTS 'initium_special'@'%'");
-    root_conn
-        .query_drop(format!(
-            "CREATE USER 'initium_special'@'%' IDENTIFIED BY '{}'",
-            special_password.replace('\'', "\\'")
-        ))
-        .expect("failed to create mysql user");
-    root_conn
-        .query_drop("GRANT ALL PRIVILEGES ON initium_test.* TO 'initium_special'@'%'")
-        .expect("failed to grant mysql privileges");
-    root_conn.query_drop("FLUSH PRIVILEGES").unwrap();
+phases:
+  - name: setup
+    seed_sets:
+      - name: typed
+        mode: reconcile
+        tables:
+          - table: typed_values
+            unique_key: [code]
+            auto_id:
+              column: id
+            rows:
+              - code: "123e4567-e89b-12d3-a456-426614174000"
+                qty: {qty}
+                day: "2024-02-29"
+                path: 'C:\temp\it''s'
+                active: true
+                note: ~
+                settings: {{ retries: {qty}, hosts: [a, b] }}
+"#
+            ),
+        )
+        .expect("failed to write spec");
+    };
+
+    for qty in [5, 7] {
+        write_spec(qty);
+        let out = Command::new(initium_bin())
+            .args(["seed", "--spec", spec_path.to_str().unwrap()])
+            .output()
+            .expect("failed to run seed");
+        assert!(
+            out.status.success(),
+            "seed postgres typed values should succeed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
 
-    // Prepare tables using regular connection
-    let mut conn = mysql_conn();
-    conn.query_drop("DROP TABLE IF EXISTS orders").unwrap();
-    conn.query_drop("DROP TABLE IF EXISTS products").unwrap();
-    conn.query_drop("DROP TABLE IF EXISTS initium_seed")
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) OVER (), qty, day::text, path, active, note IS NULL, settings->>'retries', settings->'hosts'->>1 FROM typed_values",
+            &[],
+        )
         .unwrap();
-    conn.query_drop(
-        "CREATE TABLE products (id INT AUTO_INCREMENT PRIMARY KEY, sku VARCHAR(255) UNIQUE, name VARCHAR(255), price VARCHAR(50))",
-    )
-    .unwrap();
-    conn.query_drop(
-        "CREATE TABLE orders (id INT AUTO_INCREMENT PRIMARY KEY, product_id INT, quantity VARCHAR(50), FOREIGN KEY (product_id) REFERENCES products(id))",
-    )
-    .unwrap();
+    assert_eq!(row.get::<_, i64>(0), 1, "reconcile must update, not insert");
+    assert_eq!(row.get::<_, i32>(1), 7);
+    assert_eq!(row.get::<_, String>(2), "2024-02-29");
+    assert_eq!(row.get::<_, String>(3), r"C:\temp\it's");
+    assert!(row.get::<_, bool>(4));
+    assert!(row.get::<_, bool>(5), "YAML null must be stored as NULL");
+    assert_eq!(row.get::<_, String>(6), "7");
+    assert_eq!(row.get::<_, String>(7), "b");
+}
+
+// ---------------------------------------------------------------------------
+// seed: PostgreSQL — on_conflict: update upserts changed rows
+// ---------------------------------------------------------------------------
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_on_conflict_update() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS upsert_prices;
+             DROP TABLE IF EXISTS initium_seed_upsert;
+             DROP TABLE IF EXISTS initium_seed_upsert_rows;
+             CREATE TABLE upsert_prices (id SERIAL PRIMARY KEY, sku TEXT UNIQUE, price INTEGER);
+             INSERT INTO upsert_prices (sku, price) VALUES ('basic', 1);",
+        )
+        .expect("failed to create postgres tables");
 
-    // Write a spec with structured config using the special password
     let workdir = tempfile::TempDir::new().expect("tempdir");
     let spec_path = workdir.path().join("spec.yaml");
-    std::fs::write(
-        &spec_path,
-        format!(
-            r#"database:
-  driver: mysql
-  host: localhost
-  port: 13306
-  user: initium_special
-  password: "{password}"
-  name: initium_test
-  tracking_table: initium_seed
+    for price in [5, 7] {
+        std::fs::write(
+            &spec_path,
+            format!(
+                r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+  tracking_table: initium_seed_upsert
 
 phases:
   - name: setup
-    order: 1
     seed_sets:
-      - name: products_special
-        order: 1
+      - name: pricing
         tables:
-          - table: products
+          - table: upsert_prices
             unique_key: [sku]
+            on_conflict: update
             auto_id:
               column: id
             rows:
-              - _ref: prod_widget
-                sku: WIDGET-001
-                name: Widget
-                price: "9.99"
-              - _ref: prod_gadget
-                sku: GADGET-001
-                name: Gadget
-                price: "19.99"
+              - sku: basic
+                price: {price}
+              - sku: pro
+                price: 20
+"#
+            ),
+        )
+        .expect("failed to write spec");
+        let out = Command::new(initium_bin())
+            .args(["seed", "--spec", spec_path.to_str().unwrap()])
+            .output()
+            .expect("failed to run seed");
+        assert!(
+            out.status.success(),
+            "seed postgres upsert should succeed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
 
-      - name: orders_special
-        order: 2
+    let rows: Vec<(i32, String, i32)> = client
+        .query("SELECT id, sku, price FROM upsert_prices ORDER BY sku", &[])
+        .unwrap()
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2)))
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0], (1, "basic".to_string(), 7));
+    assert_eq!((rows[1].1.as_str(), rows[1].2), ("pro", 20));
+}
+
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_uuid_auto_id() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS uuid_members;
+             DROP TABLE IF EXISTS uuid_tenants;
+             DROP TABLE IF EXISTS initium_seed_uuid;
+             CREATE TABLE uuid_tenants (id UUID PRIMARY KEY, slug TEXT UNIQUE);
+             CREATE TABLE uuid_members (email TEXT PRIMARY KEY, tenant_id UUID REFERENCES uuid_tenants(id));",
+        )
+        .expect("failed to create postgres tables");
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        format!(
+            r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+  tracking_table: initium_seed_uuid
+
+phases:
+  - name: setup
+    seed_sets:
+      - name: tenants
         tables:
-          - table: orders
+          - table: uuid_tenants
+            order: 1
             auto_id:
               column: id
+              id_type: uuid
             rows:
-              - product_id: "@ref:prod_widget.id"
-                quantity: "2"
-              - product_id: "@ref:prod_gadget.id"
-                quantity: "1"
-"#,
-            password = special_password
+              - _ref: acme
+                slug: acme
+          - table: uuid_members
+            order: 2
+            rows:
+              - email: alice@example.com
+                tenant_id: "@ref:acme.id"
+"#
         ),
     )
     .expect("failed to write spec");
-
     let out = Command::new(initium_bin())
         .args(["seed", "--spec", spec_path.to_str().unwrap()])
         .output()
         .expect("failed to run seed");
-    let stderr = String::from_utf8_lossy(&out.stderr);
     assert!(
         out.status.success(),
-        "seed mysql with special-character password should succeed: {}",
-        stderr
-    );
-    assert!(
-        stderr.contains("seed execution completed"),
-        "expected completion log: {}",
-        stderr
+        "uuid auto_id seed should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
     );
 
-    let prod_count: Option<i64> = conn
-        .exec_first("SELECT COUNT(*) FROM products", ())
-        .unwrap();
-    assert_eq!(prod_count, Some(2), "expected 2 products");
-
-    let order_count: Option<i64> = conn.exec_first("SELECT COUNT(*) FROM orders", ()).unwrap();
-    assert_eq!(order_count, Some(2), "expected 2 orders");
-
-    let rows: Vec<(String, String)> = conn
-        .exec(
-            "SELECT p.name, o.quantity FROM orders o JOIN products p ON o.product_id = p.id ORDER BY p.name",
-            (),
+    let row = client
+        .query_one(
+            "SELECT t.id::text, m.tenant_id::text FROM uuid_tenants t JOIN uuid_members m ON m.tenant_id = t.id",
+            &[],
         )
         .unwrap();
-    assert_eq!(rows.len(), 2);
-    assert_eq!(rows[0].0, "Gadget");
-    assert_eq!(rows[0].1, "1");
-    assert_eq!(rows[1].0, "Widget");
-    assert_eq!(rows[1].1, "2");
-
-    // Cleanup
-    let _ = root_conn.query_drop("DROP USER IF EXISTS 'initium_special'@'%'");
+    let (tenant_id, member_tenant): (String, String) = (row.get(0), row.get(1));
+    assert_eq!(tenant_id, member_tenant);
 }
 
-// ---------------------------------------------------------------------------
-// seed: PostgreSQL — structured config with options (connect_timeout)
-// ---------------------------------------------------------------------------
 #[cfg(feature = "postgres")]
 #[test]
-fn test_seed_postgres_structured_options() {
+fn test_seed_postgres_sync_sequence() {
     if !integration_enabled() {
         return;
     }
@@ -984,370 +1178,527 @@ fn test_seed_postgres_structured_options() {
     let mut client = pg_client();
     client
         .batch_execute(
-            "DROP TABLE IF EXISTS employees;
-             DROP TABLE IF EXISTS departments;
-             DROP TABLE IF EXISTS initium_seed;
-             CREATE TABLE departments (id SERIAL PRIMARY KEY, name TEXT UNIQUE);
-             CREATE TABLE employees (id SERIAL PRIMARY KEY, name TEXT, email TEXT UNIQUE, department_id INTEGER REFERENCES departments(id));",
+            "DROP TABLE IF EXISTS seq_plans;
+             DROP TABLE IF EXISTS seq_tiers;
+             DROP TABLE IF EXISTS initium_seed_seq;
+             CREATE TABLE seq_plans (id SERIAL PRIMARY KEY, name TEXT UNIQUE);
+             CREATE TABLE seq_tiers (id INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, name TEXT);",
         )
         .expect("failed to create postgres tables");
 
-    // Write a spec with structured config including options
     let workdir = tempfile::TempDir::new().expect("tempdir");
     let spec_path = workdir.path().join("spec.yaml");
     std::fs::write(
         &spec_path,
-        r#"database:
+        format!(
+            r#"database:
   driver: postgres
-  host: localhost
-  port: 15432
-  user: initium
-  password: initium
-  name: initium_test
-  tracking_table: initium_seed
-  options:
-    connect_timeout: "5"
+  url: "{PG_URL}"
+  tracking_table: initium_seed_seq
 
 phases:
   - name: setup
-    order: 1
     seed_sets:
-      - name: departments_opts
-        order: 1
+      - name: plans
         tables:
-          - table: departments
-            unique_key: [name]
-            auto_id:
-              column: id
+          - table: seq_plans
+            sync_sequence: id
             rows:
-              - _ref: dept_eng
-                name: Engineering
-              - _ref: dept_sales
-                name: Sales
-
-      - name: employees_opts
-        order: 2
-        tables:
-          - table: employees
-            unique_key: [email]
-            auto_id:
-              column: id
+              - id: 1
+                name: free
+              - id: 42
+                name: pro
+          - table: seq_tiers
+            sync_sequence: id
             rows:
-              - name: Alice
-                email: alice@example.com
-                department_id: "@ref:dept_eng.id"
-              - name: Bob
-                email: bob@example.com
-                department_id: "@ref:dept_sales.id"
-"#,
+              - id: 7
+                name: gold
+"#
+        ),
     )
     .expect("failed to write spec");
-
     let out = Command::new(initium_bin())
         .args(["seed", "--spec", spec_path.to_str().unwrap()])
         .output()
         .expect("failed to run seed");
-    let stderr = String::from_utf8_lossy(&out.stderr);
     assert!(
         out.status.success(),
-        "seed postgres with options should succeed: {}",
-        stderr
-    );
-    assert!(
-        stderr.contains("seed execution completed"),
-        "expected completion log: {}",
-        stderr
+        "sync_sequence seed should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
     );
 
-    let dept_count: i64 = client
-        .query_one("SELECT COUNT(*) FROM departments", &[])
+    let plan_id: i32 = client
+        .query_one(
+            "INSERT INTO seq_plans (name) VALUES ('team') RETURNING id",
+            &[],
+        )
         .unwrap()
         .get(0);
-    assert_eq!(dept_count, 2, "expected 2 departments");
-
-    let emp_count: i64 = client
-        .query_one("SELECT COUNT(*) FROM employees", &[])
+    assert_eq!(plan_id, 43);
+    let tier_id: i32 = client
+        .query_one(
+            "INSERT INTO seq_tiers (name) VALUES ('silver') RETURNING id",
+            &[],
+        )
         .unwrap()
         .get(0);
-    assert_eq!(emp_count, 2, "expected 2 employees");
+    assert_eq!(tier_id, 8);
 }
 
-// ---------------------------------------------------------------------------
-// seed: PostgreSQL — structured config with create_if_missing for
-// non-existent database (issue #50)
-//
-// When using structured config with `name` pointing to a database that
-// does not exist yet, initium should connect to the default database first,
-// create the target, then reconnect. Currently this fails because the
-// initial connection includes the non-existent database name.
-// ---------------------------------------------------------------------------
 #[cfg(feature = "postgres")]
 #[test]
-fn test_seed_postgres_structured_create_nonexistent_db() {
+fn test_seed_postgres_batched_inserts() {
     if !integration_enabled() {
         return;
     }
 
     let mut client = pg_client();
-    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_structured_newdb");
-
-    // Verify the database does NOT exist before seeding
-    let count: i64 = client
-        .query_one(
-            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_structured_newdb'",
-            &[],
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS batch_items;
+             DROP TABLE IF EXISTS initium_seed_batch;
+             CREATE TABLE batch_items (code INTEGER PRIMARY KEY, label TEXT NOT NULL, active BOOLEAN);",
         )
-        .unwrap()
-        .get(0);
-    assert_eq!(count, 0, "database should not exist before test");
+        .expect("failed to create postgres tables");
 
-    // Write a spec with structured config where name = the non-existent database
     let workdir = tempfile::TempDir::new().expect("tempdir");
     let spec_path = workdir.path().join("spec.yaml");
     std::fs::write(
         &spec_path,
-        r#"database:
+        format!(
+            r#"database:
   driver: postgres
-  host: localhost
-  port: 15432
-  user: initium
-  password: initium
-  name: initium_structured_newdb
-  tracking_table: initium_seed
+  url: "{PG_URL}"
+  tracking_table: initium_seed_batch
 
 phases:
-  - name: create-database
-    order: 1
-    database: initium_structured_newdb
-    create_if_missing: true
-"#,
+  - name: setup
+    seed_sets:
+      - name: items
+        tables:
+          - table: batch_items
+            unique_key: [code]
+            batch_size: 300
+            rows:
+{{% for i in range(1000) %}}
+              - code: {{{{ i }}}}
+                label: "item {{{{ i }}}}"
+                active: {{{{ "true" if i % 2 == 0 else "false" }}}}
+{{% endfor %}}
+"#
+        ),
     )
     .expect("failed to write spec");
-
     let out = Command::new(initium_bin())
         .args(["seed", "--spec", spec_path.to_str().unwrap()])
         .output()
         .expect("failed to run seed");
-    let stderr = String::from_utf8_lossy(&out.stderr);
     assert!(
         out.status.success(),
-        "seed postgres structured create_if_missing should succeed: {}",
-        stderr
-    );
-    assert!(
-        stderr.contains("creating database if missing"),
-        "expected create database log: {}",
-        stderr
+        "batched seed should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
     );
 
-    // Verify the database was created
-    let count: i64 = client
+    let row = client
         .query_one(
-            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_structured_newdb'",
+            "SELECT COUNT(*), COUNT(*) FILTER (WHERE active) FROM batch_items",
             &[],
         )
-        .unwrap()
-        .get(0);
-    assert_eq!(count, 1, "database should now exist");
-
-    // Idempotent re-run should also succeed
-    let out = Command::new(initium_bin())
-        .args(["seed", "--spec", spec_path.to_str().unwrap()])
-        .output()
-        .expect("failed to re-run seed");
-    assert!(
-        out.status.success(),
-        "idempotent re-run should succeed: {}",
-        String::from_utf8_lossy(&out.stderr)
-    );
-
-    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_structured_newdb");
+        .unwrap();
+    let (total, active): (i64, i64) = (row.get(0), row.get(1));
+    assert_eq!((total, active), (1000, 500));
 }
 
-// ---------------------------------------------------------------------------
-// seed: PostgreSQL — structured config with create_if_missing using
-// custom default_database for bootstrap
-// ---------------------------------------------------------------------------
-#[cfg(feature = "postgres")]
 #[test]
-fn test_seed_postgres_structured_create_nonexistent_db_custom_default() {
+fn test_seed_postgres_concurrent_runs() {
     if !integration_enabled() {
         return;
     }
 
     let mut client = pg_client();
-    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_structured_newdb2");
-
-    let count: i64 = client
-        .query_one(
-            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_structured_newdb2'",
-            &[],
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS lock_items;
+             DROP TABLE IF EXISTS initium_seed_lock_rows;
+             DROP TABLE IF EXISTS initium_seed_lock;
+             CREATE TABLE lock_items (code INTEGER NOT NULL, label TEXT NOT NULL);",
         )
-        .unwrap()
-        .get(0);
-    assert_eq!(count, 0, "database should not exist before test");
+        .expect("failed to create postgres tables");
 
-    // Use initium_test as the bootstrap database instead of the default postgres
+    // No unique constraint on lock_items: only the seed lock keeps replicas
+    // from inserting the same rows twice.
     let workdir = tempfile::TempDir::new().expect("tempdir");
     let spec_path = workdir.path().join("spec.yaml");
     std::fs::write(
         &spec_path,
-        r#"database:
+        format!(
+            r#"database:
   driver: postgres
-  host: localhost
-  port: 15432
-  user: initium
-  password: initium
-  name: initium_structured_newdb2
-  default_database: initium_test
-  tracking_table: initium_seed
+  url: "{PG_URL}"
+  tracking_table: initium_seed_lock
 
 phases:
-  - name: create-database
-    order: 1
-    database: initium_structured_newdb2
-    create_if_missing: true
-"#,
+  - name: setup
+    seed_sets:
+      - name: items
+        tables:
+          - table: lock_items
+            unique_key: [code]
+            rows:
+{{% for i in range(200) %}}
+              - code: {{{{ i }}}}
+                label: "item {{{{ i }}}}"
+{{% endfor %}}
+"#
+        ),
     )
     .expect("failed to write spec");
+    let spec = spec_path.to_str().unwrap();
+
+    let children: Vec<_> = (0..4)
+        .map(|_| {
+            Command::new(initium_bin())
+                .args(["seed", "--spec", spec])
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+                .expect("failed to run seed")
+        })
+        .collect();
+    for child in children {
+        let out = child.wait_with_output().unwrap();
+        assert!(
+            out.status.success(),
+            "concurrent seed should succeed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+    let count: i64 = client
+        .query_one("SELECT COUNT(*) FROM lock_items", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 200, "rows must be inserted exactly once");
 
+    // While another session holds the lock, a seeder gives up after
+    // --lock-timeout.
+    client
+        .execute(
+            "SELECT pg_advisory_lock(hashtext($1)::bigint)",
+            &[&"initium_seed:initium_seed_lock"],
+        )
+        .unwrap();
     let out = Command::new(initium_bin())
-        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .args(["seed", "--spec", spec, "--lock-timeout", "1s"])
         .output()
         .expect("failed to run seed");
-    let stderr = String::from_utf8_lossy(&out.stderr);
-    assert!(
-        out.status.success(),
-        "seed postgres with custom default_database should succeed: {}",
-        stderr
-    );
-    assert!(
-        stderr.contains("bootstrapping via default database"),
-        "expected bootstrap log: {}",
-        stderr
-    );
-
-    let count: i64 = client
-        .query_one(
-            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_structured_newdb2'",
-            &[],
+    client
+        .execute(
+            "SELECT pg_advisory_unlock(hashtext($1)::bigint)",
+            &[&"initium_seed:initium_seed_lock"],
         )
-        .unwrap()
-        .get(0);
-    assert_eq!(count, 1, "database should now exist");
-
-    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_structured_newdb2");
+        .unwrap();
+    assert!(!out.status.success(), "seed should time out on the lock");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("timed out"), "unexpected error: {}", stderr);
 }
 
 // ---------------------------------------------------------------------------
-// seed: MySQL — structured config with create_if_missing for
-// non-existent database (issue #50)
+// seed: MySQL — structured config with special-character password
 // ---------------------------------------------------------------------------
 #[cfg(feature = "mysql")]
 #[test]
-fn test_seed_mysql_structured_create_nonexistent_db() {
+fn test_seed_mysql_structured_special_password() {
     if !integration_enabled() {
         return;
     }
     use mysql::prelude::Queryable;
 
+    let special_password = "p@ss:w0rd/h#sh?k=v&a=b%20";
+
     let mut root_conn = mysql_root_conn();
-    let _ = root_conn.query_drop("DROP DATABASE IF EXISTS initium_structured_newdb");
 
-    // Verify the database does NOT exist before seeding
-    let count: Option<i64> = root_conn
-        .exec_first(
-            "SELECT COUNT(*) FROM information_schema.schemata WHERE SCHEMA_NAME = 'initium_structured_newdb'",
-            (),
-        )
+    // Create user with the special password
+    let _ = root_conn.query_drop("DROP USER IF EXISTS 'initium_special'@'%'");
+    root_conn
+        .query_drop(format!(
+            "CREATE USER 'initium_special'@'%' IDENTIFIED BY '{}'",
+            special_password.replace('\'', "\\'")
+        ))
+        .expect("failed to create mysql user");
+    root_conn
+        .query_drop("GRANT ALL PRIVILEGES ON initium_test.* TO 'initium_special'@'%'")
+        .expect("failed to grant mysql privileges");
+    root_conn.query_drop("FLUSH PRIVILEGES").unwrap();
+
+    // Prepare tables using regular connection
+    let mut conn = mysql_conn();
+    conn.query_drop("DROP TABLE IF EXISTS orders").unwrap();
+    conn.query_drop("DROP TABLE IF EXISTS products").unwrap();
+    conn.query_drop("DROP TABLE IF EXISTS initium_seed")
         .unwrap();
-    assert_eq!(count, Some(0), "database should not exist before test");
+    conn.query_drop(
+        "CREATE TABLE products (id INT AUTO_INCREMENT PRIMARY KEY, sku VARCHAR(255) UNIQUE, name VARCHAR(255), price VARCHAR(50))",
+    )
+    .unwrap();
+    conn.query_drop(
+        "CREATE TABLE orders (id INT AUTO_INCREMENT PRIMARY KEY, product_id INT, quantity VARCHAR(50), FOREIGN KEY (product_id) REFERENCES products(id))",
+    )
+    .unwrap();
 
-    // Write a spec with structured config where name = the non-existent database
+    // Write a spec with structured config using the special password
     let workdir = tempfile::TempDir::new().expect("tempdir");
     let spec_path = workdir.path().join("spec.yaml");
     std::fs::write(
         &spec_path,
-        r#"database:
+        format!(
+            r#"database:
   driver: mysql
   host: localhost
   port: 13306
-  user: root
-  password: rootpass
-  name: initium_structured_newdb
+  user: initium_special
+  password: "{password}"
+  name: initium_test
   tracking_table: initium_seed
 
 phases:
-  - name: create-database
+  - name: setup
     order: 1
-    database: initium_structured_newdb
-    create_if_missing: true
-"#,
-    )
-    .expect("failed to write spec");
+    seed_sets:
+      - name: products_special
+        order: 1
+        tables:
+          - table: products
+            unique_key: [sku]
+            auto_id:
+              column: id
+            rows:
+              - _ref: prod_widget
+                sku: WIDGET-001
+                name: Widget
+                price: "9.99"
+              - _ref: prod_gadget
+                sku: GADGET-001
+                name: Gadget
+                price: "19.99"
 
-    let out = Command::new(initium_bin())
-        .args(["seed", "--spec", spec_path.to_str().unwrap()])
-        .output()
-        .expect("failed to run seed");
-    let stderr = String::from_utf8_lossy(&out.stderr);
-    assert!(
-        out.status.success(),
-        "seed mysql structured create_if_missing should succeed: {}",
+      - name: orders_special
+        order: 2
+        tables:
+          - table: orders
+            auto_id:
+              column: id
+            rows:
+              - product_id: "@ref:prod_widget.id"
+                quantity: "2"
+              - product_id: "@ref:prod_gadget.id"
+                quantity: "1"
+"#,
+            password = special_password
+        ),
+    )
+    .expect("failed to write spec");
+
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to run seed");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        out.status.success(),
+        "seed mysql with special-character password should succeed: {}",
         stderr
     );
     assert!(
-        stderr.contains("creating database if missing"),
-        "expected create database log: {}",
+        stderr.contains("seed execution completed"),
+        "expected completion log: {}",
         stderr
     );
 
-    // Verify the database was created
-    let count: Option<i64> = root_conn
-        .exec_first(
-            "SELECT COUNT(*) FROM information_schema.schemata WHERE SCHEMA_NAME = 'initium_structured_newdb'",
+    let prod_count: Option<i64> = conn
+        .exec_first("SELECT COUNT(*) FROM products", ())
+        .unwrap();
+    assert_eq!(prod_count, Some(2), "expected 2 products");
+
+    let order_count: Option<i64> = conn.exec_first("SELECT COUNT(*) FROM orders", ()).unwrap();
+    assert_eq!(order_count, Some(2), "expected 2 orders");
+
+    let rows: Vec<(String, String)> = conn
+        .exec(
+            "SELECT p.name, o.quantity FROM orders o JOIN products p ON o.product_id = p.id ORDER BY p.name",
             (),
         )
         .unwrap();
-    assert_eq!(count, Some(1), "database should now exist");
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].0, "Gadget");
+    assert_eq!(rows[0].1, "1");
+    assert_eq!(rows[1].0, "Widget");
+    assert_eq!(rows[1].1, "2");
+
+    // Cleanup
+    let _ = root_conn.query_drop("DROP USER IF EXISTS 'initium_special'@'%'");
+}
+
+// ---------------------------------------------------------------------------
+// seed: PostgreSQL — structured config with options (connect_timeout)
+// ---------------------------------------------------------------------------
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_structured_options() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS employees;
+             DROP TABLE IF EXISTS departments;
+             DROP TABLE IF EXISTS initium_seed;
+             CREATE TABLE departments (id SERIAL PRIMARY KEY, name TEXT UNIQUE);
+             CREATE TABLE employees (id SERIAL PRIMARY KEY, name TEXT, email TEXT UNIQUE, department_id INTEGER REFERENCES departments(id));",
+        )
+        .expect("failed to create postgres tables");
+
+    // Write a spec with structured config including options
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        r#"database:
+  driver: postgres
+  host: localhost
+  port: 15432
+  user: initium
+  password: initium
+  name: initium_test
+  tracking_table: initium_seed
+  options:
+    connect_timeout: "5"
+
+phases:
+  - name: setup
+    order: 1
+    seed_sets:
+      - name: departments_opts
+        order: 1
+        tables:
+          - table: departments
+            unique_key: [name]
+            auto_id:
+              column: id
+            rows:
+              - _ref: dept_eng
+                name: Engineering
+              - _ref: dept_sales
+                name: Sales
+
+      - name: employees_opts
+        order: 2
+        tables:
+          - table: employees
+            unique_key: [email]
+            auto_id:
+              column: id
+            rows:
+              - name: Alice
+                email: alice@example.com
+                department_id: "@ref:dept_eng.id"
+              - name: Bob
+                email: bob@example.com
+                department_id: "@ref:dept_sales.id"
+"#,
+    )
+    .expect("failed to write spec");
 
-    // Idempotent re-run should also succeed
     let out = Command::new(initium_bin())
         .args(["seed", "--spec", spec_path.to_str().unwrap()])
         .output()
-        .expect("failed to re-run seed");
+        .expect("failed to run seed");
+    let stderr = String::from_utf8_lossy(&out.stderr);
     assert!(
         out.status.success(),
-        "idempotent re-run should succeed: {}",
-        String::from_utf8_lossy(&out.stderr)
+        "seed postgres with options should succeed: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("seed execution completed"),
+        "expected completion log: {}",
+        stderr
     );
 
-    let _ = root_conn.query_drop("DROP DATABASE IF EXISTS initium_structured_newdb");
+    let dept_count: i64 = client
+        .query_one("SELECT COUNT(*) FROM departments", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(dept_count, 2, "expected 2 departments");
+
+    let emp_count: i64 = client
+        .query_one("SELECT COUNT(*) FROM employees", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(emp_count, 2, "expected 2 employees");
 }
 
 // ---------------------------------------------------------------------------
-// seed: PostgreSQL — create database via seed phase
+// seed: PostgreSQL — structured config with create_if_missing for
+// non-existent database (issue #50)
+//
+// When using structured config with `name` pointing to a database that
+// does not exist yet, initium should connect to the default database first,
+// create the target, then reconnect. Currently this fails because the
+// initial connection includes the non-existent database name.
 // ---------------------------------------------------------------------------
 #[cfg(feature = "postgres")]
 #[test]
-fn test_seed_postgres_create_database() {
+fn test_seed_postgres_structured_create_nonexistent_db() {
     if !integration_enabled() {
         return;
     }
 
     let mut client = pg_client();
-    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_created_db");
+    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_structured_newdb");
+
+    // Verify the database does NOT exist before seeding
+    let count: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_structured_newdb'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 0, "database should not exist before test");
+
+    // Write a spec with structured config where name = the non-existent database
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        r#"database:
+  driver: postgres
+  host: localhost
+  port: 15432
+  user: initium
+  password: initium
+  name: initium_structured_newdb
+  tracking_table: initium_seed
+
+phases:
+  - name: create-database
+    order: 1
+    database: initium_structured_newdb
+    create_if_missing: true
+"#,
+    )
+    .expect("failed to write spec");
 
-    let spec = format!("{}/create-db-postgres.yaml", input_dir());
     let out = Command::new(initium_bin())
-        .args(["seed", "--spec", &spec])
-        .env("POSTGRES_URL", PG_URL)
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
         .output()
         .expect("failed to run seed");
     let stderr = String::from_utf8_lossy(&out.stderr);
     assert!(
         out.status.success(),
-        "seed create database should succeed: {}",
+        "seed postgres structured create_if_missing should succeed: {}",
         stderr
     );
     assert!(
@@ -1356,70 +1707,1229 @@ fn test_seed_postgres_create_database() {
         stderr
     );
 
+    // Verify the database was created
     let count: i64 = client
         .query_one(
-            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_created_db'",
+            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_structured_newdb'",
             &[],
         )
         .unwrap()
         .get(0);
-    assert_eq!(count, 1, "expected database to exist");
+    assert_eq!(count, 1, "database should now exist");
 
-    // Idempotent re-run
+    // Idempotent re-run should also succeed
     let out = Command::new(initium_bin())
-        .args(["seed", "--spec", &spec])
-        .env("POSTGRES_URL", PG_URL)
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
         .output()
         .expect("failed to re-run seed");
     assert!(
         out.status.success(),
-        "idempotent create database should succeed"
+        "idempotent re-run should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
     );
 
-    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_created_db");
+    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_structured_newdb");
 }
 
 // ---------------------------------------------------------------------------
-// seed: PostgreSQL — create schema via seed phase
+// seed: PostgreSQL — structured config with create_if_missing using
+// custom default_database for bootstrap
 // ---------------------------------------------------------------------------
 #[cfg(feature = "postgres")]
 #[test]
-fn test_seed_postgres_create_schema() {
+fn test_seed_postgres_structured_create_nonexistent_db_custom_default() {
     if !integration_enabled() {
         return;
     }
 
     let mut client = pg_client();
-    let _ = client.batch_execute("DROP SCHEMA IF EXISTS test_analytics CASCADE");
-
-    let spec = format!("{}/create-schema-postgres.yaml", input_dir());
-    let out = Command::new(initium_bin())
-        .args(["seed", "--spec", &spec])
-        .env("POSTGRES_URL", PG_URL)
-        .output()
-        .expect("failed to run seed");
-    let stderr = String::from_utf8_lossy(&out.stderr);
-    assert!(
-        out.status.success(),
-        "seed create schema should succeed: {}",
-        stderr
-    );
-    assert!(
-        stderr.contains("creating schema if missing"),
-        "expected create schema log: {}",
-        stderr
-    );
+    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_structured_newdb2");
 
     let count: i64 = client
         .query_one(
-            "SELECT COUNT(*) FROM information_schema.schemata WHERE schema_name = 'test_analytics'",
+            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_structured_newdb2'",
             &[],
         )
         .unwrap()
         .get(0);
-    assert_eq!(count, 1, "expected schema to exist");
+    assert_eq!(count, 0, "database should not exist before test");
 
-    let _ = client.batch_execute("DROP SCHEMA IF EXISTS test_analytics CASCADE");
+    // Use initium_test as the bootstrap database instead of the default postgres
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        r#"database:
+  driver: postgres
+  host: localhost
+  port: 15432
+  user: initium
+  password: initium
+  name: initium_structured_newdb2
+  default_database: initium_test
+  tracking_table: initium_seed
+
+phases:
+  - name: create-database
+    order: 1
+    database: initium_structured_newdb2
+    create_if_missing: true
+"#,
+    )
+    .expect("failed to write spec");
+
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to run seed");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        out.status.success(),
+        "seed postgres with custom default_database should succeed: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("bootstrapping via default database"),
+        "expected bootstrap log: {}",
+        stderr
+    );
+
+    let count: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_structured_newdb2'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 1, "database should now exist");
+
+    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_structured_newdb2");
+}
+
+// ---------------------------------------------------------------------------
+// seed: MySQL — structured config with create_if_missing for
+// non-existent database (issue #50)
+// ---------------------------------------------------------------------------
+#[cfg(feature = "mysql")]
+#[test]
+fn test_seed_mysql_structured_create_nonexistent_db() {
+    if !integration_enabled() {
+        return;
+    }
+    use mysql::prelude::Queryable;
+
+    let mut root_conn = mysql_root_conn();
+    let _ = root_conn.query_drop("DROP DATABASE IF EXISTS initium_structured_newdb");
+
+    // Verify the database does NOT exist before seeding
+    let count: Option<i64> = root_conn
+        .exec_first(
+            "SELECT COUNT(*) FROM information_schema.schemata WHERE SCHEMA_NAME = 'initium_structured_newdb'",
+            (),
+        )
+        .unwrap();
+    assert_eq!(count, Some(0), "database should not exist before test");
+
+    // Write a spec with structured config where name = the non-existent database
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        r#"database:
+  driver: mysql
+  host: localhost
+  port: 13306
+  user: root
+  password: rootpass
+  name: initium_structured_newdb
+  tracking_table: initium_seed
+
+phases:
+  - name: create-database
+    order: 1
+    database: initium_structured_newdb
+    create_if_missing: true
+"#,
+    )
+    .expect("failed to write spec");
+
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to run seed");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        out.status.success(),
+        "seed mysql structured create_if_missing should succeed: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("creating database if missing"),
+        "expected create database log: {}",
+        stderr
+    );
+
+    // Verify the database was created
+    let count: Option<i64> = root_conn
+        .exec_first(
+            "SELECT COUNT(*) FROM information_schema.schemata WHERE SCHEMA_NAME = 'initium_structured_newdb'",
+            (),
+        )
+        .unwrap();
+    assert_eq!(count, Some(1), "database should now exist");
+
+    // Idempotent re-run should also succeed
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to re-run seed");
+    assert!(
+        out.status.success(),
+        "idempotent re-run should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let _ = root_conn.query_drop("DROP DATABASE IF EXISTS initium_structured_newdb");
+}
+
+// ---------------------------------------------------------------------------
+// seed: PostgreSQL — create database via seed phase
+// ---------------------------------------------------------------------------
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_create_database() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_created_db");
+
+    let spec = format!("{}/create-db-postgres.yaml", input_dir());
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", &spec])
+        .env("POSTGRES_URL", PG_URL)
+        .output()
+        .expect("failed to run seed");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        out.status.success(),
+        "seed create database should succeed: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("creating database if missing"),
+        "expected create database log: {}",
+        stderr
+    );
+
+    let count: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM pg_database WHERE datname = 'initium_created_db'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 1, "expected database to exist");
+
+    // Idempotent re-run
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", &spec])
+        .env("POSTGRES_URL", PG_URL)
+        .output()
+        .expect("failed to re-run seed");
+    assert!(
+        out.status.success(),
+        "idempotent create database should succeed"
+    );
+
+    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_created_db");
+}
+
+// ---------------------------------------------------------------------------
+// seed: PostgreSQL — seed sets of a create_if_missing phase land in the new
+// database
+// ---------------------------------------------------------------------------
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_seeds_created_database() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_phase_db");
+    client
+        .batch_execute("DROP TABLE IF EXISTS phase_db_items")
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        format!(
+            r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+
+phases:
+  - name: phase_db
+    database: initium_phase_db
+    create_if_missing: true
+    sql:
+      - CREATE TABLE IF NOT EXISTS phase_db_items (code TEXT PRIMARY KEY)
+    seed_sets:
+      - name: items
+        tables:
+          - table: phase_db_items
+            unique_key: [code]
+            rows:
+              - code: a
+              - code: b
+"#
+        ),
+    )
+    .expect("failed to write spec");
+    for _ in 0..2 {
+        let out = Command::new(initium_bin())
+            .args(["seed", "--spec", spec_path.to_str().unwrap()])
+            .output()
+            .expect("failed to run seed");
+        assert!(
+            out.status.success(),
+            "seed into created database should succeed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+
+    let mut created = postgres::Client::connect(
+        &PG_URL.replace("/initium_test", "/initium_phase_db"),
+        postgres::NoTls,
+    )
+    .expect("failed to connect to created database");
+    let count: i64 = created
+        .query_one("SELECT COUNT(*) FROM phase_db_items", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 2, "rows must be seeded into the created database");
+    let in_original: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_name = 'phase_db_items'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(in_original, 0, "original database must stay untouched");
+    drop(created);
+
+    let _ = client.batch_execute("DROP DATABASE IF EXISTS initium_phase_db");
+}
+
+// ---------------------------------------------------------------------------
+// seed: PostgreSQL — create schema via seed phase
+// ---------------------------------------------------------------------------
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_create_schema() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    let _ = client.batch_execute("DROP SCHEMA IF EXISTS test_analytics CASCADE");
+
+    let spec = format!("{}/create-schema-postgres.yaml", input_dir());
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", &spec])
+        .env("POSTGRES_URL", PG_URL)
+        .output()
+        .expect("failed to run seed");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        out.status.success(),
+        "seed create schema should succeed: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("creating schema if missing"),
+        "expected create schema log: {}",
+        stderr
+    );
+
+    let count: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM information_schema.schemata WHERE schema_name = 'test_analytics'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 1, "expected schema to exist");
+
+    let _ = client.batch_execute("DROP SCHEMA IF EXISTS test_analytics CASCADE");
+}
+
+// ---------------------------------------------------------------------------
+// seed: PostgreSQL — phase schema as search_path and schema-qualified tables
+// ---------------------------------------------------------------------------
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_phase_schema() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP SCHEMA IF EXISTS test_phase_app CASCADE;
+             DROP SCHEMA IF EXISTS test_phase_audit CASCADE;
+             DROP TABLE IF EXISTS initium_seed_phase_schema_rows;
+             DROP TABLE IF EXISTS initium_seed_phase_schema;
+             CREATE SCHEMA test_phase_audit;
+             CREATE TABLE test_phase_audit.events (name TEXT PRIMARY KEY);",
+        )
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        format!(
+            r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+  tracking_table: initium_seed_phase_schema
+
+phases:
+  - name: app
+    schema: test_phase_app
+    create_if_missing: true
+    sql:
+      - CREATE TABLE IF NOT EXISTS settings (key TEXT PRIMARY KEY, value TEXT)
+    seed_sets:
+      - name: settings
+        tables:
+          - table: settings
+            unique_key: [key]
+            rows:
+              - key: theme
+                value: dark
+  - name: audit
+    order: 1
+    schema: test_phase_audit
+    wait_for:
+      - type: table
+        name: events
+    seed_sets:
+      - name: events
+        tables:
+          - table: events
+            unique_key: [name]
+            rows:
+              - name: seeded
+          - table: test_phase_app.settings
+            unique_key: [key]
+            rows:
+              - key: audit
+                value: "on"
+"#
+        ),
+    )
+    .expect("failed to write spec");
+    for _ in 0..2 {
+        let out = Command::new(initium_bin())
+            .args(["seed", "--spec", spec_path.to_str().unwrap()])
+            .output()
+            .expect("failed to run seed");
+        assert!(
+            out.status.success(),
+            "seed with phase schema should succeed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+
+    let settings: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM test_phase_app.settings WHERE key IN ('theme', 'audit')",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(settings, 2);
+    let events: i64 = client
+        .query_one("SELECT COUNT(*) FROM test_phase_audit.events", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(events, 1);
+    let tracking_schema: String = client
+        .query_one(
+            "SELECT table_schema FROM information_schema.tables \
+             WHERE table_name = 'initium_seed_phase_schema'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(tracking_schema, "public", "tracking table stays in place");
+
+    let _ = client.batch_execute(
+        "DROP SCHEMA IF EXISTS test_phase_app CASCADE;
+         DROP SCHEMA IF EXISTS test_phase_audit CASCADE;",
+    );
+}
+
+// ---------------------------------------------------------------------------
+// seed: PostgreSQL — call steps for stored procedures and functions
+// ---------------------------------------------------------------------------
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_call_steps() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS test_call_rollups;
+             DROP TABLE IF EXISTS initium_seed_calls_rows;
+             DROP TABLE IF EXISTS initium_seed_calls;
+             CREATE TABLE test_call_rollups (name TEXT PRIMARY KEY, total INTEGER);
+             CREATE OR REPLACE PROCEDURE test_call_refresh(p_name TEXT, p_total INTEGER)
+             LANGUAGE SQL AS $$
+               INSERT INTO test_call_rollups VALUES (p_name, p_total)
+               ON CONFLICT (name) DO UPDATE SET total = EXCLUDED.total
+             $$;
+             CREATE OR REPLACE FUNCTION test_call_bump(p_name TEXT) RETURNS INTEGER
+             LANGUAGE SQL AS $$
+               UPDATE test_call_rollups SET total = total + 1 WHERE name = p_name RETURNING total
+             $$;",
+        )
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        format!(
+            r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+  tracking_table: initium_seed_calls
+
+phases:
+  - name: rollups
+    sql:
+      - call:
+          procedure: test_call_refresh
+          args: [orders, 41]
+    after:
+      - call:
+          function: public.test_call_bump
+          args: [orders]
+"#
+        ),
+    )
+    .expect("failed to write spec");
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to run seed");
+    assert!(
+        out.status.success(),
+        "seed with call steps should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let total: i32 = client
+        .query_one(
+            "SELECT total FROM test_call_rollups WHERE name = 'orders'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(total, 42);
+
+    let _ = client.batch_execute(
+        "DROP TABLE IF EXISTS test_call_rollups;
+         DROP PROCEDURE IF EXISTS test_call_refresh;
+         DROP FUNCTION IF EXISTS test_call_bump;",
+    );
+}
+
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_session_settings() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS test_session_settings;
+             CREATE TABLE test_session_settings (name TEXT, timeout TEXT, path TEXT);",
+        )
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    let write_spec = |sql: &str| {
+        std::fs::write(
+            &spec_path,
+            format!(
+                r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+  statement_timeout: 1s
+  application_name: initium-seed
+  search_path: public
+
+phases:
+  - name: settings
+    sql:
+      - {sql}
+"#
+            ),
+        )
+        .expect("failed to write spec");
+    };
+
+    write_spec(
+        "INSERT INTO test_session_settings SELECT current_setting('application_name'), \
+         current_setting('statement_timeout'), current_setting('search_path')",
+    );
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to run seed");
+    assert!(
+        out.status.success(),
+        "seed with session settings should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let row = client
+        .query_one("SELECT name, timeout, path FROM test_session_settings", &[])
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), "initium-seed");
+    assert_eq!(row.get::<_, String>(1), "1s");
+    assert_eq!(row.get::<_, String>(2), "public");
+
+    write_spec("SELECT pg_sleep(3)");
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to run seed");
+    assert!(
+        !out.status.success(),
+        "statement_timeout should cancel the seed"
+    );
+    assert!(
+        String::from_utf8_lossy(&out.stderr).contains("SQLSTATE 57014"),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let _ = client.batch_execute("DROP TABLE IF EXISTS test_session_settings;");
+}
+
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_reset_truncate_cascade() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS test_truncate_orders;
+             DROP TABLE IF EXISTS test_truncate_plans;
+             DROP TABLE IF EXISTS initium_seed_truncate_rows;
+             DROP TABLE IF EXISTS initium_seed_truncate;
+             CREATE TABLE test_truncate_plans (id SERIAL PRIMARY KEY, name TEXT UNIQUE);
+             CREATE TABLE test_truncate_orders (
+               id SERIAL PRIMARY KEY,
+               plan_id INTEGER REFERENCES test_truncate_plans(id)
+             );",
+        )
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    let seed = |strategy: &str| {
+        std::fs::write(
+            &spec_path,
+            format!(
+                r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+  tracking_table: initium_seed_truncate
+
+phases:
+  - name: plans
+    seed_sets:
+      - name: plans
+        reset_strategy: {strategy}
+        tables:
+          - table: test_truncate_plans
+            unique_key: [name]
+            auto_id:
+              column: id
+            rows:
+              - name: basic
+"#
+            ),
+        )
+        .expect("failed to write spec");
+        Command::new(initium_bin())
+            .args(["seed", "--spec", spec_path.to_str().unwrap(), "--reset"])
+            .output()
+            .expect("failed to run seed")
+    };
+
+    assert!(seed("delete").status.success());
+    client
+        .batch_execute(
+            "INSERT INTO test_truncate_orders (plan_id) SELECT id FROM test_truncate_plans",
+        )
+        .unwrap();
+
+    // Rows outside the spec reference the plan, so DELETE fails.
+    let out = seed("delete");
+    assert!(
+        !out.status.success(),
+        "delete should fail on a referenced row"
+    );
+
+    let out = seed("truncate_cascade");
+    assert!(
+        out.status.success(),
+        "truncate_cascade should clear referenced tables: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let orders: i64 = client
+        .query_one("SELECT COUNT(*) FROM test_truncate_orders", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(orders, 0, "the referencing rows are truncated too");
+    let id: i32 = client
+        .query_one(
+            "SELECT id FROM test_truncate_plans WHERE name = 'basic'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(id, 1, "RESTART IDENTITY resets the sequence");
+
+    let _ = client.batch_execute(
+        "DROP TABLE IF EXISTS test_truncate_orders;
+         DROP TABLE IF EXISTS test_truncate_plans;
+         DROP TABLE IF EXISTS initium_seed_truncate_rows;
+         DROP TABLE IF EXISTS initium_seed_truncate;",
+    );
+}
+
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_tracking_schema() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP SCHEMA IF EXISTS initium_audit CASCADE;
+             DROP TABLE IF EXISTS test_tracking_schema;
+             CREATE TABLE test_tracking_schema (name TEXT PRIMARY KEY);",
+        )
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        format!(
+            r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+  tracking_schema: initium_audit
+
+phases:
+  - name: data
+    seed_sets:
+      - name: names
+        mode: reconcile
+        tables:
+          - table: test_tracking_schema
+            unique_key: [name]
+            rows:
+              - name: alice
+"#
+        ),
+    )
+    .expect("failed to write spec");
+
+    for _ in 0..2 {
+        let out = Command::new(initium_bin())
+            .args(["seed", "--spec", spec_path.to_str().unwrap()])
+            .output()
+            .expect("failed to run seed");
+        assert!(
+            out.status.success(),
+            "seed with tracking_schema should succeed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+
+    let row = client
+        .query_one(
+            "SELECT initium_version, spec_checksum, duration_ms \
+             FROM initium_audit.initium_seed WHERE seed_set = 'names'",
+            &[],
+        )
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), env!("CARGO_PKG_VERSION"));
+    assert_eq!(row.get::<_, String>(1).len(), 64);
+    assert!(row.get::<_, i64>(2) >= 0);
+    let tracked: i64 = client
+        .query_one("SELECT COUNT(*) FROM initium_audit.initium_seed_rows", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(tracked, 1);
+
+    client
+        .batch_execute("DROP SCHEMA initium_audit CASCADE; DROP TABLE test_tracking_schema;")
+        .unwrap();
+}
+
+#[test]
+fn test_migrate_postgres() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS test_migrate_orders, test_migrate_users, test_migrations;",
+        )
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let dir = workdir.path();
+    std::fs::write(
+        dir.join("V001__create_users.sql"),
+        "CREATE TABLE test_migrate_users (id SERIAL PRIMARY KEY, email TEXT NOT NULL);",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("V002__create_orders.sql"),
+        "CREATE TABLE test_migrate_orders (id SERIAL PRIMARY KEY, \
+         user_id INT REFERENCES test_migrate_users(id));\n\
+         INSERT INTO test_migrate_users (email) VALUES ('admin@example.com');",
+    )
+    .unwrap();
+    let migrate = || {
+        Command::new(initium_bin())
+            .args([
+                "migrate",
+                "--dir",
+                dir.to_str().unwrap(),
+                "--url",
+                PG_URL,
+                "--table",
+                "test_migrations",
+            ])
+            .output()
+            .expect("failed to run migrate")
+    };
+
+    for _ in 0..2 {
+        let out = migrate();
+        assert!(
+            out.status.success(),
+            "migrate should succeed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+    let users: i64 = client
+        .query_one("SELECT COUNT(*) FROM test_migrate_users", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(users, 1, "applied migrations must not run again");
+
+    // A failing migration is rolled back, including its DDL.
+    std::fs::write(
+        dir.join("V003__broken.sql"),
+        "ALTER TABLE test_migrate_users ADD COLUMN name TEXT;\nSELECT * FROM missing_table;",
+    )
+    .unwrap();
+    let out = migrate();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("migration 'V003__broken' failed"),
+        "{}",
+        stderr
+    );
+    let has_name: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM information_schema.columns \
+             WHERE table_name = 'test_migrate_users' AND column_name = 'name'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(has_name, 0);
+    let applied: Vec<String> = client
+        .query(
+            "SELECT seed_set FROM test_migrations ORDER BY seed_set",
+            &[],
+        )
+        .unwrap()
+        .iter()
+        .map(|r| r.get(0))
+        .collect();
+    assert_eq!(applied, vec!["V001__create_users", "V002__create_orders"]);
+
+    // --down reverts the newer migrations with their down files.
+    std::fs::remove_file(dir.join("V003__broken.sql")).unwrap();
+    std::fs::write(
+        dir.join("V002__create_orders__down.sql"),
+        "DROP TABLE test_migrate_orders;",
+    )
+    .unwrap();
+    let out = Command::new(initium_bin())
+        .args([
+            "migrate",
+            "--dir",
+            dir.to_str().unwrap(),
+            "--url",
+            PG_URL,
+            "--table",
+            "test_migrations",
+            "--down",
+            "--to",
+            "1",
+        ])
+        .output()
+        .expect("failed to run migrate --down");
+    assert!(
+        out.status.success(),
+        "migrate --down should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let orders: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_name = 'test_migrate_orders'",
+            &[],
+        )
+        .unwrap()
+        .get(0);
+    assert_eq!(orders, 0);
+    let applied: i64 = client
+        .query_one("SELECT COUNT(*) FROM test_migrations", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(applied, 1);
+
+    client
+        .batch_execute("DROP TABLE test_migrate_users, test_migrations;")
+        .unwrap();
+}
+
+#[test]
+fn test_migrate_postgres_concurrent() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute("DROP TABLE IF EXISTS test_migrate_counter, test_migrations_concurrent;")
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let dir = workdir.path();
+    // The sleep keeps the first run inside its lock while the others start.
+    std::fs::write(
+        dir.join("V001__create_counter.sql"),
+        "SELECT pg_sleep(1);\n\
+         CREATE TABLE test_migrate_counter (id INT);\n\
+         INSERT INTO test_migrate_counter VALUES (1);",
+    )
+    .unwrap();
+    let runs: Vec<_> = (0..3)
+        .map(|_| {
+            Command::new(initium_bin())
+                .args([
+                    "migrate",
+                    "--dir",
+                    dir.to_str().unwrap(),
+                    "--url",
+                    PG_URL,
+                    "--table",
+                    "test_migrations_concurrent",
+                    "--lock",
+                    "db",
+                ])
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+                .expect("failed to run migrate")
+        })
+        .collect();
+    for run in runs {
+        let out = run.wait_with_output().unwrap();
+        assert!(
+            out.status.success(),
+            "concurrent migrate should succeed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+    let rows: i64 = client
+        .query_one("SELECT COUNT(*) FROM test_migrate_counter", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(rows, 1, "the migration must run once");
+
+    client
+        .batch_execute("DROP TABLE test_migrate_counter, test_migrations_concurrent;")
+        .unwrap();
+}
+
+#[test]
+fn test_migrate_postgres_timeout() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute("DROP TABLE IF EXISTS test_migrate_slow, test_migrations_timeout;")
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let dir = workdir.path();
+    std::fs::write(
+        dir.join("V001__slow.sql"),
+        "CREATE TABLE test_migrate_slow (id INT);\nSELECT pg_sleep(30);",
+    )
+    .unwrap();
+    let started = std::time::Instant::now();
+    let out = Command::new(initium_bin())
+        .args([
+            "migrate",
+            "--dir",
+            dir.to_str().unwrap(),
+            "--url",
+            PG_URL,
+            "--table",
+            "test_migrations_timeout",
+            "--timeout",
+            "1s",
+        ])
+        .output()
+        .expect("failed to run migrate");
+    assert!(!out.status.success());
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("migrate timed out"), "{}", stderr);
+
+    // The abandoned backend keeps sleeping until it notices the closed
+    // connection; end it so its transaction rolls back now.
+    client
+        .batch_execute(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity \
+             WHERE query LIKE '%pg_sleep(30)%' AND pid <> pg_backend_pid();",
+        )
+        .unwrap();
+    client
+        .batch_execute("DROP TABLE IF EXISTS test_migrate_slow, test_migrations_timeout;")
+        .unwrap();
+}
+
+#[test]
+fn test_seed_postgres_export() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS test_export_members;
+             DROP TABLE IF EXISTS test_export_teams;
+             DROP TABLE IF EXISTS initium_seed_export_rows;
+             DROP TABLE IF EXISTS initium_seed_export;
+             CREATE TABLE test_export_teams (id SERIAL PRIMARY KEY, name TEXT UNIQUE, logo BYTEA);
+             CREATE TABLE test_export_members (
+               email TEXT PRIMARY KEY,
+               team_id INTEGER REFERENCES test_export_teams(id),
+               tags JSONB
+             );
+             INSERT INTO test_export_teams (id, name, logo) VALUES (7, 'core', '\\x0102');
+             INSERT INTO test_export_members VALUES ('alice@example.com', 7, '[\"admin\"]');",
+        )
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    let out = Command::new(initium_bin())
+        .args([
+            "seed",
+            "export",
+            "--url",
+            PG_URL,
+            "--tables",
+            "test_export_members,test_export_teams",
+            "--output",
+            spec_path.to_str().unwrap(),
+        ])
+        .output()
+        .expect("failed to run seed export");
+    assert!(
+        out.status.success(),
+        "seed export should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let spec = std::fs::read_to_string(&spec_path).unwrap();
+    assert!(
+        spec.contains("team_id: '@ref:test_export_teams_7.id'"),
+        "{}",
+        spec
+    );
+
+    // Applying the export to emptied tables recreates the rows, with the
+    // team under a new id.
+    client
+        .batch_execute(
+            "TRUNCATE test_export_members, test_export_teams;
+             INSERT INTO test_export_teams (name) VALUES ('other');",
+        )
+        .unwrap();
+    let spec = spec.replace(
+        "driver: postgres",
+        &format!("driver: postgres\n  url: \"{PG_URL}\"\n  tracking_table: initium_seed_export"),
+    );
+    std::fs::write(&spec_path, spec).unwrap();
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to run seed");
+    assert!(
+        out.status.success(),
+        "seeding the export should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let row = client
+        .query_one(
+            "SELECT t.name, t.logo, m.tags::text FROM test_export_members m \
+             JOIN test_export_teams t ON t.id = m.team_id",
+            &[],
+        )
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), "core");
+    assert_eq!(row.get::<_, Vec<u8>>(1), vec![1, 2]);
+    assert_eq!(row.get::<_, String>(2), r#"["admin"]"#);
+
+    let _ = client.batch_execute(
+        "DROP TABLE IF EXISTS test_export_members;
+         DROP TABLE IF EXISTS test_export_teams;
+         DROP TABLE IF EXISTS initium_seed_export_rows;
+         DROP TABLE IF EXISTS initium_seed_export;",
+    );
+}
+
+#[cfg(feature = "postgres")]
+#[test]
+fn test_seed_postgres_waits_for_migration() {
+    if !integration_enabled() {
+        return;
+    }
+
+    let mut client = pg_client();
+    client
+        .batch_execute(
+            "DROP TABLE IF EXISTS test_wait_migrations;
+             DROP TABLE IF EXISTS test_wait_users;
+             DROP TABLE IF EXISTS initium_seed_wait_rows;
+             DROP TABLE IF EXISTS initium_seed_wait;
+             CREATE TABLE test_wait_users (id INT PRIMARY KEY);",
+        )
+        .unwrap();
+
+    let workdir = tempfile::TempDir::new().expect("tempdir");
+    let spec_path = workdir.path().join("spec.yaml");
+    std::fs::write(
+        &spec_path,
+        format!(
+            r#"database:
+  driver: postgres
+  url: "{PG_URL}"
+  tracking_table: initium_seed_wait
+
+phases:
+  - name: users
+    timeout: 20s
+    wait_for:
+      - type: row
+        table: test_wait_migrations
+        where:
+          version: "2024_10_01"
+          dirty: false
+      - type: column
+        table: public.test_wait_users
+        column: email
+    seed_sets:
+      - name: users
+        tables:
+          - table: test_wait_users
+            unique_key: [id]
+            rows:
+              - id: 1
+                email: alice@example.com
+"#
+        ),
+    )
+    .expect("failed to write spec");
+
+    // The "migration job" creates its table, then the column, then records
+    // the version, while the seeder is already waiting.
+    let migrator = std::thread::spawn(|| {
+        let mut client = pg_client();
+        for sql in [
+            "CREATE TABLE test_wait_migrations (version TEXT, dirty BOOLEAN)",
+            "INSERT INTO test_wait_migrations VALUES ('2024_09_01', false)",
+            "ALTER TABLE test_wait_users ADD COLUMN email TEXT",
+            "INSERT INTO test_wait_migrations VALUES ('2024_10_01', false)",
+        ] {
+            std::thread::sleep(std::time::Duration::from_millis(700));
+            client.batch_execute(sql).unwrap();
+        }
+    });
+    let out = Command::new(initium_bin())
+        .args(["seed", "--spec", spec_path.to_str().unwrap()])
+        .output()
+        .expect("failed to run seed");
+    migrator.join().unwrap();
+    assert!(
+        out.status.success(),
+        "seed waiting for the migration should succeed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let email: String = client
+        .query_one("SELECT email FROM test_wait_users WHERE id = 1", &[])
+        .unwrap()
+        .get(0);
+    assert_eq!(email, "alice@example.com");
+
+    let _ = client.batch_execute(
+        "DROP TABLE IF EXISTS test_wait_migrations;
+         DROP TABLE IF EXISTS test_wait_users;",
+    );
 }
 
 // ---------------------------------------------------------------------------